@@ -1,3 +1,8 @@
 
 ## User
-user-intro = Welcome, { $name }!
\ No newline at end of file
+user-intro = Welcome, { $name }!
+
+## Validation
+validation-required = The field `{ $field }` is required.
+validation-format = The field `{ $field }` has an invalid format: { $message }
+validation-too_long = The field `{ $field }` is too long: { $message }
@@ -1,3 +1,8 @@
 
 ## User
-user-intro = 欢迎{ $name }！
\ No newline at end of file
+user-intro = 欢迎{ $name }！
+
+## Validation
+validation-required = 字段`{ $field }`不能为空。
+validation-format = 字段`{ $field }`的格式不正确：{ $message }
+validation-too_long = 字段`{ $field }`的长度过长：{ $message }
@@ -4,11 +4,12 @@ use crate::{
     model::Tag,
 };
 use actix_web::web::{get, post, scope, ServiceConfig};
-use zino::{DefaultController, RouterConfigure};
+use zino::{DefaultController, HealthController, JobController, RouterConfigure};
 use zino_model::User;
 
 pub fn routes() -> Vec<RouterConfigure> {
     vec![
+        health_router as RouterConfigure,
         auth_router as RouterConfigure,
         file_router as RouterConfigure,
         user_router as RouterConfigure,
@@ -19,11 +20,17 @@ pub fn routes() -> Vec<RouterConfigure> {
 pub fn debug_routes() -> Vec<RouterConfigure> {
     vec![
         stats_router as RouterConfigure,
+        job_router as RouterConfigure,
         user_debug_router as RouterConfigure,
         tag_debug_router as RouterConfigure,
     ]
 }
 
+fn health_router(cfg: &mut ServiceConfig) {
+    cfg.route("/health/live", get().to(HealthController::live))
+        .route("/health/ready", get().to(HealthController::ready));
+}
+
 fn auth_router(cfg: &mut ServiceConfig) {
     cfg.route("/auth/login", post().to(auth::login));
     cfg.service(
@@ -70,6 +77,19 @@ fn stats_router(cfg: &mut ServiceConfig) {
     cfg.route("/stats", get().to(stats::index));
 }
 
+fn job_router(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/job")
+            .route("/list", get().to(JobController::list))
+            .route("/{id}/history", get().to(JobController::history))
+            .route("/{id}/preview", get().to(JobController::preview))
+            .route("/{id}/pause", post().to(JobController::pause))
+            .route("/{id}/resume", post().to(JobController::resume))
+            .route("/{id}/trigger", post().to(JobController::trigger))
+            .route("/{id}/reschedule", post().to(JobController::reschedule)),
+    );
+}
+
 fn user_debug_router(cfg: &mut ServiceConfig) {
     cfg.route("/user/schema", get().to(User::schema))
         .route("/user/definition", get().to(User::definition))
@@ -1,10 +1,15 @@
 use crate::model::{User, UserColumn::*};
 use zino::{prelude::*, Request, Response, Result};
+use zino_core::JsonValue;
 use zino_model::user::JwtAuthService;
 
 pub async fn login(mut req: Request) -> Result {
     let body: Map = req.parse_body().await?;
-    let (user_id, mut data) = User::generate_token(body).await.extract(&req)?;
+    let device = req.get_header("user-agent").unwrap_or_default().to_owned();
+    let ip = req.client_ip().map(|ip| ip.to_string()).unwrap_or_default();
+    let (user_id, mut data) = User::generate_token_with_client(body, &device, &ip)
+        .await
+        .extract(&req)?;
 
     let last_login_ip = data.remove("current_login_ip");
     let last_login_at = data
@@ -58,3 +63,19 @@ pub async fn logout(req: Request) -> Result {
     res.set_json_data(Map::data_entry(user.snapshot()));
     Ok(res.into())
 }
+
+pub async fn jwks(req: Request) -> Result {
+    let jwks = JsonValue::from(JwkSet::shared().to_jwks());
+    let mut res = Response::default().context(&req);
+    res.set_content_type("application/jwk-set+json");
+    res.set_bytes_data(jwks.to_string());
+    Ok(res.into())
+}
+
+pub async fn openid_configuration(req: Request) -> Result {
+    let metadata = JsonValue::from(AuthorizationServer::shared().discovery());
+    let mut res = Response::default().context(&req);
+    res.set_content_type("application/json");
+    res.set_bytes_data(metadata.to_string());
+    Ok(res.into())
+}
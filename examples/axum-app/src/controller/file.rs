@@ -1,5 +1,9 @@
 use std::time::{Duration, Instant};
 use zino::{prelude::*, Cluster, Request, Response, Result};
+use zino_core::accessor::{ChunkedUpload, GlobalAccessor};
+
+/// Directories of the objects which can be presigned.
+const PRESIGNED_PATH_PREFIXES: [&str; 1] = ["files/"];
 
 pub async fn upload(mut req: Request) -> Result {
     let (mut body, files) = req.parse_form_data::<Map>().await?;
@@ -66,3 +70,146 @@ pub async fn decrypt(req: Request) -> Result {
     res.send_file(file);
     Ok(res.into())
 }
+
+pub async fn init_upload(mut req: Request) -> Result {
+    let body = req.parse_body::<Map>().await?;
+    let Some(file_name) = body.get_str("file_name") else {
+        reject!(req, "file_name", "it should be specified");
+    };
+    let Some(total_chunks) = body.get_usize("total_chunks") else {
+        reject!(req, "total_chunks", "it should be a positive integer");
+    };
+
+    let mut upload = ChunkedUpload::create("uploads", file_name, total_chunks)
+        .await
+        .extract(&req)?;
+    if let Some(checksum) = body.get_str("checksum") {
+        upload.set_checksum(checksum).await.extract(&req)?;
+    }
+
+    let mut res = Response::default().context(&req);
+    res.set_json_data(Map::data_entry(upload.manifest().clone()));
+    Ok(res.into())
+}
+
+pub async fn upload_chunk(mut req: Request) -> Result {
+    let upload_id = req.parse_param::<Uuid>("id")?.to_string();
+    let (mut body, files) = req.parse_form_data::<Map>().await?;
+    let upload = ChunkedUpload::open("uploads", &upload_id)
+        .await
+        .extract(&req)?;
+    for mut file in files {
+        file.append_extra_attributes(&mut body);
+        upload.upload_chunk(&file).await.extract(&req)?;
+    }
+
+    let missing_chunks = upload.missing_chunks().await.extract(&req)?;
+    let mut data = Map::new();
+    data.upsert("upload_id", upload_id);
+    data.upsert("missing_chunks", missing_chunks);
+
+    let mut res = Response::default().context(&req);
+    res.set_json_data(Map::data_entry(data));
+    Ok(res.into())
+}
+
+pub async fn upload_status(req: Request) -> Result {
+    let upload_id = req.parse_param::<Uuid>("id")?.to_string();
+    let upload = ChunkedUpload::open("uploads", &upload_id)
+        .await
+        .extract(&req)?;
+    let missing_chunks = upload.missing_chunks().await.extract(&req)?;
+
+    let mut data = upload.manifest().clone();
+    data.upsert("missing_chunks", missing_chunks);
+
+    let mut res = Response::default().context(&req);
+    res.set_json_data(Map::data_entry(data));
+    Ok(res.into())
+}
+
+pub async fn complete_upload(req: Request) -> Result {
+    let upload_id = req.parse_param::<Uuid>("id")?.to_string();
+    let upload = ChunkedUpload::open("uploads", &upload_id)
+        .await
+        .extract(&req)?;
+    let path = format!("files/{}/{}", upload_id, upload.file_name());
+    let metadata = upload.finalize(&path).await.extract(&req)?;
+
+    let mut res = Response::default().context(&req);
+    res.set_json_data(Map::data_entry(metadata));
+    Ok(res.into())
+}
+
+pub async fn presign(req: Request) -> Result {
+    let query = req.parse_query::<Map>()?;
+    let Some(path) = query.get_str("path") else {
+        reject!(req, "path", "it should be specified");
+    };
+    let Some(path) = normalize_presigned_path(path) else {
+        reject!(req, "path", "it should be a file path in the allowed directories");
+    };
+    let expires_in = Duration::from_secs(600);
+    let presigned_url = if query.get_str("method") == Some("PUT") {
+        GlobalAccessor::presign_write("uploads", &path, expires_in).await
+    } else {
+        GlobalAccessor::presign_read("uploads", &path, expires_in).await
+    }
+    .extract(&req)?;
+
+    let mut res = Response::default().context(&req);
+    res.set_data(&presigned_url);
+    Ok(res.into())
+}
+
+pub async fn read_presigned(req: Request) -> Result {
+    let name = req.parse_param::<String>("name")?;
+    let path = req.parse_param::<String>("path")?;
+    req.verify_presigned_url(&name, &path)?;
+
+    let Some(operator) = GlobalAccessor::get(&name) else {
+        reject!(req, not_found, "the storage accessor does not exist");
+    };
+    let buffer = operator.read(&path).await.extract(&req)?;
+    let file_name = path.rsplit('/').next().unwrap_or_default();
+    let mut file = NamedFile::new(file_name);
+    file.set_bytes(buffer.to_bytes());
+
+    let mut res = Response::default().context(&req);
+    res.send_file(file);
+    Ok(res.into())
+}
+
+pub async fn write_presigned(mut req: Request) -> Result {
+    let name = req.parse_param::<String>("name")?;
+    let path = req.parse_param::<String>("path")?;
+    req.verify_presigned_url(&name, &path)?;
+
+    let Some(operator) = GlobalAccessor::get(&name) else {
+        reject!(req, not_found, "the storage accessor does not exist");
+    };
+    let bytes = req.read_body_bytes().await.extract(&req)?;
+    operator.write(&path, bytes).await.extract(&req)?;
+
+    let res = Response::default().context(&req);
+    Ok(res.into())
+}
+
+/// Normalizes the object path to be presigned. It returns `None` if the path
+/// contains parent or hidden segments, or it is not in the allowed directories.
+fn normalize_presigned_path(path: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => (),
+            _ if segment.starts_with('.') || segment.contains('\\') => return None,
+            _ => segments.push(segment),
+        }
+    }
+
+    let path = segments.join("/");
+    PRESIGNED_PATH_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix) && path.len() > prefix.len())
+        .then_some(path)
+}
@@ -8,27 +8,52 @@ use axum::{
     routing::{get, post},
     Router,
 };
-use zino::DefaultController;
+use zino::{DefaultController, HealthController, JobController};
 
 pub fn routes() -> Vec<Router> {
     let mut routes = Vec::new();
 
+    // Health controller.
+    let router = Router::new()
+        .route("/health/live", get(HealthController::live))
+        .route("/health/ready", get(HealthController::ready));
+    routes.push(router);
+
     // Auth controller.
-    let router = Router::new().route("/auth/login", post(auth::login)).merge(
-        Router::new()
-            .route("/auth/refresh", get(auth::refresh))
-            .route("/auth/logout", post(auth::logout))
-            .layer(from_fn(middleware::init_user_session)),
-    );
+    let router = Router::new()
+        .route("/.well-known/jwks.json", get(auth::jwks))
+        .route(
+            "/.well-known/openid-configuration",
+            get(auth::openid_configuration),
+        )
+        .route("/auth/login", post(auth::login))
+        .merge(
+            Router::new()
+                .route("/auth/refresh", get(auth::refresh))
+                .route("/auth/logout", post(auth::logout))
+                .layer(from_fn(middleware::init_user_session)),
+        );
     routes.push(router);
 
     // File controller.
     let router = Router::new()
         .route("/file/upload", post(file::upload))
         .route("/file/decrypt", get(file::decrypt))
+        .route("/file/upload/init", post(file::init_upload))
+        .route("/file/upload/:id/chunk", post(file::upload_chunk))
+        .route("/file/upload/:id/status", get(file::upload_status))
+        .route("/file/upload/:id/complete", post(file::complete_upload))
+        .route("/file/presign", get(file::presign))
         .layer(from_fn(middleware::init_user_session));
     routes.push(router);
 
+    // Presigned URLs for the storage accessors.
+    let router = Router::new().route(
+        "/presigned/:name/*path",
+        get(file::read_presigned).put(file::write_presigned),
+    );
+    routes.push(router);
+
     // User controller.
     let router = Router::new()
         .route("/user/new", post(user::new))
@@ -63,6 +88,17 @@ pub fn debug_routes() -> Vec<Router> {
     let router = Router::new().route("/stats", get(stats::index));
     routes.push(router);
 
+    // Job controller.
+    let router = Router::new()
+        .route("/job/list", get(JobController::list))
+        .route("/job/:id/history", get(JobController::history))
+        .route("/job/:id/preview", get(JobController::preview))
+        .route("/job/:id/pause", post(JobController::pause))
+        .route("/job/:id/resume", post(JobController::resume))
+        .route("/job/:id/trigger", post(JobController::trigger))
+        .route("/job/:id/reschedule", post(JobController::reschedule));
+    routes.push(router);
+
     // User schema controller.
     let router = Router::new()
         .route("/user/schema", get(User::schema))
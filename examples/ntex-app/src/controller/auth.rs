@@ -3,7 +3,11 @@ use zino_model::user::{JwtAuthService, User, UserColumn::*};
 
 pub async fn login(mut req: Request) -> Result {
     let body: Map = req.parse_body().await?;
-    let (user_id, mut data) = User::generate_token(body).await.extract(&req)?;
+    let device = req.get_header("user-agent").unwrap_or_default().to_owned();
+    let ip = req.client_ip().map(|ip| ip.to_string()).unwrap_or_default();
+    let (user_id, mut data) = User::generate_token_with_client(body, &device, &ip)
+        .await
+        .extract(&req)?;
 
     let last_login_ip = data.remove("current_login_ip");
     let last_login_at = data
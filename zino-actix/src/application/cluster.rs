@@ -11,7 +11,7 @@ use actix_web::{
 use std::{fs, time::Duration};
 use utoipa_rapidoc::RapiDoc;
 use zino_core::{
-    application::{Application, Plugin, ServerTag},
+    application::{Application, Plugin, ServerTag, ShutdownToken},
     extension::TomlTableExt,
     response::Response,
     schedule::AsyncScheduler,
@@ -56,13 +56,22 @@ impl Application for Cluster {
             Self::load().await;
             app_env.load_plugins(self.custom_plugins).await;
         });
+        let shutdown_token = ShutdownToken::shared();
         if scheduler.is_ready() {
             runtime.spawn(async move {
                 loop {
+                    let guard = shutdown_token.track();
+                    if shutdown_token.is_shutting_down() {
+                        break;
+                    }
                     scheduler.tick().await;
+                    drop(guard);
 
                     // Cannot use `std::thread::sleep` because it blocks the Tokio runtime.
-                    rt::time::sleep(scheduler.time_till_next_job()).await;
+                    let duration = scheduler.time_till_next_job();
+                    shutdown_token
+                        .run_until_cancelled(rt::time::sleep(duration))
+                        .await;
                 }
             });
         }
@@ -222,15 +231,63 @@ impl Application for Cluster {
                 .backlog(backlog)
                 .max_connections(max_connections)
                 .client_request_timeout(request_timeout)
+                .shutdown_timeout(shutdown_token.drain_timeout().as_secs())
+                .disable_signals()
                 .bind(addr)
                 .unwrap_or_else(|err| panic!("fail to create an HTTP server: {err}"))
                 .run()
             });
+            let servers = servers.collect::<Vec<_>>();
+            let handles = servers
+                .iter()
+                .map(|server| server.handle())
+                .collect::<Vec<_>>();
+            rt::spawn(async move {
+                Self::wait_for_signal().await;
+
+                // Stops accepting connections after the readiness delay.
+                rt::time::sleep(shutdown_token.readiness_delay()).await;
+                futures::future::join_all(handles.iter().map(|handle| handle.stop(true))).await;
+            });
             for result in futures::future::join_all(servers).await {
                 if let Err(err) = result {
                     tracing::error!("actix server error: {err}");
                 }
             }
+            if shutdown_token.is_shutting_down() {
+                // The servers and the in-flight tasks share the deadline.
+                let time_till_deadline = shutdown_token.time_till_deadline();
+                if rt::time::timeout(time_till_deadline, shutdown_token.drained())
+                    .await
+                    .is_err()
+                {
+                    tracing::warn!("drain timeout elapsed with in-flight tasks");
+                }
+            }
+            Self::shutdown().await;
         });
     }
 }
+
+impl Cluster {
+    /// Waits for a shutdown signal and starts the graceful shutdown.
+    async fn wait_for_signal() {
+        let ctrl_c = async {
+            if let Err(err) = rt::signal::ctrl_c().await {
+                tracing::error!("fail to install the `Ctrl+C` handler: {err}");
+            }
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            rt::signal::unix::signal(rt::signal::unix::SignalKind::terminate())
+                .expect("fail to install the terminate signal handler")
+                .recv()
+                .await;
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        futures::future::select(Box::pin(ctrl_c), Box::pin(terminate)).await;
+        tracing::warn!("signal received, starting graceful shutdown");
+        ShutdownToken::shared().shutdown();
+    }
+}
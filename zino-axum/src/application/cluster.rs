@@ -21,7 +21,7 @@ use tower_http::{
 };
 use utoipa_rapidoc::RapiDoc;
 use zino_core::{
-    application::{Application, Plugin, ServerTag},
+    application::{Application, Plugin, ServerTag, ShutdownToken},
     extension::TomlTableExt,
     response::Response,
     schedule::AsyncScheduler,
@@ -73,16 +73,26 @@ impl Application for Cluster {
             Self::load().await;
             app_env.load_plugins(self.custom_plugins).await;
         });
+        let shutdown_token = ShutdownToken::shared();
         if scheduler.is_ready() {
             runtime.spawn(async move {
                 loop {
+                    let guard = shutdown_token.track();
+                    if shutdown_token.is_shutting_down() {
+                        break;
+                    }
                     scheduler.tick().await;
+                    drop(guard);
 
                     // Cannot use `std::thread::sleep` because it blocks the Tokio runtime.
-                    tokio::time::sleep(scheduler.time_till_next_job()).await;
+                    let duration = scheduler.time_till_next_job();
+                    shutdown_token
+                        .run_until_cancelled(tokio::time::sleep(duration))
+                        .await;
                 }
             });
         }
+        runtime.spawn(Self::wait_for_signal());
 
         runtime.block_on(async {
             let default_routes = self.default_routes;
@@ -272,25 +282,52 @@ impl Application for Cluster {
                         tcp_listener,
                         app.into_make_service_with_connect_info::<SocketAddr>(),
                     )
-                    .with_graceful_shutdown(Self::shutdown())
+                    .with_graceful_shutdown(async move {
+                        // Stops accepting connections after the readiness delay.
+                        shutdown_token.cancelled().await;
+                        tokio::time::sleep(shutdown_token.readiness_delay()).await;
+                    })
                     .await
                 })
             });
-            for result in futures::future::join_all(servers).await {
-                if let Err(err) = result {
-                    tracing::error!("axum server error: {err}");
+            let drain_timeout = async {
+                shutdown_token.cancelled().await;
+                tokio::time::sleep(shutdown_token.time_till_deadline()).await;
+            };
+            tokio::select! {
+                results = futures::future::join_all(servers) => {
+                    for result in results {
+                        if let Err(err) = result {
+                            tracing::error!("axum server error: {err}");
+                        }
+                    }
+                }
+                _ = drain_timeout => {
+                    tracing::warn!("drain timeout elapsed with in-flight requests");
                 }
             }
+            if shutdown_token.is_shutting_down() {
+                // The servers and the in-flight tasks share the deadline.
+                let time_till_deadline = shutdown_token.time_till_deadline();
+                if tokio::time::timeout(time_till_deadline, shutdown_token.drained())
+                    .await
+                    .is_err()
+                {
+                    tracing::warn!("drain timeout elapsed with in-flight tasks");
+                }
+            }
+            Self::shutdown().await;
         });
     }
+}
 
-    async fn shutdown() {
+impl Cluster {
+    /// Waits for a shutdown signal and starts the graceful shutdown.
+    async fn wait_for_signal() {
         let ctrl_c = async {
             if let Err(err) = signal::ctrl_c().await {
                 tracing::error!("fail to install the `Ctrl+C` handler: {err}");
             }
-            #[cfg(feature = "orm")]
-            zino_core::orm::GlobalPool::close_all().await;
         };
         #[cfg(unix)]
         let terminate = async {
@@ -298,8 +335,6 @@ impl Application for Cluster {
                 .expect("fail to install the terminate signal handler")
                 .recv()
                 .await;
-            #[cfg(feature = "orm")]
-            zino_core::orm::GlobalPool::close_all().await;
         };
         #[cfg(not(unix))]
         let terminate = std::future::pending::<()>();
@@ -308,5 +343,6 @@ impl Application for Cluster {
             _ = terminate => {},
         };
         tracing::warn!("signal received, starting graceful shutdown");
+        ShutdownToken::shared().shutdown();
     }
 }
@@ -0,0 +1,179 @@
+use super::GlobalAccessor;
+use crate::{
+    bail,
+    error::Error,
+    schedule::{Lease, LeaseStore},
+    warn, BoxFuture, Map,
+};
+use opendal::{ErrorKind, Operator};
+use std::time::Duration;
+
+/// Directory for the leases in the storage service.
+const LEASES_DIR: &str = ".leases";
+
+/// Lease store backed by a storage accessor.
+///
+/// Each fencing token of a lease is stored as `.leases/{key}/{token}.json`,
+/// and the lease with the largest token is the current one. A new token is acquired
+/// by creating the file with `If-None-Match: *`, so that only one of the contenders
+/// can succeed. It requires the storage service to support conditional writes
+/// such as S3; otherwise use the SQL or advisory-lock stores instead.
+#[derive(Debug, Clone, Copy)]
+pub struct AccessorLeaseStore {
+    /// Operator for the storage service.
+    operator: &'static Operator,
+}
+
+impl AccessorLeaseStore {
+    /// Creates a new instance with the operator.
+    #[inline]
+    pub fn new(operator: &'static Operator) -> Self {
+        Self { operator }
+    }
+
+    /// Attempts to create a new instance with the storage accessor name.
+    pub fn try_new(accessor_name: &str) -> Result<Self, Error> {
+        let store = GlobalAccessor::get(accessor_name)
+            .map(Self::new)
+            .ok_or_else(|| warn!("storage accessor `{}` is not available", accessor_name))?;
+        store.check_capability()?;
+        Ok(store)
+    }
+
+    /// Checks whether the storage service supports conditional writes.
+    fn check_capability(&self) -> Result<(), Error> {
+        let info = self.operator.info();
+        if !info.full_capability().write_with_if_none_match {
+            bail!(
+                "storage service `{}` does not support conditional writes for the leases",
+                info.scheme()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the directory of the lease.
+    fn lease_dir(key: &str) -> String {
+        format!("{LEASES_DIR}/{key}/")
+    }
+
+    /// Returns the path of the lease with the fencing token.
+    fn lease_path(key: &str, token: u64) -> String {
+        format!("{LEASES_DIR}/{key}/{token}.json")
+    }
+
+    /// Reads the current lease. It returns `None` if the lease does not exist.
+    async fn read_lease(&self, key: &str) -> Result<Option<Lease>, Error> {
+        let token = match self.operator.list(&Self::lease_dir(key)).await {
+            Ok(entries) => entries
+                .iter()
+                .filter_map(|entry| entry.name().strip_suffix(".json")?.parse::<u64>().ok())
+                .max(),
+            Err(err) if err.kind() == ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let Some(token) = token else {
+            return Ok(None);
+        };
+        match self.operator.read(&Self::lease_path(key, token)).await {
+            Ok(buffer) => {
+                let map = serde_json::from_slice::<Map>(&buffer.to_vec())?;
+                Lease::try_from_map(&map).map(Some)
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Creates the lease with a new fencing token.
+    /// It returns `false` if the token has been acquired by another holder.
+    async fn create_lease(&self, lease: &Lease) -> Result<bool, Error> {
+        let map = lease.clone().into_map();
+        let path = Self::lease_path(lease.key(), lease.token());
+        let result = self
+            .operator
+            .write_with(&path, serde_json::to_vec(&map)?)
+            .if_none_match("*")
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::ConditionNotMatch => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the lease with the fencing token held by the holder.
+    async fn write_lease(&self, lease: &Lease) -> Result<(), Error> {
+        let map = lease.clone().into_map();
+        let path = Self::lease_path(lease.key(), lease.token());
+        self.operator
+            .write(&path, serde_json::to_vec(&map)?)
+            .await?;
+        Ok(())
+    }
+}
+
+impl LeaseStore for AccessorLeaseStore {
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        holder: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            self.check_capability()?;
+            let token = match self.read_lease(key).await? {
+                Some(lease) if !lease.is_expired() && lease.holder() != holder => return Ok(None),
+                Some(lease) if !lease.is_expired() => {
+                    let lease = Lease::new(key, holder, lease.token(), ttl);
+                    self.write_lease(&lease).await?;
+                    return Ok(Some(lease));
+                }
+                Some(lease) => lease.token() + 1,
+                None => 1,
+            };
+            let lease = Lease::new(key, holder, token, ttl);
+            if !self.create_lease(&lease).await? {
+                return Ok(None);
+            }
+            if token > 1 {
+                // The previous tokens are never read again.
+                let path = Self::lease_path(key, token - 1);
+                if let Err(err) = self.operator.delete(&path).await {
+                    tracing::warn!(key, "fail to delete the previous lease: {err}");
+                }
+            }
+            Ok(Some(lease))
+        })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a Lease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            match self.read_lease(lease.key()).await? {
+                Some(mut current_lease) if current_lease.is_held_by(lease) => {
+                    current_lease.extend(ttl);
+                    self.write_lease(&current_lease).await?;
+                    Ok(Some(current_lease))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, lease: &'a Lease) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(mut current_lease) = self.read_lease(lease.key()).await? {
+                if current_lease.is_held_by(lease) {
+                    // Keeps the fencing token for the next holder.
+                    current_lease.expire();
+                    self.write_lease(&current_lease).await?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -63,6 +63,18 @@ use opendal::{
 };
 use toml::Table;
 
+mod lease_store;
+mod presign;
+mod session_store;
+mod upload;
+
+pub use lease_store::AccessorLeaseStore;
+pub use presign::PresignedUrl;
+pub use session_store::AccessorSessionStore;
+pub use upload::ChunkedUpload;
+
+pub(crate) use presign::fallback_secret_key;
+
 /// Global storage accessor built on the top of [`opendal`](https://crates.io/crates/opendal).
 #[derive(Debug, Clone, Copy, Default)]
 pub struct GlobalAccessor;
@@ -0,0 +1,239 @@
+use super::GlobalAccessor;
+use crate::{
+    auth::{AccessKeyId, SecretAccessKey, SecurityToken},
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, LazyLock, Map,
+};
+use opendal::{raw::PresignedRequest, Operator};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+use serde::Serialize;
+use std::time::Duration;
+
+/// A time-limited presigned URL for reading or writing an object in the storage service.
+///
+/// For the storage services which support presigning, such as `s3`, `oss`, `cos`, `obs`,
+/// `gcs` and `azblob`, the URL is generated by the service itself. For other services
+/// such as `fs` and `memory`, a zino-signed URL for the fallback route is generated,
+/// which can be verified by [`RequestContext::verify_presigned_url()`].
+///
+/// The fallback route is configured by the `presign.fallback-url` with the default
+/// value `/presigned`, and it should match the pattern `{fallback-url}/{name}/{*path}`.
+///
+/// [`RequestContext::verify_presigned_url()`]: crate::request::RequestContext::verify_presigned_url
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedUrl {
+    /// HTTP method.
+    method: &'static str,
+    /// URL.
+    url: String,
+    /// HTTP headers which should be sent with the request.
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    headers: Map,
+    /// Expires time.
+    expires_at: DateTime,
+}
+
+impl PresignedUrl {
+    /// Returns the HTTP method.
+    #[inline]
+    pub fn method(&self) -> &'static str {
+        self.method
+    }
+
+    /// Returns the URL.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the HTTP headers which should be sent with the request.
+    #[inline]
+    pub fn headers(&self) -> &Map {
+        &self.headers
+    }
+
+    /// Returns the expires time.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+
+    /// Returns `true` if the URL is signed by zino for the fallback route.
+    #[inline]
+    pub fn is_fallback(&self) -> bool {
+        self.url.starts_with(FALLBACK_URL.as_str())
+    }
+
+    /// Creates a new instance from the presigned request of the storage service.
+    fn from_presigned_request(request: PresignedRequest, expires_at: DateTime) -> Self {
+        let method = if request.method() == "PUT" {
+            "PUT"
+        } else {
+            "GET"
+        };
+        let mut headers = Map::new();
+        for (name, value) in request.header() {
+            if let Ok(value) = value.to_str() {
+                headers.upsert(name.as_str(), value);
+            }
+        }
+        Self {
+            method,
+            url: request.uri().to_string(),
+            headers,
+            expires_at,
+        }
+    }
+
+    /// Signs a new URL for the fallback route.
+    fn sign_fallback(
+        method: &'static str,
+        name: &str,
+        path: &str,
+        expires_at: DateTime,
+    ) -> Result<Self, Error> {
+        let access_key_id = AccessKeyId::new();
+        let secret_key = fallback_secret_key(&access_key_id, method, name, path);
+        let security_token = SecurityToken::try_new(access_key_id, expires_at, secret_key)?;
+
+        let mut query = Map::new();
+        query.upsert("access_key_id", security_token.access_key_id().as_str());
+        query.upsert("expires", expires_at.timestamp());
+        query.upsert("security_token", security_token.as_str());
+
+        let fallback_url = FALLBACK_URL.as_str();
+        let name = encode_path(name);
+        let path = encode_path(path.trim_start_matches('/'));
+        let query = query.to_query_string();
+        Ok(Self {
+            method,
+            url: format!("{fallback_url}/{name}/{path}?{query}"),
+            headers: Map::new(),
+            expires_at,
+        })
+    }
+}
+
+impl GlobalAccessor {
+    /// Generates a presigned URL to read the object at the path
+    /// in the storage service with the given name.
+    pub async fn presign_read(
+        name: &str,
+        path: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedUrl, Error> {
+        let operator = get_operator(name)?;
+        let expires_at = DateTime::now() + expires_in;
+        if operator.info().full_capability().presign_read {
+            let request = operator.presign_read(path, expires_in).await?;
+            Ok(PresignedUrl::from_presigned_request(request, expires_at))
+        } else {
+            PresignedUrl::sign_fallback("GET", name, path, expires_at)
+        }
+    }
+
+    /// Generates a presigned URL to write the object at the path
+    /// in the storage service with the given name.
+    pub async fn presign_write(
+        name: &str,
+        path: &str,
+        expires_in: Duration,
+    ) -> Result<PresignedUrl, Error> {
+        let operator = get_operator(name)?;
+        let expires_at = DateTime::now() + expires_in;
+        if operator.info().full_capability().presign_write {
+            let request = operator.presign_write(path, expires_in).await?;
+            Ok(PresignedUrl::from_presigned_request(request, expires_at))
+        } else {
+            PresignedUrl::sign_fallback("PUT", name, path, expires_at)
+        }
+    }
+}
+
+/// Returns the secret key for signing the fallback URL.
+/// It is bound to the HTTP method, the storage accessor and the object path.
+pub(crate) fn fallback_secret_key(
+    access_key_id: &AccessKeyId,
+    method: &str,
+    name: &str,
+    path: &str,
+) -> SecretAccessKey {
+    let path = path.trim_start_matches('/');
+    let resource = format!("{access_key_id}:{method}:{name}/{path}");
+    SecretAccessKey::new(&resource.into())
+}
+
+/// Percent-encodes each segment of the path, while the separators `/` are kept.
+fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| percent_encoding::utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Characters to be percent-encoded in a path segment.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Gets the operator for the storage accessor.
+fn get_operator(name: &str) -> Result<&'static Operator, Error> {
+    GlobalAccessor::get(name).ok_or_else(|| warn!("storage accessor `{}` is not available", name))
+}
+
+/// URL prefix of the fallback route.
+static FALLBACK_URL: LazyLock<String> = LazyLock::new(|| {
+    State::shared()
+        .get_config("presign")
+        .and_then(|config| config.get_str("fallback-url"))
+        .unwrap_or("/presigned")
+        .trim_end_matches('/')
+        .to_owned()
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_path, fallback_secret_key, PresignedUrl};
+    use crate::{
+        auth::{AccessKeyId, SecurityToken},
+        datetime::DateTime,
+        extension::JsonObjectExt,
+        Map,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn it_signs_fallback_urls() {
+        assert_eq!(
+            encode_path("docs/a b/report#1.pdf"),
+            "docs/a%20b/report%231.pdf"
+        );
+        assert_eq!(encode_path("报告?.txt"), "%E6%8A%A5%E5%91%8A%3F.txt");
+
+        let expires_at = DateTime::now() + Duration::from_secs(600);
+        let path = "/docs/a b/report#1.pdf";
+        let presigned_url =
+            PresignedUrl::sign_fallback("GET", "uploads", path, expires_at).unwrap();
+        assert!(presigned_url.is_fallback());
+        assert!(presigned_url
+            .url()
+            .starts_with("/presigned/uploads/docs/a%20b/report%231.pdf?"));
+
+        let query = presigned_url.url().split_once('?').unwrap().1;
+        let query = serde_qs::from_str::<Map>(query).unwrap();
+        let token = query.get_str("security_token").unwrap();
+        let access_key_id = AccessKeyId::from(query.get_str("access_key_id").unwrap());
+        let secret_key = fallback_secret_key(&access_key_id, "GET", "uploads", path);
+        assert!(SecurityToken::parse_with(token.to_owned(), secret_key.as_ref()).is_ok());
+
+        let secret_key = fallback_secret_key(&access_key_id, "PUT", "uploads", path);
+        assert!(SecurityToken::parse_with(token.to_owned(), secret_key.as_ref()).is_err());
+        let secret_key = fallback_secret_key(&access_key_id, "GET", "uploads", "docs/other.pdf");
+        assert!(SecurityToken::parse_with(token.to_owned(), secret_key.as_ref()).is_err());
+    }
+}
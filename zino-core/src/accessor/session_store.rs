@@ -0,0 +1,341 @@
+use super::GlobalAccessor;
+use crate::{
+    auth::{SessionRecord, SessionStore},
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    warn, BoxFuture, LazyLock, Map,
+};
+use futures::lock::Mutex;
+use opendal::{ErrorKind, Operator};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Lock for updating the revocation list.
+static REVOCATION_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// Lock for updating the sessions and the session index of users.
+static SESSION_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+/// Directory for the sessions in the storage service.
+const SESSIONS_DIR: &str = ".sessions";
+
+/// Session store backed by a storage accessor.
+///
+/// Each session is stored as `.sessions/{session_id}.json`, and the session IDs
+/// of a user are indexed in `.sessions/users/{user_id}.json`. The revoked tokens
+/// are stored in `.sessions/revoked.json`.
+///
+/// If the storage service supports conditional writes, the redemptions of single-use tokens
+/// and the rotations of refresh tokens are guarded by the markers
+/// `.sessions/redeemed/{jwt_id}.json` and `.sessions/rotated/{session_id}/{jwt_id}.json`
+/// created with `If-None-Match: *`, so that they succeed only once across the instances.
+/// Otherwise, they are serialized within the process only, and the store is single-node only.
+/// A transactional store such as `SqlSessionStore` in `zino-model` should be used
+/// for multiple instances instead. A session lost from the index by a concurrent update
+/// on another instance is added back when it is saved again.
+#[derive(Debug, Clone, Copy)]
+pub struct AccessorSessionStore {
+    /// Operator for the storage service.
+    operator: &'static Operator,
+    /// A flag to indicate whether the storage service supports conditional writes.
+    conditional_writes: bool,
+}
+
+impl AccessorSessionStore {
+    /// Creates a new instance with the operator.
+    #[inline]
+    pub fn new(operator: &'static Operator) -> Self {
+        let info = operator.info();
+        let conditional_writes = info.full_capability().write_with_if_none_match;
+        if !conditional_writes {
+            tracing::warn!(
+                "storage service `{}` does not support conditional writes, \
+                    so the sessions should be used by a single instance",
+                info.scheme()
+            );
+        }
+        Self {
+            operator,
+            conditional_writes,
+        }
+    }
+
+    /// Attempts to create a new instance with the storage accessor name.
+    pub fn try_new(accessor_name: &str) -> Result<Self, Error> {
+        GlobalAccessor::get(accessor_name)
+            .map(Self::new)
+            .ok_or_else(|| warn!("storage accessor `{}` is not available", accessor_name))
+    }
+
+    /// Returns the path of the session.
+    fn session_path(session_id: &str) -> String {
+        format!("{SESSIONS_DIR}/{session_id}.json")
+    }
+
+    /// Returns the path of the session index for the user.
+    fn user_path(user_id: &str) -> String {
+        format!("{SESSIONS_DIR}/users/{user_id}.json")
+    }
+
+    /// Returns the path of the revoked tokens.
+    fn revoked_tokens_path() -> String {
+        format!("{SESSIONS_DIR}/revoked.json")
+    }
+
+    /// Returns the path of the marker for the redeemed token.
+    fn redeemed_token_path(jwt_id: &str) -> String {
+        format!("{SESSIONS_DIR}/redeemed/{jwt_id}.json")
+    }
+
+    /// Returns the directory of the markers for the rotated refresh tokens of the session.
+    fn rotated_tokens_dir(session_id: &str) -> String {
+        format!("{SESSIONS_DIR}/rotated/{session_id}/")
+    }
+
+    /// Reads a JSON file. It returns `None` if the file does not exist.
+    async fn read_json<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>, Error> {
+        match self.operator.read(path).await {
+            Ok(buffer) => Ok(Some(serde_json::from_slice(&buffer.to_vec())?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Creates a marker with the expiration time only if it does not exist.
+    /// It returns `false` if the marker has been created before.
+    async fn create_marker(&self, path: &str, expires_at: DateTime) -> Result<bool, Error> {
+        let marker = Map::from_entry("expires_at", expires_at.to_string());
+        let result = self
+            .operator
+            .write_with(path, serde_json::to_vec(&marker)?)
+            .if_none_match("*")
+            .await;
+        match result {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::ConditionNotMatch => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes a JSON file.
+    async fn write_json<T: Serialize>(&self, path: &str, value: &T) -> Result<(), Error> {
+        self.operator
+            .write(path, serde_json::to_vec(value)?)
+            .await?;
+        Ok(())
+    }
+
+    /// Reads the session. It returns `None` if the session does not exist.
+    async fn read_session(&self, session_id: &str) -> Result<Option<SessionRecord>, Error> {
+        let session = self
+            .read_json::<Map>(&Self::session_path(session_id))
+            .await?;
+        session
+            .as_ref()
+            .map(SessionRecord::try_from_map)
+            .transpose()
+    }
+
+    /// Inserts the JWT ID into the revocation list. It returns `false` if the JWT ID
+    /// has been revoked and `overwrite` is `false`.
+    async fn insert_revoked_token(
+        &self,
+        jwt_id: &str,
+        expires_at: DateTime,
+        overwrite: bool,
+    ) -> Result<bool, Error> {
+        if !overwrite
+            && self.conditional_writes
+            && !self
+                .create_marker(&Self::redeemed_token_path(jwt_id), expires_at)
+                .await?
+        {
+            return Ok(false);
+        }
+
+        let _guard = REVOCATION_LOCK.lock().await;
+        let path = Self::revoked_tokens_path();
+        let now = DateTime::now();
+        let mut revoked_tokens = self.read_json::<Map>(&path).await?.unwrap_or_default();
+        let mut expired_tokens = Vec::new();
+        revoked_tokens.retain(|key, value| {
+            let active = value
+                .as_str()
+                .and_then(|s| s.parse::<DateTime>().ok())
+                .is_some_and(|expires_at| expires_at > now);
+            if !active {
+                expired_tokens.push(Self::redeemed_token_path(key));
+            }
+            active
+        });
+        if !overwrite && !self.conditional_writes && revoked_tokens.contains_key(jwt_id) {
+            return Ok(false);
+        }
+        if self.conditional_writes && !expired_tokens.is_empty() {
+            // The markers of the expired tokens are removed on a best-effort basis.
+            if let Err(err) = self.operator.remove(expired_tokens).await {
+                tracing::warn!("fail to remove the markers of the expired tokens: {err}");
+            }
+        }
+        revoked_tokens.upsert(jwt_id, expires_at.to_string());
+        self.write_json(&path, &revoked_tokens).await?;
+        Ok(true)
+    }
+
+    /// Reads the session IDs of the user.
+    async fn read_session_ids(&self, user_id: &str) -> Result<Vec<String>, Error> {
+        let session_ids = self.read_json(&Self::user_path(user_id)).await?;
+        Ok(session_ids.unwrap_or_default())
+    }
+}
+
+impl SessionStore for AccessorSessionStore {
+    fn save_session<'a>(&'a self, session: &'a SessionRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let session_id = session.session_id();
+            self.write_json(&Self::session_path(session_id), &session.clone().into_map())
+                .await?;
+
+            let _guard = SESSION_LOCK.lock().await;
+            let user_id = session.user_id();
+            let mut session_ids = self.read_session_ids(user_id).await?;
+            if !session_ids.iter().any(|s| s == session_id) {
+                session_ids.push(session_id.to_owned());
+                self.write_json(&Self::user_path(user_id), &session_ids)
+                    .await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session: &'a SessionRecord,
+        refresh_token_id: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let _guard = SESSION_LOCK.lock().await;
+            let session_id = session.session_id();
+            let rotated = self
+                .read_session(session_id)
+                .await?
+                .map_or(true, |s| s.refresh_token_id() != refresh_token_id);
+            if rotated {
+                return Ok(false);
+            }
+            if self.conditional_writes && session.refresh_token_id() != refresh_token_id {
+                // Only one of the concurrent rotations can create the marker.
+                let path = format!(
+                    "{}{refresh_token_id}.json",
+                    Self::rotated_tokens_dir(session_id)
+                );
+                if !self.create_marker(&path, session.expires_at()).await? {
+                    return Ok(false);
+                }
+            }
+            self.write_json(&Self::session_path(session_id), &session.clone().into_map())
+                .await?;
+            Ok(true)
+        })
+    }
+
+    fn find_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SessionRecord>, Error>> {
+        Box::pin(async move { self.read_session(session_id).await })
+    }
+
+    fn list_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<SessionRecord>, Error>> {
+        Box::pin(async move {
+            let session_ids = self.read_session_ids(user_id).await?;
+            let mut sessions = Vec::with_capacity(session_ids.len());
+            let mut inactive_session_ids = Vec::new();
+            for session_id in session_ids {
+                match self.read_session(&session_id).await? {
+                    Some(session) if !session.is_expired() => sessions.push(session),
+                    Some(_) => {
+                        self.operator
+                            .delete(&Self::session_path(&session_id))
+                            .await?;
+                        self.operator
+                            .remove_all(&Self::rotated_tokens_dir(&session_id))
+                            .await?;
+                        inactive_session_ids.push(session_id);
+                    }
+                    None => inactive_session_ids.push(session_id),
+                }
+            }
+            if !inactive_session_ids.is_empty() {
+                let _guard = SESSION_LOCK.lock().await;
+                let mut session_ids = self.read_session_ids(user_id).await?;
+                session_ids.retain(|s| !inactive_session_ids.contains(s));
+                self.write_json(&Self::user_path(user_id), &session_ids)
+                    .await?;
+            }
+            Ok(sessions)
+        })
+    }
+
+    fn remove_session<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let Some(session) = self.read_session(session_id).await? else {
+                return Ok(false);
+            };
+            self.operator
+                .delete(&Self::session_path(session_id))
+                .await?;
+            self.operator
+                .remove_all(&Self::rotated_tokens_dir(session_id))
+                .await?;
+
+            let _guard = SESSION_LOCK.lock().await;
+            let user_id = session.user_id();
+            let mut session_ids = self.read_session_ids(user_id).await?;
+            session_ids.retain(|s| s != session_id);
+            self.write_json(&Self::user_path(user_id), &session_ids)
+                .await?;
+            Ok(true)
+        })
+    }
+
+    fn revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.insert_revoked_token(jwt_id, expires_at, true)
+                .await
+                .map(|_| ())
+        })
+    }
+
+    fn try_revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move { self.insert_revoked_token(jwt_id, expires_at, false).await })
+    }
+
+    fn list_revoked_tokens(&self) -> BoxFuture<'_, Result<Vec<(String, DateTime)>, Error>> {
+        Box::pin(async move {
+            let now = DateTime::now();
+            let revoked_tokens = self
+                .read_json::<Map>(&Self::revoked_tokens_path())
+                .await?
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|(jwt_id, value)| {
+                    let expires_at = value.as_str()?.parse::<DateTime>().ok()?;
+                    (expires_at > now).then_some((jwt_id, expires_at))
+                })
+                .collect();
+            Ok(revoked_tokens)
+        })
+    }
+}
@@ -0,0 +1,328 @@
+use super::GlobalAccessor;
+use crate::{
+    bail, datetime::DateTime, encoding::hex, error::Error, extension::JsonObjectExt,
+    file::NamedFile, warn, Map, Uuid,
+};
+use opendal::{ErrorKind, Operator};
+use sha1::{Digest, Sha1};
+
+/// Directory for the upload sessions in the storage service.
+const UPLOADS_DIR: &str = ".uploads";
+
+/// A resumable upload which accepts file chunks over several requests
+/// and assembles them in a storage service.
+///
+/// The state of the upload session is persisted in the storage service
+/// so that the chunks can be uploaded in any order and the upload can be resumed
+/// after a failure or a restart. Each chunk is stored as
+/// `.uploads/{upload_id}/{chunk_number}.part` with a manifest file
+/// `.uploads/{upload_id}/manifest.json`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::{accessor::ChunkedUpload, error::Error, file::NamedFile};
+///
+/// async fn upload_file(file: NamedFile) -> Result<(), Error> {
+///     let file_name = file.file_name().unwrap_or("unnamed");
+///     let chunks = file.split_chunks(5 * 1024 * 1024);
+///     let upload = ChunkedUpload::create("uploads", file_name, chunks.len()).await?;
+///     for mut chunk in chunks.into_iter().rev() {
+///         let checksum = format!("{:x}", chunk.checksum());
+///         chunk.set_extra_attribute("checksum", checksum);
+///         upload.upload_chunk(&chunk).await?;
+///     }
+///     assert!(upload.missing_chunks().await?.is_empty());
+///     upload.finalize(&format!("files/{file_name}")).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkedUpload {
+    /// Upload ID.
+    upload_id: String,
+    /// Operator for the storage service.
+    operator: &'static Operator,
+    /// Manifest of the upload session.
+    manifest: Map,
+}
+
+impl ChunkedUpload {
+    /// Creates a new upload session in the storage accessor with the given name.
+    /// Only the base name of the file is kept, so the file name can be used
+    /// as a path segment safely.
+    pub async fn create(
+        accessor_name: &str,
+        file_name: &str,
+        total_chunks: usize,
+    ) -> Result<Self, Error> {
+        if total_chunks == 0 {
+            bail!("the total number of chunks should be positive");
+        }
+
+        let file_name = sanitize_file_name(file_name)?;
+        let operator = Self::get_operator(accessor_name)?;
+        let upload_id = Uuid::now_v7().to_string();
+        let mut manifest = Map::new();
+        manifest.upsert("upload_id", upload_id.as_str());
+        manifest.upsert("accessor_name", accessor_name);
+        manifest.upsert("file_name", file_name);
+        manifest.upsert("total_chunks", total_chunks);
+        manifest.upsert("created_at", DateTime::now().to_string());
+
+        let upload = Self {
+            upload_id,
+            operator,
+            manifest,
+        };
+        upload.save_manifest().await?;
+        Ok(upload)
+    }
+
+    /// Opens an existing upload session in the storage accessor with the given name.
+    pub async fn open(accessor_name: &str, upload_id: &str) -> Result<Self, Error> {
+        let operator = Self::get_operator(accessor_name)?;
+        let upload_id = upload_id.parse::<Uuid>()?.to_string();
+        let manifest_path = format!("{UPLOADS_DIR}/{upload_id}/manifest.json");
+        let manifest = match operator.read(&manifest_path).await {
+            Ok(buffer) => serde_json::from_slice::<Map>(&buffer.to_vec())?,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                bail!("404 Not Found: the upload `{}` does not exist", upload_id);
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let file_name = manifest.get_str("file_name").unwrap_or_default();
+        if sanitize_file_name(file_name).ok() != Some(file_name) {
+            bail!("the file name of the upload `{}` is invalid", upload_id);
+        }
+        Ok(Self {
+            upload_id,
+            operator,
+            manifest,
+        })
+    }
+
+    /// Sets the checksum for the whole file.
+    /// It will be verified when the upload is finalized.
+    pub async fn set_checksum(&mut self, checksum: &str) -> Result<(), Error> {
+        self.manifest
+            .upsert("checksum", checksum.to_ascii_lowercase());
+        self.save_manifest().await
+    }
+
+    /// Returns the upload ID.
+    #[inline]
+    pub fn upload_id(&self) -> &str {
+        &self.upload_id
+    }
+
+    /// Returns the file name.
+    #[inline]
+    pub fn file_name(&self) -> &str {
+        self.manifest.get_str("file_name").unwrap_or_default()
+    }
+
+    /// Returns the total number of chunks.
+    #[inline]
+    pub fn total_chunks(&self) -> usize {
+        self.manifest.get_usize("total_chunks").unwrap_or_default()
+    }
+
+    /// Returns the manifest of the upload session.
+    #[inline]
+    pub fn manifest(&self) -> &Map {
+        &self.manifest
+    }
+
+    /// Uploads a chunk. The chunk number should be specified by the `chunk_number`
+    /// extra attribute of the file. If the `checksum` extra attribute is present,
+    /// it is compared with the hex-formatted SHA-1 digest of the chunk
+    /// before the chunk is persisted.
+    ///
+    /// Uploading the same chunk more than once is allowed and the last one wins.
+    pub async fn upload_chunk(&self, chunk: &NamedFile) -> Result<(), Error> {
+        let Some(chunk_number) = chunk.chunk_number() else {
+            bail!("the chunk number should be specified");
+        };
+        let total_chunks = self.total_chunks();
+        if chunk_number >= total_chunks {
+            bail!(
+                "the chunk number `{}` should be less than the total number of chunks `{}`",
+                chunk_number,
+                total_chunks
+            );
+        }
+        if chunk
+            .total_chunks()
+            .is_some_and(|num_chunks| num_chunks != total_chunks)
+        {
+            bail!("the total number of chunks does not match the upload");
+        }
+        if let Some(checksum) = chunk.extra().get_str("checksum") {
+            let chunk_checksum = hex::encode(chunk.checksum());
+            if !chunk_checksum.eq_ignore_ascii_case(checksum) {
+                bail!(
+                    "409 Conflict: the checksum of the chunk `{}` does not match",
+                    chunk_number
+                );
+            }
+        }
+        self.operator
+            .write(&self.chunk_path(chunk_number), chunk)
+            .await?;
+        Ok(())
+    }
+
+    /// Returns a list of the chunk numbers which have not been uploaded.
+    pub async fn missing_chunks(&self) -> Result<Vec<usize>, Error> {
+        let mut missing_chunks = Vec::new();
+        for chunk_number in 0..self.total_chunks() {
+            if !self.chunk_exists(chunk_number).await? {
+                missing_chunks.push(chunk_number);
+            }
+        }
+        Ok(missing_chunks)
+    }
+
+    /// Assembles the chunks into the file at the path and removes the upload session.
+    /// It returns the metadata of the file.
+    pub async fn finalize(&self, path: &str) -> Result<Map, Error> {
+        let missing_chunks = self.missing_chunks().await?;
+        if !missing_chunks.is_empty() {
+            bail!(
+                "409 Conflict: the upload is incomplete with missing chunks `{:?}`",
+                missing_chunks
+            );
+        }
+
+        let mut hasher = Sha1::new();
+        let mut file_size = 0;
+        let mut writer = self.operator.writer(path).await?;
+        for chunk_number in 0..self.total_chunks() {
+            let buffer = self.operator.read(&self.chunk_path(chunk_number)).await?;
+            let bytes = buffer.to_bytes();
+            hasher.update(&bytes);
+            file_size += bytes.len();
+            writer.write(bytes).await?;
+        }
+
+        let checksum = hex::encode(hasher.finalize());
+        if self
+            .manifest
+            .get_str("checksum")
+            .is_some_and(|s| s != checksum)
+        {
+            writer.abort().await?;
+            bail!("409 Conflict: the checksum of the file does not match");
+        }
+        writer.close().await?;
+        self.abort().await?;
+
+        let mut metadata = Map::new();
+        metadata.upsert("upload_id", self.upload_id());
+        metadata.upsert("file_name", self.file_name());
+        metadata.upsert("file_size", file_size);
+        metadata.upsert("checksum", checksum);
+        metadata.upsert("path", path);
+        Ok(metadata)
+    }
+
+    /// Aborts the upload session by removing the manifest and the uploaded chunks.
+    pub async fn abort(&self) -> Result<(), Error> {
+        let upload_dir = format!("{UPLOADS_DIR}/{}/", self.upload_id);
+        self.operator.remove_all(&upload_dir).await?;
+        Ok(())
+    }
+
+    /// Gets the operator for the storage accessor.
+    fn get_operator(accessor_name: &str) -> Result<&'static Operator, Error> {
+        GlobalAccessor::get(accessor_name)
+            .ok_or_else(|| warn!("storage accessor `{}` is not available", accessor_name))
+    }
+
+    /// Returns the path of the chunk.
+    fn chunk_path(&self, chunk_number: usize) -> String {
+        format!("{UPLOADS_DIR}/{}/{chunk_number}.part", self.upload_id)
+    }
+
+    /// Returns `true` if the chunk has been uploaded.
+    async fn chunk_exists(&self, chunk_number: usize) -> Result<bool, Error> {
+        match self.operator.stat(&self.chunk_path(chunk_number)).await {
+            Ok(_) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Saves the manifest of the upload session.
+    async fn save_manifest(&self) -> Result<(), Error> {
+        let manifest_path = format!("{UPLOADS_DIR}/{}/manifest.json", self.upload_id);
+        let manifest = serde_json::to_vec(&self.manifest)?;
+        self.operator.write(&manifest_path, manifest).await?;
+        Ok(())
+    }
+}
+
+/// Returns the base name of the file, which does not contain any path separators
+/// or control characters.
+fn sanitize_file_name(file_name: &str) -> Result<&str, Error> {
+    let file_name = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    if matches!(file_name, "" | "." | "..") || file_name.chars().any(|c| c.is_control()) {
+        bail!("400 Bad Request: the file name `{}` is invalid", file_name);
+    }
+    Ok(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize_file_name;
+
+    #[test]
+    fn it_sanitizes_file_names() {
+        assert_eq!(sanitize_file_name("report.pdf").unwrap(), "report.pdf");
+        assert_eq!(sanitize_file_name("../../etc/passwd").unwrap(), "passwd");
+        assert_eq!(sanitize_file_name("C:\\temp\\a.txt").unwrap(), "a.txt");
+        assert!(sanitize_file_name("..").is_err());
+        assert!(sanitize_file_name("files/").is_err());
+        assert!(sanitize_file_name("a\nb").is_err());
+    }
+
+    #[cfg(feature = "accessor-memory")]
+    #[test]
+    fn it_assembles_chunks() {
+        use super::ChunkedUpload;
+        use crate::{extension::JsonObjectExt, file::NamedFile, Map};
+        use opendal::{services::Memory, Operator};
+
+        let operator = Operator::new(Memory::default()).unwrap().finish();
+        let mut manifest = Map::new();
+        manifest.upsert("file_name", "hello.txt");
+        manifest.upsert("total_chunks", 2);
+
+        let upload = ChunkedUpload {
+            upload_id: "0193a7b0-6f1e-7c3a-9d3e-5b8e2f1c4a60".to_owned(),
+            operator: Box::leak(Box::new(operator)),
+            manifest,
+        };
+        let mut file = NamedFile::new("hello.txt");
+        file.set_bytes("hello world");
+        futures::executor::block_on(async {
+            let chunks = file.split_chunks(6);
+            assert_eq!(upload.missing_chunks().await.unwrap(), vec![0, 1]);
+            upload.upload_chunk(&chunks[1]).await.unwrap();
+            assert_eq!(upload.missing_chunks().await.unwrap(), vec![0]);
+            assert!(upload.finalize("files/hello.txt").await.is_err());
+
+            upload.upload_chunk(&chunks[0]).await.unwrap();
+            let metadata = upload.finalize("files/hello.txt").await.unwrap();
+            assert_eq!(metadata.get_usize("file_size"), Some(11));
+
+            let buffer = upload.operator.read("files/hello.txt").await.unwrap();
+            assert_eq!(buffer.to_vec(), b"hello world");
+        });
+    }
+}
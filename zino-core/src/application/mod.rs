@@ -62,6 +62,7 @@ use utoipa::openapi::{OpenApi, OpenApiBuilder};
 mod plugin;
 mod secret_key;
 mod server_tag;
+mod shutdown;
 mod static_record;
 
 #[cfg(feature = "metrics")]
@@ -82,6 +83,7 @@ pub(crate) use secret_key::SECRET_KEY;
 
 pub use plugin::Plugin;
 pub use server_tag::ServerTag;
+pub use shutdown::{DrainGuard, ShutdownPhase, ShutdownToken};
 pub use static_record::StaticRecord;
 
 /// Application interfaces.
@@ -93,6 +95,8 @@ pub trait Application {
     fn register(self, routes: Self::Routes) -> Self;
 
     /// Runs the application with an optional scheduler for async jobs.
+    /// Multiple schedulers can be combined as a tuple, such as
+    /// `(AsyncJobScheduler, &'static JobQueue)`.
     fn run_with<T: AsyncScheduler + Send + 'static>(self, scheduler: T);
 
     /// Boots the application with the default initialization.
@@ -289,15 +293,25 @@ pub trait Application {
         join_path(&PROJECT_DIR, path)
     }
 
-    /// Spawns a new thread to run cron jobs.
+    /// Spawns a new thread to run cron jobs until the graceful shutdown has been started.
     fn spawn<T>(self, mut scheduler: T) -> Self
     where
         Self: Sized,
         T: Scheduler + Send + 'static,
     {
-        thread::spawn(move || loop {
-            scheduler.tick();
-            thread::sleep(scheduler.time_till_next_job());
+        thread::spawn(move || {
+            let shutdown_token = ShutdownToken::shared();
+            loop {
+                // The tick is tracked before checking the token, so that it can not
+                // be started after the in-flight work has been drained.
+                let guard = shutdown_token.track();
+                if shutdown_token.is_shutting_down() {
+                    break;
+                }
+                scheduler.tick();
+                drop(guard);
+                thread::sleep(scheduler.time_till_next_job());
+            }
         });
         self
     }
@@ -320,11 +334,12 @@ pub trait Application {
         crate::orm::GlobalPool::connect_all().await;
     }
 
-    /// Handles the graceful shutdown.
+    /// Handles the graceful shutdown after the in-flight work has been drained.
     #[inline]
     async fn shutdown() {
         #[cfg(feature = "orm")]
         crate::orm::GlobalPool::close_all().await;
+        ShutdownToken::shared().terminate();
     }
 
     /// Makes an HTTP request to the provided URL.
@@ -0,0 +1,382 @@
+use crate::{extension::TomlTableExt, state::State};
+use parking_lot::Mutex;
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    mem,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering::*},
+        OnceLock,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+use toml::Table;
+
+/// Phase of the application lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShutdownPhase {
+    /// The application is running and ready to serve.
+    Running,
+    /// The application is not ready and the in-flight work is being drained.
+    Draining,
+    /// The application has been terminated.
+    Terminated,
+}
+
+impl ShutdownPhase {
+    /// Returns the phase as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "Running",
+            Self::Draining => "Draining",
+            Self::Terminated => "Terminated",
+        }
+    }
+
+    /// Converts the phase from a `u8`.
+    #[inline]
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Running,
+            1 => Self::Draining,
+            _ => Self::Terminated,
+        }
+    }
+}
+
+impl fmt::Display for ShutdownPhase {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A framework-wide token for the graceful shutdown.
+///
+/// When a shutdown signal is received, the application flips the readiness to "not ready",
+/// waits for the readiness delay so that the load balancers can stop routing new requests,
+/// then stops accepting connections and drains the in-flight work within the drain timeout.
+/// Cron jobs, queue workers, streams and plugins can observe the token
+/// by [`cancelled()`](Self::cancelled) or [`is_shutting_down()`](Self::is_shutting_down),
+/// and keep the shutdown waiting by holding a guard returned by [`track()`](Self::track).
+///
+/// # Examples
+///
+/// ```toml
+/// [shutdown]
+/// readiness-delay = "5s"
+/// drain-timeout = "30s"
+/// ```
+///
+/// ```rust,ignore
+/// use futures::StreamExt;
+/// use zino_core::application::ShutdownToken;
+///
+/// let token = ShutdownToken::shared();
+/// let ticks = ticker.take_until(token.cancelled());
+/// ```
+pub struct ShutdownToken {
+    /// Current phase.
+    phase: AtomicU8,
+    /// Number of the in-flight tasks.
+    in_flight: AtomicUsize,
+    /// Wakers of the pending waiters.
+    wakers: Mutex<HashMap<usize, Waker>>,
+    /// Key for the next waiter.
+    next_key: AtomicUsize,
+    /// Delay between flipping the readiness and draining.
+    readiness_delay: Duration,
+    /// Timeout for draining the in-flight work.
+    drain_timeout: Duration,
+    /// Deadline for the shutdown.
+    deadline: OnceLock<Instant>,
+}
+
+impl ShutdownToken {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            phase: AtomicU8::new(0),
+            in_flight: AtomicUsize::new(0),
+            wakers: Mutex::new(HashMap::new()),
+            next_key: AtomicUsize::new(0),
+            readiness_delay: Duration::ZERO,
+            drain_timeout: Duration::from_secs(30),
+            deadline: OnceLock::new(),
+        }
+    }
+
+    /// Creates a new instance with the configuration.
+    pub fn with_config(config: &Table) -> Self {
+        let mut token = Self::new();
+        if let Some(delay) = config.get_duration("readiness-delay") {
+            token.readiness_delay = delay;
+        }
+        if let Some(timeout) = config.get_duration("drain-timeout") {
+            token.drain_timeout = timeout;
+        }
+        token
+    }
+
+    /// Returns the shared shutdown token, which is configured by the `shutdown` table.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        SHARED_SHUTDOWN_TOKEN.get_or_init(|| {
+            if let Some(config) = State::shared().get_config("shutdown") {
+                Self::with_config(config)
+            } else {
+                Self::new()
+            }
+        })
+    }
+
+    /// Returns the current phase.
+    #[inline]
+    pub fn phase(&self) -> ShutdownPhase {
+        ShutdownPhase::from_u8(self.phase.load(Acquire))
+    }
+
+    /// Returns `true` if the application is ready to serve.
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.phase() == ShutdownPhase::Running
+    }
+
+    /// Returns `true` if the shutdown has been started.
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.phase() != ShutdownPhase::Running
+    }
+
+    /// Returns the delay between flipping the readiness and draining.
+    #[inline]
+    pub fn readiness_delay(&self) -> Duration {
+        self.readiness_delay
+    }
+
+    /// Returns the timeout for draining the in-flight work.
+    #[inline]
+    pub fn drain_timeout(&self) -> Duration {
+        self.drain_timeout
+    }
+
+    /// Returns the time left before the deadline of the shutdown, which is the readiness delay
+    /// plus the drain timeout after the shutdown has been started. Stopping the servers
+    /// and draining the in-flight tasks should share the deadline.
+    pub fn time_till_deadline(&self) -> Duration {
+        match self.deadline.get() {
+            Some(deadline) => deadline.saturating_duration_since(Instant::now()),
+            None => self.readiness_delay + self.drain_timeout,
+        }
+    }
+
+    /// Returns the number of the in-flight tasks.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Acquire)
+    }
+
+    /// Starts the shutdown by flipping the readiness to "not ready".
+    /// It returns `false` if the shutdown has already been started.
+    pub fn shutdown(&self) -> bool {
+        let started = self.phase.compare_exchange(0, 1, AcqRel, Acquire).is_ok();
+        if started {
+            let deadline = Instant::now() + self.readiness_delay + self.drain_timeout;
+            self.deadline.get_or_init(|| deadline);
+            tracing::warn!(
+                in_flight = self.in_flight(),
+                "start draining the application"
+            );
+            self.wake_all();
+        }
+        started
+    }
+
+    /// Marks the application as terminated.
+    pub fn terminate(&self) {
+        if self.phase.swap(2, AcqRel) != 2 {
+            let in_flight = self.in_flight();
+            if in_flight > 0 {
+                tracing::warn!(in_flight, "terminate the application with in-flight tasks");
+            }
+            self.wake_all();
+        }
+    }
+
+    /// Tracks an in-flight task until the returned guard is dropped.
+    #[inline]
+    pub fn track(&self) -> DrainGuard<'_> {
+        self.in_flight.fetch_add(1, AcqRel);
+        DrainGuard { token: self }
+    }
+
+    /// Waits until the shutdown has been started.
+    #[inline]
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + '_ {
+        Waiter::new(self, Self::is_shutting_down)
+    }
+
+    /// Waits until the application has been terminated.
+    #[inline]
+    pub fn terminated(&self) -> impl Future<Output = ()> + Send + '_ {
+        Waiter::new(self, |token| token.phase() == ShutdownPhase::Terminated)
+    }
+
+    /// Waits until all the in-flight tasks have finished.
+    #[inline]
+    pub fn drained(&self) -> impl Future<Output = ()> + Send + '_ {
+        Waiter::new(self, |token| token.in_flight() == 0)
+    }
+
+    /// Runs the future until it completes or the shutdown has been started.
+    /// It returns `None` if the future has been cancelled.
+    pub async fn run_until_cancelled<F: Future>(&self, fut: F) -> Option<F::Output> {
+        let fut = pin!(fut);
+        let cancelled = pin!(self.cancelled());
+        match futures::future::select(fut, cancelled).await {
+            futures::future::Either::Left((output, _)) => Some(output),
+            futures::future::Either::Right(_) => None,
+        }
+    }
+
+    /// Wakes all the pending waiters.
+    fn wake_all(&self) {
+        let wakers = mem::take(&mut *self.wakers.lock());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for ShutdownToken {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ShutdownToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShutdownToken")
+            .field("phase", &self.phase())
+            .field("in_flight", &self.in_flight())
+            .field("readiness_delay", &self.readiness_delay)
+            .field("drain_timeout", &self.drain_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A guard for an in-flight task, which is finished when the guard is dropped.
+#[derive(Debug)]
+#[must_use = "the task is finished immediately if the guard is unused"]
+pub struct DrainGuard<'a> {
+    /// Shutdown token.
+    token: &'a ShutdownToken,
+}
+
+impl Drop for DrainGuard<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if self.token.in_flight.fetch_sub(1, AcqRel) == 1 {
+            self.token.wake_all();
+        }
+    }
+}
+
+/// A future which waits until the condition of the shutdown token is satisfied.
+struct Waiter<'a> {
+    /// Shutdown token.
+    token: &'a ShutdownToken,
+    /// Condition to wait for.
+    condition: fn(&ShutdownToken) -> bool,
+    /// Key of the registered waker.
+    key: Option<usize>,
+}
+
+impl<'a> Waiter<'a> {
+    /// Creates a new instance.
+    #[inline]
+    fn new(token: &'a ShutdownToken, condition: fn(&ShutdownToken) -> bool) -> Self {
+        Self {
+            token,
+            condition,
+            key: None,
+        }
+    }
+}
+
+impl Future for Waiter<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let token = self.token;
+        if (self.condition)(token) {
+            return Poll::Ready(());
+        }
+
+        // Checks the condition again with the lock held to avoid a lost wakeup.
+        let mut wakers = token.wakers.lock();
+        if (self.condition)(token) {
+            return Poll::Ready(());
+        }
+
+        let key = *self
+            .key
+            .get_or_insert_with(|| token.next_key.fetch_add(1, Relaxed));
+        wakers.insert(key, cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for Waiter<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.wakers.lock().remove(&key);
+        }
+    }
+}
+
+/// Shared shutdown token.
+static SHARED_SHUTDOWN_TOKEN: OnceLock<ShutdownToken> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{ShutdownPhase, ShutdownToken};
+    use futures::{executor::block_on, future::FutureExt};
+    use std::time::Duration;
+
+    #[test]
+    fn it_drains_in_flight_tasks() {
+        let token = ShutdownToken::new();
+        assert!(token.is_ready());
+        assert!(token.cancelled().now_or_never().is_none());
+
+        let guard = token.track();
+        assert_eq!(token.in_flight(), 1);
+        assert!(token.shutdown());
+        assert!(!token.shutdown());
+        assert!(!token.is_ready());
+        assert_eq!(token.phase(), ShutdownPhase::Draining);
+        assert!(token.cancelled().now_or_never().is_some());
+        assert!(token.drained().now_or_never().is_none());
+        assert!(block_on(token.run_until_cancelled(async { 1 })).is_some());
+
+        let time_till_deadline = token.time_till_deadline();
+        assert!(time_till_deadline > Duration::ZERO);
+        assert!(time_till_deadline <= token.drain_timeout());
+
+        drop(guard);
+        assert!(token.drained().now_or_never().is_some());
+        assert!(token.terminated().now_or_never().is_none());
+        token.terminate();
+        assert_eq!(token.phase(), ShutdownPhase::Terminated);
+        assert!(token.terminated().now_or_never().is_some());
+    }
+}
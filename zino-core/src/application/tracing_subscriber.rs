@@ -1,14 +1,14 @@
 use super::Application;
-use crate::extension::TomlTableExt;
+use crate::{extension::TomlTableExt, model::MaskingPolicy};
 use std::{fs, io, sync::OnceLock, time::Duration};
-use tracing::Level;
+use tracing::{Level, Metadata};
 use tracing_appender::{
     non_blocking::WorkerGuard,
     rolling::{RollingFileAppender, Rotation},
 };
 use tracing_subscriber::{
     filter::LevelFilter,
-    fmt::{time::OffsetTime, writer::MakeWriterExt},
+    fmt::{time::OffsetTime, writer::MakeWriterExt, MakeWriter},
     layer::SubscriberExt,
 };
 
@@ -128,6 +128,11 @@ pub(super) fn init<APP: Application + ?Sized>() {
 
     // Format layer
     let stdout = io::stdout.with_max_level(stdout_max_level);
+    let masking_policy = MaskingPolicy::shared();
+    let writer = MaskingMakeWriter {
+        inner: stdout.and(non_blocking_appender),
+        policy: masking_policy.masks_log().then_some(masking_policy),
+    };
     let fmt_layer = tracing_subscriber::fmt::layer()
         .with_ansi(ansi_terminal)
         .with_target(display_target)
@@ -136,7 +141,7 @@ pub(super) fn init<APP: Application + ?Sized>() {
         .with_thread_ids(display_thread_ids)
         .with_thread_names(display_thread_names)
         .with_timer(local_offset_time)
-        .with_writer(stdout.and(non_blocking_appender));
+        .with_writer(writer);
 
     // Optional layers
     #[cfg(feature = "env-filter")]
@@ -204,3 +209,57 @@ pub(super) fn init<APP: Application + ?Sized>() {
 
 /// Tracing appender guard.
 static TRACING_APPENDER_GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+
+/// A writer maker which masks the sensitive values written into the logs.
+struct MaskingMakeWriter<M> {
+    /// Inner writer maker.
+    inner: M,
+    /// Masking policy.
+    policy: Option<&'static MaskingPolicy>,
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for MaskingMakeWriter<M> {
+    type Writer = MaskingWriter<M::Writer>;
+
+    #[inline]
+    fn make_writer(&'a self) -> Self::Writer {
+        MaskingWriter {
+            inner: self.inner.make_writer(),
+            policy: self.policy,
+        }
+    }
+
+    #[inline]
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        MaskingWriter {
+            inner: self.inner.make_writer_for(meta),
+            policy: self.policy,
+        }
+    }
+}
+
+/// A writer which masks the sensitive values written into the logs.
+struct MaskingWriter<W> {
+    /// Inner writer.
+    inner: W,
+    /// Masking policy.
+    policy: Option<&'static MaskingPolicy>,
+}
+
+impl<W: io::Write> io::Write for MaskingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(policy) = self.policy {
+            if let Ok(message) = std::str::from_utf8(buf) {
+                let message = policy.mask_log(message);
+                self.inner.write_all(message.as_bytes())?;
+                return Ok(buf.len());
+            }
+        }
+        self.inner.write(buf)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
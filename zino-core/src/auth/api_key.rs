@@ -0,0 +1,652 @@
+use super::{AccessKeyId, SecretAccessKey};
+use crate::{
+    bail,
+    crypto::{self, Digest},
+    datetime::DateTime,
+    encoding::{base64, hex},
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    BoxFuture, Map,
+};
+use hmac::Hmac;
+use parking_lot::RwLock;
+use std::{collections::HashMap, net::IpAddr, sync::OnceLock, time::Duration};
+
+/// Record of an API key for a machine client.
+///
+/// The secret access key is generated randomly, and only the digest is stored,
+/// so the secret can not be recovered from the record.
+/// The secret is returned once when the key is created or rotated.
+/// A request signed by [`Authentication`](super::Authentication) is validated with
+/// the signing key derived from the digest, see [`ApiKeyRecord::derive_signing_key()`],
+/// so the record should still be kept private.
+///
+/// The scopes are of the form `{resource}:{action}`, where both parts can be
+/// the wildcard `*`. The IP allowlist consists of IP addresses or CIDR blocks,
+/// and an empty allowlist allows any IP address.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeyRecord {
+    /// Access key ID.
+    access_key_id: String,
+    /// Name.
+    name: String,
+    /// Owner ID.
+    owner_id: String,
+    /// Status.
+    status: String,
+    /// Scopes.
+    scopes: Vec<String>,
+    /// IP allowlist.
+    ip_allowlist: Vec<String>,
+    /// Digest of the secret access key.
+    secret_digest: String,
+    /// Creation time.
+    created_at: DateTime,
+    /// Rotation time.
+    rotated_at: DateTime,
+    /// Expiration time.
+    expires_at: Option<DateTime>,
+    /// Last-used time.
+    last_used_at: Option<DateTime>,
+    /// Last-used IP address.
+    last_used_ip: String,
+}
+
+impl ApiKeyRecord {
+    /// Creates a new instance for the owner. A secret access key is generated
+    /// when the record is created by the [`ApiKeyManager`].
+    pub fn new(owner_id: impl ToString, name: impl ToString) -> Self {
+        let now = DateTime::now();
+        Self {
+            access_key_id: AccessKeyId::new().to_string(),
+            name: name.to_string(),
+            owner_id: owner_id.to_string(),
+            status: "Active".to_owned(),
+            created_at: now,
+            rotated_at: now,
+            ..Self::default()
+        }
+    }
+
+    /// Attempts to create a new instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(access_key_id) = map.get_str("access_key_id") else {
+            bail!("the `access_key_id` field should be specified");
+        };
+        let parse_datetime = |key| -> Result<Option<DateTime>, Error> {
+            match map.get_str(key).filter(|s| !s.is_empty()) {
+                Some(value) => Ok(Some(value.parse()?)),
+                None => Ok(None),
+            }
+        };
+        let parse_array = |key| -> Vec<String> {
+            map.get_str_array(key)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| s.to_owned())
+                .collect()
+        };
+        Ok(Self {
+            access_key_id: access_key_id.to_owned(),
+            name: map.get_str("name").unwrap_or_default().to_owned(),
+            owner_id: map.get_str("owner_id").unwrap_or_default().to_owned(),
+            status: map.get_str("status").unwrap_or("Active").to_owned(),
+            scopes: parse_array("scopes"),
+            ip_allowlist: parse_array("ip_allowlist"),
+            secret_digest: map.get_str("secret_digest").unwrap_or_default().to_owned(),
+            created_at: parse_datetime("created_at")?.unwrap_or_default(),
+            rotated_at: parse_datetime("rotated_at")?.unwrap_or_default(),
+            expires_at: parse_datetime("expires_at")?,
+            last_used_at: parse_datetime("last_used_at")?,
+            last_used_ip: map.get_str("last_used_ip").unwrap_or_default().to_owned(),
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("access_key_id", self.access_key_id);
+        map.upsert("name", self.name);
+        map.upsert("owner_id", self.owner_id);
+        map.upsert("status", self.status);
+        map.upsert("scopes", self.scopes);
+        map.upsert("ip_allowlist", self.ip_allowlist);
+        map.upsert("secret_digest", self.secret_digest);
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("rotated_at", self.rotated_at.to_string());
+        map.upsert("expires_at", self.expires_at.map(|dt| dt.to_string()));
+        map.upsert("last_used_at", self.last_used_at.map(|dt| dt.to_string()));
+        map.upsert("last_used_ip", self.last_used_ip);
+        map
+    }
+
+    /// Sets the scopes.
+    #[inline]
+    pub fn set_scopes(&mut self, scopes: Vec<String>) {
+        self.scopes = scopes;
+    }
+
+    /// Sets the IP allowlist.
+    pub fn set_ip_allowlist(&mut self, ip_allowlist: Vec<String>) -> Result<(), Error> {
+        for entry in &ip_allowlist {
+            if parse_ip_block(entry).is_none() {
+                bail!("invalid IP address or CIDR block `{}`", entry);
+            }
+        }
+        self.ip_allowlist = ip_allowlist;
+        Ok(())
+    }
+
+    /// Sets the expiration time.
+    #[inline]
+    pub fn set_expires_at(&mut self, expires_at: Option<DateTime>) {
+        self.expires_at = expires_at;
+    }
+
+    /// Sets the key to expire in `max-age`.
+    #[inline]
+    pub fn set_max_age(&mut self, max_age: Duration) {
+        self.expires_at = Some(DateTime::now() + max_age);
+    }
+
+    /// Generates a new random secret access key, which invalidates the previous one.
+    /// Only the digest of the secret is kept in the record.
+    pub fn rotate(&mut self) -> String {
+        let secret_access_key = base64::encode(rand::random::<[u8; 32]>());
+        self.secret_digest = hex::encode(crypto::digest(secret_access_key.as_bytes()));
+        self.rotated_at = DateTime::now();
+        secret_access_key
+    }
+
+    /// Revokes the key.
+    #[inline]
+    pub fn revoke(&mut self) {
+        self.status = "Revoked".to_owned();
+    }
+
+    /// Records the usage of the key.
+    #[inline]
+    pub fn touch(&mut self, ip: Option<IpAddr>) {
+        self.last_used_at = Some(DateTime::now());
+        self.last_used_ip = ip.map(|ip| ip.to_string()).unwrap_or_default();
+    }
+
+    /// Returns the key derived from the digest of the secret access key,
+    /// which is used to validate the signature of a request.
+    #[inline]
+    pub fn signing_key(&self) -> SecretAccessKey {
+        let access_key_id = AccessKeyId::from(self.access_key_id.as_str());
+        SecretAccessKey::with_key::<Hmac<Digest>>(&access_key_id, &self.secret_digest)
+    }
+
+    /// Derives the signing key from the access key ID and the secret access key,
+    /// which should be used by a client to sign the requests.
+    pub fn derive_signing_key(access_key_id: &str, secret_access_key: &str) -> SecretAccessKey {
+        let access_key_id = AccessKeyId::from(access_key_id);
+        let digest = hex::encode(crypto::digest(secret_access_key.as_bytes()));
+        SecretAccessKey::with_key::<Hmac<Digest>>(&access_key_id, digest)
+    }
+
+    /// Returns `true` if the secret access key matches the digest.
+    /// The digests are compared in constant time.
+    pub fn verify_secret(&self, secret_access_key: &str) -> bool {
+        let digest = hex::encode(crypto::digest(secret_access_key.as_bytes()));
+        !self.secret_digest.is_empty()
+            && crypto::constant_time_eq(digest.as_bytes(), self.secret_digest.as_bytes())
+    }
+
+    /// Returns `true` if the key has not been revoked and has not expired.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.status == "Active" && !self.is_expired()
+    }
+
+    /// Returns `true` if the key has expired.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= DateTime::now())
+    }
+
+    /// Returns `true` if one of the scopes allows the action on the resource.
+    pub fn allows(&self, resource: &str, action: &str) -> bool {
+        self.scopes.iter().any(|scope| {
+            let (scope_resource, scope_action) = scope.split_once(':').unwrap_or((scope, "*"));
+            (scope_resource == "*" || scope_resource == resource)
+                && (scope_action == "*" || scope_action == action)
+        })
+    }
+
+    /// Returns `true` if the IP address is in the allowlist.
+    pub fn allows_ip(&self, ip: Option<IpAddr>) -> bool {
+        if self.ip_allowlist.is_empty() {
+            return true;
+        }
+        ip.is_some_and(|ip| {
+            self.ip_allowlist
+                .iter()
+                .filter_map(|entry| parse_ip_block(entry))
+                .any(|(network, prefix)| ip_in_block(ip, network, prefix))
+        })
+    }
+
+    /// Returns the access key ID.
+    #[inline]
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    /// Returns the name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the owner ID.
+    #[inline]
+    pub fn owner_id(&self) -> &str {
+        &self.owner_id
+    }
+
+    /// Returns the status.
+    #[inline]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    /// Returns the scopes.
+    #[inline]
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Returns the IP allowlist.
+    #[inline]
+    pub fn ip_allowlist(&self) -> &[String] {
+        &self.ip_allowlist
+    }
+
+    /// Returns the digest of the secret access key.
+    #[inline]
+    pub fn secret_digest(&self) -> &str {
+        &self.secret_digest
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    /// Returns the rotation time.
+    #[inline]
+    pub fn rotated_at(&self) -> DateTime {
+        self.rotated_at
+    }
+
+    /// Returns the expiration time.
+    #[inline]
+    pub fn expires_at(&self) -> Option<DateTime> {
+        self.expires_at
+    }
+
+    /// Returns the last-used time.
+    #[inline]
+    pub fn last_used_at(&self) -> Option<DateTime> {
+        self.last_used_at
+    }
+
+    /// Returns the last-used IP address.
+    #[inline]
+    pub fn last_used_ip(&self) -> &str {
+        &self.last_used_ip
+    }
+}
+
+/// Storage backend for the API keys.
+pub trait ApiKeyStore: Send + Sync {
+    /// Saves the API key.
+    fn save_api_key<'a>(&'a self, api_key: &'a ApiKeyRecord) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Finds the API key by the access key ID.
+    fn find_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<ApiKeyRecord>, Error>>;
+
+    /// Updates the last-used time and IP address of the API key only,
+    /// so that a concurrent rotation or revocation is not overwritten.
+    fn touch_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+        last_used_at: DateTime,
+        last_used_ip: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Lists the API keys of the owner.
+    fn list_api_keys<'a>(
+        &'a self,
+        owner_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<ApiKeyRecord>, Error>>;
+}
+
+/// In-memory API key store, which is only suitable for testing.
+#[derive(Debug, Default)]
+pub struct MemoryApiKeyStore {
+    /// API keys.
+    api_keys: RwLock<HashMap<String, ApiKeyRecord>>,
+}
+
+impl MemoryApiKeyStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+    fn save_api_key<'a>(&'a self, api_key: &'a ApiKeyRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.api_keys
+                .write()
+                .insert(api_key.access_key_id.clone(), api_key.clone());
+            Ok(())
+        })
+    }
+
+    fn find_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<ApiKeyRecord>, Error>> {
+        Box::pin(async move { Ok(self.api_keys.read().get(access_key_id).cloned()) })
+    }
+
+    fn touch_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+        last_used_at: DateTime,
+        last_used_ip: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(api_key) = self.api_keys.write().get_mut(access_key_id) {
+                api_key.last_used_at = Some(last_used_at);
+                api_key.last_used_ip = last_used_ip.to_owned();
+            }
+            Ok(())
+        })
+    }
+
+    fn list_api_keys<'a>(
+        &'a self,
+        owner_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<ApiKeyRecord>, Error>> {
+        Box::pin(async move {
+            let api_keys = self
+                .api_keys
+                .read()
+                .values()
+                .filter(|api_key| api_key.owner_id == owner_id)
+                .cloned()
+                .collect();
+            Ok(api_keys)
+        })
+    }
+}
+
+/// Manager of the API keys for machine clients.
+///
+/// It supports creating, listing, rotating, revoking and expiring the API keys
+/// in an [`ApiKeyStore`]. A request is authenticated by
+/// `RequestContext::verify_api_key()` with either a signature generated by
+/// [`Authentication`](super::Authentication), or the `x-api-key` header
+/// of the form `{access_key_id}:{secret_access_key}`.
+///
+/// The shared manager can be configured by the `api-key` table with the `memory` store,
+/// or registered by [`ApiKeyManager::register()`] before the first use.
+///
+/// # Examples
+///
+/// ```toml
+/// [api-key]
+/// store = "memory"
+/// max-age = "90d"
+/// ```
+pub struct ApiKeyManager {
+    /// API key store.
+    store: Box<dyn ApiKeyStore>,
+    /// Default max age of the API keys.
+    max_age: Option<Duration>,
+    /// Minimum interval between two updates of the last-used time.
+    touch_interval: Duration,
+}
+
+impl ApiKeyManager {
+    /// Creates a new instance with the API key store.
+    #[inline]
+    pub fn new(store: impl ApiKeyStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            max_age: None,
+            touch_interval: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets the default max age of the API keys.
+    #[inline]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Registers the shared API key manager. It returns `false` if the shared manager
+    /// has already been initialized.
+    #[inline]
+    pub fn register(manager: Self) -> bool {
+        SHARED_API_KEY_MANAGER.set(Some(manager)).is_ok()
+    }
+
+    /// Returns a reference to the shared API key manager if it has been configured.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_API_KEY_MANAGER
+            .get_or_init(Self::from_config)
+            .as_ref()
+    }
+
+    /// Creates the API key and returns it with the secret access key,
+    /// which should be shown to the client only once.
+    pub async fn create_api_key(
+        &self,
+        mut api_key: ApiKeyRecord,
+    ) -> Result<(ApiKeyRecord, String), Error> {
+        if api_key.expires_at.is_none() {
+            if let Some(max_age) = self.max_age {
+                api_key.set_max_age(max_age);
+            }
+        }
+
+        let secret_access_key = api_key.rotate();
+        self.store.save_api_key(&api_key).await?;
+        Ok((api_key, secret_access_key))
+    }
+
+    /// Finds the API key by the access key ID.
+    #[inline]
+    pub async fn find_api_key(&self, access_key_id: &str) -> Result<Option<ApiKeyRecord>, Error> {
+        self.store.find_api_key(access_key_id).await
+    }
+
+    /// Lists the API keys of the owner, sorted by the creation time in descending order.
+    pub async fn list_api_keys(&self, owner_id: &str) -> Result<Vec<ApiKeyRecord>, Error> {
+        let mut api_keys = self.store.list_api_keys(owner_id).await?;
+        api_keys.sort_by_key(|api_key| std::cmp::Reverse(api_key.created_at));
+        Ok(api_keys)
+    }
+
+    /// Rotates the secret access key of the active API key and returns the new one.
+    pub async fn rotate_api_key(&self, access_key_id: &str) -> Result<String, Error> {
+        let Some(mut api_key) = self.find_api_key(access_key_id).await? else {
+            bail!(
+                "404 Not Found: the API key `{}` does not exist",
+                access_key_id
+            );
+        };
+        if !api_key.is_active() {
+            bail!("409 Conflict: the API key `{}` is inactive", access_key_id);
+        }
+
+        let secret_access_key = api_key.rotate();
+        self.store.save_api_key(&api_key).await?;
+        Ok(secret_access_key)
+    }
+
+    /// Revokes the API key. It returns `false` if the key does not exist
+    /// or has been revoked.
+    pub async fn revoke_api_key(&self, access_key_id: &str) -> Result<bool, Error> {
+        let Some(mut api_key) = self.find_api_key(access_key_id).await? else {
+            return Ok(false);
+        };
+        if api_key.status == "Revoked" {
+            return Ok(false);
+        }
+        api_key.revoke();
+        self.store.save_api_key(&api_key).await?;
+        Ok(true)
+    }
+
+    /// Sets the expiration time of the API key. It returns `false` if the key
+    /// does not exist.
+    pub async fn expire_api_key(
+        &self,
+        access_key_id: &str,
+        expires_at: DateTime,
+    ) -> Result<bool, Error> {
+        let Some(mut api_key) = self.find_api_key(access_key_id).await? else {
+            return Ok(false);
+        };
+        api_key.set_expires_at(Some(expires_at));
+        self.store.save_api_key(&api_key).await?;
+        Ok(true)
+    }
+
+    /// Records the usage of the API key. The last-used time is updated
+    /// at most once in the touch interval.
+    pub async fn touch_api_key(
+        &self,
+        api_key: &mut ApiKeyRecord,
+        ip: Option<IpAddr>,
+    ) -> Result<(), Error> {
+        let last_used_ip = ip.map(|ip| ip.to_string()).unwrap_or_default();
+        let recently_used = api_key
+            .last_used_at
+            .is_some_and(|last_used_at| DateTime::now() < last_used_at + self.touch_interval);
+        if recently_used && api_key.last_used_ip == last_used_ip {
+            return Ok(());
+        }
+        api_key.touch(ip);
+        self.store
+            .touch_api_key(
+                &api_key.access_key_id,
+                api_key.last_used_at.unwrap_or_default(),
+                &api_key.last_used_ip,
+            )
+            .await
+    }
+
+    /// Creates a new instance from the `api-key` config.
+    fn from_config() -> Option<Self> {
+        let config = State::shared().get_config("api-key")?;
+        let manager = match config.get_str("store").unwrap_or("memory") {
+            "memory" => Self::new(MemoryApiKeyStore::new()),
+            store => {
+                tracing::error!("unsupported API key store `{store}`");
+                return None;
+            }
+        };
+        if let Some(max_age) = config.get_duration("max-age") {
+            Some(manager.with_max_age(max_age))
+        } else {
+            Some(manager)
+        }
+    }
+}
+
+impl std::fmt::Debug for ApiKeyManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKeyManager")
+            .field("max_age", &self.max_age)
+            .field("touch_interval", &self.touch_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Parses an IP address or a CIDR block as a network address and a prefix length.
+fn parse_ip_block(entry: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (
+            addr.parse::<IpAddr>().ok()?,
+            Some(prefix.parse::<u8>().ok()?),
+        ),
+        None => (entry.parse::<IpAddr>().ok()?, None),
+    };
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max_prefix);
+    (prefix <= max_prefix).then_some((addr, prefix))
+}
+
+/// Returns `true` if the IP address is in the network block.
+fn ip_in_block(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V4(_)) => ip
+            .to_ipv4_mapped()
+            .is_some_and(|ip| ip_in_block(IpAddr::V4(ip), network, prefix)),
+        _ => false,
+    }
+}
+
+/// Shared API key manager.
+static SHARED_API_KEY_MANAGER: OnceLock<Option<ApiKeyManager>> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::ApiKeyRecord;
+
+    #[test]
+    fn it_checks_api_key_scopes() {
+        let mut api_key = ApiKeyRecord::new("alice", "deploy");
+        api_key.set_scopes(vec!["order:read".to_owned(), "file:*".to_owned()]);
+        assert!(api_key.allows("order", "read"));
+        assert!(!api_key.allows("order", "write"));
+        assert!(api_key.allows("file", "write"));
+        assert!(!api_key.allows("user", "read"));
+
+        assert!(api_key.allows_ip(None));
+        assert!(api_key
+            .set_ip_allowlist(vec!["10.0.0.0/8".to_owned(), "192.168.1.7".to_owned()])
+            .is_ok());
+        assert!(api_key.allows_ip("10.1.2.3".parse().ok()));
+        assert!(api_key.allows_ip("192.168.1.7".parse().ok()));
+        assert!(!api_key.allows_ip("192.168.1.8".parse().ok()));
+        assert!(api_key.allows_ip("::ffff:10.0.0.1".parse().ok()));
+        assert!(!api_key.allows_ip(None));
+        assert!(api_key
+            .set_ip_allowlist(vec!["10.0.0.0/33".to_owned()])
+            .is_err());
+
+        let map = api_key.clone().into_map();
+        let record = ApiKeyRecord::try_from_map(&map).unwrap();
+        assert_eq!(record.access_key_id(), api_key.access_key_id());
+        assert_eq!(record.scopes(), api_key.scopes());
+        assert!(record.is_active());
+    }
+}
@@ -0,0 +1,564 @@
+use super::{JwkSet, JwtClaims, JwtVerifier, SessionManager};
+use crate::{
+    bail,
+    datetime::DateTime,
+    encoding::base64,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, LazyLock, Map, Uuid,
+};
+use ::base64::{engine::general_purpose::STANDARD, Engine};
+use jwt_simple::{algorithms::MACLike, prelude::Token};
+use parking_lot::Mutex;
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+use toml::Table;
+use url::Url;
+
+/// OAuth 2.0 authorization server which acts as an OpenID Connect provider.
+///
+/// It issues the authorization codes, access tokens, refresh tokens and ID tokens
+/// as [`JwtClaims`] signed with the active key of the shared [`JwkSet`],
+/// or the shared secret key if there is no asymmetric key. The authorization codes
+/// and refresh tokens are single-use, and the revocation is backed by
+/// the shared [`SessionManager`].
+///
+/// The tokens are marked with a `token_use` claim and an audience, which is the issuer
+/// for the codes, access tokens and refresh tokens, or the client for the ID tokens.
+/// Since the first-party session tokens have no audience, the tokens issued to
+/// the clients are rejected by `RequestContext::parse_jwt_claims()`.
+///
+/// # Examples
+///
+/// ```toml
+/// [oauth-server]
+/// issuer = "https://accounts.example.com"
+/// authorization-endpoint = "/oauth/authorize"
+/// token-endpoint = "/oauth/token"
+/// introspection-endpoint = "/oauth/introspect"
+/// revocation-endpoint = "/oauth/revoke"
+/// userinfo-endpoint = "/oauth/userinfo"
+/// jwks-uri = "/.well-known/jwks.json"
+/// authorization-code-max-age = "10m"
+/// access-token-max-age = "1h"
+/// refresh-token-max-age = "30d"
+/// scopes = ["openid", "profile", "email", "offline_access"]
+/// ```
+#[derive(Debug)]
+pub struct AuthorizationServer {
+    /// Issuer identifier.
+    issuer: String,
+    /// Endpoints relative to the issuer.
+    endpoints: Map,
+    /// Max age of the authorization code.
+    authorization_code_max_age: Duration,
+    /// Max age of the access token.
+    access_token_max_age: Duration,
+    /// Max age of the refresh token.
+    refresh_token_max_age: Duration,
+    /// Supported scopes.
+    scopes: Vec<String>,
+    /// Single-use tokens which have been redeemed without a session manager.
+    redeemed_tokens: Mutex<HashMap<String, DateTime>>,
+}
+
+impl AuthorizationServer {
+    /// Creates a new instance with the issuer.
+    pub fn new(issuer: impl Into<String>) -> Self {
+        let mut endpoints = Map::new();
+        endpoints.upsert("authorization_endpoint", "/oauth/authorize");
+        endpoints.upsert("token_endpoint", "/oauth/token");
+        endpoints.upsert("introspection_endpoint", "/oauth/introspect");
+        endpoints.upsert("revocation_endpoint", "/oauth/revoke");
+        endpoints.upsert("userinfo_endpoint", "/oauth/userinfo");
+        endpoints.upsert("jwks_uri", "/.well-known/jwks.json");
+        Self {
+            issuer: issuer.into().trim_end_matches('/').to_owned(),
+            endpoints,
+            authorization_code_max_age: Duration::from_secs(10 * 60),
+            access_token_max_age: Duration::from_secs(60 * 60),
+            refresh_token_max_age: Duration::from_secs(60 * 60 * 24 * 30),
+            scopes: ["openid", "profile", "email", "offline_access"]
+                .into_iter()
+                .map(|s| s.to_owned())
+                .collect(),
+            redeemed_tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let issuer = config
+            .get_str("issuer")
+            .ok_or_else(|| warn!("the `issuer` field should be specified"))?;
+        let mut server = Self::new(issuer);
+        for (key, value) in server.endpoints.iter_mut() {
+            if let Some(endpoint) = config.get_str(&key.replace('_', "-")) {
+                *value = endpoint.into();
+            }
+        }
+        if let Some(max_age) = config.get_duration("authorization-code-max-age") {
+            server.authorization_code_max_age = max_age;
+        }
+        if let Some(max_age) = config.get_duration("access-token-max-age") {
+            server.access_token_max_age = max_age;
+        }
+        if let Some(max_age) = config.get_duration("refresh-token-max-age") {
+            server.refresh_token_max_age = max_age;
+        }
+        if let Some(scopes) = config.get_str_array("scopes") {
+            server.scopes = scopes.into_iter().map(|s| s.to_owned()).collect();
+        }
+        Ok(server)
+    }
+
+    /// Returns the issuer identifier.
+    #[inline]
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Returns the supported scopes.
+    #[inline]
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Returns the OpenID Provider metadata for the discovery document
+    /// `/.well-known/openid-configuration`.
+    pub fn discovery(&self) -> Map {
+        let mut metadata = Map::new();
+        metadata.upsert("issuer", self.issuer.as_str());
+        for (key, value) in self.endpoints.iter() {
+            if let Some(endpoint) = value.as_str() {
+                let uri = if endpoint.starts_with('/') {
+                    format!("{}{endpoint}", self.issuer)
+                } else {
+                    endpoint.to_owned()
+                };
+                metadata.upsert(key, uri);
+            }
+        }
+        metadata.upsert("response_types_supported", vec!["code"]);
+        metadata.upsert(
+            "grant_types_supported",
+            vec!["authorization_code", "client_credentials", "refresh_token"],
+        );
+        metadata.upsert("code_challenge_methods_supported", vec!["S256"]);
+        metadata.upsert(
+            "token_endpoint_auth_methods_supported",
+            vec!["client_secret_basic", "client_secret_post", "none"],
+        );
+        metadata.upsert("subject_types_supported", vec!["public"]);
+        metadata.upsert(
+            "id_token_signing_alg_values_supported",
+            vec![Self::signing_algorithm()],
+        );
+        metadata.upsert("scopes_supported", self.scopes.clone());
+        metadata.upsert(
+            "claims_supported",
+            vec![
+                "sub",
+                "iss",
+                "aud",
+                "exp",
+                "iat",
+                "nonce",
+                "name",
+                "nickname",
+                "picture",
+                "website",
+                "locale",
+                "email",
+                "updated_at",
+            ],
+        );
+        metadata
+    }
+
+    /// Issues an authorization code for the user. The grant should contain
+    /// the `client_id`, `redirect_uri`, `scope`, and optional `code_challenge`,
+    /// `code_challenge_method` and `nonce`.
+    pub fn issue_authorization_code(&self, user_id: &str, grant: Map) -> Result<String, Error> {
+        let mut claims = JwtClaims::with_max_age(user_id, self.authorization_code_max_age);
+        claims.set_issuer(&self.issuer);
+        claims.set_audience(&self.issuer);
+        claims.set_jwt_id(Uuid::now_v7());
+        claims.set_data(grant);
+        claims.add_data_entry("token_use", "code");
+        self.sign(claims)
+    }
+
+    /// Builds the redirection URI of the authorization response.
+    pub fn authorization_response(
+        &self,
+        redirect_uri: &str,
+        code: &str,
+        state: Option<&str>,
+    ) -> Result<String, Error> {
+        let mut url = Url::parse(redirect_uri)?;
+        {
+            let mut pairs = url.query_pairs_mut();
+            pairs.append_pair("code", code);
+            if let Some(state) = state {
+                pairs.append_pair("state", state);
+            }
+            pairs.append_pair("iss", &self.issuer);
+        }
+        Ok(url.into())
+    }
+
+    /// Redeems the authorization code for the client. The `code_verifier` is checked
+    /// against the `code_challenge` of the authorization request.
+    pub async fn redeem_authorization_code(
+        &self,
+        code: &str,
+        client_id: &str,
+        redirect_uri: Option<&str>,
+        code_verifier: Option<&str>,
+    ) -> Result<JwtClaims, Error> {
+        let claims = self.verify_grant(code, "code", client_id)?;
+        let data = claims.data();
+        if let Some(expected_uri) = data.get_str("redirect_uri") {
+            if redirect_uri != Some(expected_uri) {
+                bail!("400 Bad Request: invalid_grant: the `redirect_uri` does not match");
+            }
+        }
+        match (data.get_str("code_challenge"), code_verifier) {
+            (Some(code_challenge), Some(code_verifier)) => {
+                let method = data.get_str("code_challenge_method").unwrap_or_default();
+                if !verify_code_challenge(code_verifier, code_challenge, method) {
+                    bail!("400 Bad Request: invalid_grant: the `code_verifier` is invalid");
+                }
+            }
+            (Some(_), None) => {
+                bail!("400 Bad Request: invalid_grant: the `code_verifier` is required");
+            }
+            (None, Some(_)) => {
+                bail!("400 Bad Request: invalid_grant: the `code_challenge` is absent");
+            }
+            (None, None) => (),
+        }
+        self.redeem(&claims).await?;
+        Ok(claims)
+    }
+
+    /// Redeems the refresh token for the client. The refresh token is rotated,
+    /// so it can not be used again.
+    pub async fn redeem_refresh_token(
+        &self,
+        refresh_token: &str,
+        client_id: &str,
+    ) -> Result<JwtClaims, Error> {
+        let claims = self.verify_grant(refresh_token, "refresh", client_id)?;
+        self.redeem(&claims).await?;
+        Ok(claims)
+    }
+
+    /// Issues an access token and an optional refresh token in the token response.
+    pub fn issue_tokens(
+        &self,
+        subject: &str,
+        client_id: &str,
+        scope: &str,
+        refreshable: bool,
+    ) -> Result<Map, Error> {
+        let mut data = Map::new();
+        data.upsert("client_id", client_id);
+        data.upsert("scope", scope);
+
+        let mut claims = JwtClaims::with_max_age(subject, self.access_token_max_age);
+        claims.set_issuer(&self.issuer);
+        claims.set_audience(&self.issuer);
+        claims.set_jwt_id(Uuid::now_v7());
+        claims.set_data(data.clone());
+        claims.add_data_entry("token_use", "access");
+
+        let mut response = Map::new();
+        response.upsert("token_type", "Bearer");
+        response.upsert("expires_in", claims.expires_in().as_secs());
+        response.upsert("scope", scope);
+        response.upsert("access_token", self.sign(claims)?);
+        if refreshable {
+            let mut claims = JwtClaims::with_max_age(subject, self.refresh_token_max_age);
+            claims.set_issuer(&self.issuer);
+            claims.set_audience(&self.issuer);
+            claims.set_jwt_id(Uuid::now_v7());
+            claims.set_data(data);
+            claims.add_data_entry("token_use", "refresh");
+            response.upsert("refresh_token", self.sign(claims)?);
+        }
+        Ok(response)
+    }
+
+    /// Issues an ID token with the standard claims of the user.
+    pub fn issue_id_token(
+        &self,
+        user_id: &str,
+        client_id: &str,
+        nonce: Option<&str>,
+        standard_claims: Map,
+    ) -> Result<String, Error> {
+        let mut claims = JwtClaims::with_max_age(user_id, self.access_token_max_age);
+        claims.set_issuer(&self.issuer);
+        claims.set_audience(client_id);
+        if let Some(nonce) = nonce {
+            claims.set_nonce(nonce);
+        }
+        claims.set_data(standard_claims);
+        claims.add_data_entry("token_use", "id");
+        self.sign(claims)
+    }
+
+    /// Verifies the token issued by the server. The token is rejected
+    /// if it has been revoked or redeemed, or if its issuer or audience
+    /// is not the server. Thus the ID tokens can not be used as the grants.
+    pub fn verify_token(&self, token: &str) -> Result<JwtClaims, Error> {
+        let metadata = Token::decode_metadata(token).map_err(|err| Error::new(err.to_string()))?;
+        let mut options = super::default_verification_options();
+        options.allowed_issuers = Some(HashSet::from([self.issuer.clone()]));
+        options.allowed_audiences = Some(HashSet::from([self.issuer.clone()]));
+        let claims = if metadata.key_id().is_some() && !JwkSet::shared().is_empty() {
+            JwkSet::shared().verify_jwt(token, options)?
+        } else {
+            JwtClaims::shared_key().verify_jwt(token, options)?
+        };
+        if let Some(jwt_id) = claims.jwt_id.as_deref() {
+            let revoked = if let Some(manager) = SessionManager::shared() {
+                manager.is_revoked(jwt_id)
+            } else {
+                self.redeemed_tokens.lock().contains_key(jwt_id)
+            };
+            if revoked {
+                bail!("the token has been revoked");
+            }
+        }
+        Ok(JwtClaims(claims))
+    }
+
+    /// Introspects the token and returns the response of RFC 7662.
+    pub fn introspect(&self, token: &str) -> Map {
+        let mut response = Map::new();
+        let claims = match self.verify_token(token) {
+            Ok(claims) => claims,
+            Err(err) => {
+                tracing::debug!("inactive token: {err}");
+                response.upsert("active", false);
+                return response;
+            }
+        };
+        let data = claims.data();
+        let token_type = match data.get_str("token_use") {
+            Some("access") => "access_token",
+            Some("refresh") => "refresh_token",
+            _ => {
+                response.upsert("active", false);
+                return response;
+            }
+        };
+        response.upsert("active", true);
+        response.upsert("token_type", token_type);
+        response.upsert("iss", self.issuer.as_str());
+        response.upsert("sub", claims.subject());
+        response.upsert("jti", claims.jwt_id());
+        response.upsert("client_id", data.get("client_id").cloned());
+        response.upsert("scope", data.get("scope").cloned());
+        response.upsert("iat", claims.issued_at().timestamp());
+        response.upsert("exp", claims.expires_at().timestamp());
+        response
+    }
+
+    /// Revokes the access token or refresh token issued to the client as RFC 7009.
+    /// An invalid token is ignored.
+    pub async fn revoke(&self, token: &str, client_id: &str) -> Result<(), Error> {
+        let Ok(claims) = self.verify_token(token) else {
+            return Ok(());
+        };
+        if claims.data().get_str("client_id") != Some(client_id) {
+            bail!("400 Bad Request: unauthorized_client: the token was issued to another client");
+        }
+        let Some(jwt_id) = claims.jwt_id() else {
+            return Ok(());
+        };
+        let Some(manager) = SessionManager::shared() else {
+            bail!("503 Service Unavailable: the session manager is not configured");
+        };
+        manager.revoke_token(jwt_id, claims.expires_at()).await
+    }
+
+    /// Parses the client credentials from the `Authorization` header with the
+    /// `Basic` scheme, or the `client_id` and `client_secret` parameters.
+    pub fn parse_client_credentials(
+        authorization: Option<&str>,
+        params: &Map,
+    ) -> Result<(String, Option<String>), Error> {
+        if let Some(credentials) = authorization.and_then(|s| s.strip_prefix("Basic ")) {
+            let credentials = STANDARD.decode(credentials.trim())?;
+            let credentials = String::from_utf8(credentials)?;
+            let Some((client_id, client_secret)) = credentials.split_once(':') else {
+                bail!("401 Unauthorized: invalid_client: the credentials are malformed");
+            };
+            let client_id = percent_decode_str(client_id).decode_utf8()?;
+            let client_secret = percent_decode_str(client_secret).decode_utf8()?;
+            Ok((client_id.into_owned(), Some(client_secret.into_owned())))
+        } else if let Some(client_id) = params.get_str("client_id") {
+            let client_secret = params.get_str("client_secret").map(|s| s.to_owned());
+            Ok((client_id.to_owned(), client_secret))
+        } else {
+            bail!("401 Unauthorized: invalid_client: the client credentials are absent");
+        }
+    }
+
+    /// Returns a reference to the shared authorization server.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_AUTHORIZATION_SERVER
+    }
+
+    /// Signs the claims with the active signing key of the shared JWK set,
+    /// or the shared secret key.
+    fn sign(&self, claims: JwtClaims) -> Result<String, Error> {
+        if let Some(key) = JwkSet::shared().signing_key() {
+            claims.sign_with_key(key)
+        } else {
+            claims.access_token()
+        }
+    }
+
+    /// Returns the algorithm for signing the tokens.
+    fn signing_algorithm() -> &'static str {
+        if let Some(key) = JwkSet::shared().signing_key() {
+            key.algorithm()
+        } else {
+            super::JwtHmacKey::jwt_alg_name()
+        }
+    }
+
+    /// Verifies the single-use grant of the token type for the client.
+    fn verify_grant(
+        &self,
+        token: &str,
+        token_use: &str,
+        client_id: &str,
+    ) -> Result<JwtClaims, Error> {
+        let claims = self
+            .verify_token(token)
+            .map_err(|err| warn!("400 Bad Request: invalid_grant: {}", err.message()))?;
+        let data = claims.data();
+        if data.get_str("token_use") != Some(token_use) {
+            bail!("400 Bad Request: invalid_grant: the token type is invalid");
+        }
+        if data.get_str("client_id") != Some(client_id) {
+            bail!("400 Bad Request: invalid_grant: the grant was issued to another client");
+        }
+        Ok(claims)
+    }
+
+    /// Redeems the single-use token.
+    async fn redeem(&self, claims: &JwtClaims) -> Result<(), Error> {
+        let Some(jwt_id) = claims.jwt_id() else {
+            bail!("400 Bad Request: invalid_grant: the grant does not have a JWT ID");
+        };
+        let expires_at = claims.expires_at();
+        if let Some(manager) = SessionManager::shared() {
+            if !manager.redeem_token(jwt_id, expires_at).await? {
+                bail!("400 Bad Request: invalid_grant: the grant has been redeemed");
+            }
+            Ok(())
+        } else {
+            let now = DateTime::now();
+            let mut redeemed_tokens = self.redeemed_tokens.lock();
+            redeemed_tokens.retain(|_, expires_at| *expires_at > now);
+            if redeemed_tokens
+                .insert(jwt_id.to_owned(), expires_at)
+                .is_some()
+            {
+                bail!("400 Bad Request: invalid_grant: the grant has been redeemed");
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Verifies the PKCE code verifier against the code challenge as RFC 7636.
+/// Only the `S256` method is supported.
+fn verify_code_challenge(code_verifier: &str, code_challenge: &str, method: &str) -> bool {
+    method == "S256"
+        && (43..=128).contains(&code_verifier.len())
+        && base64::encode_url(Sha256::digest(code_verifier.as_bytes())) == code_challenge
+}
+
+/// Shared authorization server.
+static SHARED_AUTHORIZATION_SERVER: LazyLock<AuthorizationServer> = LazyLock::new(|| {
+    if let Some(config) = State::shared().get_config("oauth-server") {
+        AuthorizationServer::try_from_config(config)
+            .unwrap_or_else(|err| panic!("fail to configure the authorization server: {err}"))
+    } else {
+        tracing::warn!("the `oauth-server.issuer` should be specified");
+        AuthorizationServer::new("http://localhost")
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{verify_code_challenge, AuthorizationServer, JwtClaims};
+    use crate::{extension::JsonObjectExt, Map, Uuid};
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn it_rejects_double_redemption() {
+        let server = AuthorizationServer::new("http://localhost");
+        let mut claims = JwtClaims::with_max_age("alice", Duration::from_secs(60));
+        claims.set_jwt_id(Uuid::now_v7());
+        assert!(block_on(server.redeem(&claims)).is_ok());
+        assert!(block_on(server.redeem(&claims)).is_err());
+    }
+
+    #[test]
+    fn it_binds_tokens_to_the_issuer() {
+        let server = AuthorizationServer::new("http://localhost");
+        let response = server
+            .issue_tokens("alice", "client", "openid", true)
+            .unwrap();
+        let access_token = response.get_str("access_token").unwrap();
+        let claims = server.verify_token(access_token).unwrap();
+        assert_eq!(claims.data().get_str("token_use"), Some("access"));
+        assert_eq!(claims.data().get_str("client_id"), Some("client"));
+        assert!(server.introspect(access_token).get_bool("active") == Some(true));
+
+        let refresh_token = response.get_str("refresh_token").unwrap();
+        assert!(server
+            .verify_grant(refresh_token, "refresh", "client")
+            .is_ok());
+        assert!(server
+            .verify_grant(access_token, "refresh", "client")
+            .is_err());
+
+        let id_token = server
+            .issue_id_token("alice", "client", None, Map::new())
+            .unwrap();
+        assert!(server.verify_token(&id_token).is_err());
+
+        let other_server = AuthorizationServer::new("http://127.0.0.1");
+        assert!(other_server.verify_token(access_token).is_err());
+    }
+
+    #[test]
+    fn it_verifies_code_challenge() {
+        let code_verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let code_challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+        assert!(verify_code_challenge(code_verifier, code_challenge, "S256"));
+        assert!(!verify_code_challenge(code_verifier, code_verifier, "S256"));
+        assert!(!verify_code_challenge(
+            code_verifier,
+            code_verifier,
+            "plain"
+        ));
+    }
+}
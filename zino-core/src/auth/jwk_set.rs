@@ -0,0 +1,520 @@
+use super::{JwtSigningKey, JwtVerifier, JwtVerifyingKey};
+use crate::{
+    application::{self, http_client, PROJECT_DIR},
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, JsonValue, LazyLock, Map,
+};
+use futures::lock::Mutex;
+use jwt_simple::{claims::JWTClaims, common::VerificationOptions, prelude::Token};
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+use toml::Table;
+
+/// A set of asymmetric keys identified by `kid` for signing and verifying the JWT token.
+///
+/// The first key with a private key is the active signing key, and other keys are
+/// kept for verifying the tokens signed before the key rotation.
+///
+/// # Examples
+///
+/// ```toml
+/// [[jwt.keys]]
+/// kid = "2024-12"
+/// algorithm = "ES256"
+/// private-key = "config/keys/jwt-2024-12.pem"
+///
+/// [[jwt.keys]]
+/// kid = "2024-06"
+/// algorithm = "ES256"
+/// public-key = "config/keys/jwt-2024-06.pub.pem"
+/// ```
+#[derive(Debug, Default)]
+pub struct JwkSet {
+    /// Active signing key.
+    signing_key: Option<JwtSigningKey>,
+    /// Verifying keys.
+    verifying_keys: Vec<JwtVerifyingKey>,
+}
+
+impl JwkSet {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attempts to create a new instance with the `keys` configuration.
+    pub fn try_from_config(keys: &[Table]) -> Result<Self, Error> {
+        let mut key_set = Self::new();
+        for config in keys {
+            let algorithm = config.get_str("algorithm").unwrap_or("ES256");
+            let key_id = config
+                .get_str("kid")
+                .ok_or_else(|| warn!("the `kid` field should be specified"))?;
+            if let Some(private_key) = config.get_str("private-key") {
+                let path = application::join_path(&PROJECT_DIR, private_key);
+                let key = JwtSigningKey::read_pem_file(algorithm, path)?.with_key_id(key_id);
+                key_set.add_signing_key(key);
+            } else if let Some(public_key) = config.get_str("public-key") {
+                let path = application::join_path(&PROJECT_DIR, public_key);
+                let key = JwtVerifyingKey::read_pem_file(algorithm, path)?.with_key_id(key_id);
+                key_set.add_verifying_key(key);
+            } else {
+                bail!("the `private-key` or `public-key` field should be specified");
+            }
+        }
+        Ok(key_set)
+    }
+
+    /// Attempts to create a new instance from a JWKS document.
+    /// Unsupported keys in the document are ignored.
+    pub fn try_from_jwks(jwks: &Map) -> Result<Self, Error> {
+        let Some(keys) = jwks.get_array("keys") else {
+            bail!("the JWKS document should contain the `keys` field");
+        };
+        let mut key_set = Self::new();
+        for jwk in keys.iter().filter_map(|v| v.as_object()) {
+            match JwtVerifyingKey::from_jwk(jwk) {
+                Ok(key) => key_set.add_verifying_key(key),
+                Err(err) => tracing::warn!("fail to parse the JWK: {err}"),
+            }
+        }
+        Ok(key_set)
+    }
+
+    /// Adds a signing key. The first signing key added is the active one.
+    pub fn add_signing_key(&mut self, key: JwtSigningKey) {
+        self.verifying_keys.push(key.public_key());
+        if self.signing_key.is_none() {
+            self.signing_key = Some(key);
+        }
+    }
+
+    /// Adds a verifying key.
+    #[inline]
+    pub fn add_verifying_key(&mut self, key: JwtVerifyingKey) {
+        self.verifying_keys.push(key);
+    }
+
+    /// Returns the active signing key.
+    #[inline]
+    pub fn signing_key(&self) -> Option<&JwtSigningKey> {
+        self.signing_key.as_ref()
+    }
+
+    /// Returns the verifying key with the key ID.
+    #[inline]
+    pub fn get_key(&self, key_id: &str) -> Option<&JwtVerifyingKey> {
+        self.verifying_keys
+            .iter()
+            .find(|key| key.key_id() == Some(key_id))
+    }
+
+    /// Returns `true` if the key set contains no keys.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.verifying_keys.is_empty()
+    }
+
+    /// Signs the claims with the active signing key and returns the token.
+    pub fn sign<T: Serialize>(&self, claims: JWTClaims<T>) -> Result<String, Error> {
+        let Some(signing_key) = self.signing_key() else {
+            bail!("there is no active signing key in the JWK set");
+        };
+        signing_key.sign(claims)
+    }
+
+    /// Converts `self` to a JWKS document which publishes the public keys.
+    pub fn to_jwks(&self) -> Map {
+        let keys = self
+            .verifying_keys
+            .iter()
+            .map(|key| key.to_jwk().into())
+            .collect::<Vec<JsonValue>>();
+        Map::from_entry("keys", keys)
+    }
+
+    /// Returns a reference to the shared JWK set.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_JWK_SET
+    }
+}
+
+impl JwtVerifier for JwkSet {
+    fn verify_jwt<T>(
+        &self,
+        token: &str,
+        options: VerificationOptions,
+    ) -> Result<JWTClaims<T>, Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let metadata = Token::decode_metadata(token).map_err(|err| Error::new(err.to_string()))?;
+        if let Some(key_id) = metadata.key_id() {
+            let Some(key) = self.get_key(key_id) else {
+                bail!("the JWK with the key ID `{}` does not exist", key_id);
+            };
+            key.verify_jwt(token, options)
+        } else {
+            let algorithm = metadata.algorithm();
+            for key in self.verifying_keys.iter() {
+                if key.algorithm() == algorithm {
+                    if let Ok(claims) = key.verify_jwt(token, options.clone()) {
+                        return Ok(claims);
+                    }
+                }
+            }
+            bail!("there is no JWK to verify the token");
+        }
+    }
+}
+
+/// A JWK set fetched from a remote JWKS endpoint, which is cached and refreshed
+/// periodically or when an unknown `kid` is encountered.
+///
+/// Concurrent refreshes are coalesced into a single request, and the endpoint is
+/// fetched at most once per `min-refresh-interval`. If a refresh fails,
+/// the previously cached key set is kept for verifying the tokens.
+///
+/// # Examples
+///
+/// ```toml
+/// [[jwt.issuers]]
+/// issuer = "https://accounts.example.com"
+/// jwks-uri = "https://accounts.example.com/.well-known/jwks.json"
+/// cache-ttl = "1h"
+/// ```
+#[derive(Debug)]
+pub struct RemoteJwkSet {
+    /// Issuer.
+    issuer: Option<String>,
+    /// JWKS URI.
+    jwks_uri: String,
+    /// Time-to-live of the cached key set.
+    cache_ttl: Duration,
+    /// Minimum interval between two refreshes.
+    min_refresh_interval: Duration,
+    /// Cached key set.
+    key_set: RwLock<JwkSet>,
+    /// Time when the key set was refreshed at.
+    refreshed_at: RwLock<Option<DateTime>>,
+    /// Time when the last refresh was attempted at, which also serializes the refreshes.
+    attempted_at: Mutex<Option<DateTime>>,
+}
+
+impl RemoteJwkSet {
+    /// Creates a new instance with the JWKS URI.
+    pub fn new(jwks_uri: impl Into<String>) -> Self {
+        Self {
+            issuer: None,
+            jwks_uri: jwks_uri.into(),
+            cache_ttl: Duration::from_secs(60 * 60),
+            min_refresh_interval: Duration::from_secs(60),
+            key_set: RwLock::new(JwkSet::new()),
+            refreshed_at: RwLock::new(None),
+            attempted_at: Mutex::new(None),
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let jwks_uri = config
+            .get_str("jwks-uri")
+            .ok_or_else(|| warn!("the `jwks-uri` field should be specified"))?;
+        let mut jwks = Self::new(jwks_uri);
+        if let Some(issuer) = config.get_str("issuer") {
+            jwks.issuer = Some(issuer.to_owned());
+        }
+        if let Some(cache_ttl) = config.get_duration("cache-ttl") {
+            jwks.cache_ttl = cache_ttl;
+        }
+        if let Some(min_refresh_interval) = config.get_duration("min-refresh-interval") {
+            jwks.min_refresh_interval = min_refresh_interval;
+        }
+        Ok(jwks)
+    }
+
+    /// Returns the issuer.
+    #[inline]
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// Returns the JWKS URI.
+    #[inline]
+    pub fn jwks_uri(&self) -> &str {
+        &self.jwks_uri
+    }
+
+    /// Returns `true` if the cached key set should be refreshed.
+    fn is_stale(&self, key_id: Option<&str>) -> bool {
+        let Some(refreshed_at) = *self.refreshed_at.read() else {
+            return true;
+        };
+        let elapsed = refreshed_at.span_before_now().unwrap_or_default();
+        if elapsed >= self.cache_ttl {
+            return true;
+        }
+        key_id.is_some_and(|key_id| self.key_set.read().get_key(key_id).is_none())
+            && elapsed >= self.min_refresh_interval
+    }
+
+    /// Fetches the JWKS document and replaces the cached key set.
+    pub async fn refresh(&self) -> Result<(), Error> {
+        let jwks = http_client::request_builder(&self.jwks_uri, None)?
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Map>()
+            .await?;
+        let key_set = JwkSet::try_from_jwks(&jwks)?;
+        *self.key_set.write() = key_set;
+        *self.refreshed_at.write() = Some(DateTime::now());
+        Ok(())
+    }
+
+    /// Prepares the cached key set for verifying the token.
+    /// It refreshes the key set if it has expired or the `kid` of the token is unknown.
+    pub async fn prepare(&self, token: &str) -> Result<(), Error> {
+        let metadata =
+            Token::decode_metadata(token).map_err(|err| warn!("401 Unauthorized: {}", err))?;
+        let key_id = metadata.key_id();
+        if !self.is_stale(key_id) {
+            return Ok(());
+        }
+
+        // Only one task refreshes the key set and the others wait for the result.
+        let mut attempted_at = self.attempted_at.lock().await;
+        if !self.is_stale(key_id) {
+            return Ok(());
+        }
+        if attempted_at
+            .is_some_and(|dt| dt.span_before_now().unwrap_or_default() < self.min_refresh_interval)
+        {
+            return Ok(());
+        }
+        *attempted_at = Some(DateTime::now());
+        if let Err(err) = self.refresh().await {
+            if self.key_set.read().is_empty() {
+                return Err(err);
+            }
+            tracing::warn!(
+                jwks_uri = self.jwks_uri,
+                "fail to refresh the remote JWK set: {err}"
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the remote JWK set for the issuer, which is configured in `jwt.issuers`.
+    #[inline]
+    pub fn get(issuer: &str) -> Option<&'static Self> {
+        SHARED_REMOTE_JWK_SETS
+            .iter()
+            .find(|jwks| jwks.issuer() == Some(issuer))
+    }
+}
+
+impl JwtVerifier for RemoteJwkSet {
+    fn verify_jwt<T>(
+        &self,
+        token: &str,
+        mut options: VerificationOptions,
+    ) -> Result<JWTClaims<T>, Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        if let Some(issuer) = self.issuer() {
+            options
+                .allowed_issuers
+                .get_or_insert_with(Default::default)
+                .insert(issuer.to_owned());
+        }
+        self.key_set.read().verify_jwt(token, options)
+    }
+}
+
+/// Shared JWK set.
+static SHARED_JWK_SET: LazyLock<JwkSet> = LazyLock::new(|| {
+    let keys = State::shared()
+        .get_config("jwt")
+        .and_then(|config| config.get_array("keys"))
+        .map(|keys| {
+            keys.iter()
+                .filter_map(|v| v.as_table())
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    JwkSet::try_from_config(&keys).unwrap_or_else(|err| panic!("fail to load JWT keys: {err}"))
+});
+
+/// Shared remote JWK sets.
+static SHARED_REMOTE_JWK_SETS: LazyLock<Vec<RemoteJwkSet>> = LazyLock::new(|| {
+    let mut jwk_sets = Vec::new();
+    if let Some(issuers) = State::shared()
+        .get_config("jwt")
+        .and_then(|config| config.get_array("issuers"))
+    {
+        for config in issuers.iter().filter_map(|v| v.as_table()) {
+            match RemoteJwkSet::try_from_config(config) {
+                Ok(jwks) => jwk_sets.push(jwks),
+                Err(err) => tracing::error!("fail to configure the remote JWK set: {err}"),
+            }
+        }
+    }
+    jwk_sets
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{JwkSet, RemoteJwkSet};
+    use crate::{
+        auth::{JwtSigningKey, JwtVerifier},
+        datetime::DateTime,
+        extension::JsonObjectExt,
+        JsonValue,
+    };
+    use futures::executor::block_on;
+    use jwt_simple::{
+        claims::{Claims, NoCustomClaims},
+        common::VerificationOptions,
+        prelude::{Duration, ES256KeyPair, Ed25519KeyPair},
+    };
+
+    #[test]
+    fn it_selects_keys_by_kid() {
+        let new_key = JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("2024-12");
+        let old_key_pair = Ed25519KeyPair::generate();
+        let old_key = JwtSigningKey::EdDSA(old_key_pair.clone()).with_key_id("2024-06");
+        let mut key_set = JwkSet::new();
+        key_set.add_signing_key(new_key);
+        key_set.add_signing_key(JwtSigningKey::EdDSA(old_key_pair).with_key_id("2024-06"));
+        assert_eq!(
+            key_set.signing_key().and_then(|key| key.key_id()),
+            Some("2024-12")
+        );
+        assert!(key_set.get_key("2024-06").is_some());
+        assert!(key_set.get_key("2023-12").is_none());
+
+        let options = VerificationOptions::default();
+        let token = key_set
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+        assert!(key_set
+            .verify_jwt::<NoCustomClaims>(&token, options.clone())
+            .is_ok());
+
+        let token = old_key
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+        assert!(key_set
+            .verify_jwt::<NoCustomClaims>(&token, options.clone())
+            .is_ok());
+
+        let unknown_key = JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("2023-12");
+        let token = unknown_key
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+        assert!(key_set
+            .verify_jwt::<NoCustomClaims>(&token, options.clone())
+            .is_err());
+
+        // A token without `kid` is verified by the keys with the same algorithm.
+        let anonymous_key_pair = Ed25519KeyPair::generate();
+        let anonymous_key = JwtSigningKey::EdDSA(anonymous_key_pair.clone());
+        let mut anonymous_key_set = JwkSet::new();
+        anonymous_key_set.add_signing_key(JwtSigningKey::EdDSA(anonymous_key_pair));
+        let token = anonymous_key
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+        assert!(anonymous_key_set
+            .verify_jwt::<NoCustomClaims>(&token, options.clone())
+            .is_ok());
+        assert!(key_set
+            .verify_jwt::<NoCustomClaims>(&token, options)
+            .is_err());
+    }
+
+    #[test]
+    fn it_publishes_jwks() {
+        let signing_key = JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("2024-12");
+        let mut key_set = JwkSet::new();
+        key_set.add_signing_key(signing_key);
+
+        let mut jwks = key_set.to_jwks();
+        let keys = jwks.get_array("keys").unwrap().clone();
+        assert_eq!(keys.len(), 1);
+        assert!(keys[0].as_object().unwrap().get("d").is_none());
+
+        let mut unsupported_key = crate::Map::new();
+        unsupported_key.upsert("kty", "oct");
+        let mut keys = keys;
+        keys.push(unsupported_key.into());
+        jwks.upsert("keys", JsonValue::Array(keys));
+
+        let remote_key_set = JwkSet::try_from_jwks(&jwks).unwrap();
+        assert!(remote_key_set.signing_key().is_none());
+        assert!(remote_key_set.get_key("2024-12").is_some());
+
+        let token = key_set
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+        assert!(remote_key_set
+            .verify_jwt::<NoCustomClaims>(&token, VerificationOptions::default())
+            .is_ok());
+        assert!(JwkSet::try_from_jwks(&crate::Map::new()).is_err());
+    }
+
+    #[test]
+    fn it_refreshes_stale_remote_jwks() {
+        let signing_key = JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("2024-12");
+        let mut key_set = JwkSet::new();
+        key_set.add_signing_key(signing_key);
+
+        let remote_key_set = RemoteJwkSet::new("https://accounts.example.com/jwks.json");
+        assert!(remote_key_set.is_stale(None));
+
+        *remote_key_set.key_set.write() = key_set;
+        *remote_key_set.refreshed_at.write() = Some(DateTime::now());
+        assert!(!remote_key_set.is_stale(None));
+        assert!(!remote_key_set.is_stale(Some("2024-12")));
+        assert!(!remote_key_set.is_stale(Some("2025-06")));
+
+        let elapsed = std::time::Duration::from_secs(120);
+        *remote_key_set.refreshed_at.write() = Some(DateTime::now() - elapsed);
+        assert!(!remote_key_set.is_stale(Some("2024-12")));
+        assert!(remote_key_set.is_stale(Some("2025-06")));
+    }
+
+    #[test]
+    fn it_keeps_cached_jwks_when_refresh_fails() {
+        let signing_key = JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("2024-12");
+        let mut key_set = JwkSet::new();
+        key_set.add_signing_key(signing_key);
+        let token = key_set
+            .sign(Claims::create(Duration::from_mins(5)))
+            .unwrap();
+
+        // The global HTTP client is not initialized, so every refresh fails.
+        let remote_key_set = RemoteJwkSet::new("https://accounts.example.com/jwks.json");
+        assert!(block_on(remote_key_set.prepare(&token)).is_err());
+
+        let elapsed = std::time::Duration::from_secs(2 * 60 * 60);
+        *remote_key_set.key_set.write() = key_set;
+        *remote_key_set.refreshed_at.write() = Some(DateTime::now() - elapsed);
+        *block_on(remote_key_set.attempted_at.lock()) = None;
+        assert!(remote_key_set.is_stale(None));
+        assert!(block_on(remote_key_set.prepare(&token)).is_ok());
+        assert!(block_on(remote_key_set.attempted_at.lock()).is_some());
+        assert!(remote_key_set
+            .verify_jwt::<NoCustomClaims>(&token, VerificationOptions::default())
+            .is_ok());
+    }
+}
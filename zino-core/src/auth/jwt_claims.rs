@@ -1,3 +1,4 @@
+use super::JwtSigningKey;
 use crate::{
     crypto,
     datetime::DateTime,
@@ -44,7 +45,8 @@ impl<T: Default + Serialize + DeserializeOwned> JwtClaims<T> {
         Self::constructor(subject.to_string(), T::default(), max_age)
     }
 
-    /// Generates an access token signed with the shared secret access key.
+    /// Generates a refresh token signed with the shared secret access key.
+    /// The JWT identifier is retained in the refresh token.
     pub fn refresh_token(&self) -> Result<String, Error> {
         let mut claims = Claims::create((*DEFAULT_REFRESH_INTERVAL).into());
         claims.invalid_before = self
@@ -52,6 +54,7 @@ impl<T: Default + Serialize + DeserializeOwned> JwtClaims<T> {
             .expires_at
             .map(|max_age| max_age - (*DEFAULT_TIME_TOLERANCE).into());
         claims.subject = self.0.subject.as_ref().cloned();
+        claims.jwt_id = self.0.jwt_id.as_ref().cloned();
         JwtClaims::shared_key()
             .authenticate(claims)
             .map_err(|err| Error::new(err.to_string()))
@@ -69,6 +72,12 @@ impl<T: Default + Serialize + DeserializeOwned> JwtClaims<T> {
         key.authenticate(self.0)
             .map_err(|err| Error::new(err.to_string()))
     }
+
+    /// Generates a signature with the asymmetric signing key.
+    #[inline]
+    pub fn sign_with_key(self, key: &JwtSigningKey) -> Result<String, Error> {
+        key.sign(self.0)
+    }
 }
 
 impl<T> JwtClaims<T> {
@@ -141,6 +150,12 @@ impl<T> JwtClaims<T> {
         self.0.subject.as_deref()
     }
 
+    /// Returns the JWT identifier.
+    #[inline]
+    pub fn jwt_id(&self) -> Option<&str> {
+        self.0.jwt_id.as_deref()
+    }
+
     /// Returns the nonce.
     #[inline]
     pub fn nonce(&self) -> Option<&str> {
@@ -0,0 +1,351 @@
+use crate::{bail, encoding::base64, error::Error, extension::JsonObjectExt, warn, Map};
+use jwt_simple::{
+    algorithms::MACLike,
+    claims::JWTClaims,
+    common::VerificationOptions,
+    prelude::{
+        ECDSAP256KeyPairLike, ECDSAP256PublicKeyLike, ES256KeyPair, ES256PublicKey, Ed25519KeyPair,
+        Ed25519PublicKey, EdDSAKeyPairLike, EdDSAPublicKeyLike, PS256KeyPair, PS256PublicKey,
+        RS256KeyPair, RS256PublicKey, RSAKeyPairLike, RSAPublicKeyLike,
+    },
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, path::Path};
+
+/// A verifier for the JWT token.
+///
+/// It has been implemented for all HMAC keys, [`JwtVerifyingKey`],
+/// [`JwkSet`](super::JwkSet) and [`RemoteJwkSet`](super::RemoteJwkSet).
+pub trait JwtVerifier {
+    /// Verifies the token and returns the claims.
+    fn verify_jwt<T>(
+        &self,
+        token: &str,
+        options: VerificationOptions,
+    ) -> Result<JWTClaims<T>, Error>
+    where
+        T: Serialize + DeserializeOwned;
+}
+
+impl<K: MACLike> JwtVerifier for K {
+    fn verify_jwt<T>(
+        &self,
+        token: &str,
+        options: VerificationOptions,
+    ) -> Result<JWTClaims<T>, Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        self.verify_token(token, Some(options))
+            .map_err(|err| Error::new(err.to_string()))
+    }
+}
+
+/// Asymmetric key pair for signing the JWT token.
+///
+/// Supported algorithms: `RS256` | `PS256` | `ES256` | `EdDSA`.
+#[derive(Debug)]
+pub enum JwtSigningKey {
+    /// RSASSA-PKCS1-v1_5 using SHA-256.
+    RS256(RS256KeyPair),
+    /// RSASSA-PSS using SHA-256.
+    PS256(PS256KeyPair),
+    /// ECDSA using P-256 and SHA-256.
+    ES256(ES256KeyPair),
+    /// EdDSA using Ed25519.
+    EdDSA(Ed25519KeyPair),
+}
+
+impl JwtSigningKey {
+    /// Attempts to create a new instance from the PEM-encoded private key.
+    pub fn from_pem(algorithm: &str, pem: &str) -> Result<Self, Error> {
+        let key = match algorithm {
+            "RS256" => RS256KeyPair::from_pem(pem).map(Self::RS256),
+            "PS256" => PS256KeyPair::from_pem(pem).map(Self::PS256),
+            "ES256" => ES256KeyPair::from_pem(pem).map(Self::ES256),
+            "EdDSA" => Ed25519KeyPair::from_pem(pem).map(Self::EdDSA),
+            _ => bail!("JWT algorithm `{}` is unsupported", algorithm),
+        };
+        key.map_err(|err| Error::new(err.to_string()))
+    }
+
+    /// Attempts to create a new instance by reading the PEM-encoded private key file.
+    pub fn read_pem_file(algorithm: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let pem = fs::read_to_string(path)?;
+        Self::from_pem(algorithm, &pem)
+    }
+
+    /// Sets the key ID.
+    pub fn with_key_id(self, key_id: &str) -> Self {
+        match self {
+            Self::RS256(key) => Self::RS256(key.with_key_id(key_id)),
+            Self::PS256(key) => Self::PS256(key.with_key_id(key_id)),
+            Self::ES256(key) => Self::ES256(key.with_key_id(key_id)),
+            Self::EdDSA(key) => Self::EdDSA(key.with_key_id(key_id)),
+        }
+    }
+
+    /// Returns the algorithm name.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::RS256(_) => "RS256",
+            Self::PS256(_) => "PS256",
+            Self::ES256(_) => "ES256",
+            Self::EdDSA(_) => "EdDSA",
+        }
+    }
+
+    /// Returns the key ID.
+    pub fn key_id(&self) -> Option<&str> {
+        let key_id = match self {
+            Self::RS256(key) => key.key_id(),
+            Self::PS256(key) => key.key_id(),
+            Self::ES256(key) => key.key_id(),
+            Self::EdDSA(key) => key.key_id(),
+        };
+        key_id.as_deref()
+    }
+
+    /// Returns the public key for verification.
+    pub fn public_key(&self) -> JwtVerifyingKey {
+        let public_key = match self {
+            Self::RS256(key) => JwtVerifyingKey::RS256(key.public_key()),
+            Self::PS256(key) => JwtVerifyingKey::PS256(key.public_key()),
+            Self::ES256(key) => JwtVerifyingKey::ES256(key.public_key()),
+            Self::EdDSA(key) => JwtVerifyingKey::EdDSA(key.public_key()),
+        };
+        match self.key_id() {
+            Some(key_id) => public_key.with_key_id(key_id),
+            None => public_key,
+        }
+    }
+
+    /// Signs the claims and returns the token.
+    pub fn sign<T: Serialize>(&self, claims: JWTClaims<T>) -> Result<String, Error> {
+        let token = match self {
+            Self::RS256(key) => key.sign(claims),
+            Self::PS256(key) => key.sign(claims),
+            Self::ES256(key) => key.sign(claims),
+            Self::EdDSA(key) => key.sign(claims),
+        };
+        token.map_err(|err| Error::new(err.to_string()))
+    }
+}
+
+/// Asymmetric public key for verifying the JWT token.
+///
+/// Supported algorithms: `RS256` | `PS256` | `ES256` | `EdDSA`.
+#[derive(Debug, Clone)]
+pub enum JwtVerifyingKey {
+    /// RSASSA-PKCS1-v1_5 using SHA-256.
+    RS256(RS256PublicKey),
+    /// RSASSA-PSS using SHA-256.
+    PS256(PS256PublicKey),
+    /// ECDSA using P-256 and SHA-256.
+    ES256(ES256PublicKey),
+    /// EdDSA using Ed25519.
+    EdDSA(Ed25519PublicKey),
+}
+
+impl JwtVerifyingKey {
+    /// Attempts to create a new instance from the PEM-encoded public key.
+    pub fn from_pem(algorithm: &str, pem: &str) -> Result<Self, Error> {
+        let key = match algorithm {
+            "RS256" => RS256PublicKey::from_pem(pem).map(Self::RS256),
+            "PS256" => PS256PublicKey::from_pem(pem).map(Self::PS256),
+            "ES256" => ES256PublicKey::from_pem(pem).map(Self::ES256),
+            "EdDSA" => Ed25519PublicKey::from_pem(pem).map(Self::EdDSA),
+            _ => bail!("JWT algorithm `{}` is unsupported", algorithm),
+        };
+        key.map_err(|err| Error::new(err.to_string()))
+    }
+
+    /// Attempts to create a new instance by reading the PEM-encoded public key file.
+    pub fn read_pem_file(algorithm: &str, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let pem = fs::read_to_string(path)?;
+        Self::from_pem(algorithm, &pem)
+    }
+
+    /// Attempts to create a new instance from a JSON Web Key.
+    /// See [RFC 7517](https://www.rfc-editor.org/rfc/rfc7517).
+    pub fn from_jwk(jwk: &Map) -> Result<Self, Error> {
+        let key_type = jwk.get_str("kty").unwrap_or_default();
+        let algorithm = jwk.get_str("alg");
+        let decode_param = |name: &str| -> Result<Vec<u8>, Error> {
+            let value = jwk
+                .get_str(name)
+                .ok_or_else(|| warn!("the JWK parameter `{}` should be specified", name))?;
+            base64::decode_url(value).map_err(Error::from)
+        };
+        let key = match (key_type, algorithm) {
+            ("RSA", Some("PS256")) => {
+                let (n, e) = (decode_param("n")?, decode_param("e")?);
+                PS256PublicKey::from_components(&n, &e).map(Self::PS256)
+            }
+            ("RSA", Some("RS256") | None) => {
+                let (n, e) = (decode_param("n")?, decode_param("e")?);
+                RS256PublicKey::from_components(&n, &e).map(Self::RS256)
+            }
+            ("EC", Some("ES256") | None) => {
+                if jwk.get_str("crv").is_some_and(|crv| crv != "P-256") {
+                    bail!("the JWK curve should be `P-256`");
+                }
+                let (x, y) = (decode_param("x")?, decode_param("y")?);
+                let bytes = [&[0x04][..], &x, &y].concat();
+                ES256PublicKey::from_bytes(&bytes).map(Self::ES256)
+            }
+            ("OKP", Some("EdDSA") | None) => {
+                if jwk.get_str("crv").is_some_and(|crv| crv != "Ed25519") {
+                    bail!("the JWK curve should be `Ed25519`");
+                }
+                let x = decode_param("x")?;
+                Ed25519PublicKey::from_bytes(&x).map(Self::EdDSA)
+            }
+            _ => bail!("the JWK with the key type `{}` is unsupported", key_type),
+        };
+        let key = key.map_err(|err| Error::new(err.to_string()))?;
+        match jwk.get_str("kid") {
+            Some(key_id) => Ok(key.with_key_id(key_id)),
+            None => Ok(key),
+        }
+    }
+
+    /// Sets the key ID.
+    pub fn with_key_id(self, key_id: &str) -> Self {
+        match self {
+            Self::RS256(key) => Self::RS256(key.with_key_id(key_id)),
+            Self::PS256(key) => Self::PS256(key.with_key_id(key_id)),
+            Self::ES256(key) => Self::ES256(key.with_key_id(key_id)),
+            Self::EdDSA(key) => Self::EdDSA(key.with_key_id(key_id)),
+        }
+    }
+
+    /// Returns the algorithm name.
+    pub fn algorithm(&self) -> &'static str {
+        match self {
+            Self::RS256(_) => "RS256",
+            Self::PS256(_) => "PS256",
+            Self::ES256(_) => "ES256",
+            Self::EdDSA(_) => "EdDSA",
+        }
+    }
+
+    /// Returns the key ID.
+    pub fn key_id(&self) -> Option<&str> {
+        let key_id = match self {
+            Self::RS256(key) => key.key_id(),
+            Self::PS256(key) => key.key_id(),
+            Self::ES256(key) => key.key_id(),
+            Self::EdDSA(key) => key.key_id(),
+        };
+        key_id.as_deref()
+    }
+
+    /// Converts `self` to a JSON Web Key.
+    pub fn to_jwk(&self) -> Map {
+        let mut jwk = Map::new();
+        match self {
+            Self::RS256(key) => {
+                let components = key.to_components();
+                jwk.upsert("kty", "RSA");
+                jwk.upsert("n", base64::encode_url(components.n));
+                jwk.upsert("e", base64::encode_url(components.e));
+            }
+            Self::PS256(key) => {
+                let components = key.to_components();
+                jwk.upsert("kty", "RSA");
+                jwk.upsert("n", base64::encode_url(components.n));
+                jwk.upsert("e", base64::encode_url(components.e));
+            }
+            Self::ES256(key) => {
+                let bytes = ECDSAP256PublicKeyLike::public_key(key).to_bytes_uncompressed();
+                jwk.upsert("kty", "EC");
+                jwk.upsert("crv", "P-256");
+                jwk.upsert("x", base64::encode_url(&bytes[1..33]));
+                jwk.upsert("y", base64::encode_url(&bytes[33..]));
+            }
+            Self::EdDSA(key) => {
+                jwk.upsert("kty", "OKP");
+                jwk.upsert("crv", "Ed25519");
+                jwk.upsert("x", base64::encode_url(key.to_bytes()));
+            }
+        }
+        jwk.upsert("alg", self.algorithm());
+        jwk.upsert("use", "sig");
+        if let Some(key_id) = self.key_id() {
+            jwk.upsert("kid", key_id);
+        }
+        jwk
+    }
+}
+
+impl JwtVerifier for JwtVerifyingKey {
+    fn verify_jwt<T>(
+        &self,
+        token: &str,
+        options: VerificationOptions,
+    ) -> Result<JWTClaims<T>, Error>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let options = Some(options);
+        let claims = match self {
+            Self::RS256(key) => key.verify_token(token, options),
+            Self::PS256(key) => key.verify_token(token, options),
+            Self::ES256(key) => key.verify_token(token, options),
+            Self::EdDSA(key) => key.verify_token(token, options),
+        };
+        claims.map_err(|err| Error::new(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JwtSigningKey, JwtVerifier, JwtVerifyingKey};
+    use crate::{extension::JsonObjectExt, Map};
+    use jwt_simple::{
+        claims::{Claims, NoCustomClaims},
+        common::VerificationOptions,
+        prelude::{Duration, ES256KeyPair, Ed25519KeyPair},
+    };
+
+    #[test]
+    fn it_converts_keys_to_jwks() {
+        let signing_keys = [
+            JwtSigningKey::ES256(ES256KeyPair::generate()).with_key_id("es256"),
+            JwtSigningKey::EdDSA(Ed25519KeyPair::generate()).with_key_id("eddsa"),
+        ];
+        for signing_key in signing_keys {
+            let jwk = signing_key.public_key().to_jwk();
+            assert_eq!(jwk.get_str("alg"), Some(signing_key.algorithm()));
+            assert_eq!(jwk.get_str("kid"), signing_key.key_id());
+
+            let verifying_key = JwtVerifyingKey::from_jwk(&jwk).unwrap();
+            assert_eq!(verifying_key.algorithm(), signing_key.algorithm());
+            assert_eq!(verifying_key.key_id(), signing_key.key_id());
+            assert_eq!(verifying_key.to_jwk(), jwk);
+
+            let claims = Claims::create(Duration::from_mins(5)).with_subject("alice");
+            let token = signing_key.sign(claims).unwrap();
+            let claims = verifying_key
+                .verify_jwt::<NoCustomClaims>(&token, VerificationOptions::default())
+                .unwrap();
+            assert_eq!(claims.subject.as_deref(), Some("alice"));
+        }
+    }
+
+    #[test]
+    fn it_rejects_unsupported_jwks() {
+        let jwk = Map::from_entry("kty", "oct");
+        assert!(JwtVerifyingKey::from_jwk(&jwk).is_err());
+
+        let signing_key = JwtSigningKey::ES256(ES256KeyPair::generate());
+        let mut jwk = signing_key.public_key().to_jwk();
+        jwk.upsert("crv", "P-384");
+        assert!(JwtVerifyingKey::from_jwk(&jwk).is_err());
+
+        jwk.remove("x");
+        jwk.upsert("crv", "P-256");
+        assert!(JwtVerifyingKey::from_jwk(&jwk).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{error::Error, extension::TomlTableExt, state::State, LazyLock};
+use std::time::Duration;
+use toml::Table;
+
+/// Throttling of the failed login attempts.
+///
+/// After the free attempts, each failed attempt delays the next attempt progressively,
+/// which starts from the `base-delay` and doubles up to the `max-delay`.
+/// The account is locked temporarily for the `lockout-duration`
+/// once the number of failed attempts reaches the `max-attempts`.
+///
+/// # Examples
+///
+/// ```toml
+/// [login-throttle]
+/// free-attempts = 3
+/// base-delay = "1s"
+/// max-delay = "5m"
+/// max-attempts = 10
+/// lockout-duration = "30m"
+/// ```
+///
+/// ```rust
+/// use std::time::Duration;
+/// use zino_core::auth::LoginThrottle;
+///
+/// let throttle = LoginThrottle::default();
+/// assert_eq!(throttle.delay(3), Duration::ZERO);
+/// assert_eq!(throttle.delay(5), Duration::from_secs(2));
+/// assert!(throttle.should_lock(10));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LoginThrottle {
+    /// Number of failed attempts without a delay.
+    free_attempts: u32,
+    /// Delay after the first throttled attempt.
+    base_delay: Duration,
+    /// Maximum delay.
+    max_delay: Duration,
+    /// Number of failed attempts to lock the account.
+    max_attempts: u32,
+    /// Duration of the temporary lockout.
+    lockout_duration: Duration,
+}
+
+impl LoginThrottle {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(max_attempts: u32, lockout_duration: Duration) -> Self {
+        Self {
+            free_attempts: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(5 * 60),
+            max_attempts,
+            lockout_duration,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let mut throttle = Self::default();
+        if let Some(max_attempts) = config.get_u32("max-attempts") {
+            throttle.max_attempts = max_attempts;
+        }
+        if let Some(lockout_duration) = config.get_duration("lockout-duration") {
+            throttle.lockout_duration = lockout_duration;
+        }
+        if let Some(free_attempts) = config.get_u32("free-attempts") {
+            throttle.free_attempts = free_attempts;
+        }
+        if let Some(base_delay) = config.get_duration("base-delay") {
+            throttle.base_delay = base_delay;
+        }
+        if let Some(max_delay) = config.get_duration("max-delay") {
+            throttle.max_delay = max_delay;
+        }
+        Ok(throttle)
+    }
+
+    /// Sets the number of failed attempts without a delay.
+    #[inline]
+    pub fn with_free_attempts(mut self, free_attempts: u32) -> Self {
+        self.free_attempts = free_attempts;
+        self
+    }
+
+    /// Sets the base delay and the maximum delay.
+    #[inline]
+    pub fn with_delay(mut self, base_delay: Duration, max_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self.max_delay = max_delay.max(base_delay);
+        self
+    }
+
+    /// Returns the delay before the next attempt after the number of failed attempts.
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        if failed_attempts <= self.free_attempts {
+            return Duration::ZERO;
+        }
+
+        let exponent = (failed_attempts - self.free_attempts - 1).min(31);
+        self.base_delay
+            .checked_mul(1 << exponent)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// Returns `true` if the account should be locked after the number of failed attempts.
+    #[inline]
+    pub fn should_lock(&self, failed_attempts: u32) -> bool {
+        self.max_attempts > 0 && failed_attempts >= self.max_attempts
+    }
+
+    /// Returns the duration of the temporary lockout.
+    #[inline]
+    pub fn lockout_duration(&self) -> Duration {
+        self.lockout_duration
+    }
+
+    /// Returns a reference to the shared login throttle.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_LOGIN_THROTTLE
+    }
+}
+
+impl Default for LoginThrottle {
+    #[inline]
+    fn default() -> Self {
+        Self::new(10, Duration::from_secs(30 * 60))
+    }
+}
+
+/// Shared login throttle.
+static SHARED_LOGIN_THROTTLE: LazyLock<LoginThrottle> = LazyLock::new(|| {
+    if let Some(config) = State::shared().get_config("login-throttle") {
+        LoginThrottle::try_from_config(config)
+            .unwrap_or_else(|err| panic!("fail to configure the login throttle: {err}"))
+    } else {
+        LoginThrottle::default()
+    }
+});
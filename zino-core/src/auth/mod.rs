@@ -1,33 +1,61 @@
 //! Authentication and authorization.
 
 mod access_key;
+mod api_key;
 mod authentication;
 mod authorization_provider;
 mod client_credentials;
+mod login_throttle;
+mod password_policy;
 mod security_token;
 mod session_id;
+mod session_store;
+mod totp;
 mod user_session;
 
 pub(crate) use security_token::ParseSecurityTokenError;
+pub(crate) use user_session::session_roles;
 
 pub use access_key::{AccessKeyId, SecretAccessKey};
+pub use api_key::{ApiKeyManager, ApiKeyRecord, ApiKeyStore, MemoryApiKeyStore};
 pub use authentication::Authentication;
 pub use authorization_provider::AuthorizationProvider;
 pub use client_credentials::ClientCredentials;
+pub use login_throttle::LoginThrottle;
+pub use password_policy::PasswordPolicy;
 pub use security_token::SecurityToken;
 pub use session_id::SessionId;
+pub use session_store::{MemorySessionStore, SessionManager, SessionRecord, SessionStore};
+pub use totp::TotpKey;
 pub use user_session::UserSession;
 
+#[cfg(feature = "jwt")]
+mod authorization_server;
+#[cfg(feature = "jwt")]
+mod jwk_set;
 #[cfg(feature = "jwt")]
 mod jwt_claims;
+#[cfg(feature = "jwt")]
+mod jwt_key;
 #[cfg(feature = "opa")]
 mod rego_engine;
+#[cfg(feature = "webauthn")]
+mod webauthn;
 
 #[cfg(feature = "jwt")]
 pub(crate) use jwt_claims::{default_time_tolerance, default_verification_options};
 
+#[cfg(feature = "jwt")]
+pub use authorization_server::AuthorizationServer;
+#[cfg(feature = "jwt")]
+pub use jwk_set::{JwkSet, RemoteJwkSet};
 #[cfg(feature = "jwt")]
 pub use jwt_claims::{JwtClaims, JwtHmacKey};
+#[cfg(feature = "jwt")]
+pub use jwt_key::{JwtSigningKey, JwtVerifier, JwtVerifyingKey};
 
 #[cfg(feature = "opa")]
 pub use rego_engine::RegoEngine;
+
+#[cfg(feature = "webauthn")]
+pub use webauthn::{PasskeyCredential, RelyingParty};
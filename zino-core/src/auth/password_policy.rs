@@ -0,0 +1,226 @@
+use crate::{
+    application::{self, PROJECT_DIR},
+    bail,
+    error::Error,
+    extension::TomlTableExt,
+    state::State,
+    LazyLock,
+};
+use std::{collections::HashSet, fs, path::Path};
+use toml::Table;
+
+/// Password policy for user accounts.
+///
+/// The policy checks the length and character classes of a password,
+/// and rejects the passwords in a breached-password list, which is a local file
+/// with one password per line. The number of previous passwords which can not be reused
+/// is specified by the `history-size`.
+///
+/// # Examples
+///
+/// ```toml
+/// [password-policy]
+/// min-length = 10
+/// max-length = 128
+/// require-uppercase = true
+/// require-lowercase = true
+/// require-digit = true
+/// require-symbol = false
+/// breached-passwords = "local/breached-passwords.txt"
+/// history-size = 5
+/// ```
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum length.
+    min_length: usize,
+    /// Maximum length.
+    max_length: usize,
+    /// A flag to require an uppercase letter.
+    require_uppercase: bool,
+    /// A flag to require a lowercase letter.
+    require_lowercase: bool,
+    /// A flag to require a digit.
+    require_digit: bool,
+    /// A flag to require a symbol.
+    require_symbol: bool,
+    /// Breached passwords in lowercase.
+    breached_passwords: HashSet<String>,
+    /// Number of previous passwords which can not be reused.
+    history_size: usize,
+}
+
+impl PasswordPolicy {
+    /// Creates a new instance with the minimum length.
+    #[inline]
+    pub fn new(min_length: usize) -> Self {
+        Self {
+            min_length,
+            max_length: 128,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_digit: false,
+            require_symbol: false,
+            breached_passwords: HashSet::new(),
+            history_size: 0,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let mut policy = Self::new(config.get_usize("min-length").unwrap_or(8));
+        if let Some(max_length) = config.get_usize("max-length") {
+            policy.max_length = max_length.max(policy.min_length);
+        }
+        policy.require_uppercase = config.get_bool("require-uppercase").unwrap_or(false);
+        policy.require_lowercase = config.get_bool("require-lowercase").unwrap_or(false);
+        policy.require_digit = config.get_bool("require-digit").unwrap_or(false);
+        policy.require_symbol = config.get_bool("require-symbol").unwrap_or(false);
+        policy.history_size = config.get_usize("history-size").unwrap_or(0);
+        if let Some(path) = config.get_str("breached-passwords") {
+            let path = application::join_path(&PROJECT_DIR, path);
+            policy.read_breached_passwords(path)?;
+        }
+        Ok(policy)
+    }
+
+    /// Sets the maximum length.
+    #[inline]
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.max(self.min_length);
+        self
+    }
+
+    /// Requires the password to contain an uppercase letter, a lowercase letter,
+    /// a digit and a symbol respectively.
+    #[inline]
+    pub fn require_character_classes(
+        mut self,
+        uppercase: bool,
+        lowercase: bool,
+        digit: bool,
+        symbol: bool,
+    ) -> Self {
+        self.require_uppercase = uppercase;
+        self.require_lowercase = lowercase;
+        self.require_digit = digit;
+        self.require_symbol = symbol;
+        self
+    }
+
+    /// Sets the number of previous passwords which can not be reused.
+    #[inline]
+    pub fn with_history_size(mut self, history_size: usize) -> Self {
+        self.history_size = history_size;
+        self
+    }
+
+    /// Adds a breached password.
+    #[inline]
+    pub fn add_breached_password(&mut self, password: &str) {
+        self.breached_passwords
+            .insert(password.trim().to_lowercase());
+    }
+
+    /// Reads the breached passwords from a local file with one password per line.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn read_breached_passwords(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let passwords = fs::read_to_string(path).map_err(|err| {
+            let path = path.display();
+            Error::new(format!(
+                "fail to read the breached passwords `{path}`: {err}"
+            ))
+        })?;
+        for password in passwords.lines() {
+            let password = password.trim();
+            if !(password.is_empty() || password.starts_with('#')) {
+                self.add_breached_password(password);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the number of previous passwords which can not be reused.
+    #[inline]
+    pub fn history_size(&self) -> usize {
+        self.history_size
+    }
+
+    /// Validates the password against the policy.
+    pub fn validate(&self, password: &str) -> Result<(), Error> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            bail!(
+                "the password should have at least {} characters",
+                self.min_length
+            );
+        }
+        if length > self.max_length {
+            bail!(
+                "the password should have at most {} characters",
+                self.max_length
+            );
+        }
+        if self.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+            bail!("the password should contain an uppercase letter");
+        }
+        if self.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+            bail!("the password should contain a lowercase letter");
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            bail!("the password should contain a digit");
+        }
+        if self.require_symbol
+            && !password
+                .chars()
+                .any(|c| !(c.is_alphanumeric() || c.is_whitespace()))
+        {
+            bail!("the password should contain a symbol");
+        }
+        if self.breached_passwords.contains(&password.to_lowercase()) {
+            bail!("the password has appeared in a data breach");
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the shared password policy.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_PASSWORD_POLICY
+    }
+}
+
+impl Default for PasswordPolicy {
+    #[inline]
+    fn default() -> Self {
+        Self::new(8)
+    }
+}
+
+/// Shared password policy.
+static SHARED_PASSWORD_POLICY: LazyLock<PasswordPolicy> = LazyLock::new(|| {
+    if let Some(config) = State::shared().get_config("password-policy") {
+        PasswordPolicy::try_from_config(config)
+            .unwrap_or_else(|err| panic!("fail to configure the password policy: {err}"))
+    } else {
+        PasswordPolicy::default()
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use super::PasswordPolicy;
+
+    #[test]
+    fn it_validates_passwords() {
+        let mut policy = PasswordPolicy::new(10).require_character_classes(true, true, true, false);
+        policy.add_breached_password("Password1234");
+        assert!(policy.validate("Zino2024rocks").is_ok());
+        assert!(policy.validate("Zino2024").is_err());
+        assert!(policy.validate("zino2024rocks").is_err());
+        assert!(policy.validate("ZINO2024ROCKS").is_err());
+        assert!(policy.validate("ZinoRocksHard").is_err());
+        assert!(policy.validate("pASSWORD1234").is_err());
+        assert!(policy.validate("Password1234").is_err());
+    }
+}
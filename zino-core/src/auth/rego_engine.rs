@@ -88,6 +88,23 @@ impl RegoEngine {
             .map_err(|err| Error::new(err.to_string()))
     }
 
+    /// Evaluates a Rego query that produces a boolean value for the input document
+    /// in the JSON format. The engine is locked across setting the input and evaluating,
+    /// so that concurrent evaluations can not observe each other's input.
+    pub fn eval_bool_query_with_input(
+        &self,
+        input_json: &str,
+        query: impl Into<String>,
+    ) -> Result<bool, Error> {
+        let mut engine = self.engine.lock();
+        engine
+            .set_input_json(input_json)
+            .map_err(|err| Error::new(err.to_string()))?;
+        engine
+            .eval_bool_query(query.into(), false)
+            .map_err(|err| Error::new(err.to_string()))
+    }
+
     /// Evaluates an `allow` query.
     #[inline]
     pub fn eval_allow_query(&self, query: impl Into<String>) -> bool {
@@ -0,0 +1,718 @@
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    BoxFuture, Map, Uuid,
+};
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+/// Record of an active session for a user.
+///
+/// The session ID is used as the `jti` of the access tokens, and the refresh token
+/// has a `jti` of the form `{session_id}.{nonce}` which is rotated on each refresh.
+#[derive(Debug, Clone, Default)]
+pub struct SessionRecord {
+    /// Session ID.
+    session_id: String,
+    /// User ID.
+    user_id: String,
+    /// Device of the client.
+    device: String,
+    /// IP address of the client.
+    ip: String,
+    /// Creation time.
+    created_at: DateTime,
+    /// Last-seen time.
+    last_seen_at: DateTime,
+    /// Expiration time.
+    expires_at: DateTime,
+    /// ID of the current refresh token.
+    refresh_token_id: String,
+}
+
+impl SessionRecord {
+    /// Creates a new instance for the user, expiring in `max-age`.
+    pub fn new(user_id: impl ToString, max_age: Duration) -> Self {
+        let now = DateTime::now();
+        let mut session = Self {
+            session_id: Uuid::now_v7().to_string(),
+            user_id: user_id.to_string(),
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + max_age,
+            ..Self::default()
+        };
+        session.rotate_refresh_token();
+        session
+    }
+
+    /// Attempts to create a new instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(session_id) = map.get_str("session_id") else {
+            bail!("the `session_id` field should be specified");
+        };
+        let Some(user_id) = map.get_str("user_id") else {
+            bail!("the `user_id` field should be specified");
+        };
+        let parse_datetime = |key| -> Result<DateTime, Error> {
+            match map.get_str(key) {
+                Some(value) => Ok(value.parse()?),
+                None => bail!("the `{}` field should be specified", key),
+            }
+        };
+        Ok(Self {
+            session_id: session_id.to_owned(),
+            user_id: user_id.to_owned(),
+            device: map.get_str("device").unwrap_or_default().to_owned(),
+            ip: map.get_str("ip").unwrap_or_default().to_owned(),
+            created_at: parse_datetime("created_at")?,
+            last_seen_at: parse_datetime("last_seen_at")?,
+            expires_at: parse_datetime("expires_at")?,
+            refresh_token_id: map
+                .get_str("refresh_token_id")
+                .unwrap_or_default()
+                .to_owned(),
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("session_id", self.session_id);
+        map.upsert("user_id", self.user_id);
+        map.upsert("device", self.device);
+        map.upsert("ip", self.ip);
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("last_seen_at", self.last_seen_at.to_string());
+        map.upsert("expires_at", self.expires_at.to_string());
+        map.upsert("refresh_token_id", self.refresh_token_id);
+        map
+    }
+
+    /// Sets the device of the client.
+    #[inline]
+    pub fn set_device(&mut self, device: impl ToString) {
+        self.device = device.to_string();
+    }
+
+    /// Sets the IP address of the client.
+    #[inline]
+    pub fn set_ip(&mut self, ip: impl ToString) {
+        self.ip = ip.to_string();
+    }
+
+    /// Updates the last-seen time.
+    #[inline]
+    pub fn touch(&mut self) {
+        self.last_seen_at = DateTime::now();
+    }
+
+    /// Generates a new ID for the refresh token, which invalidates the previous one.
+    pub fn rotate_refresh_token(&mut self) -> &str {
+        let nonce = format!("{:016x}", rand::random::<u64>());
+        self.refresh_token_id = format!("{}.{nonce}", self.session_id);
+        &self.refresh_token_id
+    }
+
+    /// Returns the session ID.
+    #[inline]
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Returns the user ID.
+    #[inline]
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Returns the device of the client.
+    #[inline]
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Returns the IP address of the client.
+    #[inline]
+    pub fn ip(&self) -> &str {
+        &self.ip
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    /// Returns the last-seen time.
+    #[inline]
+    pub fn last_seen_at(&self) -> DateTime {
+        self.last_seen_at
+    }
+
+    /// Returns the expiration time.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+
+    /// Returns the ID of the current refresh token.
+    #[inline]
+    pub fn refresh_token_id(&self) -> &str {
+        &self.refresh_token_id
+    }
+
+    /// Returns `true` if the session has expired.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= DateTime::now()
+    }
+}
+
+/// Storage backend for the sessions and the revoked tokens.
+pub trait SessionStore: Send + Sync {
+    /// Saves the session.
+    fn save_session<'a>(&'a self, session: &'a SessionRecord) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Updates the session only if the refresh token ID of the stored session
+    /// is `refresh_token_id`. It returns `false` if the refresh token has been rotated
+    /// by another request. The check and the update should be atomic.
+    fn update_session<'a>(
+        &'a self,
+        session: &'a SessionRecord,
+        refresh_token_id: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Finds the session by ID.
+    fn find_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SessionRecord>, Error>>;
+
+    /// Lists the sessions of the user.
+    fn list_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<SessionRecord>, Error>>;
+
+    /// Removes the session by ID and returns `true` if it exists.
+    fn remove_session<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Adds the JWT ID to the revocation list until the expiration time.
+    fn revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Adds the JWT ID to the revocation list only if it is absent, which is used to
+    /// redeem the single-use tokens. It returns `false` if the JWT ID has been revoked.
+    /// The check and the insertion should be atomic.
+    fn try_revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Lists the revoked JWT IDs which have not expired.
+    fn list_revoked_tokens(&self) -> BoxFuture<'_, Result<Vec<(String, DateTime)>, Error>>;
+}
+
+/// In-memory session store, which is only suitable for a single instance.
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    /// Sessions.
+    sessions: RwLock<HashMap<String, SessionRecord>>,
+    /// Revoked tokens.
+    revoked_tokens: RwLock<HashMap<String, DateTime>>,
+}
+
+impl MemorySessionStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn save_session<'a>(&'a self, session: &'a SessionRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut sessions = self.sessions.write();
+            sessions.retain(|_, session| !session.is_expired());
+            sessions.insert(session.session_id.clone(), session.clone());
+            Ok(())
+        })
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session: &'a SessionRecord,
+        refresh_token_id: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut sessions = self.sessions.write();
+            let rotated = sessions
+                .get(&session.session_id)
+                .map_or(true, |s| s.refresh_token_id != refresh_token_id);
+            if rotated {
+                return Ok(false);
+            }
+            sessions.insert(session.session_id.clone(), session.clone());
+            Ok(true)
+        })
+    }
+
+    fn find_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SessionRecord>, Error>> {
+        Box::pin(async move { Ok(self.sessions.read().get(session_id).cloned()) })
+    }
+
+    fn list_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<SessionRecord>, Error>> {
+        Box::pin(async move {
+            let sessions = self
+                .sessions
+                .read()
+                .values()
+                .filter(|session| session.user_id == user_id)
+                .cloned()
+                .collect();
+            Ok(sessions)
+        })
+    }
+
+    fn remove_session<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move { Ok(self.sessions.write().remove(session_id).is_some()) })
+    }
+
+    fn revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let now = DateTime::now();
+            let mut revoked_tokens = self.revoked_tokens.write();
+            revoked_tokens.retain(|_, expires_at| *expires_at > now);
+            revoked_tokens.insert(jwt_id.to_owned(), expires_at);
+            Ok(())
+        })
+    }
+
+    fn try_revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let now = DateTime::now();
+            let mut revoked_tokens = self.revoked_tokens.write();
+            revoked_tokens.retain(|_, expires_at| *expires_at > now);
+            if revoked_tokens.contains_key(jwt_id) {
+                return Ok(false);
+            }
+            revoked_tokens.insert(jwt_id.to_owned(), expires_at);
+            Ok(true)
+        })
+    }
+
+    fn list_revoked_tokens(&self) -> BoxFuture<'_, Result<Vec<(String, DateTime)>, Error>> {
+        Box::pin(async move {
+            let now = DateTime::now();
+            let revoked_tokens = self
+                .revoked_tokens
+                .read()
+                .iter()
+                .filter(|(_, &expires_at)| expires_at > now)
+                .map(|(jwt_id, &expires_at)| (jwt_id.clone(), expires_at))
+                .collect();
+            Ok(revoked_tokens)
+        })
+    }
+}
+
+/// Manager of the server-side sessions.
+///
+/// It records the active sessions of users in a [`SessionStore`], supports logout
+/// of one session or of all sessions, and keeps a revocation list for the JWT IDs.
+/// The revocation list is cached in memory so that it can be checked by
+/// `RequestContext::parse_jwt_claims()` for each request. For multiple instances
+/// sharing a persistent store, the revocations from other instances are loaded
+/// by [`sync_revoked_tokens()`] when a session is touched, at most once in the `sync-interval`.
+/// The last-seen time of a session is updated at most once in the `touch-interval`.
+///
+/// Refresh tokens are rotated on each refresh. If a refresh token is reused
+/// after the rotation, the session is considered compromised and terminated.
+///
+/// The shared manager can be configured by the `session` table with the `memory`
+/// or `accessor` store, or registered by [`SessionManager::register()`]
+/// before the first use.
+///
+/// # Examples
+///
+/// ```toml
+/// [session]
+/// store = "accessor"
+/// accessor = "redis"
+/// max-age = "30d"
+/// touch-interval = "1m"
+/// sync-interval = "1m"
+/// ```
+///
+/// [`sync_revoked_tokens()`]: SessionManager::sync_revoked_tokens
+pub struct SessionManager {
+    /// Session store.
+    store: Box<dyn SessionStore>,
+    /// Max age of the sessions.
+    max_age: Duration,
+    /// Min interval for updating the last-seen time of a session.
+    touch_interval: Duration,
+    /// Min interval for loading the revocation list from the store.
+    sync_interval: Duration,
+    /// Cached revocation list.
+    revoked_tokens: RwLock<HashMap<String, DateTime>>,
+    /// Last time when the revocation list was loaded.
+    synced_at: RwLock<Option<Instant>>,
+}
+
+impl SessionManager {
+    /// Creates a new instance with the session store.
+    #[inline]
+    pub fn new(store: impl SessionStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            max_age: Duration::from_secs(60 * 60 * 24 * 30),
+            touch_interval: Duration::from_secs(60),
+            sync_interval: Duration::from_secs(60),
+            revoked_tokens: RwLock::new(HashMap::new()),
+            synced_at: RwLock::new(None),
+        }
+    }
+
+    /// Sets the max age of the sessions.
+    #[inline]
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Sets the min interval for updating the last-seen time of a session.
+    #[inline]
+    pub fn with_touch_interval(mut self, touch_interval: Duration) -> Self {
+        self.touch_interval = touch_interval;
+        self
+    }
+
+    /// Sets the min interval for loading the revocation list from the store.
+    #[inline]
+    pub fn with_sync_interval(mut self, sync_interval: Duration) -> Self {
+        self.sync_interval = sync_interval;
+        self
+    }
+
+    /// Registers the shared session manager. It returns `false` if the shared manager
+    /// has already been initialized.
+    #[inline]
+    pub fn register(manager: Self) -> bool {
+        SHARED_SESSION_MANAGER.set(Some(manager)).is_ok()
+    }
+
+    /// Returns a reference to the shared session manager if it has been configured.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_SESSION_MANAGER
+            .get_or_init(Self::from_config)
+            .as_ref()
+    }
+
+    /// Creates a new session for the user. The `device` and `ip` should be taken from
+    /// the request headers and the remote address instead of the request body.
+    pub async fn create_session(
+        &self,
+        user_id: &str,
+        device: &str,
+        ip: &str,
+    ) -> Result<SessionRecord, Error> {
+        let mut session = SessionRecord::new(user_id, self.max_age);
+        session.set_device(device);
+        session.set_ip(ip);
+        self.store.save_session(&session).await?;
+        Ok(session)
+    }
+
+    /// Finds the active session by ID.
+    pub async fn find_session(&self, session_id: &str) -> Result<Option<SessionRecord>, Error> {
+        let session = self.store.find_session(session_id).await?;
+        Ok(session.filter(|session| !session.is_expired()))
+    }
+
+    /// Updates the last-seen time and the IP address of the active session.
+    /// The session is only saved if the IP address has changed or the last-seen time
+    /// is older than the `touch-interval`.
+    pub async fn touch_session(
+        &self,
+        session_id: &str,
+        ip: Option<&str>,
+    ) -> Result<Option<SessionRecord>, Error> {
+        self.sync_revoked_tokens_if_stale().await;
+
+        let Some(mut session) = self.find_session(session_id).await? else {
+            return Ok(None);
+        };
+        let ip_changed = ip.is_some_and(|ip| ip != session.ip);
+        if ip_changed || session.last_seen_at + self.touch_interval <= DateTime::now() {
+            session.touch();
+            if let Some(ip) = ip {
+                session.set_ip(ip);
+            }
+
+            // The update is skipped if the refresh token has been rotated concurrently.
+            let refresh_token_id = session.refresh_token_id.clone();
+            self.store
+                .update_session(&session, &refresh_token_id)
+                .await?;
+        }
+        Ok(Some(session))
+    }
+
+    /// Lists the active sessions of the user.
+    pub async fn list_sessions(&self, user_id: &str) -> Result<Vec<SessionRecord>, Error> {
+        let mut sessions = self.store.list_sessions(user_id).await?;
+        sessions.retain(|session| !session.is_expired());
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_seen_at));
+        Ok(sessions)
+    }
+
+    /// Rotates the refresh token with the JWT ID, and returns the session
+    /// with a new refresh token ID. The session is terminated if the refresh token
+    /// has been used before, including a concurrent refresh with the same token.
+    pub async fn rotate_refresh_token(&self, jwt_id: &str) -> Result<SessionRecord, Error> {
+        let Some((session_id, _)) = jwt_id.split_once('.') else {
+            bail!("401 Unauthorized: the refresh token is not bound to a session");
+        };
+        let Some(mut session) = self.find_session(session_id).await? else {
+            bail!("401 Unauthorized: the session has been terminated");
+        };
+        let mut rotated = session.refresh_token_id == jwt_id;
+        if rotated {
+            session.rotate_refresh_token();
+            session.touch();
+            rotated = self.store.update_session(&session, jwt_id).await?;
+        }
+        if !rotated {
+            tracing::warn!(
+                session_id,
+                user_id = session.user_id,
+                "reuse of the refresh token is detected"
+            );
+            self.logout(session_id).await?;
+            bail!("401 Unauthorized: the refresh token has been used");
+        }
+        Ok(session)
+    }
+
+    /// Terminates the session and revokes its tokens.
+    /// It returns `false` if the session does not exist.
+    pub async fn logout(&self, session_id: &str) -> Result<bool, Error> {
+        let Some(session) = self.store.find_session(session_id).await? else {
+            return Ok(false);
+        };
+        self.store.remove_session(session_id).await?;
+        if !session.is_expired() {
+            self.revoke_token(session_id, session.expires_at).await?;
+        }
+        Ok(true)
+    }
+
+    /// Terminates all the sessions of the user and returns the number of sessions.
+    pub async fn logout_all(&self, user_id: &str) -> Result<usize, Error> {
+        let sessions = self.store.list_sessions(user_id).await?;
+        let mut num_sessions = 0;
+        for session in sessions {
+            if self.logout(&session.session_id).await? {
+                num_sessions += 1;
+            }
+        }
+        Ok(num_sessions)
+    }
+
+    /// Revokes the token with the JWT ID until the expiration time.
+    pub async fn revoke_token(&self, jwt_id: &str, expires_at: DateTime) -> Result<(), Error> {
+        self.store.revoke_token(jwt_id, expires_at).await?;
+        self.revoked_tokens
+            .write()
+            .insert(jwt_id.to_owned(), expires_at);
+        Ok(())
+    }
+
+    /// Redeems the single-use token with the JWT ID until the expiration time.
+    /// It returns `false` if the token has already been redeemed or revoked.
+    pub async fn redeem_token(&self, jwt_id: &str, expires_at: DateTime) -> Result<bool, Error> {
+        if !self.store.try_revoke_token(jwt_id, expires_at).await? {
+            return Ok(false);
+        }
+        self.revoked_tokens
+            .write()
+            .insert(jwt_id.to_owned(), expires_at);
+        Ok(true)
+    }
+
+    /// Loads the revocation list from the session store.
+    pub async fn sync_revoked_tokens(&self) -> Result<(), Error> {
+        let revoked_tokens = self.store.list_revoked_tokens().await?;
+        let now = DateTime::now();
+        let mut cache = self.revoked_tokens.write();
+        cache.retain(|_, expires_at| *expires_at > now);
+        cache.extend(revoked_tokens);
+        Ok(())
+    }
+
+    /// Loads the revocation list if it has not been loaded in the `sync-interval`.
+    async fn sync_revoked_tokens_if_stale(&self) {
+        let is_stale = self
+            .synced_at
+            .read()
+            .map_or(true, |synced_at| synced_at.elapsed() >= self.sync_interval);
+        if is_stale {
+            *self.synced_at.write() = Some(Instant::now());
+            if let Err(err) = self.sync_revoked_tokens().await {
+                tracing::warn!("fail to load the revoked tokens: {err}");
+            }
+        }
+    }
+
+    /// Returns `true` if the JWT ID or the session it belongs to has been revoked.
+    pub fn is_revoked(&self, jwt_id: &str) -> bool {
+        let now = DateTime::now();
+        let revoked_tokens = self.revoked_tokens.read();
+        let is_revoked = |jwt_id: &str| {
+            revoked_tokens
+                .get(jwt_id)
+                .is_some_and(|&expires_at| expires_at > now)
+        };
+        is_revoked(jwt_id)
+            || jwt_id
+                .split_once('.')
+                .is_some_and(|(session_id, _)| is_revoked(session_id))
+    }
+
+    /// Creates a new instance from the `session` config.
+    fn from_config() -> Option<Self> {
+        let config = State::shared().get_config("session")?;
+        let mut manager = match config.get_str("store").unwrap_or("memory") {
+            "memory" => Self::new(MemorySessionStore::new()),
+            #[cfg(feature = "accessor")]
+            "accessor" => {
+                let accessor_name = config.get_str("accessor").unwrap_or("session");
+                match crate::accessor::AccessorSessionStore::try_new(accessor_name) {
+                    Ok(store) => Self::new(store),
+                    Err(err) => {
+                        tracing::error!("fail to configure the session store: {err}");
+                        return None;
+                    }
+                }
+            }
+            store => {
+                tracing::error!("unsupported session store `{store}`");
+                return None;
+            }
+        };
+        if let Some(max_age) = config.get_duration("max-age") {
+            manager = manager.with_max_age(max_age);
+        }
+        if let Some(touch_interval) = config.get_duration("touch-interval") {
+            manager = manager.with_touch_interval(touch_interval);
+        }
+        if let Some(sync_interval) = config.get_duration("sync-interval") {
+            manager = manager.with_sync_interval(sync_interval);
+        }
+        Some(manager)
+    }
+}
+
+impl std::fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("max_age", &self.max_age)
+            .field("touch_interval", &self.touch_interval)
+            .field("sync_interval", &self.sync_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared session manager.
+static SHARED_SESSION_MANAGER: OnceLock<Option<SessionManager>> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionManager, SessionRecord};
+    use crate::datetime::DateTime;
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn it_rotates_refresh_tokens() {
+        let mut session = SessionRecord::new("alice", Duration::from_secs(60));
+        let refresh_token_id = session.refresh_token_id().to_owned();
+        assert!(refresh_token_id.starts_with(session.session_id()));
+        assert_ne!(session.rotate_refresh_token(), refresh_token_id);
+        assert!(!session.is_expired());
+
+        let map = session.clone().into_map();
+        let record = SessionRecord::try_from_map(&map).unwrap();
+        assert_eq!(record.refresh_token_id(), session.refresh_token_id());
+        assert_eq!(
+            record.expires_at().timestamp(),
+            session.expires_at().timestamp()
+        );
+
+        let manager = SessionManager::new(super::MemorySessionStore::new());
+        let expires_at = DateTime::now() + Duration::from_secs(60);
+        manager
+            .revoked_tokens
+            .write()
+            .insert(session.session_id().to_owned(), expires_at);
+        assert!(manager.is_revoked(session.session_id()));
+        assert!(manager.is_revoked(&refresh_token_id));
+        assert!(!manager.is_revoked("alice"));
+
+        let jwt_id = "0193a7b0-6f1e-7c3a-9d3e-5b8e2f1c4a60";
+        assert!(block_on(manager.redeem_token(jwt_id, expires_at)).unwrap());
+        assert!(!block_on(manager.redeem_token(jwt_id, expires_at)).unwrap());
+        assert!(manager.is_revoked(jwt_id));
+    }
+
+    #[test]
+    fn it_terminates_sessions_on_reused_refresh_tokens() {
+        block_on(async {
+            let manager = SessionManager::new(super::MemorySessionStore::new());
+            let session = manager.create_session("alice", "", "").await.unwrap();
+            let refresh_token_id = session.refresh_token_id();
+            let rotated_session = manager
+                .rotate_refresh_token(refresh_token_id)
+                .await
+                .unwrap();
+            assert_ne!(rotated_session.refresh_token_id(), refresh_token_id);
+
+            assert!(manager
+                .rotate_refresh_token(refresh_token_id)
+                .await
+                .is_err());
+            let session_id = session.session_id();
+            assert!(manager.find_session(session_id).await.unwrap().is_none());
+            assert!(manager.is_revoked(rotated_session.refresh_token_id()));
+        });
+    }
+}
@@ -1,10 +1,11 @@
 use super::{AccessKeyId, SessionId};
+use crate::Uuid;
 use crate::{application::APP_DOMAIN, crypto::Digest};
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
+use std::{any::Any, str::FromStr};
 
 #[cfg(feature = "jwt")]
-use crate::{auth::JwtClaims, error::Error, extension::JsonObjectExt, warn};
+use crate::{auth::JwtClaims, bail, error::Error, extension::JsonObjectExt, warn};
 
 /// Role-based user sessions.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,9 +102,14 @@ where
     <U as FromStr>::Err: std::error::Error + Send + 'static,
 {
     /// Attempts to construct an instance from a `JwtClaims`.
+    /// The tokens issued to the OAuth clients are rejected,
+    /// since they are limited by the scopes instead of the roles.
     #[cfg(feature = "jwt")]
     pub fn try_from_jwt_claims(claims: JwtClaims) -> Result<Self, Error> {
         let data = claims.data();
+        if data.contains_key("token_use") || data.contains_key("client_id") {
+            bail!("the JWT token is issued to a third-party client");
+        }
         let user_id = claims
             .subject()
             .map(|s| s.into())
@@ -254,3 +260,43 @@ impl<U, T> UserSession<U, String, T> {
         true
     }
 }
+
+/// Returns the roles of the user session carried by the extension data.
+/// Only the user sessions with the user ID of `Uuid`, `i64`, `u64` or `String`
+/// and the roles of `String` are supported.
+pub(crate) fn session_roles(extension: &dyn Any) -> Option<&[String]> {
+    extension
+        .downcast_ref::<UserSession<Uuid>>()
+        .map(|session| session.roles())
+        .or_else(|| {
+            extension
+                .downcast_ref::<UserSession<i64>>()
+                .map(|session| session.roles())
+        })
+        .or_else(|| {
+            extension
+                .downcast_ref::<UserSession<u64>>()
+                .map(|session| session.roles())
+        })
+        .or_else(|| {
+            extension
+                .downcast_ref::<UserSession<String>>()
+                .map(|session| session.roles())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{session_roles, UserSession};
+
+    #[test]
+    fn it_gets_session_roles() {
+        let mut session = UserSession::<i64>::new(1, None);
+        session.set_roles(vec!["hr:manager".to_owned()]);
+        assert_eq!(
+            session_roles(&session),
+            Some(["hr:manager".to_owned()].as_slice())
+        );
+        assert_eq!(session_roles(&()), None);
+    }
+}
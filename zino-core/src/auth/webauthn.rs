@@ -0,0 +1,699 @@
+use crate::{
+    application::APP_NAME,
+    bail, crypto,
+    datetime::DateTime,
+    encoding::base64,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    json,
+    state::State,
+    warn, JsonValue, LazyLock, Map,
+};
+use ciborium::Value;
+use hmac::{Hmac, Mac};
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+use toml::Table;
+
+/// Flag of the user presence.
+const FLAG_USER_PRESENT: u8 = 0x01;
+
+/// Flag of the user verification.
+const FLAG_USER_VERIFIED: u8 = 0x04;
+
+/// Flag of the attested credential data.
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// COSE algorithm identifier for ES256.
+const COSE_ALG_ES256: i128 = -7;
+
+/// A WebAuthn relying party for registering and authenticating passkeys.
+/// See [Web Authentication](https://www.w3.org/TR/webauthn-3/).
+///
+/// Challenges are stateless: each challenge is bound to a subject such as the user ID
+/// and signed with the secret key of the relying party, so that it can be verified
+/// without a server-side session. To make a challenge single-use, the caller should record
+/// the expiration time returned by [`verify_assertion()`](RelyingParty::verify_assertion)
+/// and reject a challenge which does not expire later than the recorded one.
+/// Only the `ES256` credentials are supported, and the attestation statements are not verified.
+///
+/// An authenticator without a signature counter always reports `0`, which disables
+/// the detection of cloned authenticators. Such authenticators can be rejected
+/// by setting `sign-count-required` to `true`.
+///
+/// # Examples
+///
+/// ```toml
+/// [webauthn]
+/// rp-id = "example.com"
+/// rp-name = "Example"
+/// origins = ["https://example.com"]
+/// challenge-ttl = "5m"
+/// user-verification = "required"
+/// sign-count-required = false
+/// ```
+#[derive(Debug, Clone)]
+pub struct RelyingParty {
+    /// Relying party ID.
+    id: String,
+    /// Relying party name.
+    name: String,
+    /// Allowed origins.
+    origins: Vec<String>,
+    /// Time-to-live of the challenges.
+    challenge_ttl: Duration,
+    /// A flag to require the user verification.
+    user_verification_required: bool,
+    /// A flag to require the signature counter of the authenticator.
+    sign_count_required: bool,
+    /// Secret key for signing the challenges.
+    secret_key: Option<Vec<u8>>,
+}
+
+impl RelyingParty {
+    /// Creates a new instance with the relying party ID and the origin.
+    pub fn new(id: impl Into<String>, origin: impl Into<String>) -> Self {
+        let id = id.into();
+        Self {
+            name: id.clone(),
+            id,
+            origins: vec![origin.into()],
+            challenge_ttl: Duration::from_secs(5 * 60),
+            user_verification_required: false,
+            sign_count_required: false,
+            secret_key: None,
+        }
+    }
+
+    /// Attempts to create a new instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let id = config
+            .get_str("rp-id")
+            .ok_or_else(|| warn!("the `rp-id` field should be specified"))?;
+        let mut origins = config.get_str_array("origins").unwrap_or_default();
+        if let Some(origin) = config.get_str("origin") {
+            origins.push(origin);
+        }
+
+        let Some((origin, other_origins)) = origins.split_first() else {
+            bail!("the `origins` field should be specified");
+        };
+        let mut rp = Self::new(id, *origin);
+        for origin in other_origins {
+            rp.origins.push((*origin).to_owned());
+        }
+        if let Some(name) = config.get_str("rp-name") {
+            rp.name = name.to_owned();
+        }
+        if let Some(challenge_ttl) = config.get_duration("challenge-ttl") {
+            rp.challenge_ttl = challenge_ttl;
+        }
+        if let Some(user_verification) = config.get_str("user-verification") {
+            rp.user_verification_required = user_verification == "required";
+        }
+        if let Some(sign_count_required) = config.get_bool("sign-count-required") {
+            rp.sign_count_required = sign_count_required;
+        }
+        Ok(rp)
+    }
+
+    /// Sets the relying party name.
+    #[inline]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the secret key for signing the challenges.
+    #[inline]
+    pub fn with_secret_key(mut self, secret_key: &[u8]) -> Self {
+        self.secret_key = Some(secret_key.to_vec());
+        self
+    }
+
+    /// Requires the user verification such as a PIN or biometrics.
+    #[inline]
+    pub fn require_user_verification(mut self, required: bool) -> Self {
+        self.user_verification_required = required;
+        self
+    }
+
+    /// Requires the authenticator to support the signature counter.
+    #[inline]
+    pub fn require_sign_count(mut self, required: bool) -> Self {
+        self.sign_count_required = required;
+        self
+    }
+
+    /// Returns the relying party ID.
+    #[inline]
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the relying party name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Generates a challenge bound to the subject.
+    pub fn generate_challenge(&self, subject: &str) -> String {
+        let expires_at = DateTime::now() + self.challenge_ttl;
+        let mut payload = rand::random::<[u8; 16]>().to_vec();
+        payload.extend_from_slice(&expires_at.timestamp().to_be_bytes());
+
+        let mac = self.challenge_mac(subject, &payload);
+        payload.extend_from_slice(&mac.finalize().into_bytes()[..16]);
+        base64::encode_url(payload)
+    }
+
+    /// Verifies the challenge for the subject and returns its expiration time.
+    pub fn verify_challenge(&self, subject: &str, challenge: &str) -> Result<DateTime, Error> {
+        let data = base64::decode_url(challenge)?;
+        if data.len() != 40 {
+            bail!("401 Unauthorized: invalid WebAuthn challenge");
+        }
+
+        let (payload, tag) = data.split_at(24);
+        if self
+            .challenge_mac(subject, payload)
+            .verify_truncated_left(tag)
+            .is_err()
+        {
+            bail!("401 Unauthorized: invalid WebAuthn challenge");
+        }
+
+        let mut timestamp = [0; 8];
+        timestamp.copy_from_slice(&payload[16..]);
+        let expires_at = DateTime::from_timestamp(i64::from_be_bytes(timestamp));
+        if DateTime::now() > expires_at {
+            bail!("401 Unauthorized: the WebAuthn challenge has expired");
+        }
+        Ok(expires_at)
+    }
+
+    /// Returns the options for `navigator.credentials.create()` to register a passkey.
+    pub fn creation_options(
+        &self,
+        user_id: &str,
+        user_name: &str,
+        exclude_credentials: &[PasskeyCredential],
+    ) -> Map {
+        let exclude_credentials = exclude_credentials
+            .iter()
+            .map(|passkey| passkey.descriptor())
+            .collect::<Vec<_>>();
+        let options = json!({
+            "challenge": self.generate_challenge(user_id),
+            "rp": {
+                "id": self.id,
+                "name": self.name,
+            },
+            "user": {
+                "id": base64::encode_url(user_id),
+                "name": user_name,
+                "displayName": user_name,
+            },
+            "pubKeyCredParams": [
+                {
+                    "type": "public-key",
+                    "alg": COSE_ALG_ES256 as i64,
+                },
+            ],
+            "timeout": self.challenge_ttl.as_millis() as u64,
+            "attestation": "none",
+            "authenticatorSelection": {
+                "residentKey": "preferred",
+                "userVerification": self.user_verification(),
+            },
+            "excludeCredentials": exclude_credentials,
+        });
+        options.as_object().cloned().unwrap_or_default()
+    }
+
+    /// Returns the options for `navigator.credentials.get()` to authenticate with a passkey.
+    pub fn request_options(&self, subject: &str, allow_credentials: &[PasskeyCredential]) -> Map {
+        let allow_credentials = allow_credentials
+            .iter()
+            .map(|passkey| passkey.descriptor())
+            .collect::<Vec<_>>();
+        let options = json!({
+            "challenge": self.generate_challenge(subject),
+            "rpId": self.id,
+            "timeout": self.challenge_ttl.as_millis() as u64,
+            "userVerification": self.user_verification(),
+            "allowCredentials": allow_credentials,
+        });
+        options.as_object().cloned().unwrap_or_default()
+    }
+
+    /// Verifies the registration response of a new credential for the subject.
+    /// The `credential` is the JSON serialization of a `PublicKeyCredential`.
+    pub fn verify_registration(
+        &self,
+        subject: &str,
+        credential: &Map,
+    ) -> Result<PasskeyCredential, Error> {
+        let response = credential
+            .get_object("response")
+            .ok_or_else(|| warn!("the credential `response` should be specified"))?;
+        self.verify_client_data(subject, response, "webauthn.create")?;
+
+        let attestation_object = decode_field(response, "attestationObject")?;
+        let attestation = ciborium::from_reader::<Value, _>(attestation_object.as_slice())?;
+        let auth_data = get_cbor_entry(&attestation, "authData")
+            .and_then(|value| value.as_bytes())
+            .ok_or_else(|| warn!("the attestation object should contain `authData`"))?;
+        let auth_data = self.parse_authenticator_data(auth_data)?;
+        let Some((credential_id, public_key)) = auth_data.attested_credential else {
+            bail!("the authenticator data should contain the attested credential data");
+        };
+
+        let credential_id = base64::encode_url(credential_id);
+        if credential
+            .get_str("id")
+            .is_some_and(|id| id != credential_id)
+        {
+            bail!("the credential ID does not match the authenticator data");
+        }
+        Ok(PasskeyCredential {
+            credential_id,
+            public_key: base64::encode_url(public_key),
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Verifies the assertion response for the subject with the registered passkey,
+    /// and updates the signature counter of the passkey. It returns the expiration time
+    /// of the challenge, which should be recorded to make the challenge single-use.
+    pub fn verify_assertion(
+        &self,
+        subject: &str,
+        passkey: &mut PasskeyCredential,
+        credential: &Map,
+    ) -> Result<DateTime, Error> {
+        if credential.get_str("id") != Some(passkey.credential_id()) {
+            bail!("401 Unauthorized: the credential ID does not match the passkey");
+        }
+
+        let response = credential
+            .get_object("response")
+            .ok_or_else(|| warn!("the credential `response` should be specified"))?;
+        let (client_data_json, expires_at) =
+            self.verify_client_data(subject, response, "webauthn.get")?;
+        let authenticator_data = decode_field(response, "authenticatorData")?;
+        let auth_data = self.parse_authenticator_data(&authenticator_data)?;
+
+        let signature = decode_field(response, "signature")?;
+        let signature = Signature::from_der(&signature)?;
+        let public_key = base64::decode_url(&passkey.public_key)?;
+        let verifying_key = VerifyingKey::from_sec1_bytes(&public_key)?;
+        let message = [
+            authenticator_data.as_slice(),
+            Sha256::digest(client_data_json).as_slice(),
+        ]
+        .concat();
+        if verifying_key.verify(&message, &signature).is_err() {
+            bail!("401 Unauthorized: invalid signature of the assertion");
+        }
+
+        let sign_count = auth_data.sign_count;
+        if sign_count == 0 && passkey.sign_count == 0 {
+            if self.sign_count_required {
+                bail!("401 Unauthorized: the authenticator does not support the signature counter");
+            }
+        } else if sign_count <= passkey.sign_count {
+            bail!("401 Unauthorized: the signature counter of the authenticator does not increase");
+        }
+        passkey.sign_count = sign_count;
+        Ok(expires_at)
+    }
+
+    /// Returns a reference to the shared relying party.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_RELYING_PARTY
+    }
+
+    /// Returns the user verification requirement.
+    fn user_verification(&self) -> &'static str {
+        if self.user_verification_required {
+            "required"
+        } else {
+            "preferred"
+        }
+    }
+
+    /// Creates a MAC for the challenge payload.
+    fn challenge_mac(&self, subject: &str, payload: &[u8]) -> Hmac<Sha256> {
+        let secret_key = self.secret_key.as_deref().unwrap_or(SECRET_KEY.as_slice());
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret_key).expect("HMAC can take key of any size");
+        mac.update(subject.as_bytes());
+        mac.update(&[0]);
+        mac.update(payload);
+        mac
+    }
+
+    /// Verifies the client data and returns the raw JSON bytes
+    /// together with the expiration time of the challenge.
+    fn verify_client_data(
+        &self,
+        subject: &str,
+        response: &Map,
+        expected_type: &str,
+    ) -> Result<(Vec<u8>, DateTime), Error> {
+        let client_data_json = decode_field(response, "clientDataJSON")?;
+        let client_data = serde_json::from_slice::<Map>(&client_data_json)?;
+        if client_data.get_str("type") != Some(expected_type) {
+            bail!("the client data type should be `{}`", expected_type);
+        }
+
+        let challenge = client_data
+            .get_str("challenge")
+            .ok_or_else(|| warn!("the client data should contain `challenge`"))?;
+        let expires_at = self.verify_challenge(subject, challenge)?;
+
+        let origin = client_data.get_str("origin").unwrap_or_default();
+        if !self.origins.iter().any(|s| s == origin) {
+            bail!("401 Unauthorized: the origin `{}` is not allowed", origin);
+        }
+        Ok((client_data_json, expires_at))
+    }
+
+    /// Parses the authenticator data and verifies the RP ID hash and flags.
+    fn parse_authenticator_data<'a>(&self, data: &'a [u8]) -> Result<AuthenticatorData<'a>, Error> {
+        if data.len() < 37 {
+            bail!("the authenticator data is too short");
+        }
+        if data[..32] != Sha256::digest(self.id.as_bytes())[..] {
+            bail!("401 Unauthorized: the RP ID hash does not match");
+        }
+
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 {
+            bail!("401 Unauthorized: the user is not present");
+        }
+        if self.user_verification_required && flags & FLAG_USER_VERIFIED == 0 {
+            bail!("401 Unauthorized: the user is not verified");
+        }
+
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+            // AAGUID (16 bytes) and the length of credential ID (2 bytes)
+            let Some(credential_data) = data.get(37..) else {
+                bail!("the attested credential data is absent");
+            };
+            if credential_data.len() < 18 {
+                bail!("the attested credential data is too short");
+            }
+
+            let id_length = usize::from(u16::from_be_bytes([
+                credential_data[16],
+                credential_data[17],
+            ]));
+            let Some(credential_id) = credential_data.get(18..18 + id_length) else {
+                bail!("the credential ID is truncated");
+            };
+            let mut cose_key = &credential_data[18 + id_length..];
+            let public_key = ciborium::from_reader::<Value, _>(&mut cose_key)?;
+            Some((credential_id, parse_cose_key(&public_key)?))
+        } else {
+            None
+        };
+        Ok(AuthenticatorData {
+            sign_count,
+            attested_credential,
+        })
+    }
+}
+
+/// A passkey credential registered by a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasskeyCredential {
+    /// Base64url-encoded credential ID.
+    credential_id: String,
+    /// Base64url-encoded public key in the SEC1 uncompressed form.
+    public_key: String,
+    /// Signature counter.
+    sign_count: u32,
+}
+
+impl PasskeyCredential {
+    /// Returns the credential ID.
+    #[inline]
+    pub fn credential_id(&self) -> &str {
+        &self.credential_id
+    }
+
+    /// Returns the signature counter.
+    #[inline]
+    pub fn sign_count(&self) -> u32 {
+        self.sign_count
+    }
+
+    /// Attempts to create a new instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let credential_id = map
+            .get_str("credential_id")
+            .ok_or_else(|| warn!("the `credential_id` field should be specified"))?;
+        let public_key = map
+            .get_str("public_key")
+            .ok_or_else(|| warn!("the `public_key` field should be specified"))?;
+        Ok(Self {
+            credential_id: credential_id.to_owned(),
+            public_key: public_key.to_owned(),
+            sign_count: map.get_u32("sign_count").unwrap_or_default(),
+        })
+    }
+
+    /// Converts `self` to a map.
+    pub fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.upsert("credential_id", self.credential_id.as_str());
+        map.upsert("public_key", self.public_key.as_str());
+        map.upsert("sign_count", self.sign_count);
+        map
+    }
+
+    /// Returns the credential descriptor.
+    fn descriptor(&self) -> JsonValue {
+        json!({
+            "type": "public-key",
+            "id": self.credential_id,
+        })
+    }
+}
+
+/// Parsed authenticator data.
+struct AuthenticatorData<'a> {
+    /// Signature counter.
+    sign_count: u32,
+    /// Credential ID and the public key.
+    attested_credential: Option<(&'a [u8], Vec<u8>)>,
+}
+
+/// Decodes a base64url-encoded field of the response.
+fn decode_field(response: &Map, field: &str) -> Result<Vec<u8>, Error> {
+    let value = response
+        .get_str(field)
+        .ok_or_else(|| warn!("the `{}` field should be specified", field))?;
+    base64::decode_url(value).map_err(Error::from)
+}
+
+/// Gets the entry of a CBOR map with a text key.
+fn get_cbor_entry<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    value
+        .as_map()?
+        .iter()
+        .find_map(|(k, v)| (k.as_text() == Some(key)).then_some(v))
+}
+
+/// Parses a COSE key and returns the public key in the SEC1 uncompressed form.
+fn parse_cose_key(value: &Value) -> Result<Vec<u8>, Error> {
+    let entries = value
+        .as_map()
+        .ok_or_else(|| warn!("the COSE key should be a map"))?;
+    let get = |label: i128| {
+        entries.iter().find_map(|(k, v)| {
+            k.as_integer()
+                .is_some_and(|i| i128::from(i) == label)
+                .then_some(v)
+        })
+    };
+    let get_integer = |label: i128| get(label).and_then(|v| v.as_integer()).map(i128::from);
+    if get_integer(1) != Some(2)
+        || get_integer(3) != Some(COSE_ALG_ES256)
+        || get_integer(-1) != Some(1)
+    {
+        bail!("only the `ES256` credentials with the P-256 curve are supported");
+    }
+
+    let x = get(-2).and_then(|v| v.as_bytes());
+    let y = get(-3).and_then(|v| v.as_bytes());
+    match (x, y) {
+        (Some(x), Some(y)) if x.len() == 32 && y.len() == 32 => Ok([&[0x04][..], x, y].concat()),
+        _ => bail!("invalid coordinates of the COSE key"),
+    }
+}
+
+/// Shared relying party.
+static SHARED_RELYING_PARTY: LazyLock<RelyingParty> = LazyLock::new(|| {
+    let config = State::shared()
+        .get_config("webauthn")
+        .expect("field `webauthn` should be a table");
+    RelyingParty::try_from_config(config)
+        .unwrap_or_else(|err| panic!("fail to configure the WebAuthn relying party: {err}"))
+});
+
+/// Secret key for signing the challenges.
+static SECRET_KEY: LazyLock<[u8; 64]> = LazyLock::new(|| {
+    let app_config = State::shared().config();
+    let config = app_config.get_table("webauthn").unwrap_or(app_config);
+    let checksum: [u8; 32] = config
+        .get_str("checksum")
+        .and_then(|checksum| checksum.as_bytes().try_into().ok())
+        .unwrap_or_else(|| {
+            let secret = config.get_str("secret").unwrap_or_else(|| {
+                tracing::warn!("auto-generated `secret` is used for deriving a secret key");
+                APP_NAME.as_ref()
+            });
+            crypto::digest(secret.as_bytes())
+        });
+    let info = config.get_str("info").unwrap_or("ZINO:WEBAUTHN");
+    crypto::derive_key(info, &checksum)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::{PasskeyCredential, RelyingParty};
+    use crate::{encoding::base64, extension::JsonObjectExt, json, Map};
+    use ciborium::Value;
+    use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+    use sha2::{Digest, Sha256};
+
+    const ORIGIN: &str = "https://example.com";
+
+    /// A software authenticator with a fixed P-256 key.
+    struct SoftwareAuthenticator {
+        signing_key: SigningKey,
+        credential_id: Vec<u8>,
+        sign_count: u32,
+    }
+
+    impl SoftwareAuthenticator {
+        fn new() -> Self {
+            Self {
+                signing_key: SigningKey::from_slice(&[7; 32]).unwrap(),
+                credential_id: b"software-credential".to_vec(),
+                sign_count: 0,
+            }
+        }
+
+        fn client_data(kind: &str, challenge: &str) -> Vec<u8> {
+            json!({
+                "type": kind,
+                "challenge": challenge,
+                "origin": ORIGIN,
+            })
+            .to_string()
+            .into_bytes()
+        }
+
+        fn auth_data(&self, rp_id: &str, flags: u8, attested: bool) -> Vec<u8> {
+            let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+            data.push(flags);
+            data.extend_from_slice(&self.sign_count.to_be_bytes());
+            if attested {
+                let point = self.signing_key.verifying_key().to_encoded_point(false);
+                let cose_key = Value::Map(vec![
+                    (1.into(), 2.into()),
+                    (3.into(), (-7).into()),
+                    ((-1).into(), 1.into()),
+                    ((-2).into(), Value::Bytes(point.x().unwrap().to_vec())),
+                    ((-3).into(), Value::Bytes(point.y().unwrap().to_vec())),
+                ]);
+                data.extend_from_slice(&[0; 16]);
+                data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+                data.extend_from_slice(&self.credential_id);
+                ciborium::into_writer(&cose_key, &mut data).unwrap();
+            }
+            data
+        }
+
+        fn create(&self, rp_id: &str, options: &Map) -> Map {
+            let challenge = options.get_str("challenge").unwrap();
+            let attestation = Value::Map(vec![
+                ("fmt".into(), "none".into()),
+                ("attStmt".into(), Value::Map(vec![])),
+                (
+                    "authData".into(),
+                    Value::Bytes(self.auth_data(rp_id, 0x45, true)),
+                ),
+            ]);
+            let mut attestation_object = Vec::new();
+            ciborium::into_writer(&attestation, &mut attestation_object).unwrap();
+            let credential = json!({
+                "id": base64::encode_url(&self.credential_id),
+                "type": "public-key",
+                "response": {
+                    "clientDataJSON": base64::encode_url(Self::client_data("webauthn.create", challenge)),
+                    "attestationObject": base64::encode_url(attestation_object),
+                },
+            });
+            credential.as_object().cloned().unwrap()
+        }
+
+        fn get(&mut self, rp_id: &str, options: &Map) -> Map {
+            self.sign_count += 1;
+            let challenge = options.get_str("challenge").unwrap();
+            let client_data = Self::client_data("webauthn.get", challenge);
+            let auth_data = self.auth_data(rp_id, 0x05, false);
+            let message = [
+                auth_data.as_slice(),
+                Sha256::digest(&client_data).as_slice(),
+            ]
+            .concat();
+            let signature: Signature = self.signing_key.sign(&message);
+            let credential = json!({
+                "id": base64::encode_url(&self.credential_id),
+                "type": "public-key",
+                "response": {
+                    "clientDataJSON": base64::encode_url(client_data),
+                    "authenticatorData": base64::encode_url(auth_data),
+                    "signature": base64::encode_url(signature.to_der()),
+                },
+            });
+            credential.as_object().cloned().unwrap()
+        }
+    }
+
+    #[test]
+    fn it_registers_and_authenticates_passkeys() {
+        let rp = RelyingParty::new("example.com", ORIGIN)
+            .with_secret_key(b"test-secret-key")
+            .require_user_verification(true);
+        let mut authenticator = SoftwareAuthenticator::new();
+
+        let options = rp.creation_options("alice", "alice@example.com", &[]);
+        let credential = authenticator.create(rp.id(), &options);
+        assert!(rp.verify_registration("bob", &credential).is_err());
+
+        let passkey = rp.verify_registration("alice", &credential).unwrap();
+        let mut passkey = PasskeyCredential::try_from_map(&passkey.to_map()).unwrap();
+        assert_eq!(passkey.sign_count(), 0);
+
+        let options = rp.request_options("alice", &[passkey.clone()]);
+        let assertion = authenticator.get(rp.id(), &options);
+        assert!(rp
+            .verify_assertion("alice", &mut passkey, &assertion)
+            .is_ok());
+        assert_eq!(passkey.sign_count(), 1);
+        assert!(rp
+            .verify_assertion("alice", &mut passkey, &assertion)
+            .is_err());
+    }
+}
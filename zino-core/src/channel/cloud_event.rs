@@ -1,5 +1,6 @@
 use crate::{datetime::DateTime, JsonValue, Map, SharedString};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 
 /// Cloud event.
 /// See [the spec](https://github.com/cloudevents/spec/blob/v1.0.2/cloudevents/spec.md).
@@ -114,6 +115,48 @@ impl<T> CloudEvent<T> {
         self.session_id.as_deref()
     }
 
+    /// Returns a reference to the event data.
+    #[inline]
+    pub fn data(&self) -> &JsonValue {
+        &self.data
+    }
+
+    /// Returns the value of a context attribute named as in the spec,
+    /// such as `type`, `source`, `subject` and `sessionid`.
+    pub fn attribute(&self, name: &str) -> Option<Cow<'_, str>> {
+        match name {
+            "specversion" => Some(Cow::Borrowed(self.spec_version.as_ref())),
+            "id" => Some(Cow::Borrowed(&self.id)),
+            "source" => Some(Cow::Borrowed(&self.source)),
+            "type" => Some(Cow::Borrowed(self.event_type.as_ref())),
+            "time" => Some(Cow::Owned(self.timestamp.to_string())),
+            "datacontenttype" => self.data_content_type.as_deref().map(Cow::Borrowed),
+            "dataschema" => self.data_schema.as_deref().map(Cow::Borrowed),
+            "subject" => self.subject.as_deref().map(Cow::Borrowed),
+            "sessionid" => self.session_id.as_deref().map(Cow::Borrowed),
+            _ => None,
+        }
+    }
+
+    /// Looks up a context attribute or a field of the event data with the path `data.*`.
+    pub(crate) fn lookup(&self, name: &str) -> Option<JsonValue> {
+        if name == "data" {
+            return (!self.data.is_null()).then(|| self.data.clone());
+        }
+        if let Some(path) = name.strip_prefix("data.") {
+            let mut value = &self.data;
+            for key in path.split('.') {
+                value = match value {
+                    JsonValue::Object(map) => map.get(key)?,
+                    JsonValue::Array(vec) => vec.get(key.parse::<usize>().ok()?)?,
+                    _ => return None,
+                };
+            }
+            return (!value.is_null()).then(|| value.clone());
+        }
+        self.attribute(name).map(|value| value.into_owned().into())
+    }
+
     /// Stringifies the event data as `String`.
     #[inline]
     pub fn stringify_data(&self) -> String {
@@ -0,0 +1,489 @@
+use super::{CloudEvent, Subscription};
+use crate::{
+    datetime::DateTime, error::Error, extension::TomlTableExt, state::State, warn, BoxFuture,
+};
+use parking_lot::RwLock;
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    sync::OnceLock,
+};
+
+/// A cloud event appended to a partition of the event bus.
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    /// Partition.
+    partition: u32,
+    /// Offset in the partition.
+    offset: u64,
+    /// Time when the event was appended.
+    appended_at: DateTime,
+    /// Cloud event.
+    event: CloudEvent,
+}
+
+impl EventRecord {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(partition: u32, offset: u64, appended_at: DateTime, event: CloudEvent) -> Self {
+        Self {
+            partition,
+            offset,
+            appended_at,
+            event,
+        }
+    }
+
+    /// Returns the partition.
+    #[inline]
+    pub fn partition(&self) -> u32 {
+        self.partition
+    }
+
+    /// Returns the offset in the partition.
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the time when the event was appended.
+    #[inline]
+    pub fn appended_at(&self) -> DateTime {
+        self.appended_at
+    }
+
+    /// Returns a reference to the cloud event.
+    #[inline]
+    pub fn event(&self) -> &CloudEvent {
+        &self.event
+    }
+
+    /// Consumes `self` and returns the cloud event.
+    #[inline]
+    pub fn into_event(self) -> CloudEvent {
+        self.event
+    }
+}
+
+/// Position to replay the events from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayPosition {
+    /// The earliest retained event.
+    Earliest,
+    /// After the latest event, i.e. only new events will be consumed.
+    Latest,
+    /// The offset in each partition.
+    Offset(u64),
+    /// The first event appended at or after the time.
+    Timestamp(DateTime),
+}
+
+/// An interface for the event bus with consumer groups and committed offsets.
+///
+/// The events are partitioned by the session ID, the subject or the event ID,
+/// so that the events in the same session are consumed in order.
+pub trait EventBus: Send + Sync {
+    /// Returns the number of partitions.
+    fn partitions(&self) -> u32;
+
+    /// Appends an event to the bus and returns the record.
+    /// An error with `429 Too Many Requests` is returned if the bus is full.
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<EventRecord, Error>>;
+
+    /// Polls at most `limit` events after the committed offsets of the consumer group,
+    /// ordered by the offsets in each partition.
+    fn poll<'a>(
+        &'a self,
+        group: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<EventRecord>, Error>>;
+
+    /// Commits the record so that it will not be delivered to the consumer group again.
+    fn commit<'a>(
+        &'a self,
+        group: &'a str,
+        record: &'a EventRecord,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Resets the committed offsets of the consumer group to replay the events.
+    fn seek<'a>(
+        &'a self,
+        group: &'a str,
+        position: ReplayPosition,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Returns the partition of the event.
+pub fn partition_of<T>(event: &CloudEvent<T>, partitions: u32) -> u32 {
+    if partitions <= 1 {
+        return 0;
+    }
+
+    // FNV-1a hash which is stable across processes.
+    let key = event
+        .session_id()
+        .or_else(|| event.subject())
+        .unwrap_or_else(|| event.id());
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    (hash % u64::from(partitions)) as u32
+}
+
+/// A consumer of the event bus which belongs to a consumer group.
+///
+/// The records are committed only after they have been handled successfully,
+/// which provides the at-least-once delivery.
+#[derive(Debug, Clone)]
+pub struct EventConsumer {
+    /// Consumer group.
+    group: String,
+    /// Filter.
+    subscription: Option<Subscription>,
+    /// Maximum number of events in a batch.
+    batch_size: usize,
+}
+
+impl EventConsumer {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(group: impl ToString) -> Self {
+        Self {
+            group: group.to_string(),
+            subscription: None,
+            batch_size: 100,
+        }
+    }
+
+    /// Sets the subscription to filter the events.
+    #[inline]
+    pub fn with_subscription(mut self, subscription: Subscription) -> Self {
+        self.subscription = Some(subscription);
+        self
+    }
+
+    /// Sets the maximum number of events in a batch.
+    #[inline]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Returns the consumer group.
+    #[inline]
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+
+    /// Polls a batch of events and handles the ones matching the subscription.
+    /// The events which do not match the subscription are skipped and committed.
+    /// If the handler fails, the rest of events in the same partition will be redelivered
+    /// in the next batch. It returns the number of events handled successfully.
+    pub async fn consume<F, Fut>(&self, bus: &dyn EventBus, mut handler: F) -> Result<usize, Error>
+    where
+        F: FnMut(&CloudEvent) -> Fut,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let group = self.group.as_str();
+        let records = bus.poll(group, self.batch_size).await?;
+        let mut num_handled = 0;
+        let mut failed_partitions = Vec::new();
+        let mut last_records = HashMap::<u32, &EventRecord>::new();
+        for record in &records {
+            let partition = record.partition();
+            if failed_partitions.contains(&partition) {
+                continue;
+            }
+
+            let event = record.event();
+            let is_subscribed = self
+                .subscription
+                .as_ref()
+                .map_or(true, |subscription| subscription.matches(event));
+            if is_subscribed {
+                if let Err(err) = handler(event).await {
+                    tracing::warn!(
+                        group,
+                        partition,
+                        offset = record.offset(),
+                        "fail to handle the event `{}`: {}",
+                        event.id(),
+                        err.message()
+                    );
+                    failed_partitions.push(partition);
+                    continue;
+                }
+                num_handled += 1;
+            }
+            last_records.insert(partition, record);
+        }
+        for record in last_records.into_values() {
+            bus.commit(group, record).await?;
+        }
+        Ok(num_handled)
+    }
+}
+
+/// An in-memory event bus with a bounded log for each partition.
+///
+/// When a partition is full, the events which have been committed by all the known
+/// consumer groups are trimmed. A consumer group is known once it has polled, committed
+/// or sought the events. If there is no known consumer group or the events have not been
+/// committed yet, they are retained and publishing fails with `429 Too Many Requests`.
+pub struct MemoryEventBus {
+    /// Maximum number of events retained in a partition.
+    capacity: usize,
+    /// Partitioned logs with the base offsets.
+    logs: Vec<RwLock<(u64, VecDeque<EventRecord>)>>,
+    /// Committed offsets of the consumer groups.
+    offsets: RwLock<HashMap<(String, u32), u64>>,
+}
+
+impl MemoryEventBus {
+    /// Creates a new instance.
+    pub fn new(partitions: u32, capacity: usize) -> Self {
+        let logs = (0..partitions.max(1))
+            .map(|_| RwLock::new((0, VecDeque::new())))
+            .collect();
+        Self {
+            capacity: capacity.max(1),
+            logs,
+            offsets: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the minimum committed offset in the partition.
+    fn min_committed_offset(&self, partition: u32) -> Option<u64> {
+        self.offsets
+            .read()
+            .iter()
+            .filter(|((_, p), _)| *p == partition)
+            .map(|(_, &offset)| offset)
+            .min()
+    }
+}
+
+impl Default for MemoryEventBus {
+    #[inline]
+    fn default() -> Self {
+        Self::new(1, 10000)
+    }
+}
+
+impl EventBus for MemoryEventBus {
+    #[inline]
+    fn partitions(&self) -> u32 {
+        self.logs.len() as u32
+    }
+
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<EventRecord, Error>> {
+        Box::pin(async move {
+            let partition = partition_of(&event, self.partitions());
+            let min_committed_offset = self.min_committed_offset(partition);
+            let mut log = self.logs[partition as usize].write();
+            let (base_offset, records) = &mut *log;
+            if records.len() >= self.capacity {
+                if let Some(committed_offset) = min_committed_offset {
+                    let trimmed_offset = committed_offset.min(*base_offset + records.len() as u64);
+                    while *base_offset < trimmed_offset {
+                        records.pop_front();
+                        *base_offset += 1;
+                    }
+                }
+                if records.len() >= self.capacity {
+                    return Err(warn!(
+                        "429 Too Many Requests: the partition `{}` of the event bus is full",
+                        partition
+                    ));
+                }
+            }
+
+            let offset = *base_offset + records.len() as u64;
+            let record = EventRecord::new(partition, offset, DateTime::now(), event);
+            records.push_back(record.clone());
+            Ok(record)
+        })
+    }
+
+    fn poll<'a>(
+        &'a self,
+        group: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<EventRecord>, Error>> {
+        Box::pin(async move {
+            let mut records = Vec::new();
+            for (partition, log) in self.logs.iter().enumerate() {
+                let remaining = limit.saturating_sub(records.len());
+                if remaining == 0 {
+                    break;
+                }
+
+                let key = (group.to_owned(), partition as u32);
+                let log = log.read();
+                let (base_offset, partition_records) = &*log;
+                let committed_offset = *self.offsets.write().entry(key).or_insert(*base_offset);
+                let skipped = committed_offset.saturating_sub(*base_offset) as usize;
+                records.extend(
+                    partition_records
+                        .iter()
+                        .skip(skipped)
+                        .take(remaining)
+                        .cloned(),
+                );
+            }
+            Ok(records)
+        })
+    }
+
+    fn commit<'a>(
+        &'a self,
+        group: &'a str,
+        record: &'a EventRecord,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let key = (group.to_owned(), record.partition());
+            let next_offset = record.offset() + 1;
+            let mut offsets = self.offsets.write();
+            let offset = offsets.entry(key).or_default();
+            *offset = next_offset.max(*offset);
+            Ok(())
+        })
+    }
+
+    fn seek<'a>(
+        &'a self,
+        group: &'a str,
+        position: ReplayPosition,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            for (partition, log) in self.logs.iter().enumerate() {
+                let log = log.read();
+                let (base_offset, records) = &*log;
+                let end_offset = *base_offset + records.len() as u64;
+                let offset = match position {
+                    ReplayPosition::Earliest => *base_offset,
+                    ReplayPosition::Latest => end_offset,
+                    ReplayPosition::Offset(offset) => offset.clamp(*base_offset, end_offset),
+                    ReplayPosition::Timestamp(timestamp) => records
+                        .iter()
+                        .find(|record| record.appended_at() >= timestamp)
+                        .map_or(end_offset, |record| record.offset()),
+                };
+                self.offsets
+                    .write()
+                    .insert((group.to_owned(), partition as u32), offset);
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Registers the shared event bus.
+///
+/// It returns `false` if the shared event bus has been initialized.
+#[inline]
+pub fn register_event_bus(bus: impl EventBus + 'static) -> bool {
+    SHARED_EVENT_BUS.set(Some(Box::new(bus))).is_ok()
+}
+
+/// Returns the shared event bus configured by the `event-bus` table.
+///
+/// # Examples
+///
+/// ```toml
+/// [event-bus]
+/// store = "memory"
+/// partitions = 4
+/// capacity = 10000
+/// ```
+#[inline]
+pub fn shared_event_bus() -> Option<&'static dyn EventBus> {
+    SHARED_EVENT_BUS
+        .get_or_init(|| {
+            let config = State::shared().get_config("event-bus")?;
+            let partitions = config.get_u32("partitions").unwrap_or(1);
+            let capacity = config.get_usize("capacity").unwrap_or(10000);
+            let bus: Box<dyn EventBus> = match config.get_str("store").unwrap_or("memory") {
+                "memory" => Box::new(MemoryEventBus::new(partitions, capacity)),
+                #[cfg(feature = "flume")]
+                "channel" => Box::new(super::MessageChannel::new()),
+                store => {
+                    tracing::warn!(store, "unsupported event bus store");
+                    return None;
+                }
+            };
+            Some(bus)
+        })
+        .as_deref()
+}
+
+/// Shared event bus.
+static SHARED_EVENT_BUS: OnceLock<Option<Box<dyn EventBus>>> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{EventBus, EventConsumer, MemoryEventBus, ReplayPosition};
+    use crate::{
+        channel::{CloudEvent, Subscription},
+        error::Error,
+        warn,
+    };
+
+    #[test]
+    fn it_consumes_events_at_least_once() {
+        futures::executor::block_on(async {
+            let bus = MemoryEventBus::new(2, 4);
+            for i in 0..4 {
+                let mut event = CloudEvent::new(i, "test", "ping");
+                event.set_session_id("session");
+                bus.publish(event).await.unwrap();
+            }
+
+            let consumer = EventConsumer::new("group").with_batch_size(10);
+            let result = consumer
+                .consume(&bus, |event| {
+                    let failed = event.id() == "2";
+                    async move {
+                        if failed {
+                            Err(warn!("fail to handle the event"))
+                        } else {
+                            Ok(())
+                        }
+                    }
+                })
+                .await;
+            assert_eq!(result.unwrap(), 2);
+            assert_eq!(bus.poll("group", 10).await.unwrap().len(), 2);
+
+            let num_handled = consumer.consume(&bus, |_| async { Ok(()) }).await;
+            assert_eq!(num_handled.unwrap(), 2);
+            assert!(bus.poll("group", 10).await.unwrap().is_empty());
+
+            let event = CloudEvent::new(4, "test", "ping");
+            assert!(bus.publish(event).await.is_ok());
+
+            bus.seek("group", ReplayPosition::Earliest).await.unwrap();
+            let subscription = Subscription::new(None, Some("pong".to_owned()));
+            let consumer = consumer.with_subscription(subscription);
+            let num_handled = consumer.consume(&bus, |_| async { Ok(()) }).await;
+            assert_eq!(num_handled.unwrap(), 0);
+            assert!(bus.poll("group", 10).await.unwrap().is_empty());
+        });
+    }
+
+    #[test]
+    fn it_retains_uncommitted_events() {
+        futures::executor::block_on(async {
+            let bus = MemoryEventBus::new(1, 1);
+            let event = CloudEvent::new(0, "test", "ping");
+            assert!(bus.publish(event.clone()).await.is_ok());
+            assert!(bus.publish(event.clone()).await.is_err());
+
+            let records = bus.poll("group", 10).await.unwrap();
+            assert!(bus.publish(event.clone()).await.is_err());
+
+            bus.commit("group", &records[0]).await.unwrap();
+            assert!(bus.publish(event).await.is_ok());
+        });
+    }
+}
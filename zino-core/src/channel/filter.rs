@@ -0,0 +1,625 @@
+use super::CloudEvent;
+use crate::{error::Error, warn, JsonValue};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, cmp::Ordering, collections::BTreeMap, fmt, str::FromStr};
+
+/// Filter dialects of the [CloudEvents Subscriptions API].
+///
+/// The attributes are named as the context attributes of the cloud event,
+/// such as `type`, `source`, `subject` and `sessionid`.
+///
+/// [CloudEvents Subscriptions API]: https://github.com/cloudevents/spec/blob/main/subscriptions/spec.md
+///
+/// # Examples
+///
+/// ```json
+/// {
+///     "all": [
+///         { "prefix": { "type": "order." } },
+///         { "not": { "exact": { "source": "legacy" } } },
+///         { "sql": "data.amount >= 100 AND subject LIKE 'vip-%'" }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventFilter {
+    /// All the attributes should be equal to the values.
+    Exact(BTreeMap<String, String>),
+    /// All the attributes should start with the values.
+    Prefix(BTreeMap<String, String>),
+    /// All the attributes should end with the values.
+    Suffix(BTreeMap<String, String>),
+    /// All the nested filters should match.
+    All(Vec<EventFilter>),
+    /// Any of the nested filters should match.
+    Any(Vec<EventFilter>),
+    /// The nested filter should not match.
+    Not(Box<EventFilter>),
+    /// The SQL-like expression should be evaluated as `true`.
+    Sql(SqlFilter),
+}
+
+impl EventFilter {
+    /// Returns `true` if the cloud event matches the filter.
+    pub fn matches<T>(&self, event: &CloudEvent<T>) -> bool {
+        match self {
+            Self::Exact(entries) => entries
+                .iter()
+                .all(|(key, value)| event.attribute(key).is_some_and(|v| v == *value)),
+            Self::Prefix(entries) => entries.iter().all(|(key, value)| {
+                event
+                    .attribute(key)
+                    .is_some_and(|v| v.starts_with(value.as_str()))
+            }),
+            Self::Suffix(entries) => entries.iter().all(|(key, value)| {
+                event
+                    .attribute(key)
+                    .is_some_and(|v| v.ends_with(value.as_str()))
+            }),
+            Self::All(filters) => filters.iter().all(|filter| filter.matches(event)),
+            Self::Any(filters) => filters.iter().any(|filter| filter.matches(event)),
+            Self::Not(filter) => !filter.matches(event),
+            Self::Sql(filter) => filter.matches(event),
+        }
+    }
+}
+
+/// A SQL-like filter expression over the context attributes and the `data` fields.
+///
+/// It supports the literals, the attributes like `type` or `data.order.amount`,
+/// the operators `AND`, `OR`, `NOT`, `=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`,
+/// `[NOT] LIKE`, `[NOT] IN` and `EXISTS`. An expression referencing a missing attribute
+/// is evaluated as `false`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct SqlFilter {
+    /// Source text.
+    text: String,
+    /// Parsed expression.
+    expr: Expr,
+}
+
+impl SqlFilter {
+    /// Returns the source text.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns `true` if the cloud event matches the filter.
+    #[inline]
+    pub fn matches<T>(&self, event: &CloudEvent<T>) -> bool {
+        self.expr.eval(event) == Some(JsonValue::Bool(true))
+    }
+}
+
+impl PartialEq for SqlFilter {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl fmt::Display for SqlFilter {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl FromStr for SqlFilter {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(text)?;
+        if tokens.len() > MAX_TOKENS {
+            return Err(warn!(
+                "the filter should not contain more than {} tokens",
+                MAX_TOKENS
+            ));
+        }
+
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(token) = parser.peek() {
+            return Err(warn!("unexpected token `{:?}` in the filter", token));
+        }
+        Ok(Self {
+            text: text.to_owned(),
+            expr,
+        })
+    }
+}
+
+impl TryFrom<String> for SqlFilter {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl From<SqlFilter> for String {
+    #[inline]
+    fn from(filter: SqlFilter) -> Self {
+        filter.text
+    }
+}
+
+/// Returns `true` if the value matches the pattern with the wildcard `*`.
+pub(super) fn matches_wildcard(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut remaining) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return remaining.is_empty();
+    };
+    for part in middle {
+        match remaining.find(part) {
+            Some(index) => remaining = &remaining[index + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// Comparison operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Filter expressions.
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(JsonValue),
+    Attribute(String),
+    Exists(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    Like(Box<Expr>, String, bool),
+    In(Box<Expr>, Vec<Expr>, bool),
+}
+
+impl Expr {
+    /// Evaluates the expression for the cloud event.
+    fn eval<T>(&self, event: &CloudEvent<T>) -> Option<JsonValue> {
+        match self {
+            Self::Literal(value) => Some(value.clone()),
+            Self::Attribute(name) => event.lookup(name),
+            Self::Exists(name) => Some(event.lookup(name).is_some().into()),
+            Self::Not(expr) => expr.eval(event)?.as_bool().map(|b| (!b).into()),
+            Self::And(left, right) => {
+                let matched = left.eval(event)?.as_bool()? && right.eval(event)?.as_bool()?;
+                Some(matched.into())
+            }
+            Self::Or(left, right) => {
+                let left = left.eval(event).and_then(|v| v.as_bool());
+                if left == Some(true) {
+                    return Some(true.into());
+                }
+                let right = right.eval(event).and_then(|v| v.as_bool());
+                match (left, right) {
+                    (_, Some(true)) => Some(true.into()),
+                    (Some(false), Some(false)) => Some(false.into()),
+                    _ => None,
+                }
+            }
+            Self::Compare(left, op, right) => {
+                let ordering = compare(&left.eval(event)?, &right.eval(event)?)?;
+                let matched = match op {
+                    CompareOp::Eq => ordering == Ordering::Equal,
+                    CompareOp::Ne => ordering != Ordering::Equal,
+                    CompareOp::Lt => ordering == Ordering::Less,
+                    CompareOp::Le => ordering != Ordering::Greater,
+                    CompareOp::Gt => ordering == Ordering::Greater,
+                    CompareOp::Ge => ordering != Ordering::Less,
+                };
+                Some(matched.into())
+            }
+            Self::Like(expr, pattern, negated) => {
+                let value = expr.eval(event)?;
+                let matched = matches_like(pattern, &stringify(&value));
+                Some((matched != *negated).into())
+            }
+            Self::In(expr, list, negated) => {
+                let value = expr.eval(event)?;
+                let matched = list.iter().any(|item| {
+                    item.eval(event)
+                        .and_then(|item| compare(&value, &item))
+                        .is_some_and(|ordering| ordering == Ordering::Equal)
+                });
+                Some((matched != *negated).into())
+            }
+        }
+    }
+}
+
+/// Stringifies a JSON value without the quotes for a string.
+fn stringify(value: &JsonValue) -> Cow<'_, str> {
+    match value {
+        JsonValue::String(s) => Cow::Borrowed(s),
+        _ => Cow::Owned(value.to_string()),
+    }
+}
+
+/// Compares two JSON values, as numbers if both of them can be parsed as numbers.
+fn compare(left: &JsonValue, right: &JsonValue) -> Option<Ordering> {
+    let as_f64 = |value: &JsonValue| match value {
+        JsonValue::Number(n) => n.as_f64(),
+        JsonValue::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    };
+    match (left, right) {
+        (JsonValue::Bool(a), JsonValue::Bool(b)) => Some(a.cmp(b)),
+        (JsonValue::Null, _) | (_, JsonValue::Null) => None,
+        _ => match (as_f64(left), as_f64(right)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(stringify(left).cmp(&stringify(right))),
+        },
+    }
+}
+
+/// Returns `true` if the value matches the `LIKE` pattern with `%` and `_`.
+fn matches_like(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let value = value.chars().collect::<Vec<_>>();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '_' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '%' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((bp, bv)) = backtrack {
+            p = bp + 1;
+            v = bv + 1;
+            backtrack = Some((bp, bv + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+/// Tokens of the filter expression.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Keyword(&'static str),
+    Literal(JsonValue),
+    Operator(&'static str),
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+/// Splits the text into tokens.
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    const KEYWORDS: [&str; 9] = [
+        "AND", "OR", "NOT", "LIKE", "IN", "EXISTS", "TRUE", "FALSE", "NULL",
+    ];
+
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            ',' => {
+                chars.next();
+                tokens.push(Token::Comma);
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => {
+                            if chars.peek() == Some(&c) {
+                                chars.next();
+                                s.push(c);
+                            } else {
+                                break;
+                            }
+                        }
+                        Some(ch) => s.push(ch),
+                        None => return Err(warn!("unterminated string in the filter")),
+                    }
+                }
+                tokens.push(Token::Literal(s.into()));
+            }
+            '=' => {
+                chars.next();
+                tokens.push(Token::Operator("="));
+            }
+            '!' | '<' | '>' => {
+                chars.next();
+                let operator = match (c, chars.peek()) {
+                    ('!', Some('=')) | ('<', Some('>')) => "!=",
+                    ('<', Some('=')) => "<=",
+                    ('>', Some('=')) => ">=",
+                    ('<', _) => "<",
+                    ('>', _) => ">",
+                    _ => return Err(warn!("unexpected character `{}` in the filter", c)),
+                };
+                if operator.len() == 2 {
+                    chars.next();
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            _ if c.is_ascii_digit() || c == '-' => {
+                let mut s = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_ascii_digit() || ch == '.' || (ch == '-' && s.is_empty()) {
+                        s.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let number = s
+                    .parse::<serde_json::Number>()
+                    .map_err(|err| warn!("invalid number `{}` in the filter: {}", s, err))?;
+                tokens.push(Token::Literal(number.into()));
+            }
+            _ if c.is_alphabetic() || c == '_' => {
+                let mut s = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_alphanumeric() || ch == '_' || ch == '.' {
+                        s.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let upper = s.to_ascii_uppercase();
+                match KEYWORDS.iter().find(|&&keyword| keyword == upper) {
+                    Some(&"TRUE") => tokens.push(Token::Literal(true.into())),
+                    Some(&"FALSE") => tokens.push(Token::Literal(false.into())),
+                    Some(&"NULL") => tokens.push(Token::Literal(JsonValue::Null)),
+                    Some(&keyword) => tokens.push(Token::Keyword(keyword)),
+                    None => tokens.push(Token::Identifier(s)),
+                }
+            }
+            _ => return Err(warn!("unexpected character `{}` in the filter", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Max number of tokens in a filter, which also bounds the length of the `AND`/`OR` chains.
+const MAX_TOKENS: usize = 1024;
+
+/// Max nesting depth of the parentheses and the `NOT` operators in a filter.
+const MAX_DEPTH: usize = 32;
+
+/// A recursive descent parser for the filter expression.
+struct Parser {
+    /// Tokens.
+    tokens: Vec<Token>,
+    /// Current position.
+    pos: usize,
+    /// Current nesting depth.
+    depth: usize,
+}
+
+impl Parser {
+    /// Increases the nesting depth and checks whether it exceeds the limit.
+    fn enter(&mut self) -> Result<(), Error> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(warn!(
+                "the filter should not be nested more than {} levels",
+                MAX_DEPTH
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the current token.
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Consumes the current token if it is the keyword.
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Keyword(k)) if *k == keyword) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes the current token and returns it.
+    fn next(&mut self) -> Result<Token, Error> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| warn!("unexpected end of the filter"))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    /// Parses the `OR` expression.
+    fn parse_or(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_and()?;
+        while self.eat_keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    /// Parses the `AND` expression.
+    fn parse_and(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.parse_not()?;
+        while self.eat_keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+        }
+        Ok(expr)
+    }
+
+    /// Parses the `NOT` expression.
+    fn parse_not(&mut self) -> Result<Expr, Error> {
+        if self.eat_keyword("NOT") {
+            self.enter()?;
+            let expr = self.parse_not()?;
+            self.depth -= 1;
+            Ok(Expr::Not(Box::new(expr)))
+        } else {
+            self.parse_comparison()
+        }
+    }
+
+    /// Parses the comparison expression.
+    fn parse_comparison(&mut self) -> Result<Expr, Error> {
+        let expr = self.parse_primary()?;
+        if let Some(Token::Operator(operator)) = self.peek() {
+            let op = match *operator {
+                "=" => CompareOp::Eq,
+                "!=" => CompareOp::Ne,
+                "<" => CompareOp::Lt,
+                "<=" => CompareOp::Le,
+                ">" => CompareOp::Gt,
+                _ => CompareOp::Ge,
+            };
+            self.pos += 1;
+            let right = self.parse_primary()?;
+            return Ok(Expr::Compare(Box::new(expr), op, Box::new(right)));
+        }
+
+        let negated = self.eat_keyword("NOT");
+        if self.eat_keyword("LIKE") {
+            match self.next()? {
+                Token::Literal(JsonValue::String(pattern)) => {
+                    Ok(Expr::Like(Box::new(expr), pattern, negated))
+                }
+                token => Err(warn!("expect a pattern after `LIKE`, found `{:?}`", token)),
+            }
+        } else if self.eat_keyword("IN") {
+            if self.next()? != Token::LeftParen {
+                return Err(warn!("expect `(` after `IN`"));
+            }
+            let mut list = vec![self.parse_primary()?];
+            loop {
+                match self.next()? {
+                    Token::Comma => list.push(self.parse_primary()?),
+                    Token::RightParen => break,
+                    token => return Err(warn!("unexpected token `{:?}` in the list", token)),
+                }
+            }
+            Ok(Expr::In(Box::new(expr), list, negated))
+        } else if negated {
+            Err(warn!("expect `LIKE` or `IN` after `NOT`"))
+        } else {
+            Ok(expr)
+        }
+    }
+
+    /// Parses the primary expression.
+    fn parse_primary(&mut self) -> Result<Expr, Error> {
+        match self.next()? {
+            Token::Literal(value) => Ok(Expr::Literal(value)),
+            Token::Identifier(name) => Ok(Expr::Attribute(name)),
+            Token::Keyword("EXISTS") => match self.next()? {
+                Token::Identifier(name) => Ok(Expr::Exists(name)),
+                token => Err(warn!(
+                    "expect an attribute after `EXISTS`, found `{:?}`",
+                    token
+                )),
+            },
+            Token::LeftParen => {
+                self.enter()?;
+                let expr = self.parse_or()?;
+                if self.next()? != Token::RightParen {
+                    return Err(warn!("expect `)` in the filter"));
+                }
+                self.depth -= 1;
+                Ok(expr)
+            }
+            token => Err(warn!("unexpected token `{:?}` in the filter", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_wildcard, EventFilter, SqlFilter};
+    use crate::channel::CloudEvent;
+    use serde_json::json;
+
+    #[test]
+    fn it_matches_event_filters() {
+        let mut event: CloudEvent = CloudEvent::new("1", "shop", "order.created");
+        event.set_subject("vip-42");
+        event.set_data(json!({ "amount": 128, "items": ["book"] }));
+
+        assert!(matches_wildcard("order.*", "order.created"));
+        assert!(matches_wildcard("*.created", "order.created"));
+        assert!(!matches_wildcard("order.*", "payment.created"));
+
+        let sql = "data.amount >= 100 AND subject LIKE 'vip-%' AND type IN ('order.created')";
+        let filter = sql.parse::<SqlFilter>().unwrap();
+        assert!(filter.matches(&event));
+
+        let filter = "NOT EXISTS data.coupon OR data.amount < 10"
+            .parse::<SqlFilter>()
+            .unwrap();
+        assert!(filter.matches(&event));
+        assert!(!"data.amount < 10"
+            .parse::<SqlFilter>()
+            .unwrap()
+            .matches(&event));
+        assert!("amount >".parse::<SqlFilter>().is_err());
+
+        let nested = format!("{}TRUE{}", "(".repeat(100_000), ")".repeat(100_000));
+        assert!(nested.parse::<SqlFilter>().is_err());
+        let nested = format!("{}TRUE{}", "(".repeat(40), ")".repeat(40));
+        assert!(nested.parse::<SqlFilter>().is_err());
+        assert!(format!("{}TRUE", "NOT ".repeat(40))
+            .parse::<SqlFilter>()
+            .is_err());
+        assert!(format!("{}TRUE{}", "(".repeat(8), ")".repeat(8))
+            .parse::<SqlFilter>()
+            .is_ok());
+
+        let filter = serde_json::from_value::<EventFilter>(json!({
+            "all": [
+                { "prefix": { "type": "order." } },
+                { "not": { "exact": { "source": "legacy" } } },
+                { "any": [{ "suffix": { "subject": "42" } }, { "sql": "FALSE" }] }
+            ]
+        }))
+        .unwrap();
+        assert!(filter.matches(&event));
+    }
+}
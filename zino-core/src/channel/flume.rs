@@ -1,10 +1,20 @@
-use super::{CloudEvent, Subscription};
-use crate::{extension::TomlTableExt, state::State, LazyLock, Uuid};
+use super::{CloudEvent, EventBus, EventRecord, ReplayPosition, Subscription};
+use crate::{
+    application::ShutdownToken, datetime::DateTime, error::Error, extension::TomlTableExt,
+    state::State, warn, BoxFuture, LazyLock, Uuid,
+};
 use ahash::{HashMap, HashMapExt};
 use flume::{Receiver, SendError, Sender, TrySendError};
-use futures::{Sink, Stream};
-use parking_lot::RwLock;
-use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+use futures::{future, stream, Sink, Stream, StreamExt};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::VecDeque,
+    iter,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
+};
 
 /// A emitter is a sender of cloud events.
 type Emitter = Sender<CloudEvent>;
@@ -48,6 +58,8 @@ pub struct MessageChannel {
     sender_id: Uuid,
     /// Receiver.
     receiver: Listener,
+    /// Polled events which have not been committed yet, with the next offset.
+    pending: Arc<Mutex<(u64, VecDeque<EventRecord>)>>,
 }
 
 impl MessageChannel {
@@ -62,6 +74,7 @@ impl MessageChannel {
         Self {
             sender_id,
             receiver,
+            pending: Arc::new(Mutex::new((0, VecDeque::new()))),
         }
     }
 
@@ -76,6 +89,7 @@ impl MessageChannel {
         Self {
             sender_id,
             receiver,
+            pending: Arc::new(Mutex::new((0, VecDeque::new()))),
         }
     }
 
@@ -96,23 +110,13 @@ impl MessageChannel {
     pub fn try_send(&self, message: impl Into<CloudEvent>) -> Result<(), TrySendError<CloudEvent>> {
         let sender_id = &self.sender_id;
         let event = message.into();
-        let source = event.source();
-        let event_type = event.event_type();
-        let event_session_id = event.session_id();
         let subscribers = CHANNEL_SUBSCRIBERS.read();
         for (uid, subscriber) in subscribers.iter() {
             let emitter = subscriber.emitter();
             if uid != sender_id && !emitter.is_disconnected() {
-                let is_subscribed = if let Some(subscription) = subscriber.filter() {
-                    subscription.source().filter(|&s| source != s).is_none()
-                        && subscription.topic().filter(|&t| event_type != t).is_none()
-                        && subscription
-                            .session_id()
-                            .filter(|&s| event_session_id.is_some_and(|sid| sid != s))
-                            .is_none()
-                } else {
-                    true
-                };
+                let is_subscribed = subscriber
+                    .filter()
+                    .map_or(true, |subscription| subscription.matches(&event));
                 if is_subscribed {
                     emitter.try_send(event.clone())?;
                 }
@@ -127,23 +131,13 @@ impl MessageChannel {
     pub async fn send(&self, message: impl Into<CloudEvent>) -> Result<(), SendError<CloudEvent>> {
         let sender_id = &self.sender_id;
         let event = message.into();
-        let source = event.source();
-        let event_type = event.event_type();
-        let event_session_id = event.session_id();
         let subscribers = CHANNEL_SUBSCRIBERS.read();
         for (uid, subscriber) in subscribers.iter() {
             let emitter = subscriber.emitter();
             if uid != sender_id && !emitter.is_disconnected() {
-                let is_subscribed = if let Some(subscription) = subscriber.filter() {
-                    subscription.source().filter(|&s| source != s).is_none()
-                        && subscription.topic().filter(|&t| event_type != t).is_none()
-                        && subscription
-                            .session_id()
-                            .filter(|&s| event_session_id.is_some_and(|sid| sid != s))
-                            .is_none()
-                } else {
-                    true
-                };
+                let is_subscribed = subscriber
+                    .filter()
+                    .map_or(true, |subscription| subscription.matches(&event));
                 if is_subscribed {
                     emitter.send_async(event.clone()).await?;
                 }
@@ -163,15 +157,125 @@ impl MessageChannel {
     }
 
     /// Returns a stream that allows asynchronously receiving messages from the channel.
-    #[inline]
+    /// When the graceful shutdown of the application has been started, the stream stops
+    /// waiting for new messages and ends after the queued messages have been drained,
+    /// or when the application has been terminated after the drain timeout.
+    ///
+    /// The stream is tracked as an in-flight task of the shutdown token until it ends
+    /// or is dropped, so the consumer should keep polling it during the shutdown.
     pub fn stream(&self) -> impl Stream<Item = CloudEvent> + '_ {
-        self.receiver.stream()
+        let token = ShutdownToken::shared();
+        let queued_messages = stream::iter(self.receiver.try_iter());
+        self.receiver
+            .stream()
+            .take_until(token.cancelled())
+            .chain(queued_messages)
+            .chain(track_until_end(token))
+            .take_until(token.terminated())
     }
 
     /// Converts `self` into a stream that allows asynchronously receiving messages from the channel.
-    #[inline]
+    /// When the graceful shutdown of the application has been started, the stream stops
+    /// waiting for new messages and ends after the queued messages have been drained,
+    /// or when the application has been terminated after the drain timeout.
+    ///
+    /// The stream is tracked as an in-flight task of the shutdown token until it ends
+    /// or is dropped, so the consumer should keep polling it during the shutdown.
     pub fn into_stream(self) -> impl Stream<Item = CloudEvent> {
-        self.receiver.into_stream()
+        let token = ShutdownToken::shared();
+        let receiver = self.receiver.clone();
+        let queued_messages = stream::iter(iter::from_fn(move || receiver.try_recv().ok()));
+        self.receiver
+            .into_stream()
+            .take_until(token.cancelled())
+            .chain(queued_messages)
+            .chain(track_until_end(token))
+            .take_until(token.terminated())
+    }
+}
+
+/// Returns an empty stream which keeps the shutdown waiting until it has been polled,
+/// i.e. the messages before it have been consumed.
+fn track_until_end(token: &'static ShutdownToken) -> impl Stream<Item = CloudEvent> {
+    let guard = token.track();
+    stream::once(async move { drop(guard) }).filter_map(|()| future::ready(None))
+}
+
+/// The message channel as an event bus with a single partition.
+///
+/// The events are broadcasted to the other channels without persistence,
+/// so the consumer group is ignored and the committed offsets can not be replayed.
+/// The polled events are retained in memory and delivered again by the next poll
+/// until they have been committed.
+impl EventBus for MessageChannel {
+    #[inline]
+    fn partitions(&self) -> u32 {
+        1
+    }
+
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<EventRecord, Error>> {
+        Box::pin(async move {
+            match self.try_send(event.clone()) {
+                Ok(()) => Ok(EventRecord::new(0, 0, DateTime::now(), event)),
+                Err(TrySendError::Full(_)) => {
+                    Err(warn!("429 Too Many Requests: the message channel is full"))
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    Err(warn!("the message channel has been disconnected"))
+                }
+            }
+        })
+    }
+
+    fn poll<'a>(
+        &'a self,
+        _group: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<EventRecord>, Error>> {
+        Box::pin(async move {
+            let mut pending = self.pending.lock();
+            let (next_offset, pending_records) = &mut *pending;
+            let mut records = pending_records
+                .iter()
+                .take(limit)
+                .cloned()
+                .collect::<Vec<_>>();
+            let remaining = limit.saturating_sub(records.len());
+            for event in self.receiver.try_iter().take(remaining) {
+                let record = EventRecord::new(0, *next_offset, DateTime::now(), event);
+                *next_offset += 1;
+                pending_records.push_back(record.clone());
+                records.push(record);
+            }
+            Ok(records)
+        })
+    }
+
+    fn commit<'a>(
+        &'a self,
+        _group: &'a str,
+        record: &'a EventRecord,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let offset = record.offset();
+            self.pending
+                .lock()
+                .1
+                .retain(|pending_record| pending_record.offset() > offset);
+            Ok(())
+        })
+    }
+
+    fn seek<'a>(
+        &'a self,
+        _group: &'a str,
+        _position: ReplayPosition,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async {
+            Err(warn!(
+                "the message channel does not support replaying events"
+            ))
+        })
     }
 }
 
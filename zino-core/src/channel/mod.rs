@@ -1,9 +1,16 @@
-//! Cloud events and subscriptions.
+//! Cloud events, subscriptions with filters and the event bus.
 
 mod cloud_event;
+mod event_bus;
+mod filter;
 mod subscription;
 
 pub use cloud_event::CloudEvent;
+pub use event_bus::{
+    partition_of, register_event_bus, shared_event_bus, EventBus, EventConsumer, EventRecord,
+    MemoryEventBus, ReplayPosition,
+};
+pub use filter::{EventFilter, SqlFilter};
 pub use subscription::Subscription;
 
 #[cfg(feature = "flume")]
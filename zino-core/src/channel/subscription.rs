@@ -1,6 +1,15 @@
+use super::{
+    filter::{matches_wildcard, EventFilter, SqlFilter},
+    CloudEvent,
+};
 use serde::{Deserialize, Serialize};
 
 /// Subscription.
+///
+/// The `source` and `topic` support the wildcard `*`, such as `order.*`.
+/// The `filter` is a SQL-like expression which is convenient to be specified
+/// in a query string, and the `filters` are the filter dialects of the
+/// CloudEvents Subscriptions API. All of them should match for an event to be delivered.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(default)]
@@ -11,6 +20,11 @@ pub struct Subscription {
     source: Option<String>,
     /// Topic.
     topic: Option<String>,
+    /// SQL-like filter expression.
+    filter: Option<SqlFilter>,
+    /// Filter dialects.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    filters: Vec<EventFilter>,
 }
 
 impl Subscription {
@@ -21,6 +35,8 @@ impl Subscription {
             session_id: None,
             source,
             topic,
+            filter: None,
+            filters: Vec::new(),
         }
     }
 
@@ -42,6 +58,18 @@ impl Subscription {
         self.topic = topic;
     }
 
+    /// Sets the SQL-like filter expression.
+    #[inline]
+    pub fn set_filter(&mut self, filter: Option<SqlFilter>) {
+        self.filter = filter;
+    }
+
+    /// Adds a filter dialect.
+    #[inline]
+    pub fn add_filter(&mut self, filter: EventFilter) {
+        self.filters.push(filter);
+    }
+
     /// Returns the session ID.
     #[inline]
     pub fn session_id(&self) -> Option<&str> {
@@ -59,4 +87,37 @@ impl Subscription {
     pub fn topic(&self) -> Option<&str> {
         self.topic.as_deref()
     }
+
+    /// Returns the SQL-like filter expression.
+    #[inline]
+    pub fn filter(&self) -> Option<&SqlFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Returns the filter dialects.
+    #[inline]
+    pub fn filters(&self) -> &[EventFilter] {
+        &self.filters
+    }
+
+    /// Returns `true` if the cloud event matches the subscription.
+    pub fn matches<T>(&self, event: &CloudEvent<T>) -> bool {
+        let event_session_id = event.session_id();
+        self.source()
+            .filter(|&s| !matches_wildcard(s, event.source()))
+            .is_none()
+            && self
+                .topic()
+                .filter(|&t| !matches_wildcard(t, event.event_type()))
+                .is_none()
+            && self
+                .session_id()
+                .filter(|&s| event_session_id.is_some_and(|sid| sid != s))
+                .is_none()
+            && self
+                .filter
+                .as_ref()
+                .map_or(true, |filter| filter.matches(event))
+            && self.filters.iter().all(|filter| filter.matches(event))
+    }
 }
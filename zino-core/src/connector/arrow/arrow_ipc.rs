@@ -0,0 +1,43 @@
+use super::ArrowSchemaExt;
+use crate::{error::Error, JsonValue, Record};
+use datafusion::arrow::{
+    datatypes::Schema,
+    ipc::{reader::StreamReader, writer::StreamWriter},
+    json::ArrayWriter,
+    record_batch::RecordBatch,
+};
+use std::{io::Cursor, sync::Arc};
+
+/// Encodes the Avro records in the Arrow IPC streaming format.
+pub(crate) fn encode_arrow_ipc(records: &[Record]) -> Result<Vec<u8>, Error> {
+    let schema = if let Some(record) = records.first() {
+        Schema::try_from_avro_record(record)?
+    } else {
+        Schema::empty()
+    };
+    let columns = schema.collect_columns_from_avro_records(records);
+    let schema = Arc::new(schema);
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let mut writer = StreamWriter::try_new(Vec::new(), &schema)?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    writer.into_inner().map_err(Error::from)
+}
+
+/// Decodes the bytes in the Arrow IPC streaming format as a list of JSON values.
+pub(crate) fn decode_arrow_ipc(bytes: &[u8]) -> Result<Vec<JsonValue>, Error> {
+    let reader = StreamReader::try_new(Cursor::new(bytes), None)?;
+    let mut writer = ArrayWriter::new(Vec::new());
+    for batch in reader {
+        writer.write(&batch?)?;
+    }
+    writer.finish()?;
+
+    let buffer = writer.into_inner();
+    if buffer.is_empty() {
+        Ok(Vec::new())
+    } else {
+        serde_json::from_slice(&buffer).map_err(Error::from)
+    }
+}
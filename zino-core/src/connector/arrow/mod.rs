@@ -31,6 +31,7 @@ use toml::value::{Array, Table};
 
 mod arrow_array;
 mod arrow_field;
+mod arrow_ipc;
 mod arrow_schema;
 mod data_frame;
 mod scalar_provider;
@@ -38,6 +39,8 @@ mod scalar_value;
 
 pub use data_frame::DataFrameExecutor;
 
+pub(crate) use arrow_ipc::{decode_arrow_ipc, encode_arrow_ipc};
+
 use arrow_array::ArrowArrayExt;
 use arrow_field::ArrowFieldExt;
 use arrow_schema::ArrowSchemaExt;
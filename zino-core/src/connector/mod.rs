@@ -56,6 +56,8 @@ mod sqlx_row;
 
 #[cfg(feature = "connector-arrow")]
 pub use arrow::{ArrowConnector, DataFrameExecutor};
+#[cfg(feature = "connector-arrow")]
+pub(crate) use arrow::{decode_arrow_ipc, encode_arrow_ipc};
 #[cfg(feature = "connector-http")]
 pub use http::HttpConnector;
 
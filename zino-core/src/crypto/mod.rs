@@ -31,3 +31,8 @@ cfg_if::cfg_if! {
 mod sha1;
 
 pub(crate) use sha1::checksum;
+
+/// Compares two byte slices in constant time.
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
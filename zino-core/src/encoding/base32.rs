@@ -0,0 +1,65 @@
+//! Base32 encoding and decoding.
+
+use crate::{bail, error::Error};
+
+/// RFC 4648 base32 alphabet.
+const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encodes the data as base32 string without padding.
+pub(crate) fn encode(data: impl AsRef<[u8]>) -> String {
+    let data = data.as_ref();
+    let mut output = String::with_capacity((data.len() * 8).div_ceil(5));
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(ALPHABET[usize::from((buffer >> bits) & 0x1f)].into());
+        }
+    }
+    if bits > 0 {
+        output.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)].into());
+    }
+    output
+}
+
+/// Decodes the base32-encoded data as `Vec<u8>`.
+/// Padding characters, whitespaces and lowercase letters are accepted.
+pub(crate) fn decode(data: impl AsRef<[u8]>) -> Result<Vec<u8>, Error> {
+    let data = data.as_ref();
+    let mut output = Vec::with_capacity(data.len() * 5 / 8);
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in data {
+        let value = match byte.to_ascii_uppercase() {
+            b @ b'A'..=b'Z' => b - b'A',
+            b @ b'2'..=b'7' => b - b'2' + 26,
+            b'=' | b' ' | b'-' => continue,
+            _ => bail!("invalid base32 character `{}`", char::from(byte)),
+        };
+        buffer = (buffer << 5) | u16::from(value);
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, encode};
+
+    #[test]
+    fn it_encodes_base32() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "MY");
+        assert_eq!(encode(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(decode("MZXW6YTBOI======").unwrap(), b"foobar");
+        assert_eq!(decode("mzxw 6ytb oi").unwrap(), b"foobar");
+        assert!(decode("MZXW1").is_err());
+    }
+}
@@ -13,6 +13,20 @@ pub(crate) fn decode(data: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
     STANDARD_NO_PAD.decode(data)
 }
 
+/// Encodes the data as base64url string without padding.
+#[cfg(any(feature = "jwt", feature = "webauthn"))]
+#[inline]
+pub(crate) fn encode_url(data: impl AsRef<[u8]>) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(data)
+}
+
+/// Decodes the base64url-encoded data as `Vec<u8>`.
+#[cfg(any(feature = "jwt", feature = "webauthn"))]
+#[inline]
+pub(crate) fn decode_url(data: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(data)
+}
+
 /// Encodes the data as base64-encoded data URL string.
 #[cfg(feature = "connector-arrow")]
 pub(crate) fn encode_data_url(data: impl AsRef<[u8]>) -> String {
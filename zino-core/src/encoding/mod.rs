@@ -1,4 +1,5 @@
 //! Encoding and decoding.
 
+pub(crate) mod base32;
 pub(crate) mod base64;
 pub(crate) mod hex;
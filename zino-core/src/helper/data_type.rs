@@ -0,0 +1,103 @@
+use crate::{bail, error::Error, JsonValue, LazyLock};
+use serde::de::DeserializeOwned;
+
+/// Data types supported for the response body, listed in the order of preference.
+pub(crate) static SERIALIZABLE_DATA_TYPES: LazyLock<Vec<&'static str>> = LazyLock::new(|| {
+    let mut data_types = vec!["json", "ndjson", "csv", "avro"];
+    if cfg!(feature = "msgpack") {
+        data_types.push("msgpack");
+    }
+    if cfg!(feature = "cbor") {
+        data_types.push("cbor");
+    }
+    if cfg!(feature = "connector-arrow") {
+        data_types.push("arrow");
+    }
+    data_types
+});
+
+/// Returns `true` if the data type is supported by [`deserialize_data()`].
+pub(crate) fn is_deserializable(data_type: &str) -> bool {
+    match data_type {
+        "avro" | "bytes" | "csv" | "form" | "json" | "multipart" | "ndjson" | "text" => true,
+        "arrow" => cfg!(feature = "connector-arrow"),
+        "cbor" => cfg!(feature = "cbor"),
+        "msgpack" => cfg!(feature = "msgpack"),
+        _ => false,
+    }
+}
+
+/// Deserializes the bytes as an instance of type `T` for the data type.
+pub(crate) fn deserialize_data<T: DeserializeOwned>(
+    data_type: &str,
+    bytes: &[u8],
+) -> Result<T, Error> {
+    let data = match data_type {
+        "form" => serde_qs::from_bytes(bytes)?,
+        // The body of other textual data types is parsed as JSON for backward compatibility.
+        "json" | "bytes" | "csv" | "multipart" | "text" => serde_json::from_slice(bytes)?,
+        "ndjson" => {
+            let mut values = Vec::new();
+            for value in serde_json::Deserializer::from_slice(bytes).into_iter::<JsonValue>() {
+                values.push(value?);
+            }
+            deserialize_json_values(values)?
+        }
+        #[cfg(feature = "msgpack")]
+        "msgpack" => rmp_serde::from_slice(bytes)?,
+        #[cfg(feature = "cbor")]
+        "cbor" => ciborium::from_reader(bytes)?,
+        "avro" => {
+            let reader = apache_avro::Reader::new(bytes)?;
+            let mut values = Vec::new();
+            for value in reader {
+                values.push(JsonValue::try_from(value?)?);
+            }
+            deserialize_json_values(values)?
+        }
+        #[cfg(feature = "connector-arrow")]
+        "arrow" => {
+            let values = crate::connector::decode_arrow_ipc(bytes)?;
+            deserialize_json_values(values)?
+        }
+        _ => bail!(
+            "deserialization of the data type `{}` is unsupported",
+            data_type
+        ),
+    };
+    Ok(data)
+}
+
+/// Deserializes the JSON values as an instance of type `T`.
+/// A single value will be deserialized directly if possible.
+fn deserialize_json_values<T: DeserializeOwned>(values: Vec<JsonValue>) -> Result<T, Error> {
+    if let [value] = values.as_slice() {
+        if let Ok(data) = T::deserialize(value) {
+            return Ok(data);
+        }
+    }
+    serde_json::from_value(JsonValue::Array(values)).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_data, is_deserializable};
+    use crate::{JsonValue, Map};
+
+    #[test]
+    fn it_deserializes_data() {
+        for data_type in ["csv", "json", "ndjson", "text"] {
+            assert!(is_deserializable(data_type));
+        }
+        assert!(!is_deserializable("application/xml"));
+
+        let map: Map = deserialize_data("text", br#"{"name":"alice"}"#).unwrap();
+        assert_eq!(map.get("name"), Some(&JsonValue::from("alice")));
+
+        let values: Vec<Map> = deserialize_data("ndjson", b"{\"id\":1}\n{\"id\":2}\n").unwrap();
+        assert_eq!(values.len(), 2);
+
+        let map: Map = deserialize_data("ndjson", b"{\"id\":1}\n").unwrap();
+        assert_eq!(map.get("id"), Some(&JsonValue::from(1)));
+    }
+}
@@ -32,6 +32,10 @@ pub(crate) fn get_data_type(content_type: &str) -> &str {
     match content_type {
         "application/json" | "application/problem+json" => "json",
         "application/jsonlines" | "application/x-ndjson" => "ndjson",
+        "application/msgpack" | "application/vnd.msgpack" | "application/x-msgpack" => "msgpack",
+        "application/cbor" => "cbor",
+        "application/avro" | "application/vnd.apache.avro" | "avro/binary" => "avro",
+        "application/vnd.apache.arrow.stream" => "arrow",
         "application/octet-stream" => "bytes",
         "application/x-www-form-urlencoded" => "form",
         "multipart/form-data" => "multipart",
@@ -46,3 +50,77 @@ pub(crate) fn get_data_type(content_type: &str) -> &str {
         }
     }
 }
+
+/// Selects the most preferred data type from the `accept` header value
+/// which is contained in the supported data types.
+pub(crate) fn select_data_type(
+    accept: &str,
+    supported_data_types: &[&'static str],
+) -> Option<&'static str> {
+    let mut data_types = accept
+        .split(',')
+        .filter_map(|media_range| {
+            let mut segments = media_range.split(';').map(|s| s.trim());
+            let media_type = segments.next().filter(|s| !s.is_empty())?;
+            let quality = segments
+                .find_map(|s| s.strip_prefix("q="))
+                .and_then(|s| s.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media_type, quality))
+        })
+        .filter(|&(_, quality)| quality > 0.0)
+        .collect::<Vec<_>>();
+    data_types.sort_by(|a, b| b.1.total_cmp(&a.1));
+    data_types.into_iter().find_map(|(media_type, _)| {
+        if media_type == "*/*" || media_type == "application/*" {
+            supported_data_types.first().copied()
+        } else {
+            let data_type = get_data_type(media_type);
+            supported_data_types
+                .iter()
+                .find(|&&supported_data_type| supported_data_type == data_type)
+                .copied()
+        }
+    })
+}
+
+/// Returns `true` if the entity tag matches the `if-none-match` header value
+/// with the weak comparison.
+pub(crate) fn match_etag(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match.split(',').any(|s| {
+        let s = s.trim();
+        s == "*" || s.trim_start_matches("W/") == etag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_etag, select_data_type};
+
+    #[test]
+    fn it_selects_data_type() {
+        let supported_data_types = ["json", "ndjson", "csv", "msgpack"];
+        assert_eq!(
+            select_data_type("text/csv;q=0.8, application/msgpack", &supported_data_types),
+            Some("msgpack"),
+        );
+        assert_eq!(
+            select_data_type("application/avro, */*;q=0.1", &supported_data_types),
+            Some("json"),
+        );
+        assert_eq!(
+            select_data_type("application/x-ndjson, text/csv;q=0", &supported_data_types),
+            Some("ndjson"),
+        );
+        assert_eq!(select_data_type("image/png", &supported_data_types), None);
+    }
+
+    #[test]
+    fn it_matches_etag() {
+        assert!(match_etag("*", r#""abc""#));
+        assert!(match_etag(r#""xyz", W/"abc""#, r#""abc""#));
+        assert!(match_etag(r#""abc""#, r#"W/"abc""#));
+        assert!(!match_etag(r#""xyz""#, r#"W/"abc""#));
+    }
+}
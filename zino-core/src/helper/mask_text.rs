@@ -1,12 +1,12 @@
 /// Masks text with masking options.
+/// All the characters are masked if the text is not longer than the prefix and suffix.
 pub(crate) fn mask_text(text: &str, num_prefix_chars: usize, num_suffix_chars: usize) -> String {
-    let length = text.len();
-    let suffix_index = if length > num_suffix_chars {
-        length - num_suffix_chars
-    } else {
-        0
-    };
+    let length = text.chars().count();
+    if length <= num_prefix_chars + num_suffix_chars {
+        return "*".repeat(length);
+    }
 
+    let suffix_index = length - num_suffix_chars;
     let mut masked_text = String::with_capacity(length);
     for (i, c) in text.chars().enumerate() {
         if i < num_prefix_chars || i >= suffix_index {
@@ -1,12 +1,16 @@
 /// Helper utilities.
+mod data_type;
 mod form_data;
 mod header;
 mod mask_text;
 mod query;
 mod str_array;
 
+pub(crate) use data_type::{deserialize_data, is_deserializable, SERIALIZABLE_DATA_TYPES};
 pub(crate) use form_data::parse_form_data;
-pub(crate) use header::{check_json_content_type, displayed_inline, get_data_type};
+pub(crate) use header::{
+    check_json_content_type, displayed_inline, get_data_type, match_etag, select_data_type,
+};
 pub(crate) use mask_text::mask_text;
 pub(crate) use query::format_query;
 pub(crate) use str_array::parse_str_array;
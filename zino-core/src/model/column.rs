@@ -198,6 +198,36 @@ impl<'a> Column<'a> {
         self.has_attribute("write_only")
     }
 
+    /// Returns `true` if the column is encrypted at rest.
+    #[inline]
+    pub fn is_encrypted(&self) -> bool {
+        self.has_attribute("encrypted")
+    }
+
+    /// Returns the field of the blind index for an encrypted column.
+    #[inline]
+    pub fn blind_index(&self) -> Option<&str> {
+        self.extra.get_str("blind_index")
+    }
+
+    /// Returns `true` if the column is readable by any of the roles.
+    /// A column without the `read_roles` attribute is readable by everyone.
+    #[inline]
+    pub fn is_readable_by(&self, roles: &[&str]) -> bool {
+        self.extra
+            .get_str("read_roles")
+            .map_or(true, |allowed_roles| check_roles(allowed_roles, roles))
+    }
+
+    /// Returns `true` if the column is writable by any of the roles.
+    /// A column without the `write_roles` attribute is writable by everyone.
+    #[inline]
+    pub fn is_writable_by(&self, roles: &[&str]) -> bool {
+        self.extra
+            .get_str("write_roles")
+            .map_or(true, |allowed_roles| check_roles(allowed_roles, roles))
+    }
+
     /// Returns `true` if the column is an option type.
     ///
     /// Only supports `Option<Uuid>` | `Option<String>` | `Option<i64>` | `Option<u64>`
@@ -761,3 +791,39 @@ pub trait EncodeColumn<DB> {
     /// Formats a column filter.
     fn format_filter(&self, key: &str, value: &JsonValue) -> String;
 }
+
+/// Returns `true` if any of the roles matches the comma-separated allowed roles.
+/// A role like `hr:manager` matches the allowed role `hr`.
+fn check_roles(allowed_roles: &str, roles: &[&str]) -> bool {
+    allowed_roles
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .any(|allowed_role| {
+            roles.iter().any(|&role| {
+                role == allowed_role
+                    || role
+                        .strip_prefix(allowed_role)
+                        .is_some_and(|s| s.starts_with(':'))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Column;
+
+    #[test]
+    fn it_checks_field_roles() {
+        let mut column = Column::new("salary", "Decimal", true);
+        assert!(column.is_readable_by(&[]));
+
+        column.set_extra_attribute("read_roles", "hr, admin");
+        column.set_extra_attribute("write_roles", "hr:manager");
+        assert!(column.is_readable_by(&["admin"]));
+        assert!(column.is_readable_by(&["hr:manager"]));
+        assert!(!column.is_readable_by(&["worker", "hrm"]));
+        assert!(column.is_writable_by(&["hr:manager"]));
+        assert!(!column.is_writable_by(&["hr"]));
+    }
+}
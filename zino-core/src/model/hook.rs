@@ -15,6 +15,19 @@ pub trait ModelHooks: Model {
     /// Extension data.
     type Extension: Clone + Send + Sync + 'static;
 
+    /// Returns the roles of the caller for the field-level access control.
+    ///
+    /// Fields declared with `read_roles` or `write_roles` are only accessible to
+    /// the callers having any of the roles. By default, the roles are derived from
+    /// the extension if it is a `UserSession`, and it should be overridden otherwise.
+    #[inline]
+    fn access_roles(extension: Option<&Self::Extension>) -> Vec<&str> {
+        extension
+            .and_then(|extension| crate::auth::session_roles(extension))
+            .map(|roles| roles.iter().map(|role| role.as_str()).collect())
+            .unwrap_or_default()
+    }
+
     /// A hook running before extracting the model data.
     #[inline]
     async fn before_extract() -> Result<(), Error> {
@@ -0,0 +1,340 @@
+use crate::{extension::TomlTableExt, helper, state::State, JsonValue, LazyLock};
+use regex::{Captures, Regex};
+use std::{borrow::Cow, collections::HashMap, str::FromStr};
+
+/// Masking rules for sensitive values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MaskingRule {
+    /// Phone numbers: keeps the first 3 and the last 4 characters.
+    Phone,
+    /// Email addresses: keeps the first character of the user and the domain.
+    Email,
+    /// ID cards: keeps the first 3 and the last 4 characters.
+    IdCard,
+    /// Bank cards: keeps the first 4 and the last 4 characters.
+    BankCard,
+    /// Masks all the characters.
+    Full,
+    /// Keeps the specific number of prefix and suffix characters.
+    Custom(usize, usize),
+}
+
+impl MaskingRule {
+    /// Masks the text with the rule.
+    pub fn mask(&self, text: &str) -> String {
+        match self {
+            Self::Phone => helper::mask_text(text, 3, 4),
+            Self::Email => {
+                if let Some((user, domain)) = text.split_once('@') {
+                    let masked_user = helper::mask_text(user, 1, 0);
+                    format!("{masked_user}@{domain}")
+                } else {
+                    helper::mask_text(text, 1, 1)
+                }
+            }
+            Self::IdCard => helper::mask_text(text, 3, 4),
+            Self::BankCard => helper::mask_text(text, 4, 4),
+            Self::Full => helper::mask_text(text, 0, 0),
+            Self::Custom(num_prefix_chars, num_suffix_chars) => {
+                helper::mask_text(text, *num_prefix_chars, *num_suffix_chars)
+            }
+        }
+    }
+
+    /// Masks the json value with the rule.
+    /// Only strings and arrays of strings are masked.
+    pub fn mask_value(&self, value: &mut JsonValue) {
+        match value {
+            JsonValue::String(s) if !s.is_empty() => {
+                *s = self.mask(s);
+            }
+            JsonValue::Array(vec) => {
+                for v in vec {
+                    self.mask_value(v);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns `true` if the text is a masked value of the rule,
+    /// which is the case when masking it again leaves it unchanged.
+    pub fn is_masked(&self, text: &str) -> bool {
+        text.contains('*') && self.mask(text) == text
+    }
+
+    /// Returns `true` if the text matched by the log pattern is a sensitive value.
+    fn matches_log(&self, text: &str) -> bool {
+        match self {
+            Self::BankCard => is_valid_card_number(text),
+            _ => true,
+        }
+    }
+
+    /// Returns the pattern for detecting the sensitive values in the logs.
+    fn log_pattern(&self) -> Option<&'static Regex> {
+        match self {
+            Self::Phone => Some(&PHONE_PATTERN),
+            Self::Email => Some(&EMAIL_PATTERN),
+            Self::IdCard => Some(&ID_CARD_PATTERN),
+            Self::BankCard => Some(&BANK_CARD_PATTERN),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for MaskingRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "phone" => Ok(Self::Phone),
+            "email" => Ok(Self::Email),
+            "id_card" => Ok(Self::IdCard),
+            "bank_card" => Ok(Self::BankCard),
+            "full" => Ok(Self::Full),
+            _ => {
+                let parse_custom_rule = || {
+                    let (prefix, suffix) = s.split_once(':')?;
+                    Some(Self::Custom(prefix.parse().ok()?, suffix.parse().ok()?))
+                };
+                parse_custom_rule().ok_or_else(|| format!("invalid masking rule `{s}`"))
+            }
+        }
+    }
+}
+
+/// Data masking policy.
+///
+/// Fields can be masked by the `mask` attribute of the model schema,
+/// or by the rules in the configuration keyed by the model name and the field.
+/// An invalid rule in the `mask` attribute panics when the schema columns are loaded.
+/// The masked values echoed back in an update are ignored instead of being written.
+///
+/// ```toml
+/// [masking]
+/// unmask-roles = ["admin", "auditor"]
+/// log-rules = ["phone", "email", "id_card"]
+///
+/// [masking.fields.user]
+/// mobile = "phone"
+/// email = "email"
+/// ```
+#[derive(Debug, Default)]
+pub struct MaskingPolicy {
+    /// Roles which can access the unmasked values.
+    unmask_roles: Vec<String>,
+    /// Masking rules keyed by the model name and the field.
+    field_rules: HashMap<String, HashMap<String, MaskingRule>>,
+    /// Masking rules for the values written into the logs.
+    log_rules: Vec<MaskingRule>,
+}
+
+impl MaskingPolicy {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the roles which can access the unmasked values.
+    #[inline]
+    pub fn set_unmask_roles(&mut self, roles: Vec<String>) {
+        self.unmask_roles = roles;
+    }
+
+    /// Adds a masking rule for the field of a model.
+    #[inline]
+    pub fn add_field_rule(&mut self, model_name: &str, field: &str, rule: MaskingRule) {
+        self.field_rules
+            .entry(model_name.to_owned())
+            .or_default()
+            .insert(field.to_owned(), rule);
+    }
+
+    /// Adds a masking rule for the values written into the logs.
+    #[inline]
+    pub fn add_log_rule(&mut self, rule: MaskingRule) {
+        if !self.log_rules.contains(&rule) {
+            self.log_rules.push(rule);
+        }
+    }
+
+    /// Returns the masking rule for the field of a model.
+    #[inline]
+    pub fn get_field_rule(&self, model_name: &str, field: &str) -> Option<MaskingRule> {
+        self.field_rules.get(model_name)?.get(field).copied()
+    }
+
+    /// Returns `true` if any of the roles can access the unmasked values.
+    pub fn can_unmask(&self, roles: &[&str]) -> bool {
+        self.unmask_roles.iter().any(|unmask_role| {
+            roles.iter().any(|&role| {
+                role == unmask_role
+                    || role
+                        .strip_prefix(unmask_role.as_str())
+                        .is_some_and(|s| s.starts_with(':'))
+            })
+        })
+    }
+
+    /// Masks the sensitive values in the log message.
+    pub fn mask_log<'a>(&self, message: &'a str) -> Cow<'a, str> {
+        let mut message = Cow::Borrowed(message);
+        for rule in &self.log_rules {
+            if let Some(pattern) = rule.log_pattern() {
+                let replacer = |caps: &Captures| {
+                    let text = &caps[0];
+                    if rule.matches_log(text) {
+                        rule.mask(text)
+                    } else {
+                        text.to_owned()
+                    }
+                };
+                let masked_message = match pattern.replace_all(&message, replacer) {
+                    Cow::Owned(s) => Some(s),
+                    Cow::Borrowed(_) => None,
+                };
+                if let Some(s) = masked_message {
+                    message = Cow::Owned(s);
+                }
+            }
+        }
+        message
+    }
+
+    /// Returns `true` if the values written into the logs should be masked.
+    #[inline]
+    pub fn masks_log(&self) -> bool {
+        !self.log_rules.is_empty()
+    }
+
+    /// Returns a reference to the shared masking policy.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_MASKING_POLICY
+    }
+}
+
+/// Shared masking policy.
+static SHARED_MASKING_POLICY: LazyLock<MaskingPolicy> = LazyLock::new(|| {
+    let mut policy = MaskingPolicy::new();
+    let Some(config) = State::shared().get_config("masking") else {
+        return policy;
+    };
+    if let Some(roles) = config.get_str_array("unmask-roles") {
+        policy.set_unmask_roles(roles.into_iter().map(|s| s.to_owned()).collect());
+    }
+    if let Some(rules) = config.get_str_array("log-rules") {
+        for rule in rules {
+            match rule.parse() {
+                Ok(rule) => policy.add_log_rule(rule),
+                Err(err) => tracing::warn!("{err}"),
+            }
+        }
+    }
+    if let Some(fields) = config.get_table("fields") {
+        for (model_name, rules) in fields {
+            let Some(rules) = rules.as_table() else {
+                continue;
+            };
+            for (field, rule) in rules {
+                match rule.as_str().unwrap_or_default().parse() {
+                    Ok(rule) => policy.add_field_rule(model_name, field, rule),
+                    Err(err) => tracing::warn!(model_name, field, "{err}"),
+                }
+            }
+        }
+    }
+    policy
+});
+
+/// Pattern for the phone numbers.
+static PHONE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b1[3-9]\d{9}\b").expect("fail to create a regex for the phone numbers")
+});
+
+/// Pattern for the email addresses.
+static EMAIL_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b")
+        .expect("fail to create a regex for the email addresses")
+});
+
+/// Pattern for the ID cards.
+static ID_CARD_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b\d{17}[\dXx]\b").expect("fail to create a regex for the ID cards")
+});
+
+/// Pattern for the bank cards issued by the major card schemes.
+static BANK_CARD_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:3[47]\d{13}|4\d{15}|5[1-5]\d{14}|62\d{14,17})\b")
+        .expect("fail to create a regex for the bank cards")
+});
+
+/// Returns `true` if the card number passes the Luhn checksum.
+fn is_valid_card_number(number: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in number.chars().rev().enumerate() {
+        let Some(mut digit) = c.to_digit(10) else {
+            return false;
+        };
+        if i % 2 == 1 {
+            digit *= 2;
+            if digit > 9 {
+                digit -= 9;
+            }
+        }
+        sum += digit;
+    }
+    sum % 10 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MaskingPolicy, MaskingRule};
+
+    #[test]
+    fn it_masks_sensitive_values() {
+        assert_eq!(MaskingRule::Phone.mask("13812345678"), "138****5678");
+        assert_eq!(
+            MaskingRule::Email.mask("alice@example.com"),
+            "a****@example.com"
+        );
+        assert_eq!(
+            MaskingRule::IdCard.mask("11010519491231002X"),
+            "110***********002X"
+        );
+        assert_eq!(
+            MaskingRule::BankCard.mask("6222021234567890"),
+            "6222********7890"
+        );
+        assert_eq!(MaskingRule::Phone.mask("1381234"), "*******");
+        assert_eq!(MaskingRule::Email.mask("a@example.com"), "*@example.com");
+        assert!(MaskingRule::Phone.is_masked("138****5678"));
+        assert!(!MaskingRule::Phone.is_masked("13912345678"));
+        assert_eq!("2:1".parse::<MaskingRule>(), Ok(MaskingRule::Custom(2, 1)));
+        assert!("unknown".parse::<MaskingRule>().is_err());
+
+        let mut policy = MaskingPolicy::new();
+        policy.set_unmask_roles(vec!["auditor".to_owned()]);
+        policy.add_log_rule(MaskingRule::Phone);
+        policy.add_log_rule(MaskingRule::Email);
+        assert!(policy.can_unmask(&["auditor:senior"]));
+        assert!(!policy.can_unmask(&["worker"]));
+        assert_eq!(
+            policy.mask_log("mobile = '13812345678', email = 'bob@example.com'"),
+            "mobile = '138****5678', email = 'b**@example.com'"
+        );
+
+        policy.add_log_rule(MaskingRule::BankCard);
+        assert_eq!(
+            policy.mask_log("card = 4111111111111111, ts = 1729312345678901234"),
+            "card = 4111********1111, ts = 1729312345678901234"
+        );
+        assert_eq!(
+            policy.mask_log("order = 4111111111111112"),
+            "order = 4111111111111112"
+        );
+    }
+}
@@ -5,6 +5,7 @@ use serde::{de::DeserializeOwned, Serialize};
 mod column;
 mod context;
 mod hook;
+mod masking;
 mod mutation;
 mod order;
 mod query;
@@ -18,6 +19,7 @@ pub use apache_avro::schema;
 pub use column::{Column, EncodeColumn};
 pub use context::QueryContext;
 pub use hook::ModelHooks;
+pub use masking::{MaskingPolicy, MaskingRule};
 pub use mutation::Mutation;
 pub use order::QueryOrder;
 pub use query::Query;
@@ -52,7 +54,7 @@ pub trait Model: Default + Serialize + DeserializeOwned {
         let mut validation = Validation::new();
         if data.is_empty() {
             let message = format!("the `{}` model data should be nonempty", Self::MODEL_NAME);
-            validation.record("data", message);
+            validation.record_with_code("data", "required", message);
         }
         validation
     }
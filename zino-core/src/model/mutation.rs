@@ -39,7 +39,7 @@ impl Mutation {
                 "fields" => {
                     if let Some(fields) = value.parse_str_array() {
                         if fields.is_empty() {
-                            validation.record("fields", "must be nonempty");
+                            validation.record_with_code("fields", "required", "must be nonempty");
                         } else {
                             self.fields.clear();
                             self.fields.extend(fields.into_iter().map(|s| s.to_owned()));
@@ -98,4 +98,11 @@ impl Mutation {
     pub fn updates(&self) -> &Map {
         &self.updates
     }
+
+    /// Returns a mutable reference to the mutation updates.
+    #[cfg(feature = "orm")]
+    #[inline]
+    pub(crate) fn updates_mut(&mut self) -> &mut Map {
+        &mut self.updates
+    }
 }
@@ -186,6 +186,41 @@ impl Query {
             .retain(|field| !fields.contains(&field.as_str()))
     }
 
+    /// Removes the projection fields, filters and sort orders in the deny list.
+    /// Logical filters are stripped recursively so that the denied fields
+    /// can not be used as an oracle.
+    pub fn restrict_fields(&mut self, fields: &[&str]) {
+        self.deny_fields(fields);
+        Self::strip_filters(&mut self.filters, fields);
+        self.sort_order
+            .retain(|order| !fields.contains(&order.field()));
+    }
+
+    /// Removes the filters on the fields in the deny list recursively.
+    fn strip_filters(filters: &mut Map, fields: &[&str]) {
+        filters.retain(|key, value| {
+            if !key.starts_with('$') {
+                return !fields.contains(&key.as_str());
+            }
+            match value {
+                JsonValue::Array(vec) => {
+                    for filter in vec.iter_mut() {
+                        if let Some(filter) = filter.as_object_mut() {
+                            Self::strip_filters(filter, fields);
+                        }
+                    }
+                    vec.retain(|filter| filter.as_object().map_or(true, |f| !f.is_empty()));
+                    !vec.is_empty()
+                }
+                JsonValue::Object(filter) => {
+                    Self::strip_filters(filter, fields);
+                    !filter.is_empty()
+                }
+                _ => true,
+            }
+        });
+    }
+
     /// Adds a projection field with the alias.
     #[inline]
     pub fn add_field_alias(&mut self, expr: impl Into<String>, alias: impl Into<String>) {
@@ -373,3 +408,34 @@ impl Default for Query {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Query;
+    use crate::{extension::JsonObjectExt, Map};
+
+    #[test]
+    fn it_restricts_fields() {
+        let mut data = Map::new();
+        data.upsert("fields", "id,name,salary");
+        data.upsert("salary", "$gt.100000");
+        data.upsert("name", "alice");
+        data.upsert("$or", "(salary.$gt.100000,name.$eq.bob)");
+        data.upsert("$and", "(salary.$lt.100)");
+        data.upsert("order_by", "salary|desc,name|asc");
+
+        let mut query = Query::default();
+        assert!(query.read_map(&data).is_success());
+        query.restrict_fields(&["salary"]);
+
+        assert_eq!(query.fields(), ["id", "name"]);
+        assert_eq!(query.sort_order().len(), 1);
+        assert_eq!(query.sort_order()[0].field(), "name");
+
+        let filters = query.filters();
+        assert!(!filters.contains_key("salary"));
+        assert!(!filters.contains_key("$and"));
+        assert_eq!(filters.get_str("name"), Some("alice"));
+        assert_eq!(filters.get_array("$or").map(|v| v.len()), Some(1));
+    }
+}
@@ -3,12 +3,13 @@ use crate::{
     bail,
     datetime::DateTime,
     error::Error,
-    extension::{JsonObjectExt, JsonValueExt},
+    extension::{JsonObjectExt, JsonValueExt, TomlTableExt},
     model::{ModelHooks, Mutation, Query},
+    state::State,
     validation::Validation,
     warn, JsonValue, Map,
 };
-use std::fmt::Display;
+use std::{borrow::Cow, fmt::Display};
 
 /// Access model fields.
 ///
@@ -345,7 +346,11 @@ where
     async fn check_constraints(&self) -> Result<Validation, Error> {
         let mut validation = Validation::new();
         if self.id() == &K::default() {
-            validation.record(Self::PRIMARY_KEY_NAME, "should not be a default value");
+            validation.record_with_code(
+                Self::PRIMARY_KEY_NAME,
+                "required",
+                "should not be a default value",
+            );
         }
         Ok(validation)
     }
@@ -355,6 +360,7 @@ where
         let mut models = Self::find(query).await?;
         let translate_enabled = query.translate_enabled();
         for model in models.iter_mut() {
+            Self::decrypt_columns(model)?;
             Self::after_decode(model).await?;
             translate_enabled.then(|| Self::translate_model(model));
         }
@@ -366,11 +372,92 @@ where
         let mut model = Self::find_by_id::<Map>(id)
             .await?
             .ok_or_else(|| warn!("404 Not Found: cannot find the model `{}`", id))?;
+        Self::decrypt_columns(&mut model)?;
         Self::after_decode(&mut model).await?;
         Self::translate_model(&mut model);
         Ok(model)
     }
 
+    /// Returns the `Cache-Control` directives for the responses of the model.
+    ///
+    /// The directives are read from the `[cache-control]` config table by the model name,
+    /// and fall back to the `default` entry. If the representation depends on the session,
+    /// the `public` directive is replaced by `private` so that shared caches will not store it.
+    fn cache_control(session_dependent: bool) -> Option<Cow<'static, str>> {
+        let directives = State::shared()
+            .get_config("cache-control")
+            .and_then(|config| {
+                config
+                    .get_str(Self::MODEL_NAME)
+                    .or_else(|| config.get_str("default"))
+            })
+            .filter(|s| !s.is_empty())?;
+        let is_private = directives
+            .split(',')
+            .any(|s| matches!(s.trim(), "private" | "no-store"));
+        if !session_dependent || is_private {
+            return Some(Cow::Borrowed(directives));
+        }
+
+        let mut private_directives = vec!["private"];
+        private_directives.extend(
+            directives
+                .split(',')
+                .map(|s| s.trim())
+                .filter(|&s| s != "public" && !s.starts_with("s-maxage")),
+        );
+        Some(Cow::Owned(private_directives.join(", ")))
+    }
+
+    /// Returns `true` if the representation of the model depends on the roles of the client,
+    /// which means that there are fields declared with `read_roles` or masking rules.
+    fn is_role_dependent() -> bool {
+        Self::columns().iter().any(|col| {
+            col.extra().contains_key("read_roles") || Self::masking_rule(col.name()).is_some()
+        })
+    }
+
+    /// Fetches the `version` and `updated_at` fields of a model selected by the primary key.
+    /// They are used as the validators for conditional requests.
+    async fn fetch_validators_by_id(id: &K) -> Result<Option<(u64, DateTime)>, Error> {
+        if !Self::has_column("updated_at") {
+            return Ok(None);
+        }
+
+        let mut fields = vec!["updated_at"];
+        if Self::has_column("version") {
+            fields.push("version");
+        }
+
+        let mut query = Query::default();
+        query.allow_fields(&fields);
+        query.add_filter(Self::PRIMARY_KEY_NAME, id.to_string());
+        let validators = Self::find_one::<Map>(&query).await?.and_then(|map| {
+            let updated_at = map.parse_string("updated_at")?.parse().ok()?;
+            let version = map.get_u64("version").unwrap_or_default();
+            Some((version, updated_at))
+        });
+        Ok(validators)
+    }
+
+    /// Fetches the number of rows and the maximum of `updated_at` values selected by the query.
+    /// They are used as the validators for conditional requests.
+    async fn fetch_list_validators(query: &Query) -> Result<Option<(u64, DateTime)>, Error> {
+        if !Self::has_column("updated_at") {
+            return Ok(None);
+        }
+
+        let mut query = Query::new(query.filters().clone());
+        query.add_field_alias("count(*)", "num_rows");
+        query.add_field_alias("max(updated_at)", "last_modified");
+        let validators = Self::find_one::<Map>(&query).await?.and_then(|map| {
+            let num_rows = map.parse_u64("num_rows")?.ok()?;
+            let last_modified = map.parse_string("last_modified")?.parse().ok()?;
+            Some((num_rows, last_modified))
+        });
+        Ok(validators)
+    }
+
     /// Deletes a model of the primary key by setting the status as `Deleted`.
     async fn soft_delete_by_id(id: &K) -> Result<(), Error> {
         let mut model = Self::try_get_model(id).await?;
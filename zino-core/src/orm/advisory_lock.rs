@@ -0,0 +1,127 @@
+use super::{ConnectionPool, DatabaseDriver, GlobalPool};
+use crate::{
+    error::Error,
+    schedule::{Lease, LeaseStore},
+    warn, BoxFuture,
+};
+use parking_lot::Mutex;
+use sqlx::{pool::PoolConnection, Row};
+use std::{collections::HashMap, fmt, time::Duration};
+
+/// Lease store backed by the PostgreSQL advisory locks.
+///
+/// The session-level advisory lock is held by a dedicated connection until the lease
+/// is released, so the lease is lost if the connection has been broken.
+/// Since the advisory lock does not expire, the instance holding it keeps acquiring
+/// the lease for the subsequent ticks until it is released or the instance stops.
+/// The fencing token is the transaction ID assigned when the lock is acquired,
+/// which increases monotonically in the database cluster.
+pub struct AdvisoryLockStore {
+    /// Connection pool.
+    pool: &'static ConnectionPool,
+    /// Connections holding the advisory locks and the fencing tokens.
+    connections: Mutex<HashMap<String, (PoolConnection<DatabaseDriver>, u64)>>,
+}
+
+impl AdvisoryLockStore {
+    /// Creates a new instance with the connection pool.
+    #[inline]
+    pub fn new(pool: &'static ConnectionPool) -> Self {
+        Self {
+            pool,
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Attempts to create a new instance with the database name.
+    pub fn try_new(database: &str) -> Result<Self, Error> {
+        GlobalPool::get(database)
+            .map(Self::new)
+            .ok_or_else(|| warn!("connection pool `{}` is not available", database))
+    }
+}
+
+impl LeaseStore for AdvisoryLockStore {
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        holder: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            if let Some((_, token)) = self.connections.lock().get(key) {
+                return Ok(Some(Lease::new(key, holder, *token, ttl)));
+            }
+
+            let mut connection = self.pool.pool().acquire().await?;
+            let sql = "SELECT pg_try_advisory_lock(hashtext($1)) AS locked, \
+                txid_current() AS token;";
+            let row = sqlx::query(sql)
+                .bind(key)
+                .fetch_one(&mut *connection)
+                .await?;
+            if !row.try_get::<bool, _>("locked")? {
+                return Ok(None);
+            }
+
+            let token = row.try_get::<i64, _>("token")? as u64;
+            self.connections
+                .lock()
+                .insert(key.to_owned(), (connection, token));
+            Ok(Some(Lease::new(key, holder, token, ttl)))
+        })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a Lease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            let key = lease.key();
+            let Some((mut connection, token)) = self.connections.lock().remove(key) else {
+                return Ok(None);
+            };
+            match sqlx::query("SELECT 1;").execute(&mut *connection).await {
+                Ok(_) => {
+                    self.connections
+                        .lock()
+                        .insert(key.to_owned(), (connection, token));
+
+                    let mut lease = lease.clone();
+                    lease.extend(ttl);
+                    Ok(Some(lease))
+                }
+                Err(err) => {
+                    tracing::warn!(key, "the connection holding the lease is broken: {err}");
+                    connection.close().await.ok();
+                    Ok(None)
+                }
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, lease: &'a Lease) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let key = lease.key();
+            let Some((mut connection, _)) = self.connections.lock().remove(key) else {
+                return Ok(());
+            };
+            let sql = "SELECT pg_advisory_unlock(hashtext($1));";
+            if let Err(err) = sqlx::query(sql).bind(key).execute(&mut *connection).await {
+                // Closes the connection to ensure that the advisory lock is released.
+                connection.close().await.ok();
+                return Err(err.into());
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Debug for AdvisoryLockStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdvisoryLockStore")
+            .field("pool", &self.pool.name())
+            .finish_non_exhaustive()
+    }
+}
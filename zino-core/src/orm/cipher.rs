@@ -0,0 +1,283 @@
+use super::helper::SECRET_KEY;
+use crate::{
+    bail,
+    crypto::{self, Digest},
+    encoding::{base64, hex},
+    error::Error,
+    extension::TomlTableExt,
+    state::State,
+    warn, LazyLock,
+};
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use std::{borrow::Cow, collections::HashMap};
+
+/// Prefix of the encrypted column value.
+const ENCRYPTED_PREFIX: &str = "enc:";
+
+/// Size of the data encryption key.
+const DATA_KEY_SIZE: usize = 32;
+
+/// Envelope encryption for the column values.
+///
+/// Each value is encrypted with a random data key, which is wrapped by a key encryption key.
+/// The encrypted value has the format `enc:{key_id}:{wrapped_key}:{ciphertext}`,
+/// so that the values encrypted by a retired key can still be decrypted
+/// and rewrapped with the current key.
+///
+/// The blind indexes are computed with a separate `blind-index-key`, which should be stable
+/// across the rotations of the key encryption keys, since they are never recomputed.
+///
+/// ```toml
+/// [database.encryption]
+/// key-id = "2024"
+/// blind-index-key = "a secret for the blind index"
+///
+/// [database.encryption.keys]
+/// 2023 = "the retired secret"
+/// 2024 = "the current secret"
+/// ```
+#[derive(Debug)]
+pub struct ColumnCipher {
+    /// Current key ID.
+    key_id: String,
+    /// Key encryption keys.
+    keys: HashMap<String, [u8; 64]>,
+    /// Key for the blind index.
+    blind_index_key: [u8; 64],
+}
+
+impl ColumnCipher {
+    /// Creates a new instance with the current key ID, the secret
+    /// and the secret for the blind index.
+    pub fn new(key_id: impl Into<String>, secret: &str, blind_index_secret: &str) -> Self {
+        let key_id = key_id.into();
+        let mut keys = HashMap::new();
+        keys.insert(key_id.clone(), derive_key_encryption_key(secret));
+
+        let checksum = crypto::digest(blind_index_secret.as_bytes());
+        Self {
+            key_id,
+            keys,
+            blind_index_key: crypto::derive_key("ZINO:ORM:BLIND-INDEX", &checksum),
+        }
+    }
+
+    /// Adds a retired key which is only used for decryption.
+    #[inline]
+    pub fn add_key(&mut self, key_id: impl Into<String>, secret: &str) {
+        self.keys
+            .insert(key_id.into(), derive_key_encryption_key(secret));
+    }
+
+    /// Returns the current key ID.
+    #[inline]
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Encrypts the plaintext.
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, Error> {
+        let key = self.current_key()?;
+        let mut data_key = [0u8; DATA_KEY_SIZE];
+        rand::thread_rng().fill(&mut data_key);
+
+        let ciphertext = crypto::encrypt(plaintext.as_bytes(), &data_key)?;
+        let wrapped_key = crypto::encrypt(&data_key, key)?;
+        Ok(format!(
+            "{ENCRYPTED_PREFIX}{}:{}:{}",
+            self.key_id,
+            base64::encode(wrapped_key),
+            base64::encode(ciphertext)
+        ))
+    }
+
+    /// Decrypts the data.
+    /// The data without the encryption prefix is returned as it is.
+    pub fn decrypt<'a>(&self, data: &'a str) -> Result<Cow<'a, str>, Error> {
+        let Some((key_id, wrapped_key, ciphertext)) = parse_encrypted_value(data) else {
+            return Ok(Cow::Borrowed(data));
+        };
+        let data_key = self.unwrap_data_key(key_id, wrapped_key)?;
+        let ciphertext = base64::decode(ciphertext)?;
+        let plaintext = crypto::decrypt(&ciphertext, &data_key)?;
+        String::from_utf8(plaintext)
+            .map(Cow::Owned)
+            .map_err(Error::from)
+    }
+
+    /// Rewraps the data key of the encrypted value with the current key,
+    /// or encrypts the value if it is a plaintext.
+    /// It returns `None` if the value has already been encrypted by the current key.
+    pub fn rewrap(&self, data: &str) -> Result<Option<String>, Error> {
+        let Some((key_id, wrapped_key, ciphertext)) = parse_encrypted_value(data) else {
+            return self.encrypt(data).map(Some);
+        };
+        if key_id == self.key_id {
+            return Ok(None);
+        }
+
+        let data_key = self.unwrap_data_key(key_id, wrapped_key)?;
+        let wrapped_key = crypto::encrypt(&data_key, self.current_key()?)?;
+        Ok(Some(format!(
+            "{ENCRYPTED_PREFIX}{}:{}:{}",
+            self.key_id,
+            base64::encode(wrapped_key),
+            ciphertext
+        )))
+    }
+
+    /// Computes the blind index of the value for equality lookups.
+    pub fn blind_index(&self, value: &str) -> String {
+        let mut mac = Hmac::<Digest>::new_from_slice(&self.blind_index_key)
+            .expect("HMAC can take key of any size");
+        mac.update(value.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Returns `true` if the data has been encrypted.
+    #[inline]
+    pub fn is_encrypted(data: &str) -> bool {
+        parse_encrypted_value(data).is_some()
+    }
+
+    /// Returns a reference to the shared column cipher.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        &SHARED_COLUMN_CIPHER
+    }
+
+    /// Returns the current key encryption key.
+    fn current_key(&self) -> Result<&[u8; 64], Error> {
+        self.keys
+            .get(&self.key_id)
+            .ok_or_else(|| warn!("encryption key `{}` does not exist", self.key_id))
+    }
+
+    /// Unwraps the data key with the key encryption key.
+    fn unwrap_data_key(&self, key_id: &str, wrapped_key: &str) -> Result<Vec<u8>, Error> {
+        let Some(key) = self.keys.get(key_id) else {
+            bail!("encryption key `{}` does not exist", key_id);
+        };
+        let wrapped_key = base64::decode(wrapped_key)?;
+        crypto::decrypt(&wrapped_key, key)
+    }
+}
+
+/// Derives a key encryption key from the secret.
+fn derive_key_encryption_key(secret: &str) -> [u8; 64] {
+    let checksum = crypto::digest(secret.as_bytes());
+    crypto::derive_key("ZINO:ORM:KEK", &checksum)
+}
+
+/// Parses the encrypted value as a tuple of `(key_id, wrapped_key, ciphertext)`.
+fn parse_encrypted_value(data: &str) -> Option<(&str, &str, &str)> {
+    let mut parts = data.strip_prefix(ENCRYPTED_PREFIX)?.splitn(3, ':');
+    let key_id = parts.next().filter(|s| !s.is_empty())?;
+    let wrapped_key = parts.next().filter(|s| !s.is_empty())?;
+    let ciphertext = parts.next().filter(|s| !s.is_empty())?;
+    Some((key_id, wrapped_key, ciphertext))
+}
+
+/// Shared column cipher.
+static SHARED_COLUMN_CIPHER: LazyLock<ColumnCipher> = LazyLock::new(|| {
+    let app_config = State::shared().config();
+    let config = app_config
+        .get_table("database")
+        .and_then(|t| t.get_table("encryption"));
+    let Some(config) = config else {
+        let mut cipher = ColumnCipher::new("default", "", "");
+        cipher.keys.insert("default".to_owned(), *SECRET_KEY);
+        cipher.blind_index_key = crypto::derive_key("ZINO:ORM:BLIND-INDEX", &SECRET_KEY[..32]);
+        return cipher;
+    };
+
+    let key_id = config.get_str("key-id").unwrap_or("default");
+    let keys = config.get_table("keys");
+    let secret = keys
+        .and_then(|t| t.get_str(key_id))
+        .filter(|secret| !secret.is_empty())
+        .unwrap_or_else(|| panic!("encryption key `{key_id}` should be configured"));
+    let blind_index_secret = config
+        .get_str("blind-index-key")
+        .filter(|secret| !secret.is_empty())
+        .expect("blind index key should be configured for the encryption");
+    let mut cipher = ColumnCipher::new(key_id, secret, blind_index_secret);
+    if let Some(keys) = keys {
+        for (id, secret) in keys {
+            if id != key_id {
+                if let Some(secret) = secret.as_str() {
+                    cipher.add_key(id, secret);
+                }
+            }
+        }
+    }
+    cipher
+});
+
+#[cfg(test)]
+mod tests {
+    use super::ColumnCipher;
+    use crate::{extension::JsonObjectExt, Map};
+
+    #[test]
+    fn it_rotates_encryption_keys() {
+        let cipher = ColumnCipher::new("v1", "secret for the first key", "blind index");
+        let encrypted = cipher.encrypt("alice@example.com").unwrap();
+        assert!(encrypted.starts_with("enc:v1:"));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "alice@example.com");
+        assert_eq!(cipher.decrypt("plain").unwrap(), "plain");
+        assert_eq!(cipher.rewrap(&encrypted).unwrap(), None);
+
+        let mut rotated_cipher =
+            ColumnCipher::new("v2", "secret for the second key", "blind index");
+        rotated_cipher.add_key("v1", "secret for the first key");
+        let rewrapped = rotated_cipher.rewrap(&encrypted).unwrap().unwrap();
+        assert!(rewrapped.starts_with("enc:v2:"));
+        assert_eq!(
+            rotated_cipher.decrypt(&rewrapped).unwrap(),
+            "alice@example.com"
+        );
+        assert!(cipher.decrypt(&rewrapped).is_err());
+    }
+
+    #[test]
+    fn it_looks_up_blind_indexes_after_rotating_keys() {
+        let cipher = ColumnCipher::new("v1", "secret for the first key", "blind index");
+        let mut rows = ["alice@example.com", "bob@example.com"]
+            .into_iter()
+            .map(|email| {
+                let mut row = Map::new();
+                row.upsert("email", cipher.encrypt(email).unwrap());
+                row.upsert("email_index", cipher.blind_index(email));
+                row
+            })
+            .collect::<Vec<_>>();
+
+        let mut rotated_cipher =
+            ColumnCipher::new("v2", "secret for the second key", "blind index");
+        rotated_cipher.add_key("v1", "secret for the first key");
+        for row in rows.iter_mut() {
+            let ciphertext = row.get_str("email").unwrap();
+            if let Some(ciphertext) = rotated_cipher.rewrap(ciphertext).unwrap() {
+                row.upsert("email", ciphertext);
+            }
+        }
+
+        let blind_index = rotated_cipher.blind_index("bob@example.com");
+        let matched_rows = rows
+            .iter()
+            .filter(|row| row.get_str("email_index") == Some(blind_index.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(matched_rows.len(), 1);
+        assert_eq!(
+            rotated_cipher
+                .decrypt(matched_rows[0].get_str("email").unwrap())
+                .unwrap(),
+            "bob@example.com"
+        );
+
+        let other_cipher = ColumnCipher::new("v2", "secret for the second key", "other index");
+        assert_ne!(other_cipher.blind_index("bob@example.com"), blind_index);
+    }
+}
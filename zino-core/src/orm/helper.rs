@@ -4,8 +4,9 @@ use crate::{
     encoding::base64,
     error::Error,
     extension::{JsonObjectExt, TomlTableExt},
+    model::{MaskingPolicy, MaskingRule},
     state::State,
-    warn, LazyLock, Map,
+    warn, JsonValue, LazyLock, Map,
 };
 use std::fmt::Display;
 
@@ -65,6 +66,62 @@ where
             }
         }
     }
+
+    /// Masks the sensitive fields of the model data
+    /// unless any of the roles can access the unmasked values.
+    fn mask_model(model: &mut Map, roles: &[&str]) {
+        let policy = MaskingPolicy::shared();
+        if policy.can_unmask(roles) {
+            return;
+        }
+
+        for col in Self::columns() {
+            let field = col.name();
+            if let Some((rule, value)) = Self::masking_rule(field).zip(model.get_mut(field)) {
+                rule.mask_value(value);
+            }
+        }
+    }
+
+    /// Removes the fields which are not readable by the roles,
+    /// and masks the sensitive fields of the model data.
+    fn restrict_model(model: &mut Map, roles: &[&str]) {
+        model.retain(|key, _value| {
+            Self::get_column(key).map_or(true, |col| col.is_readable_by(roles))
+        });
+        Self::mask_model(model, roles);
+    }
+
+    /// Removes the masked values echoed back in the data to be written,
+    /// so that they will not overwrite the real values.
+    fn strip_masked_values(data: &mut Map, roles: &[&str]) {
+        if MaskingPolicy::shared().can_unmask(roles) {
+            return;
+        }
+        for col in Self::columns() {
+            let field = col.name();
+            if let Some(rule) = Self::masking_rule(field) {
+                let is_masked = data.get(field).is_some_and(|value| match value {
+                    JsonValue::String(s) => rule.is_masked(s),
+                    JsonValue::Array(vec) => vec
+                        .iter()
+                        .any(|v| v.as_str().is_some_and(|s| rule.is_masked(s))),
+                    _ => false,
+                });
+                if is_masked {
+                    data.remove(field);
+                }
+            }
+        }
+    }
+
+    /// Returns the masking rule for the field.
+    fn masking_rule(field: &str) -> Option<MaskingRule> {
+        let rule = Self::get_column(field)
+            .and_then(|col| col.extra().get_str("mask"))
+            .and_then(|s| s.parse().ok());
+        rule.or_else(|| MaskingPolicy::shared().get_field_rule(Self::model_name(), field))
+    }
 }
 
 impl<M, K> ModelHelper<K> for M
@@ -75,7 +132,7 @@ where
 }
 
 /// Secret key.
-static SECRET_KEY: LazyLock<[u8; 64]> = LazyLock::new(|| {
+pub(super) static SECRET_KEY: LazyLock<[u8; 64]> = LazyLock::new(|| {
     let app_config = State::shared().config();
     let config = app_config.get_table("database").unwrap_or(app_config);
     let checksum: [u8; 32] = config
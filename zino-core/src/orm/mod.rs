@@ -21,6 +21,7 @@ use std::sync::{
 
 mod accessor;
 mod aggregate;
+mod cipher;
 mod column;
 mod entity;
 mod executor;
@@ -37,6 +38,7 @@ mod window;
 
 pub use accessor::ModelAccessor;
 pub use aggregate::Aggregation;
+pub use cipher::ColumnCipher;
 pub use entity::Entity;
 pub use executor::Executor;
 pub use helper::ModelHelper;
@@ -85,8 +87,11 @@ cfg_if::cfg_if! {
         /// A single row from the MySQL database.
         pub type DatabaseRow = sqlx::mysql::MySqlRow;
     } else if #[cfg(feature = "orm-postgres")] {
+        mod advisory_lock;
         mod postgres;
 
+        pub use advisory_lock::AdvisoryLockStore;
+
         /// Driver name.
         static DRIVER_NAME: &str = "postgres";
 
@@ -155,6 +160,9 @@ impl GlobalPool {
     /// attempts to establish a database connection for each of them.
     #[inline]
     pub async fn connect_all() {
+        // Initializes the column cipher eagerly, so that a missing encryption key
+        // fails at startup instead of the first query.
+        ColumnCipher::shared();
         for cp in SHARED_CONNECTION_POOLS.0.iter() {
             cp.check_availability().await;
         }
@@ -254,3 +262,44 @@ static AUTO_MIGRATION: AtomicBool = AtomicBool::new(true);
 
 /// Debug-only mode.
 static DEBUG_ONLY: AtomicBool = AtomicBool::new(false);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        extension::JsonObjectExt,
+        model::{Column, EncodeColumn, Query},
+        JsonValue, Map,
+    };
+
+    #[test]
+    fn it_formats_overlaps_filters() {
+        let column = Column::new("subjects", "Vec<String>", true);
+        let filter = JsonValue::from(Map::from_entry("$overlaps", Vec::<String>::new()));
+        assert_eq!(column.format_filter("subjects", &filter), "FALSE");
+
+        let expected_condition = if cfg!(any(
+            feature = "orm-mariadb",
+            feature = "orm-mysql",
+            feature = "orm-tidb"
+        )) {
+            "json_overlaps(`subjects`, json_array('*','role:admin'))"
+        } else if cfg!(feature = "orm-postgres") {
+            r#""subjects" && ARRAY['*','role:admin']::TEXT[]"#
+        } else {
+            "EXISTS (SELECT 1 FROM json_each(`subjects`) AS t WHERE t.value IN ('*', 'role:admin'))"
+        };
+        let filter = JsonValue::from(Map::from_entry("$overlaps", vec!["*", "role:admin"]));
+        assert_eq!(
+            column.format_filter("subjects", &filter),
+            expected_condition
+        );
+
+        // The filter parsed from the query string `subjects=$overlaps.*,role:admin`.
+        let mut query = Query::default();
+        let data = Map::from_entry("subjects", "$overlaps.*,role:admin");
+        assert!(query.read_map(&data).is_success());
+
+        let filter = query.filters().get("subjects").unwrap();
+        assert_eq!(column.format_filter("subjects", filter), expected_condition);
+    }
+}
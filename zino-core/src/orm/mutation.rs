@@ -3,7 +3,7 @@ use super::{query::QueryExt, DatabaseDriver, Entity, IntoSqlValue, Schema};
 use crate::{
     datetime::DateTime,
     extension::JsonObjectExt,
-    model::{EncodeColumn, Mutation, Query},
+    model::{Column, EncodeColumn, Mutation, Query},
     JsonValue, Map,
 };
 use std::marker::PhantomData;
@@ -177,20 +177,17 @@ impl MutationExt<DatabaseDriver> for Mutation {
         }
 
         let fields = self.fields();
-        let permissive = fields.is_empty();
         let mut mutations = Vec::new();
         for (key, value) in updates.iter() {
             match key.as_str() {
                 "$inc" => {
                     if let Some(update) = value.as_object() {
                         for (key, value) in update.iter() {
-                            if permissive || fields.contains(key) {
-                                if let Some(col) = M::get_writable_column(key) {
-                                    let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
-                                    let mutation = format!(r#"{key} = {value} + {key}"#);
-                                    mutations.push(mutation);
-                                }
+                            if let Some(col) = get_mutable_column::<M>(key, fields) {
+                                let key = Query::format_field(key);
+                                let value = col.encode_value(Some(value));
+                                let mutation = format!(r#"{key} = {value} + {key}"#);
+                                mutations.push(mutation);
                             }
                         }
                     }
@@ -198,13 +195,11 @@ impl MutationExt<DatabaseDriver> for Mutation {
                 "$mul" => {
                     if let Some(update) = value.as_object() {
                         for (key, value) in update.iter() {
-                            if permissive || fields.contains(key) {
-                                if let Some(col) = M::get_writable_column(key) {
-                                    let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
-                                    let mutation = format!(r#"{key} = {value} * {key}"#);
-                                    mutations.push(mutation);
-                                }
+                            if let Some(col) = get_mutable_column::<M>(key, fields) {
+                                let key = Query::format_field(key);
+                                let value = col.encode_value(Some(value));
+                                let mutation = format!(r#"{key} = {value} * {key}"#);
+                                mutations.push(mutation);
                             }
                         }
                     }
@@ -212,17 +207,15 @@ impl MutationExt<DatabaseDriver> for Mutation {
                 "$min" => {
                     if let Some(update) = value.as_object() {
                         for (key, value) in update.iter() {
-                            if permissive || fields.contains(key) {
-                                if let Some(col) = M::get_writable_column(key) {
-                                    let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
-                                    let mutation = if cfg!(feature = "orm-sqlite") {
-                                        format!(r#"{key} = MIN({value}, {key})"#)
-                                    } else {
-                                        format!(r#"{key} = LEAST({value}, {key})"#)
-                                    };
-                                    mutations.push(mutation);
-                                }
+                            if let Some(col) = get_mutable_column::<M>(key, fields) {
+                                let key = Query::format_field(key);
+                                let value = col.encode_value(Some(value));
+                                let mutation = if cfg!(feature = "orm-sqlite") {
+                                    format!(r#"{key} = MIN({value}, {key})"#)
+                                } else {
+                                    format!(r#"{key} = LEAST({value}, {key})"#)
+                                };
+                                mutations.push(mutation);
                             }
                         }
                     }
@@ -230,35 +223,31 @@ impl MutationExt<DatabaseDriver> for Mutation {
                 "$max" => {
                     if let Some(update) = value.as_object() {
                         for (key, value) in update.iter() {
-                            if permissive || fields.contains(key) {
-                                if let Some(col) = M::get_writable_column(key) {
-                                    let key = Query::format_field(key);
-                                    let value = col.encode_value(Some(value));
-                                    let mutation = if cfg!(feature = "orm-sqlite") {
-                                        format!(r#"{key} = MAX({value}, {key})"#)
-                                    } else {
-                                        format!(r#"{key} = GREATEST({value}, {key})"#)
-                                    };
-                                    mutations.push(mutation);
-                                }
+                            if let Some(col) = get_mutable_column::<M>(key, fields) {
+                                let key = Query::format_field(key);
+                                let value = col.encode_value(Some(value));
+                                let mutation = if cfg!(feature = "orm-sqlite") {
+                                    format!(r#"{key} = MAX({value}, {key})"#)
+                                } else {
+                                    format!(r#"{key} = GREATEST({value}, {key})"#)
+                                };
+                                mutations.push(mutation);
                             }
                         }
                     }
                 }
                 _ => {
-                    if permissive || fields.contains(key) {
-                        if let Some(col) = M::get_writable_column(key) {
-                            let key = Query::format_field(key);
-                            let mutation = if let Some(subquery) =
-                                value.as_object().and_then(|m| m.get_str("$subquery"))
-                            {
-                                format!(r#"{key} = {subquery}"#)
-                            } else {
-                                let value = col.encode_value(Some(value));
-                                format!(r#"{key} = {value}"#)
-                            };
-                            mutations.push(mutation);
-                        }
+                    if let Some(col) = get_mutable_column::<M>(key, fields) {
+                        let key = Query::format_field(key);
+                        let mutation = if let Some(subquery) =
+                            value.as_object().and_then(|m| m.get_str("$subquery"))
+                        {
+                            format!(r#"{key} = {subquery}"#)
+                        } else {
+                            let value = col.encode_value(Some(value));
+                            format!(r#"{key} = {value}"#)
+                        };
+                        mutations.push(mutation);
                     }
                 }
             }
@@ -266,3 +255,18 @@ impl MutationExt<DatabaseDriver> for Mutation {
         mutations.join(", ")
     }
 }
+
+/// Returns the column which can be updated by the mutation.
+/// Read-only columns can only be updated when they are explicitly allowed.
+fn get_mutable_column<'a, M: Schema>(
+    key: &'a str,
+    fields: &[String],
+) -> Option<&'a Column<'static>> {
+    if fields.is_empty() {
+        M::get_writable_column(key)
+    } else if fields.iter().any(|field| field == key) {
+        M::get_column(key)
+    } else {
+        None
+    }
+}
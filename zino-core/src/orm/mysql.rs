@@ -190,6 +190,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                         "$rlike" => "RLIKE",
                         "$is" => "IS",
                         "$size" => "json_length",
+                        "$overlaps" => "json_overlaps",
                         _ => {
                             if cfg!(debug_assertions) && name.starts_with('$') {
                                 tracing::warn!("unsupported operator `{name}` for MySQL");
@@ -238,6 +239,14 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             let condition = format!(r#"json_length({field}) = {length}"#);
                             conditions.push(condition);
                         }
+                    } else if operator == "json_overlaps" {
+                        if value.as_array().is_some_and(|values| values.is_empty()) {
+                            conditions.push("FALSE".to_owned());
+                        } else {
+                            let value = self.encode_value(Some(value));
+                            let condition = format!(r#"json_overlaps({field}, {value})"#);
+                            conditions.push(condition);
+                        }
                     } else {
                         let value = self.encode_value(Some(value));
                         let condition = format!(r#"{field} {operator} {value}"#);
@@ -199,6 +199,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                         "$rlike" => "~*",
                         "$is" => "IS",
                         "$size" => "array_length",
+                        "$overlaps" => "&&",
                         _ => {
                             if cfg!(debug_assertions) && name.starts_with('$') {
                                 tracing::warn!("unsupported operator `{name}` for PostgreSQL");
@@ -247,6 +248,14 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             let condition = format!(r#"array_length({field}, 1) = {length}"#);
                             conditions.push(condition);
                         }
+                    } else if operator == "&&" {
+                        if value.as_array().is_some_and(|values| values.is_empty()) {
+                            conditions.push("FALSE".to_owned());
+                        } else {
+                            let value = self.encode_value(Some(value));
+                            let condition = format!(r#"{field} && {value}"#);
+                            conditions.push(condition);
+                        }
                     } else {
                         let value = self.encode_value(Some(value));
                         let condition = format!(r#"{field} {operator} {value}"#);
@@ -1014,6 +1014,12 @@ pub(super) trait QueryExt<DB> {
             return String::new();
         }
 
+        if let Err(err) = M::check_encrypted_filters(filters) {
+            // Filters on the ciphertexts never match, so nothing is selected.
+            tracing::warn!("{err}");
+            return "WHERE FALSE".to_owned();
+        }
+
         let mut expression = String::new();
         let mut logical_and_conditions = Vec::with_capacity(filters.len());
         for (key, value) in filters {
@@ -1059,6 +1065,11 @@ pub(super) trait QueryExt<DB> {
                     }
                 }
                 _ => {
+                    let blind_index_filter = M::blind_index_filter(key, value).ok().flatten();
+                    let (key, value) = match blind_index_filter {
+                        Some((index_field, ref value)) => (index_field, value),
+                        None => (key.as_str(), value),
+                    };
                     if let Some(col) = M::get_column(key) {
                         let condition = if let Some(subquery) =
                             value.as_object().and_then(|m| m.get_str("$subquery"))
@@ -1131,6 +1142,12 @@ pub(super) trait QueryExt<DB> {
                             }
                         }
                         _ => {
+                            let blind_index_filter =
+                                M::blind_index_filter(key, value).ok().flatten();
+                            let (key, value) = match blind_index_filter {
+                                Some((index_field, ref value)) => (index_field, value),
+                                None => (key.as_str(), value),
+                            };
                             if let Some(col) = M::get_column(key) {
                                 let condition = if let Some(subquery) =
                                     value.as_object().and_then(|m| m.get_str("$subquery"))
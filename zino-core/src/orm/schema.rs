@@ -1,6 +1,6 @@
 use super::{
-    column::ColumnExt, mutation::MutationExt, query::QueryExt, ConnectionPool, DatabaseRow, Entity,
-    Executor, GlobalPool, IntoSqlValue, JoinOn, ModelHelper, QueryBuilder,
+    column::ColumnExt, mutation::MutationExt, query::QueryExt, ColumnCipher, ConnectionPool,
+    DatabaseRow, Entity, Executor, GlobalPool, IntoSqlValue, JoinOn, ModelHelper, QueryBuilder,
 };
 use crate::{
     bail,
@@ -47,6 +47,200 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Returns a reference to the write-only column fields.
     fn write_only_fields() -> &'static [&'static str];
 
+    /// Returns the column fields which are not readable by the roles.
+    fn unreadable_fields(roles: &[&str]) -> Vec<&'static str> {
+        Self::columns()
+            .iter()
+            .filter(|col| !col.is_readable_by(roles))
+            .map(|col| col.name())
+            .collect()
+    }
+
+    /// Returns the column fields which are not writable by the roles.
+    fn unwritable_fields(roles: &[&str]) -> Vec<&'static str> {
+        Self::columns()
+            .iter()
+            .filter(|col| !col.is_writable_by(roles))
+            .map(|col| col.name())
+            .collect()
+    }
+
+    /// Checks whether the data only contains the fields writable by the roles.
+    fn check_writable_fields(data: &Map, roles: &[&str]) -> Result<(), Error> {
+        for col in Self::columns() {
+            let field = col.name();
+            if data.contains_key(field) && !col.is_writable_by(roles) {
+                bail!("403 Forbidden: the field `{}` is not writable", field);
+            }
+        }
+        Ok(())
+    }
+
+    /// Encrypts the values of the `encrypted` columns and updates their blind indexes.
+    fn encrypt_columns(model: &mut Map) -> Result<(), Error> {
+        let cipher = ColumnCipher::shared();
+        for col in Self::columns().iter().filter(|col| col.is_encrypted()) {
+            let field = col.name();
+            let Some(value) = model.get_str(field) else {
+                continue;
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            let ciphertext = cipher.encrypt(value)?;
+            if let Some(index_field) = col.blind_index() {
+                let blind_index = cipher.blind_index(value);
+                model.upsert(index_field, blind_index);
+            }
+            model.upsert(field, ciphertext);
+        }
+        Ok(())
+    }
+
+    /// Decrypts the values of the `encrypted` columns.
+    fn decrypt_columns(model: &mut Map) -> Result<(), Error> {
+        let cipher = ColumnCipher::shared();
+        for col in Self::columns().iter().filter(|col| col.is_encrypted()) {
+            if let Some(JsonValue::String(value)) = model.get_mut(col.name()) {
+                if ColumnCipher::is_encrypted(value) {
+                    let plaintext = cipher.decrypt(value)?.into_owned();
+                    *value = plaintext;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the filter on the blind index for an equality lookup of the encrypted column.
+    /// Only the `$eq`, `$ne`, `$in` and `$nin` operators are supported,
+    /// and an error is returned for the other filters on the encrypted column.
+    fn blind_index_filter<'a>(
+        key: &'a str,
+        value: &JsonValue,
+    ) -> Result<Option<(&'a str, JsonValue)>, Error> {
+        let Some(col) = Self::get_column(key).filter(|col| col.is_encrypted()) else {
+            return Ok(None);
+        };
+        let Some(index_field) = col.blind_index() else {
+            bail!(
+                "400 Bad Request: the encrypted field `{}` can not be filtered",
+                key
+            );
+        };
+        let cipher = ColumnCipher::shared();
+        let blind_index = |value: &JsonValue| -> Option<JsonValue> {
+            if let Some(values) = value.as_array() {
+                let blind_indexes = values
+                    .iter()
+                    .filter_map(|v| v.as_str())
+                    .map(|s| cipher.blind_index(s))
+                    .collect::<Vec<_>>();
+                Some(blind_indexes.into())
+            } else {
+                value.as_str().map(|s| cipher.blind_index(s).into())
+            }
+        };
+        let unsupported_filter = || {
+            warn!(
+                "400 Bad Request: unsupported filter for the encrypted field `{}`",
+                key
+            )
+        };
+        let value = if let Some(map) = value.as_object() {
+            let mut filter = Map::new();
+            for (operator, value) in map {
+                if !matches!(operator.as_str(), "$eq" | "$ne" | "$in" | "$nin") {
+                    return Err(unsupported_filter());
+                }
+                filter.upsert(operator, blind_index(value).ok_or_else(unsupported_filter)?);
+            }
+            filter.into()
+        } else {
+            blind_index(value).ok_or_else(unsupported_filter)?
+        };
+        Ok(Some((index_field, value)))
+    }
+
+    /// Checks whether the filters on the `encrypted` columns are supported by the blind indexes.
+    fn check_encrypted_filters(filters: &Map) -> Result<(), Error> {
+        for (key, value) in filters {
+            match key.as_str() {
+                "$and" | "$or" | "$not" | "$nor" | "$having" => {
+                    if let Some(filters) = value.as_array() {
+                        for filter in filters.iter().filter_map(|v| v.as_object()) {
+                            Self::check_encrypted_filters(filter)?;
+                        }
+                    }
+                }
+                _ => {
+                    Self::blind_index_filter(key, value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewraps the values of the `encrypted` columns with the current encryption key,
+    /// and returns the number of rows affected.
+    async fn rotate_encryption_keys() -> Result<u64, Error> {
+        let encrypted_fields = Self::columns()
+            .iter()
+            .filter(|col| col.is_encrypted())
+            .map(|col| col.name())
+            .collect::<Vec<_>>();
+        if encrypted_fields.is_empty() {
+            return Ok(0);
+        }
+
+        let primary_key_name = Self::PRIMARY_KEY_NAME;
+        let mut query = Query::default();
+        query.allow_fields(&[&[primary_key_name], encrypted_fields.as_slice()].concat());
+        query.disable_limit();
+
+        let cipher = ColumnCipher::shared();
+        let mut rows_affected = 0;
+        for model in Self::find::<Map>(&query).await? {
+            let Some(primary_key) = model.get(primary_key_name) else {
+                continue;
+            };
+            let mut plaintexts = Map::new();
+            let mut ciphertexts = Map::new();
+            for &field in &encrypted_fields {
+                if let Some(value) = model.get_str(field).filter(|s| !s.is_empty()) {
+                    if !ColumnCipher::is_encrypted(value) {
+                        // Plaintext values are encrypted along with their blind indexes.
+                        plaintexts.upsert(field, value);
+                    } else if let Some(ciphertext) = cipher.rewrap(value)? {
+                        ciphertexts.upsert(field, ciphertext);
+                    }
+                }
+            }
+
+            let query = Query::from_entry(primary_key_name, primary_key.clone());
+            let mut updated = false;
+            if !plaintexts.is_empty() {
+                let mut mutation = Mutation::new(plaintexts);
+                let ctx = Self::update_one(&query, &mut mutation).await?;
+                updated |= ctx.rows_affected().is_some_and(|n| n > 0);
+            }
+            if !ciphertexts.is_empty() {
+                // The rewrapped ciphertexts are written as is, since the values
+                // passed to `update_one` are always encrypted.
+                let table_name = query.format_table_name::<Self>();
+                let filters = query.format_filters::<Self>();
+                let updates = Mutation::new(ciphertexts).format_updates::<Self>();
+                let sql = format!("UPDATE {table_name} SET {updates} {filters};");
+                let ctx = Self::execute(&sql, None).await?;
+                updated |= ctx.rows_affected().is_some_and(|n| n > 0);
+            }
+            if updated {
+                rows_affected += 1;
+            }
+        }
+        Ok(rows_affected)
+    }
+
     /// Retrieves a connection pool for the model reader.
     async fn acquire_reader() -> Result<&'static ConnectionPool, Error>;
 
@@ -435,7 +629,8 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
     /// Prepares the SQL to insert the model into the table.
     async fn prepare_insert(self) -> Result<QueryContext, Error> {
-        let map = self.into_map();
+        let mut map = self.into_map();
+        Self::encrypt_columns(&mut map)?;
         let table_name = Query::table_name_escaped::<Self>();
         let columns = Self::columns();
 
@@ -502,7 +697,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         for mut model in models.into_iter() {
             let _model_data = model.before_insert().await?;
 
-            let map = model.into_map();
+            let mut map = model.into_map();
+            Self::encrypt_columns(&mut map)?;
+
             let entries = columns
                 .iter()
                 .map(|col| col.encode_value(map.get(col.name())))
@@ -592,7 +789,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Query::table_name_escaped::<Self>();
         let primary_key = Query::escape_string(self.primary_key());
-        let map = self.into_map();
+        let mut map = self.into_map();
+        Self::encrypt_columns(&mut map)?;
+
         let read_only_fields = Self::read_only_fields();
         let num_writable_fields = Self::fields().len() - read_only_fields.len();
         let mut mutations = Vec::with_capacity(num_writable_fields);
@@ -650,7 +849,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Query::table_name_escaped::<Self>();
         let primary_key = Query::escape_string(self.primary_key());
-        let map = self.into_map();
+        let mut map = self.into_map();
+        Self::encrypt_columns(&mut map)?;
+
         let read_only_fields = Self::read_only_fields();
         let mut mutations = Vec::with_capacity(columns.len());
         for col in columns {
@@ -660,6 +861,14 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
                     let value = col.encode_value(map.get(field));
                     let field = Query::format_field(field);
                     mutations.push(format!("{field} = {value}"));
+
+                    let index_col = col.blind_index().and_then(Self::get_column);
+                    if let Some(index_col) = index_col {
+                        let index_field = index_col.name();
+                        let value = index_col.encode_value(map.get(index_field));
+                        let index_field = Query::format_field(index_field);
+                        mutations.push(format!("{index_field} = {value}"));
+                    }
                 }
             }
         }
@@ -707,6 +916,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         mutation: &mut Mutation,
     ) -> Result<QueryContext, Error> {
         Self::before_mutation(query, mutation).await?;
+        Self::encrypt_columns(mutation.updates_mut())?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = query.format_table_name::<Self>();
@@ -769,6 +979,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         mutation: &mut Mutation,
     ) -> Result<QueryContext, Error> {
         Self::before_mutation(query, mutation).await?;
+        Self::encrypt_columns(mutation.updates_mut())?;
 
         let table_name = query.format_table_name::<Self>();
         let filters = query.format_filters::<Self>();
@@ -799,7 +1010,9 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
     /// Prepares the SQL to update or insert the model into the table.
     async fn prepare_upsert(self) -> Result<QueryContext, Error> {
-        let map = self.into_map();
+        let mut map = self.into_map();
+        Self::encrypt_columns(&mut map)?;
+
         let table_name = Query::table_name_escaped::<Self>();
         let fields = Self::fields();
         let num_fields = fields.len();
@@ -926,6 +1139,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Prepares the SQL to delete at most one model selected by the query in the table.
     async fn prepare_delete_one(query: &Query) -> Result<QueryContext, Error> {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = query.format_table_name::<Self>();
@@ -970,6 +1184,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Prepares the SQL to delete many models selected by the query in the table.
     async fn prepare_delete_many(query: &Query) -> Result<QueryContext, Error> {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let filters = query.format_filters::<Self>();
@@ -1055,6 +1270,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
@@ -1083,6 +1299,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let mut data = Self::find::<Map>(query).await?;
         let translate_enabled = query.translate_enabled();
         for model in data.iter_mut() {
+            Self::decrypt_columns(model)?;
             Self::after_decode(model).await?;
             translate_enabled.then(|| Self::translate_model(model));
         }
@@ -1096,6 +1313,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let projection = query.format_table_fields::<Self>();
@@ -1122,6 +1340,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     async fn find_one_as<T: DeserializeOwned>(query: &Query) -> Result<Option<T>, Error> {
         match Self::find_one::<Map>(query).await? {
             Some(mut data) => {
+                Self::decrypt_columns(&mut data)?;
                 Self::after_decode(&mut data).await?;
                 query
                     .translate_enabled()
@@ -1140,6 +1359,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         columns: &[C],
     ) -> Result<u64, Error> {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let mut values = Vec::new();
@@ -1181,6 +1401,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         for row in rows {
             let mut map = Map::decode_row(&row)?;
             let primary_key = map.get(primary_key_name).cloned();
+            Self::decrypt_columns(&mut map)?;
             Self::after_decode(&mut map).await?;
             translate_enabled.then(|| Self::translate_model(&mut map));
             if let Some(key) = primary_key {
@@ -1234,6 +1455,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         columns: &[C],
     ) -> Result<(), Error> {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let mut values = Vec::new();
@@ -1273,6 +1495,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         for row in rows {
             let mut map = Map::decode_row(&row)?;
             let primary_key = map.get(primary_key_name).cloned();
+            Self::decrypt_columns(&mut map)?;
             Self::after_decode(&mut map).await?;
             translate_enabled.then(|| Self::translate_model(&mut map));
             if let Some(key) = primary_key {
@@ -1322,6 +1545,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let other_table_name = query.format_table_name::<M>();
@@ -1361,6 +1585,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         let mut data = Self::lookup::<M, Map>(query, join_on).await?;
         let translate_enabled = query.translate_enabled();
         for model in data.iter_mut() {
+            Self::decrypt_columns(model)?;
             Self::after_decode(model).await?;
             translate_enabled.then(|| Self::translate_model(model));
         }
@@ -1370,6 +1595,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Checks whether there is a model selected by the query in the table.
     async fn exists(query: &Query) -> Result<bool, Error> {
         Self::before_query(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let filters = query.format_filters::<Self>();
@@ -1389,6 +1615,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     /// Counts the number of rows selected by the query in the table.
     async fn count(query: &Query) -> Result<u64, Error> {
         Self::before_count(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let filters = query.format_filters::<Self>();
@@ -1416,6 +1643,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
         T: DecodeRow<DatabaseRow, Error = Error>,
     {
         Self::before_count(query).await?;
+        Self::check_encrypted_filters(query.filters())?;
 
         let table_name = query.format_table_name::<Self>();
         let filters = query.format_filters::<Self>();
@@ -1550,6 +1778,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     ) -> Result<Vec<T>, Error> {
         let mut data = Self::query::<Map>(query, params).await?;
         for model in data.iter_mut() {
+            Self::decrypt_columns(model)?;
             Self::after_decode(model).await?;
         }
         serde_json::from_value(data.into()).map_err(Error::from)
@@ -1588,6 +1817,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
     ) -> Result<Option<T>, Error> {
         match Self::query_one::<Map>(query, params).await? {
             Some(mut data) => {
+                Self::decrypt_columns(&mut data)?;
                 Self::after_decode(&mut data).await?;
                 serde_json::from_value(data.into()).map_err(Error::from)
             }
@@ -1643,6 +1873,8 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
 
     /// Prepares the SQL to update a model selected by the primary key in the table.
     async fn prepare_update_by_id(mutation: &mut Mutation) -> Result<QueryContext, Error> {
+        Self::encrypt_columns(mutation.updates_mut())?;
+
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let table_name = Query::table_name_escaped::<Self>();
         let updates = mutation.format_updates::<Self>();
@@ -1800,6 +2032,7 @@ pub trait Schema: 'static + Send + Sync + ModelHooks {
             Self::after_query(&ctx).await?;
 
             let mut map = Map::decode_row(&row)?;
+            Self::decrypt_columns(&mut map)?;
             Self::after_decode(&mut map).await?;
             Self::try_from_map(map).map_err(|err| {
                 warn!(
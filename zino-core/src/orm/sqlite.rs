@@ -162,6 +162,7 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                         "$rlike" => "REGEXP",
                         "$is" => "IS",
                         "$size" => "json_array_length",
+                        "$overlaps" => "json_each",
                         _ => {
                             if cfg!(debug_assertions) && name.starts_with('$') {
                                 tracing::warn!("unsupported operator `{name}` for SQLite");
@@ -214,6 +215,30 @@ impl<'c> EncodeColumn<DatabaseDriver> for Column<'c> {
                             let condition = format!(r#"json_array_length({field}) = {length}"#);
                             conditions.push(condition);
                         }
+                    } else if operator == "json_each" {
+                        let values = match value {
+                            JsonValue::Array(values) => values
+                                .iter()
+                                .map(|v| match v {
+                                    JsonValue::String(v) => Query::escape_string(v),
+                                    _ => self.encode_value(Some(v)).into_owned(),
+                                })
+                                .collect::<Vec<_>>(),
+                            JsonValue::String(value) => crate::helper::parse_str_array(value, ',')
+                                .into_iter()
+                                .map(|v| Query::escape_string(v.trim()))
+                                .collect::<Vec<_>>(),
+                            _ => vec![self.encode_value(Some(value)).into_owned()],
+                        };
+                        if values.is_empty() {
+                            conditions.push("FALSE".to_owned());
+                        } else {
+                            let value = values.join(", ");
+                            let condition = format!(
+                                r#"EXISTS (SELECT 1 FROM json_each({field}) AS t WHERE t.value IN ({value}))"#
+                            );
+                            conditions.push(condition);
+                        }
                     } else {
                         let value = self.encode_value(Some(value));
                         let condition = format!(r#"{field} {operator} {value}"#);
@@ -2,8 +2,12 @@
 
 use crate::{
     application::http_client,
-    auth::{AccessKeyId, Authentication, ParseSecurityTokenError, SecurityToken, SessionId},
+    auth::{
+        AccessKeyId, ApiKeyManager, ApiKeyRecord, Authentication, ParseSecurityTokenError,
+        SecurityToken, SessionId,
+    },
     channel::{CloudEvent, Subscription},
+    crypto::Digest,
     datetime::DateTime,
     error::Error,
     extension::{HeaderMapExt, JsonObjectExt},
@@ -15,6 +19,7 @@ use crate::{
     validation::Validation,
     warn, JsonValue, Map, SharedString, Uuid,
 };
+use hmac::Hmac;
 use multer::Multipart;
 use serde::de::DeserializeOwned;
 use std::{borrow::Cow, net::IpAddr, str::FromStr, time::Instant};
@@ -23,9 +28,7 @@ use std::{borrow::Cow, net::IpAddr, str::FromStr, time::Instant};
 use cookie::{Cookie, SameSite};
 
 #[cfg(feature = "jwt")]
-use crate::auth::JwtClaims;
-#[cfg(feature = "jwt")]
-use jwt_simple::algorithms::MACLike;
+use crate::auth::{JwtClaims, JwtVerifier, RemoteJwkSet, SessionManager};
 
 #[cfg(any(feature = "cookie", feature = "jwt"))]
 use std::time::Duration;
@@ -249,8 +252,8 @@ pub trait RequestContext {
     ///
     /// # Note
     ///
-    /// Currently, we support the following values: `bytes` | `csv` | `form` | `json` | `multipart`
-    /// | `ndjson` | `text`.
+    /// Currently, we support the following values: `arrow` | `avro` | `bytes` | `cbor` | `csv`
+    /// | `form` | `json` | `msgpack` | `multipart` | `ndjson` | `text`.
     fn data_type(&self) -> Option<&str> {
         self.get_header("content-type")
             .map(|content_type| {
@@ -263,8 +266,54 @@ pub trait RequestContext {
             .map(helper::get_data_type)
     }
 
+    /// Negotiates the data type of the response by parsing the `accept` header.
+    /// The query parameter `format` takes precedence over the header if present.
+    /// It falls back to `json` if there is no acceptable data type.
+    ///
+    /// # Note
+    ///
+    /// Currently, we support the following values: `arrow` | `avro` | `cbor` | `csv` | `json`
+    /// | `msgpack` | `ndjson`.
+    fn negotiate_data_type(&self) -> &'static str {
+        let supported_data_types = helper::SERIALIZABLE_DATA_TYPES.as_slice();
+        let data_type = if let Some(format) = self.get_query("format") {
+            let format = if format == "jsonlines" {
+                "ndjson"
+            } else {
+                format
+            };
+            supported_data_types
+                .iter()
+                .find(|&&data_type| data_type == format)
+                .copied()
+        } else if let Some(accept) = self.get_header("accept") {
+            helper::select_data_type(accept, supported_data_types)
+        } else {
+            None
+        };
+        data_type.unwrap_or("json")
+    }
+
+    /// Returns `true` if the representation of the resource has not been modified
+    /// according to the conditional headers `if-none-match` and `if-modified-since`.
+    ///
+    /// The `if-none-match` header takes precedence over `if-modified-since` if present,
+    /// and the entity tags are compared with the weak comparison.
+    fn is_not_modified(&self, etag: &str, last_modified: Option<DateTime>) -> bool {
+        if let Some(if_none_match) = self.get_header("if-none-match") {
+            helper::match_etag(if_none_match, etag)
+        } else if let Some(last_modified) = last_modified {
+            self.get_header("if-modified-since")
+                .and_then(|s| DateTime::parse_utc_str(s).ok())
+                .is_some_and(|dt| last_modified.timestamp() <= dt.timestamp())
+        } else {
+            false
+        }
+    }
+
     /// Gets the route parameter by name.
     /// The name should not include `:`, `*`, `{` or `}`.
+    /// A wildcard parameter such as `*path` captures the rest of the path.
     ///
     /// # Note
     ///
@@ -273,12 +322,15 @@ pub trait RequestContext {
     /// if you need percent-decoding.
     fn get_param(&self, name: &str) -> Option<&str> {
         const CAPTURES: [char; 4] = [':', '*', '{', '}'];
-        if let Some(index) = self
-            .matched_route()
+        let matched_route = self.matched_route();
+        if let Some((index, segment)) = matched_route
             .split('/')
-            .position(|segment| segment.trim_matches(CAPTURES.as_slice()) == name)
+            .enumerate()
+            .find(|(_, segment)| segment.trim_matches(CAPTURES.as_slice()) == name)
         {
-            self.request_path().splitn(index + 2, '/').nth(index)
+            let is_wildcard = segment.trim_start_matches('{').starts_with('*');
+            let num_parts = if is_wildcard { index + 1 } else { index + 2 };
+            self.request_path().splitn(num_parts, '/').nth(index)
         } else {
             None
         }
@@ -375,12 +427,16 @@ pub trait RequestContext {
     ///
     /// Currently, we have built-in support for the following `content-type` header values:
     ///
+    /// - `application/avro`
+    /// - `application/cbor` (requires the `cbor` feature)
     /// - `application/json`
+    /// - `application/msgpack` (requires the `msgpack` feature)
     /// - `application/problem+json`
+    /// - `application/vnd.apache.arrow.stream` (requires the `connector-arrow` feature)
     /// - `application/x-www-form-urlencoded`
     async fn parse_body<T: DeserializeOwned>(&mut self) -> Result<T, Rejection> {
         let data_type = self.data_type().unwrap_or("form");
-        if data_type.contains('/') {
+        if !helper::is_deserializable(data_type) {
             let err = warn!(
                 "deserialization of the data type `{}` is unsupported",
                 data_type
@@ -389,18 +445,13 @@ pub trait RequestContext {
             return Err(rejection);
         }
 
-        let is_form = data_type == "form";
+        let data_type = data_type.to_owned();
         let bytes = self
             .read_body_bytes()
             .await
             .map_err(|err| Rejection::from_validation_entry("body", err).context(self))?;
-        if is_form {
-            serde_qs::from_bytes(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).context(self))
-        } else {
-            serde_json::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).context(self))
-        }
+        helper::deserialize_data(&data_type, &bytes)
+            .map_err(|err| Rejection::from_validation_entry("body", err).context(self))
     }
 
     /// Parses the request body as a multipart, which is commonly used with file uploads.
@@ -464,7 +515,7 @@ pub trait RequestContext {
             if let Some(access_key_id) = query.parse_string("access_key_id") {
                 authentication.set_access_key_id(access_key_id);
             } else {
-                validation.record("access_key_id", "should be nonempty");
+                validation.record_with_code("access_key_id", "required", "should be nonempty");
             }
             if let Some(Ok(secs)) = query.parse_i64("expires") {
                 if DateTime::now().timestamp() <= secs {
@@ -522,11 +573,17 @@ pub trait RequestContext {
     }
 
     /// Attempts to construct an instance of `AccessKeyId` from an HTTP request.
-    /// The value is extracted from the query parameter `access_key_id`
-    /// or the `authorization` header.
+    /// The value is extracted from the query parameter `access_key_id`,
+    /// the `x-api-key` header or the `authorization` header.
     fn parse_access_key_id(&self) -> Result<AccessKeyId, Rejection> {
         if let Some(access_key_id) = self.get_query("access_key_id") {
             Ok(access_key_id.into())
+        } else if let Some(api_key) = self.get_header("x-api-key") {
+            let access_key_id = api_key
+                .split_once(':')
+                .map(|(access_key_id, _)| access_key_id)
+                .unwrap_or(api_key);
+            Ok(access_key_id.into())
         } else {
             let mut validation = Validation::new();
             if let Some(authorization) = self.get_header("authorization") {
@@ -547,6 +604,65 @@ pub trait RequestContext {
         }
     }
 
+    /// Verifies the API key of an HTTP request for the action on the resource.
+    ///
+    /// The API key is managed by the shared [`ApiKeyManager`]. The request is
+    /// authenticated by the `x-api-key` header of the form `{access_key_id}:{secret}`,
+    /// or a signature which is validated by [`Authentication`]. The key should be active,
+    /// have a scope for the action on the resource, and allow the client IP.
+    async fn verify_api_key(
+        &self,
+        resource: &str,
+        action: &str,
+    ) -> Result<ApiKeyRecord, Rejection> {
+        let access_key_id = self.parse_access_key_id()?;
+        let Some(manager) = ApiKeyManager::shared() else {
+            let message = "503 Service Unavailable: the API key manager is not configured";
+            return Err(Rejection::with_message(message).context(self));
+        };
+        let api_key = manager
+            .find_api_key(access_key_id.as_str())
+            .await
+            .map_err(|err| Rejection::from_error(err).context(self))?;
+        let Some(mut api_key) = api_key.filter(|api_key| api_key.is_active()) else {
+            let message = "401 Unauthorized: the API key is invalid or inactive";
+            return Err(Rejection::with_message(message).context(self));
+        };
+
+        let secret = self
+            .get_header("x-api-key")
+            .and_then(|api_key| api_key.split_once(':'))
+            .map(|(_, secret)| secret);
+        if let Some(secret) = secret {
+            if !api_key.verify_secret(secret) {
+                let message = "401 Unauthorized: invalid secret access key";
+                return Err(Rejection::with_message(message).context(self));
+            }
+        } else {
+            let authentication = self.parse_authentication()?;
+            let signing_key = api_key.signing_key();
+            let validation = authentication.validate_with::<Hmac<Digest>>(&signing_key);
+            if !validation.is_success() {
+                return Err(Rejection::bad_request(validation).context(self));
+            }
+        }
+
+        let client_ip = self.client_ip();
+        if !api_key.allows_ip(client_ip) {
+            let message = "403 Forbidden: the client IP is not allowed";
+            return Err(Rejection::with_message(message).context(self));
+        }
+        if !api_key.allows(resource, action) {
+            let message =
+                format!("403 Forbidden: the API key has no scope for `{resource}:{action}`");
+            return Err(Rejection::with_message(message).context(self));
+        }
+        if let Err(err) = manager.touch_api_key(&mut api_key, client_ip).await {
+            tracing::warn!("fail to record the usage of the API key: {err}");
+        }
+        Ok(api_key)
+    }
+
     /// Attempts to construct an instance of `SecurityToken` from an HTTP request.
     /// The value is extracted from the `x-security-token` header.
     fn parse_security_token(&self, key: &[u8]) -> Result<SecurityToken, Rejection> {
@@ -582,11 +698,22 @@ pub trait RequestContext {
                 }
             }
         } else {
-            validation.record("security_token", "should be nonempty");
+            validation.record_with_code("security_token", "required", "should be nonempty");
         }
         Err(Rejection::bad_request(validation).context(self))
     }
 
+    /// Verifies the zino-signed presigned URL for the object at the path
+    /// in the storage accessor with the given name.
+    /// See [`PresignedUrl`](crate::accessor::PresignedUrl) for more details.
+    #[cfg(feature = "accessor")]
+    fn verify_presigned_url(&self, name: &str, path: &str) -> Result<SecurityToken, Rejection> {
+        let access_key_id = self.parse_access_key_id()?;
+        let method = self.request_method().as_ref();
+        let secret_key = crate::accessor::fallback_secret_key(&access_key_id, method, name, path);
+        self.parse_security_token(secret_key.as_ref())
+    }
+
     /// Attempts to construct an instance of `SessionId` from an HTTP request.
     /// The value is extracted from the `x-session-id` or `session-id` header.
     fn parse_session_id(&self) -> Result<SessionId, Rejection> {
@@ -609,41 +736,43 @@ pub trait RequestContext {
     /// Attempts to construct an instance of `JwtClaims` from an HTTP request.
     /// The value is extracted from the query parameter `access_token` or
     /// the `authorization` header.
+    ///
+    /// The key can be an HMAC key, a [`JwtVerifyingKey`](crate::auth::JwtVerifyingKey),
+    /// a [`JwkSet`](crate::auth::JwkSet) or a [`RemoteJwkSet`].
+    /// The token is rejected if its `jti` has been revoked by the shared [`SessionManager`].
+    /// Since the first-party tokens have no audience, the tokens issued to
+    /// the OAuth clients by the [`AuthorizationServer`](crate::auth::AuthorizationServer)
+    /// are also rejected.
     #[cfg(feature = "jwt")]
     fn parse_jwt_claims<T, K>(&self, key: &K) -> Result<JwtClaims<T>, Rejection>
     where
         T: Default + serde::Serialize + DeserializeOwned,
-        K: MACLike,
+        K: JwtVerifier,
     {
-        let (param, mut token) = match self.get_query("access_token") {
-            Some(access_token) => ("access_token", access_token),
-            None => ("authorization", ""),
-        };
-        if let Some(authorization) = self.get_header("authorization") {
-            token = authorization
-                .strip_prefix("Bearer ")
-                .unwrap_or(authorization);
-        }
-        if token.is_empty() {
-            let mut validation = Validation::new();
-            validation.record(param, "JWT token is absent");
-            return Err(Rejection::bad_request(validation).context(self));
+        let claims = verify_jwt_claims(self, key)?;
+        if claims.0.audiences.is_some() {
+            let message = "401 Unauthorized: the token is issued to a third-party client";
+            return Err(Rejection::with_message(message).context(self));
         }
+        Ok(claims)
+    }
 
-        let mut options = crate::auth::default_verification_options();
-        options.reject_before = self
-            .get_query("timestamp")
-            .and_then(|s| s.parse().ok())
-            .map(|i| Duration::from_secs(i).into());
-        options.required_nonce = self.get_query("nonce").map(|s| s.to_owned());
-
-        match key.verify_token(token, Some(options)) {
-            Ok(claims) => Ok(JwtClaims(claims)),
-            Err(err) => {
-                let message = format!("401 Unauthorized: {err}");
-                Err(Rejection::with_message(message).context(self))
-            }
-        }
+    /// Attempts to construct an instance of `JwtClaims` from an HTTP request
+    /// with the remote JWK set. The JWKS document will be fetched if the cached
+    /// key set has expired or the `kid` of the token is unknown.
+    #[cfg(feature = "jwt")]
+    async fn parse_remote_jwt_claims<T>(
+        &self,
+        jwks: &RemoteJwkSet,
+    ) -> Result<JwtClaims<T>, Rejection>
+    where
+        T: Default + serde::Serialize + DeserializeOwned,
+    {
+        let token = extract_jwt_token(self)?;
+        jwks.prepare(token)
+            .await
+            .map_err(|err| Rejection::from_error(err).context(self))?;
+        verify_jwt_claims(self, jwks)
     }
 
     /// Returns a `Response` or `Rejection` from a model query validation.
@@ -668,14 +797,31 @@ pub trait RequestContext {
 
     /// Returns a `Response` or `Rejection` from a model validation.
     /// The data is extracted from [`parse_body()`](RequestContext::parse_body).
+    #[inline]
     async fn model_validation<M, S>(&mut self, model: &mut M) -> Result<Response<S>, Rejection>
     where
         Self: Sized,
         M: ModelHooks,
         S: ResponseCode,
+    {
+        self.model_validation_with(model, |_data| Ok(())).await
+    }
+
+    /// Returns a `Response` or `Rejection` from a model validation,
+    /// where the extracted data is checked by the function before validating.
+    async fn model_validation_with<M, S, F>(
+        &mut self,
+        model: &mut M,
+        check: F,
+    ) -> Result<Response<S>, Rejection>
+    where
+        Self: Sized,
+        M: ModelHooks,
+        S: ResponseCode,
+        F: FnOnce(&Map) -> Result<(), Error>,
     {
         let data_type = self.data_type().unwrap_or("form");
-        if data_type.contains('/') {
+        if !helper::is_deserializable(data_type) {
             let err = warn!(
                 "deserialization of the data type `{}` is unsupported",
                 data_type
@@ -683,64 +829,40 @@ pub trait RequestContext {
             let rejection = Rejection::from_validation_entry("data_type", err).context(self);
             return Err(rejection);
         }
+
+        let data_type = data_type.to_owned();
         M::before_extract()
             .await
             .map_err(|err| Rejection::from_error(err).context(self))?;
 
-        let is_form = data_type == "form";
         let bytes = self
             .read_body_bytes()
             .await
             .map_err(|err| Rejection::from_validation_entry("body", err).context(self))?;
         let extension = self.get_data::<M::Extension>();
-        if is_form {
-            let mut data = serde_qs::from_bytes(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).context(self))?;
-            match M::before_validation(&mut data, extension.as_ref()).await {
-                Ok(()) => {
-                    let validation = model.read_map(&data);
+        let mut data = helper::deserialize_data(&data_type, &bytes)
+            .map_err(|err| Rejection::from_validation_entry("body", err).context(self))?;
+        check(&data).map_err(|err| Rejection::from_error(err).context(self))?;
+        match M::before_validation(&mut data, extension.as_ref()).await {
+            Ok(()) => {
+                let validation = model.read_map(&data);
+                model
+                    .after_validation(&mut data)
+                    .await
+                    .map_err(|err| Rejection::from_error(err).context(self))?;
+                if let Some(extension) = extension {
                     model
-                        .after_validation(&mut data)
+                        .after_extract(extension)
                         .await
                         .map_err(|err| Rejection::from_error(err).context(self))?;
-                    if let Some(extension) = extension {
-                        model
-                            .after_extract(extension)
-                            .await
-                            .map_err(|err| Rejection::from_error(err).context(self))?;
-                    }
-                    if validation.is_success() {
-                        Ok(Response::with_context(S::OK, self))
-                    } else {
-                        Err(Rejection::bad_request(validation).context(self))
-                    }
                 }
-                Err(err) => Err(Rejection::from_error(err).context(self)),
-            }
-        } else {
-            let mut data = serde_json::from_slice(&bytes)
-                .map_err(|err| Rejection::from_validation_entry("body", err).context(self))?;
-            match M::before_validation(&mut data, extension.as_ref()).await {
-                Ok(()) => {
-                    let validation = model.read_map(&data);
-                    model
-                        .after_validation(&mut data)
-                        .await
-                        .map_err(|err| Rejection::from_error(err).context(self))?;
-                    if let Some(extension) = extension {
-                        model
-                            .after_extract(extension)
-                            .await
-                            .map_err(|err| Rejection::from_error(err).context(self))?;
-                    }
-                    if validation.is_success() {
-                        Ok(Response::with_context(S::OK, self))
-                    } else {
-                        Err(Rejection::bad_request(validation).context(self))
-                    }
+                if validation.is_success() {
+                    Ok(Response::with_context(S::OK, self))
+                } else {
+                    Err(Rejection::bad_request(validation).context(self))
                 }
-                Err(err) => Err(Rejection::from_error(err).context(self)),
             }
+            Err(err) => Err(Rejection::from_error(err).context(self)),
         }
     }
 
@@ -806,3 +928,57 @@ pub trait RequestContext {
         event
     }
 }
+
+/// Extracts the JWT token from the query parameter `access_token`
+/// or the `authorization` header.
+#[cfg(feature = "jwt")]
+fn extract_jwt_token<R: RequestContext + ?Sized>(req: &R) -> Result<&str, Rejection> {
+    let (param, mut token) = match req.get_query("access_token") {
+        Some(access_token) => ("access_token", access_token),
+        None => ("authorization", ""),
+    };
+    if let Some(authorization) = req.get_header("authorization") {
+        token = authorization
+            .strip_prefix("Bearer ")
+            .unwrap_or(authorization);
+    }
+    if token.is_empty() {
+        let mut validation = Validation::new();
+        validation.record(param, "JWT token is absent");
+        return Err(Rejection::bad_request(validation).context(req));
+    }
+    Ok(token)
+}
+
+/// Verifies the JWT token of an HTTP request with the key.
+#[cfg(feature = "jwt")]
+fn verify_jwt_claims<R, T, K>(req: &R, key: &K) -> Result<JwtClaims<T>, Rejection>
+where
+    R: RequestContext + ?Sized,
+    T: Default + serde::Serialize + DeserializeOwned,
+    K: JwtVerifier,
+{
+    let token = extract_jwt_token(req)?;
+    let mut options = crate::auth::default_verification_options();
+    options.reject_before = req
+        .get_query("timestamp")
+        .and_then(|s| s.parse().ok())
+        .map(|i| Duration::from_secs(i).into());
+    options.required_nonce = req.get_query("nonce").map(|s| s.to_owned());
+
+    match key.verify_jwt(token, options) {
+        Ok(claims) => {
+            if let Some(jwt_id) = claims.jwt_id.as_deref() {
+                if SessionManager::shared().is_some_and(|manager| manager.is_revoked(jwt_id)) {
+                    let message = "401 Unauthorized: the token has been revoked";
+                    return Err(Rejection::with_message(message).context(req));
+                }
+            }
+            Ok(JwtClaims(claims))
+        }
+        Err(err) => {
+            let message = format!("401 Unauthorized: {err}");
+            Err(Rejection::with_message(message).context(req))
+        }
+    }
+}
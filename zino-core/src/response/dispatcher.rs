@@ -0,0 +1,1193 @@
+use crate::{
+    bail,
+    channel::{CloudEvent, EventBus, EventRecord, ReplayPosition, Subscription},
+    crypto::Digest,
+    datetime::DateTime,
+    encoding::hex,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    schedule::AsyncScheduler,
+    warn, BoxFuture, JsonValue, LazyLock, Map, Uuid,
+};
+use futures::{channel::oneshot, future};
+use hmac::{Hmac, Mac};
+use http::header;
+use parking_lot::RwLock;
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect::Policy,
+    Client,
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    thread,
+    time::Duration,
+};
+use toml::Table;
+use url::{Host, Url};
+
+/// Status of a webhook delivery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DeliveryStatus {
+    /// The delivery is waiting to be sent or retried.
+    Pending,
+    /// The endpoint has responded with a success status.
+    Succeeded,
+    /// The delivery has exhausted the attempts or the endpoint has been disabled.
+    Dead,
+}
+
+impl DeliveryStatus {
+    /// Returns the status as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Succeeded => "Succeeded",
+            Self::Dead => "Dead",
+        }
+    }
+}
+
+impl fmt::Display for DeliveryStatus {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for DeliveryStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(Self::Pending),
+            "Succeeded" => Ok(Self::Succeeded),
+            "Dead" => Ok(Self::Dead),
+            _ => bail!("invalid webhook delivery status `{}`", s),
+        }
+    }
+}
+
+/// A subscriber which receives the cloud events at the webhook endpoint.
+#[derive(Clone)]
+pub struct WebhookSubscriber {
+    /// Subscriber ID.
+    id: Uuid,
+    /// Endpoint URL.
+    url: String,
+    /// Secret for signing the payload.
+    secret: String,
+    /// Subscription to filter the events.
+    subscription: Subscription,
+    /// Flag to indicate whether the endpoint is enabled.
+    enabled: bool,
+    /// Number of consecutive failures.
+    consecutive_failures: u32,
+}
+
+impl WebhookSubscriber {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(url: impl ToString, secret: impl ToString) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            subscription: Subscription::default(),
+            enabled: true,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Attempts to construct an instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(id) = map.get_uuid("id") else {
+            bail!("the `id` field should be a UUID");
+        };
+        let Some(url) = map.get_str("url") else {
+            bail!("the `url` field should be specified");
+        };
+        let subscription = match map.get("subscription") {
+            Some(value) => Subscription::deserialize(value)?,
+            None => Subscription::default(),
+        };
+        Ok(Self {
+            id,
+            url: url.to_owned(),
+            secret: map.get_str("secret").unwrap_or_default().to_owned(),
+            subscription,
+            enabled: map.get_bool("enabled").unwrap_or(true),
+            consecutive_failures: map.get_u32("consecutive_failures").unwrap_or_default(),
+        })
+    }
+
+    /// Sets the subscription to filter the events.
+    #[inline]
+    pub fn with_subscription(mut self, subscription: Subscription) -> Self {
+        self.subscription = subscription;
+        self
+    }
+
+    /// Returns the subscriber ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the endpoint URL.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the secret.
+    #[inline]
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    /// Returns a reference to the subscription.
+    #[inline]
+    pub fn subscription(&self) -> &Subscription {
+        &self.subscription
+    }
+
+    /// Returns `true` if the endpoint is enabled.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns the number of consecutive failures.
+    #[inline]
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Enables the endpoint and resets the failures.
+    #[inline]
+    pub fn enable(&mut self) {
+        self.enabled = true;
+        self.consecutive_failures = 0;
+    }
+
+    /// Records a failure and disables the endpoint if the failures reach the threshold.
+    /// It returns `true` if the endpoint has been disabled by the failure.
+    fn record_failure(&mut self, threshold: u32) -> bool {
+        self.consecutive_failures += 1;
+        if self.enabled && self.consecutive_failures >= threshold {
+            self.enabled = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl fmt::Debug for WebhookSubscriber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookSubscriber")
+            .field("id", &self.id)
+            .field("url", &self.url)
+            .field("subscription", &self.subscription)
+            .field("enabled", &self.enabled)
+            .field("consecutive_failures", &self.consecutive_failures)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A delivery of the cloud event to a webhook subscriber.
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    /// Delivery ID.
+    id: Uuid,
+    /// Subscriber ID.
+    subscriber_id: Uuid,
+    /// Event type.
+    event_type: String,
+    /// Event payload.
+    payload: Map,
+    /// Delivery status.
+    status: DeliveryStatus,
+    /// Number of attempts.
+    attempts: u32,
+    /// Time of the next attempt.
+    next_attempt_at: DateTime,
+    /// Status code of the last response.
+    response_status: Option<u16>,
+    /// Excerpt of the last response body.
+    response_excerpt: String,
+    /// Last error message.
+    last_error: String,
+    /// Creation time.
+    created_at: DateTime,
+    /// Update time.
+    updated_at: DateTime,
+}
+
+impl WebhookDelivery {
+    /// Creates a new instance.
+    pub fn new(subscriber_id: Uuid, event: &CloudEvent) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: Uuid::now_v7(),
+            subscriber_id,
+            event_type: event.event_type().to_owned(),
+            payload: event.clone().into_map(),
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            response_status: None,
+            response_excerpt: String::new(),
+            last_error: String::new(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Attempts to construct an instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(id) = map.get_uuid("id") else {
+            bail!("the `id` field should be a UUID");
+        };
+        let Some(subscriber_id) = map.get_uuid("subscriber_id") else {
+            bail!("the `subscriber_id` field should be a UUID");
+        };
+        let parse_datetime = |key| -> Result<DateTime, Error> {
+            match map.get_str(key) {
+                Some(value) => Ok(value.parse()?),
+                None => bail!("the `{}` field should be specified", key),
+            }
+        };
+        Ok(Self {
+            id,
+            subscriber_id,
+            event_type: map.get_str("event_type").unwrap_or_default().to_owned(),
+            payload: map.get_object("payload").cloned().unwrap_or_default(),
+            status: map.get_str("status").unwrap_or("Pending").parse()?,
+            attempts: map.get_u32("attempts").unwrap_or_default(),
+            next_attempt_at: parse_datetime("next_attempt_at")?,
+            response_status: map
+                .get_u32("response_status")
+                .and_then(|status| u16::try_from(status).ok()),
+            response_excerpt: map
+                .get_str("response_excerpt")
+                .unwrap_or_default()
+                .to_owned(),
+            last_error: map.get_str("last_error").unwrap_or_default().to_owned(),
+            created_at: parse_datetime("created_at")?,
+            updated_at: parse_datetime("updated_at")?,
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("subscriber_id", self.subscriber_id.to_string());
+        map.upsert("event_type", self.event_type);
+        map.upsert("payload", self.payload);
+        map.upsert("status", self.status.as_str());
+        map.upsert("attempts", self.attempts);
+        map.upsert("next_attempt_at", self.next_attempt_at.to_string());
+        map.upsert("response_status", self.response_status);
+        map.upsert("response_excerpt", self.response_excerpt);
+        map.upsert("last_error", self.last_error);
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        map
+    }
+
+    /// Claims the delivery for the timeout, which is at least one minute,
+    /// so that it will not be sent by another worker. It is intended to be called
+    /// by the webhook stores.
+    pub fn claim(&mut self, timeout: Duration) {
+        let now = DateTime::now();
+        self.next_attempt_at = now + timeout.max(Duration::from_secs(60));
+        self.updated_at = now;
+    }
+
+    /// Resets the delivery to be sent again immediately.
+    pub fn reset(&mut self) {
+        let now = DateTime::now();
+        self.status = DeliveryStatus::Pending;
+        self.next_attempt_at = now;
+        self.updated_at = now;
+    }
+
+    /// Returns the delivery ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the subscriber ID.
+    #[inline]
+    pub fn subscriber_id(&self) -> Uuid {
+        self.subscriber_id
+    }
+
+    /// Returns the event type.
+    #[inline]
+    pub fn event_type(&self) -> &str {
+        &self.event_type
+    }
+
+    /// Returns a reference to the event payload.
+    #[inline]
+    pub fn payload(&self) -> &Map {
+        &self.payload
+    }
+
+    /// Returns the delivery status.
+    #[inline]
+    pub fn status(&self) -> DeliveryStatus {
+        self.status
+    }
+
+    /// Returns the number of attempts.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the time of the next attempt.
+    #[inline]
+    pub fn next_attempt_at(&self) -> DateTime {
+        self.next_attempt_at
+    }
+
+    /// Returns the status code of the last response.
+    #[inline]
+    pub fn response_status(&self) -> Option<u16> {
+        self.response_status
+    }
+
+    /// Returns the excerpt of the last response body.
+    #[inline]
+    pub fn response_excerpt(&self) -> &str {
+        &self.response_excerpt
+    }
+
+    /// Returns the last error message.
+    #[inline]
+    pub fn last_error(&self) -> &str {
+        &self.last_error
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    /// Returns the update time.
+    #[inline]
+    pub fn updated_at(&self) -> DateTime {
+        self.updated_at
+    }
+
+    /// Returns `true` if the delivery is due to be sent.
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        self.status == DeliveryStatus::Pending && self.next_attempt_at <= DateTime::now()
+    }
+}
+
+/// An interface for storing the webhook subscribers and deliveries.
+pub trait WebhookStore: Send + Sync {
+    /// Lists the enabled subscribers.
+    fn list_subscribers(&self) -> BoxFuture<'_, Result<Vec<WebhookSubscriber>, Error>>;
+
+    /// Gets the subscriber by ID.
+    fn get_subscriber(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookSubscriber>, Error>>;
+
+    /// Inserts or updates the subscriber.
+    fn save_subscriber<'a>(
+        &'a self,
+        subscriber: &'a WebhookSubscriber,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Increments the consecutive failures of the subscriber atomically,
+    /// and disables the subscriber if the failures reach the threshold.
+    /// It returns `true` if the subscriber has been disabled by the failure.
+    fn record_failure(&self, id: Uuid, threshold: u32) -> BoxFuture<'_, Result<bool, Error>>;
+
+    /// Resets the consecutive failures of the subscriber.
+    fn reset_failures(&self, id: Uuid) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// Inserts the delivery.
+    fn push_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Claims at most `limit` deliveries which are due.
+    fn claim_deliveries(
+        &self,
+        limit: usize,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WebhookDelivery>, Error>>;
+
+    /// Gets the delivery by ID.
+    fn get_delivery(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookDelivery>, Error>>;
+
+    /// Updates the delivery.
+    fn save_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// An in-memory webhook store.
+#[derive(Debug, Default)]
+pub struct MemoryWebhookStore {
+    /// Subscribers.
+    subscribers: RwLock<HashMap<Uuid, WebhookSubscriber>>,
+    /// Deliveries.
+    deliveries: RwLock<HashMap<Uuid, WebhookDelivery>>,
+}
+
+impl MemoryWebhookStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WebhookStore for MemoryWebhookStore {
+    fn list_subscribers(&self) -> BoxFuture<'_, Result<Vec<WebhookSubscriber>, Error>> {
+        Box::pin(async move {
+            let subscribers = self
+                .subscribers
+                .read()
+                .values()
+                .filter(|subscriber| subscriber.is_enabled())
+                .cloned()
+                .collect();
+            Ok(subscribers)
+        })
+    }
+
+    fn get_subscriber(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookSubscriber>, Error>> {
+        Box::pin(async move { Ok(self.subscribers.read().get(&id).cloned()) })
+    }
+
+    fn save_subscriber<'a>(
+        &'a self,
+        subscriber: &'a WebhookSubscriber,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.subscribers
+                .write()
+                .insert(subscriber.id(), subscriber.clone());
+            Ok(())
+        })
+    }
+
+    fn record_failure(&self, id: Uuid, threshold: u32) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let disabled = self
+                .subscribers
+                .write()
+                .get_mut(&id)
+                .is_some_and(|subscriber| subscriber.record_failure(threshold));
+            Ok(disabled)
+        })
+    }
+
+    fn reset_failures(&self, id: Uuid) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(subscriber) = self.subscribers.write().get_mut(&id) {
+                subscriber.consecutive_failures = 0;
+            }
+            Ok(())
+        })
+    }
+
+    fn push_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.deliveries
+                .write()
+                .insert(delivery.id(), delivery.clone());
+            Ok(())
+        })
+    }
+
+    fn claim_deliveries(
+        &self,
+        limit: usize,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WebhookDelivery>, Error>> {
+        Box::pin(async move {
+            let mut deliveries = self.deliveries.write();
+            let mut due_deliveries = deliveries
+                .values_mut()
+                .filter(|delivery| delivery.is_due())
+                .collect::<Vec<_>>();
+            due_deliveries.sort_by_key(|delivery| delivery.next_attempt_at());
+            let claimed_deliveries = due_deliveries
+                .into_iter()
+                .take(limit)
+                .map(|delivery| {
+                    delivery.claim(timeout);
+                    delivery.clone()
+                })
+                .collect();
+            Ok(claimed_deliveries)
+        })
+    }
+
+    fn get_delivery(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookDelivery>, Error>> {
+        Box::pin(async move { Ok(self.deliveries.read().get(&id).cloned()) })
+    }
+
+    fn save_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.deliveries
+                .write()
+                .insert(delivery.id(), delivery.clone());
+            Ok(())
+        })
+    }
+}
+
+/// A dispatcher which delivers the cloud events to the webhook subscribers.
+///
+/// Each request is signed with the HMAC of `{id}.{timestamp}.{body}` using the secret
+/// of the subscriber, which is sent in the `webhook-signature` header as `v1={hex}`
+/// along with the `webhook-id` and `webhook-timestamp` headers.
+/// Failed deliveries are retried with exponential backoff, and an endpoint is disabled
+/// after the number of consecutive failures reaches the threshold.
+///
+/// The requests are only sent to the URLs accepted by [`check_webhook_url()`],
+/// and the redirects are not followed. The domain names are resolved by the dispatcher,
+/// and the requests are rejected unless all the addresses are globally reachable.
+///
+/// # Examples
+///
+/// ```toml
+/// [webhook-dispatcher]
+/// batch-size = 100
+/// poll-interval = "1s"
+/// timeout = "10s"
+/// max-attempts = 8
+/// backoff-base = "10s"
+/// backoff-max = "1h"
+/// failure-threshold = 20
+/// ```
+pub struct WebhookDispatcher {
+    /// Webhook store.
+    store: Box<dyn WebhookStore>,
+    /// Maximum number of deliveries sent in a tick.
+    batch_size: usize,
+    /// Interval between polling.
+    poll_interval: Duration,
+    /// Timeout of a request.
+    timeout: Duration,
+    /// Maximum number of attempts.
+    max_attempts: u32,
+    /// Base delay of the exponential backoff.
+    backoff_base: Duration,
+    /// Maximum delay of the exponential backoff.
+    backoff_max: Duration,
+    /// Number of consecutive failures to disable an endpoint.
+    failure_threshold: u32,
+}
+
+impl WebhookDispatcher {
+    /// Creates a new instance.
+    pub fn new(store: impl WebhookStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+            max_attempts: 8,
+            backoff_base: Duration::from_secs(10),
+            backoff_max: Duration::from_secs(3600),
+            failure_threshold: 20,
+        }
+    }
+
+    /// Creates a new instance with the configuration.
+    pub fn with_config(store: impl WebhookStore + 'static, config: &Table) -> Self {
+        let mut dispatcher = Self::new(store);
+        if let Some(batch_size) = config.get_usize("batch-size") {
+            dispatcher.batch_size = batch_size.max(1);
+        }
+        if let Some(poll_interval) = config.get_duration("poll-interval") {
+            dispatcher.poll_interval = poll_interval;
+        }
+        if let Some(timeout) = config.get_duration("timeout") {
+            dispatcher.timeout = timeout;
+        }
+        if let Some(max_attempts) = config.get_u32("max-attempts") {
+            dispatcher.max_attempts = max_attempts.max(1);
+        }
+        if let Some(backoff_base) = config.get_duration("backoff-base") {
+            dispatcher.backoff_base = backoff_base;
+        }
+        if let Some(backoff_max) = config.get_duration("backoff-max") {
+            dispatcher.backoff_max = backoff_max;
+        }
+        if let Some(failure_threshold) = config.get_u32("failure-threshold") {
+            dispatcher.failure_threshold = failure_threshold.max(1);
+        }
+        dispatcher
+    }
+
+    /// Registers the shared webhook dispatcher.
+    ///
+    /// It returns `false` if the shared webhook dispatcher has been registered.
+    #[inline]
+    pub fn register(dispatcher: Self) -> bool {
+        SHARED_WEBHOOK_DISPATCHER.set(dispatcher).is_ok()
+    }
+
+    /// Returns a reference to the shared webhook dispatcher if it has been registered.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_WEBHOOK_DISPATCHER.get()
+    }
+
+    /// Returns a reference to the webhook store.
+    #[inline]
+    pub fn store(&self) -> &dyn WebhookStore {
+        self.store.as_ref()
+    }
+
+    /// Creates the deliveries of the event for the matched subscribers,
+    /// and returns the number of deliveries.
+    pub async fn dispatch(&self, event: &CloudEvent) -> Result<usize, Error> {
+        let mut num_deliveries = 0;
+        for subscriber in self.store.list_subscribers().await? {
+            if subscriber.is_enabled() && subscriber.subscription().matches(event) {
+                let delivery = WebhookDelivery::new(subscriber.id(), event);
+                self.store.push_delivery(&delivery).await?;
+                num_deliveries += 1;
+            }
+        }
+        Ok(num_deliveries)
+    }
+
+    /// Schedules the delivery to be sent again immediately.
+    /// The endpoint will be enabled if it has been disabled.
+    pub async fn redeliver(&self, delivery_id: Uuid) -> Result<(), Error> {
+        let Some(mut delivery) = self.store.get_delivery(delivery_id).await? else {
+            bail!(
+                "404 Not Found: the webhook delivery `{}` does not exist",
+                delivery_id
+            );
+        };
+        if let Some(mut subscriber) = self.store.get_subscriber(delivery.subscriber_id).await? {
+            if !subscriber.is_enabled() {
+                subscriber.enable();
+                self.store.save_subscriber(&subscriber).await?;
+            }
+        }
+        delivery.reset();
+        self.store.save_delivery(&delivery).await
+    }
+
+    /// Sends a batch of due deliveries.
+    pub async fn tick(&self) {
+        match self
+            .store
+            .claim_deliveries(self.batch_size, self.timeout)
+            .await
+        {
+            Ok(deliveries) => {
+                let tasks = deliveries
+                    .into_iter()
+                    .map(|delivery| self.deliver(delivery));
+                future::join_all(tasks).await;
+            }
+            Err(err) => {
+                tracing::error!("fail to claim the webhook deliveries: {}", err.message());
+            }
+        }
+    }
+
+    /// Sends the delivery and saves the result.
+    async fn deliver(&self, mut delivery: WebhookDelivery) {
+        let subscriber_id = delivery.subscriber_id;
+        let subscriber = match self.store.get_subscriber(subscriber_id).await {
+            Ok(Some(subscriber)) if subscriber.is_enabled() => subscriber,
+            Ok(_) => {
+                delivery.status = DeliveryStatus::Dead;
+                delivery.last_error = "the webhook endpoint has been disabled".to_owned();
+                delivery.updated_at = DateTime::now();
+                self.save_delivery(&delivery).await;
+                return;
+            }
+            Err(err) => {
+                tracing::error!("fail to get the webhook subscriber: {}", err.message());
+                return;
+            }
+        };
+
+        delivery.attempts += 1;
+        let result = self.send(&subscriber, &mut delivery).await;
+        let now = DateTime::now();
+        delivery.updated_at = now;
+        match result {
+            Ok(()) => {
+                delivery.status = DeliveryStatus::Succeeded;
+                delivery.last_error.clear();
+                if subscriber.consecutive_failures > 0 {
+                    if let Err(err) = self.store.reset_failures(subscriber_id).await {
+                        tracing::error!(
+                            "fail to reset the failures of the webhook subscriber: {}",
+                            err.message()
+                        );
+                    }
+                }
+            }
+            Err(err) => {
+                delivery.last_error = err.message().to_owned();
+                if delivery.attempts >= self.max_attempts {
+                    delivery.status = DeliveryStatus::Dead;
+                } else {
+                    delivery.next_attempt_at = now + self.backoff(delivery.attempts);
+                }
+                match self
+                    .store
+                    .record_failure(subscriber_id, self.failure_threshold)
+                    .await
+                {
+                    Ok(true) => tracing::warn!(
+                        subscriber_id = subscriber_id.to_string(),
+                        url = subscriber.url(),
+                        "the webhook endpoint has been disabled after repeated failures"
+                    ),
+                    Ok(false) => (),
+                    Err(err) => tracing::error!(
+                        "fail to record the failure of the webhook subscriber: {}",
+                        err.message()
+                    ),
+                }
+            }
+        }
+        self.save_delivery(&delivery).await;
+    }
+
+    /// Sends a signed request to the endpoint, and records the response.
+    async fn send(
+        &self,
+        subscriber: &WebhookSubscriber,
+        delivery: &mut WebhookDelivery,
+    ) -> Result<(), Error> {
+        check_webhook_url(subscriber.url())?;
+
+        let body = JsonValue::from(delivery.payload.clone()).to_string();
+        let message_id = delivery.id.to_string();
+        let timestamp = DateTime::now().timestamp();
+        let signature = sign_payload(subscriber.secret(), &message_id, timestamp, &body);
+        let mut response = WEBHOOK_HTTP_CLIENT
+            .post(subscriber.url())
+            .timeout(self.timeout)
+            .header(header::CONTENT_TYPE, "application/cloudevents+json")
+            .header("webhook-id", message_id)
+            .header("webhook-timestamp", timestamp.to_string())
+            .header("webhook-signature", format!("v1={signature}"))
+            .body(body)
+            .send()
+            .await?;
+
+        // Reads a limited prefix of the response body.
+        let status = response.status();
+        let mut bytes = Vec::new();
+        while bytes.len() < RESPONSE_EXCERPT_LEN * 4 {
+            match response.chunk().await {
+                Ok(Some(chunk)) => bytes.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+        bytes.truncate(RESPONSE_EXCERPT_LEN * 4);
+        delivery.response_status = Some(status.as_u16());
+        delivery.response_excerpt = String::from_utf8_lossy(&bytes)
+            .chars()
+            .take(RESPONSE_EXCERPT_LEN)
+            .collect();
+        if !status.is_success() {
+            bail!(
+                "the webhook endpoint responded with the status `{}`",
+                status
+            );
+        }
+        Ok(())
+    }
+
+    /// Saves the delivery and logs the error.
+    async fn save_delivery(&self, delivery: &WebhookDelivery) {
+        if let Err(err) = self.store.save_delivery(delivery).await {
+            tracing::error!("fail to save the webhook delivery: {}", err.message());
+        }
+    }
+
+    /// Returns the delay of the exponential backoff for the attempts.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+impl fmt::Debug for WebhookDispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookDispatcher")
+            .field("batch_size", &self.batch_size)
+            .field("poll_interval", &self.poll_interval)
+            .field("timeout", &self.timeout)
+            .field("max_attempts", &self.max_attempts)
+            .field("failure_threshold", &self.failure_threshold)
+            .finish()
+    }
+}
+
+impl AsyncScheduler for &'static WebhookDispatcher {
+    #[inline]
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn time_till_next_job(&self) -> Duration {
+        self.poll_interval
+    }
+
+    #[inline]
+    async fn tick(&mut self) {
+        WebhookDispatcher::tick(self).await;
+    }
+}
+
+/// The webhook dispatcher as an event bus, which is useful for relaying
+/// the outbox events to the webhooks. It does not support polling the events.
+impl EventBus for &'static WebhookDispatcher {
+    #[inline]
+    fn partitions(&self) -> u32 {
+        1
+    }
+
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<EventRecord, Error>> {
+        Box::pin(async move {
+            self.dispatch(&event).await?;
+            Ok(EventRecord::new(0, 0, DateTime::now(), event))
+        })
+    }
+
+    fn poll<'a>(
+        &'a self,
+        _group: &'a str,
+        _limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<EventRecord>, Error>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+
+    #[inline]
+    fn commit<'a>(
+        &'a self,
+        _group: &'a str,
+        _record: &'a EventRecord,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn seek<'a>(
+        &'a self,
+        _group: &'a str,
+        _position: ReplayPosition,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async {
+            Err(warn!(
+                "the webhook dispatcher does not support replaying events"
+            ))
+        })
+    }
+}
+
+/// Signs the webhook payload with the HMAC of `{id}.{timestamp}.{body}`,
+/// and returns the hex-encoded signature.
+pub fn sign_payload(secret: &str, id: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Digest>::new_from_slice(secret.as_bytes()).expect("HMAC can take key of any size");
+    mac.update(format!("{id}.{timestamp}.").as_bytes());
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Checks whether the URL is allowed to be a webhook endpoint.
+///
+/// Only `http` and `https` URLs are allowed, and the hosts of loopback, private,
+/// link-local or cloud metadata addresses are rejected to prevent the server-side
+/// request forgery. The domain names are not resolved here, but the addresses
+/// are checked again by the [`WebhookDispatcher`] when the requests are sent.
+pub fn check_webhook_url(url: &str) -> Result<(), Error> {
+    let url = Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        bail!("the scheme of the webhook URL should be `http` or `https`");
+    }
+
+    let allowed = match url.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            domain != "localhost"
+                && !domain.ends_with(".localhost")
+                && !domain.ends_with(".internal")
+        }
+        Some(Host::Ipv4(ip)) => is_global_ip(ip.into()),
+        Some(Host::Ipv6(ip)) => is_global_ip(ip.into()),
+        None => false,
+    };
+    if !allowed {
+        bail!("the host of the webhook URL `{}` is not allowed", url);
+    }
+    Ok(())
+}
+
+/// Returns `true` if the IP address is globally reachable.
+fn is_global_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let is_shared = a == 100 && (b & 0xc0) == 64;
+            !(a == 0
+                || a >= 240
+                || is_shared
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_documentation())
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_global_ip(ip.into());
+            }
+            let segment = ip.segments()[0];
+            let is_unique_local = (segment & 0xfe00) == 0xfc00;
+            let is_link_local = (segment & 0xffc0) == 0xfe80;
+            !(ip.is_unspecified() || ip.is_loopback() || is_unique_local || is_link_local)
+        }
+    }
+}
+
+/// Resolves the host, and checks whether all the addresses are globally reachable.
+fn resolve_webhook_host(host: &str) -> Result<Vec<SocketAddr>, Error> {
+    let addrs = (host, 0).to_socket_addrs()?.collect::<Vec<_>>();
+    if addrs.is_empty() {
+        bail!("the host `{}` of the webhook URL can not be resolved", host);
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_global_ip(addr.ip())) {
+        bail!(
+            "the host `{}` of the webhook URL is resolved to a non-global address `{}`",
+            host,
+            addr.ip()
+        );
+    }
+    Ok(addrs)
+}
+
+/// DNS resolver for the webhook requests. The connections are only established
+/// to the addresses checked by the resolver, so that a domain name can not be rebound
+/// to an internal address after the check.
+#[derive(Debug, Clone, Copy, Default)]
+struct WebhookResolver;
+
+impl Resolve for WebhookResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_owned();
+        Box::pin(async move {
+            // The lookup is blocking, so it is run in a separate thread.
+            let (sender, receiver) = oneshot::channel();
+            thread::spawn(move || sender.send(resolve_webhook_host(&host)));
+            match receiver.await {
+                Ok(Ok(addrs)) => Ok(Box::new(addrs.into_iter()) as Addrs),
+                Ok(Err(err)) => Err(err.message().into()),
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+}
+
+/// Max number of characters in the response excerpt.
+const RESPONSE_EXCERPT_LEN: usize = 512;
+
+/// HTTP client for the webhook requests, which does not follow the redirects
+/// so that an endpoint can not redirect the requests to an internal address.
+/// The proxies are disabled since they would resolve the domain names by themselves.
+static WEBHOOK_HTTP_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    Client::builder()
+        .user_agent("ZinoBot/1.0")
+        .redirect(Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(WebhookResolver))
+        .build()
+        .unwrap_or_else(|err| panic!("fail to create an HTTP client for webhooks: {err}"))
+});
+
+/// Shared webhook dispatcher.
+static SHARED_WEBHOOK_DISPATCHER: OnceLock<WebhookDispatcher> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_webhook_url, resolve_webhook_host, sign_payload, DeliveryStatus, MemoryWebhookStore,
+        WebhookDelivery, WebhookDispatcher, WebhookSubscriber,
+    };
+    use crate::channel::{CloudEvent, Subscription};
+    use std::time::Duration;
+
+    #[test]
+    fn it_dispatches_events_to_subscribers() {
+        futures::executor::block_on(async {
+            let dispatcher = WebhookDispatcher::new(MemoryWebhookStore::new());
+            let subscription = Subscription::new(None, Some("order.*".to_owned()));
+            let subscriber = WebhookSubscriber::new("http://localhost:9/hook", "secret")
+                .with_subscription(subscription);
+            dispatcher
+                .store()
+                .save_subscriber(&subscriber)
+                .await
+                .unwrap();
+
+            let event = CloudEvent::new("1", "shop", "order.created");
+            assert_eq!(dispatcher.dispatch(&event).await.unwrap(), 1);
+
+            let event = CloudEvent::new("2", "shop", "payment.created");
+            assert_eq!(dispatcher.dispatch(&event).await.unwrap(), 0);
+
+            let store = dispatcher.store();
+            let deliveries = store.claim_deliveries(10, Duration::ZERO).await.unwrap();
+            assert_eq!(deliveries.len(), 1);
+            assert_eq!(deliveries[0].status(), DeliveryStatus::Pending);
+            assert!(store
+                .claim_deliveries(10, Duration::ZERO)
+                .await
+                .unwrap()
+                .is_empty());
+
+            dispatcher.redeliver(deliveries[0].id()).await.unwrap();
+            assert_eq!(
+                store
+                    .claim_deliveries(10, Duration::ZERO)
+                    .await
+                    .unwrap()
+                    .len(),
+                1
+            );
+        });
+
+        let signature = sign_payload("secret", "id", 1700000000, "{}");
+        assert_eq!(signature, sign_payload("secret", "id", 1700000000, "{}"));
+        assert_ne!(signature, sign_payload("secret", "id", 1700000001, "{}"));
+    }
+
+    #[test]
+    fn it_disables_endpoints_after_failures() {
+        futures::executor::block_on(async {
+            let mut dispatcher = WebhookDispatcher::new(MemoryWebhookStore::new());
+            dispatcher.failure_threshold = 2;
+
+            let subscriber = WebhookSubscriber::new("https://example.com/hook", "secret");
+            let subscriber_id = subscriber.id();
+            let store = dispatcher.store();
+            store.save_subscriber(&subscriber).await.unwrap();
+            assert!(!format!("{subscriber:?}").contains("secret"));
+
+            let event = CloudEvent::new("1", "shop", "order.created");
+            let delivery = WebhookDelivery::new(subscriber_id, &event);
+            store.push_delivery(&delivery).await.unwrap();
+
+            let threshold = dispatcher.failure_threshold;
+            assert!(!store
+                .record_failure(subscriber_id, threshold)
+                .await
+                .unwrap());
+            assert!(store
+                .record_failure(subscriber_id, threshold)
+                .await
+                .unwrap());
+            assert!(!store
+                .record_failure(subscriber_id, threshold)
+                .await
+                .unwrap());
+
+            let subscriber = store.get_subscriber(subscriber_id).await.unwrap().unwrap();
+            assert!(!subscriber.is_enabled());
+            assert_eq!(subscriber.consecutive_failures(), 3);
+            assert_eq!(dispatcher.dispatch(&event).await.unwrap(), 0);
+
+            // The pending delivery of a disabled endpoint is dead without being sent.
+            dispatcher.tick().await;
+            let delivery = store.get_delivery(delivery.id()).await.unwrap().unwrap();
+            assert_eq!(delivery.status(), DeliveryStatus::Dead);
+            assert_eq!(delivery.attempts(), 0);
+
+            store.reset_failures(subscriber_id).await.unwrap();
+            let subscriber = store.get_subscriber(subscriber_id).await.unwrap().unwrap();
+            assert_eq!(subscriber.consecutive_failures(), 0);
+        });
+    }
+
+    #[test]
+    fn it_backs_off_exponentially() {
+        let dispatcher = WebhookDispatcher::new(MemoryWebhookStore::new());
+        assert_eq!(dispatcher.backoff(1), Duration::from_secs(10));
+        assert_eq!(dispatcher.backoff(2), Duration::from_secs(20));
+        assert_eq!(dispatcher.backoff(4), Duration::from_secs(80));
+        assert_eq!(dispatcher.backoff(9), Duration::from_secs(2560));
+        assert_eq!(dispatcher.backoff(10), Duration::from_secs(3600));
+        assert_eq!(dispatcher.backoff(64), Duration::from_secs(3600));
+    }
+
+    #[test]
+    #[cfg(not(feature = "crypto-sm"))]
+    fn it_signs_payloads() {
+        let body = r#"{"id":"1"}"#;
+        let signature = sign_payload("whsec_test", "msg_1", 1700000000, body);
+        assert_eq!(
+            signature,
+            "5d3402a957ab8094c78013501ed003d5e4a1b6a57338b8722fad65f664b56b71"
+        );
+    }
+
+    #[test]
+    fn it_checks_webhook_urls() {
+        assert!(check_webhook_url("https://example.com/hook").is_ok());
+        assert!(check_webhook_url("http://93.184.216.34:8080/hook").is_ok());
+        assert!(check_webhook_url("ftp://example.com/hook").is_err());
+        assert!(check_webhook_url("http://localhost:9/hook").is_err());
+        assert!(check_webhook_url("http://api.localhost/hook").is_err());
+        assert!(check_webhook_url("http://metadata.google.internal/").is_err());
+        assert!(check_webhook_url("http://127.0.0.1/hook").is_err());
+        assert!(check_webhook_url("http://2130706433/hook").is_err());
+        assert!(check_webhook_url("http://10.0.0.1/hook").is_err());
+        assert!(check_webhook_url("http://169.254.169.254/latest/meta-data").is_err());
+        assert!(check_webhook_url("http://100.100.100.200/").is_err());
+        assert!(check_webhook_url("http://0.0.0.0/hook").is_err());
+        assert!(check_webhook_url("http://[::1]/hook").is_err());
+        assert!(check_webhook_url("http://[::ffff:127.0.0.1]/hook").is_err());
+        assert!(check_webhook_url("http://[fd00:ec2::254]/").is_err());
+
+        assert!(resolve_webhook_host("127.0.0.1").is_err());
+        assert!(resolve_webhook_host("localhost").is_err());
+        assert!(resolve_webhook_host("93.184.216.34").is_ok());
+    }
+}
@@ -1,6 +1,7 @@
 //! Constructing responses and rejections.
 
 use crate::{
+    datetime::DateTime,
     error::Error,
     extension::JsonValueExt,
     file::NamedFile,
@@ -8,8 +9,9 @@ use crate::{
     request::RequestContext,
     trace::{ServerTiming, TimingMetric, TraceContext},
     validation::Validation,
-    JsonValue, SharedString, Uuid,
+    AvroValue, JsonValue, Map, Record, SharedString, Uuid,
 };
+use apache_avro::{Schema, Writer};
 use bytes::Bytes;
 use etag::EntityTag;
 use serde::Serialize;
@@ -22,10 +24,15 @@ use std::{
 #[cfg(feature = "cookie")]
 use cookie::Cookie;
 
+mod dispatcher;
 mod rejection;
 mod response_code;
 mod webhook;
 
+pub use dispatcher::{
+    check_webhook_url, sign_payload, DeliveryStatus, MemoryWebhookStore, WebhookDelivery,
+    WebhookDispatcher, WebhookStore, WebhookSubscriber,
+};
 pub use rejection::{ExtractRejection, Rejection};
 pub use response_code::ResponseCode;
 pub use webhook::WebHook;
@@ -69,6 +76,12 @@ pub struct Response<S: ResponseCode> {
     /// A URI reference that identifies the specific occurrence of the problem.
     #[serde(skip_serializing_if = "Option::is_none")]
     instance: Option<SharedString>,
+    /// A list of problem detail entries for the invalid params.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invalid_params: Vec<Map>,
+    /// Extension members of the problem details.
+    #[serde(flatten)]
+    extensions: Map,
     /// Indicates the response is successful or not.
     success: bool,
     /// A context-specific descriptive message for successful response.
@@ -120,6 +133,8 @@ impl<S: ResponseCode> Response<S> {
             business_code: code.business_code(),
             detail: None,
             instance: None,
+            invalid_params: Vec::new(),
+            extensions: code.extensions().unwrap_or_default(),
             success,
             message: None,
             start_time: Instant::now(),
@@ -153,6 +168,8 @@ impl<S: ResponseCode> Response<S> {
             business_code: code.business_code(),
             detail: None,
             instance: (!success).then(|| ctx.instance().into()),
+            invalid_params: Vec::new(),
+            extensions: code.extensions().unwrap_or_default(),
             success,
             message: None,
             start_time: ctx.start_time(),
@@ -211,6 +228,7 @@ impl<S: ResponseCode> Response<S> {
                 self.status_code = code.status_code();
                 self.error_code = code.error_code();
                 self.business_code = code.business_code();
+                self.extensions = code.extensions().unwrap_or_default();
                 self.success = false;
                 self.detail = Some(err.to_string().into());
                 self.message = None;
@@ -230,6 +248,7 @@ impl<S: ResponseCode> Response<S> {
         self.status_code = code.status_code();
         self.error_code = code.error_code();
         self.business_code = code.business_code();
+        self.extensions = code.extensions().unwrap_or_default();
         self.success = success;
         if success {
             self.detail = None;
@@ -321,12 +340,20 @@ impl<S: ResponseCode> Response<S> {
     }
 
     /// Sets the response data for the validation.
+    /// The failed entries are also recorded as the `invalid_params` in the problem details.
     #[inline]
     pub fn set_validation_data(&mut self, validation: Validation) {
+        self.invalid_params = validation.to_problem_entries();
         self.json_data = validation.into_map().into();
         self.bytes_data = Bytes::new();
     }
 
+    /// Inserts an extension member of the problem details.
+    #[inline]
+    pub fn insert_extension(&mut self, key: impl Into<String>, value: impl Into<JsonValue>) {
+        self.extensions.insert(key.into(), value.into());
+    }
+
     /// Sets a transformer for the response data.
     #[inline]
     pub fn set_data_transformer(&mut self, transformer: DataTransformer) {
@@ -339,10 +366,14 @@ impl<S: ResponseCode> Response<S> {
     ///
     /// Currently, we have built-in support for the following values:
     ///
+    /// - `application/avro`
+    /// - `application/cbor`
     /// - `application/json`
     /// - `application/jsonlines`
+    /// - `application/msgpack`
     /// - `application/octet-stream`
     /// - `application/problem+json`
+    /// - `application/vnd.apache.arrow.stream`
     /// - `application/x-www-form-urlencoded`
     /// - `text/csv`
     /// - `text/html`
@@ -399,6 +430,86 @@ impl<S: ResponseCode> Response<S> {
         inner::<S>(self, data.into())
     }
 
+    /// Sets the MessagePack data as the response body.
+    #[cfg(feature = "msgpack")]
+    #[inline]
+    pub fn set_msgpack_response(&mut self, data: impl Into<JsonValue>) {
+        fn inner<S: ResponseCode>(res: &mut Response<S>, data: JsonValue) {
+            res.set_json_data(data);
+            res.set_content_type("application/msgpack");
+            res.set_data_transformer(|data| Ok(rmp_serde::to_vec_named(&data)?.into()));
+        }
+        inner::<S>(self, data.into())
+    }
+
+    /// Sets the CBOR data as the response body.
+    #[cfg(feature = "cbor")]
+    #[inline]
+    pub fn set_cbor_response(&mut self, data: impl Into<JsonValue>) {
+        fn inner<S: ResponseCode>(res: &mut Response<S>, data: JsonValue) {
+            res.set_json_data(data);
+            res.set_content_type("application/cbor");
+            res.set_data_transformer(|data| {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(&data, &mut bytes)?;
+                Ok(bytes.into())
+            });
+        }
+        inner::<S>(self, data.into())
+    }
+
+    /// Sets the Avro records as the response body in the object container file format.
+    pub fn set_avro_response(&mut self, schema: &Schema, records: Vec<Record>) {
+        let mut writer = Writer::new(schema, Vec::new());
+        let result = writer
+            .extend(records.into_iter().map(AvroValue::Record))
+            .and_then(|_| writer.into_inner());
+        match result {
+            Ok(bytes) => {
+                self.set_bytes_data(bytes);
+                self.set_content_type("application/avro");
+            }
+            Err(err) => self.set_error_message(err),
+        }
+    }
+
+    /// Sets the Avro records as the response body in the Arrow IPC streaming format.
+    #[cfg(feature = "connector-arrow")]
+    pub fn set_arrow_response(&mut self, records: Vec<Record>) {
+        match crate::connector::encode_arrow_ipc(&records) {
+            Ok(bytes) => {
+                self.set_bytes_data(bytes);
+                self.set_content_type("application/vnd.apache.arrow.stream");
+            }
+            Err(err) => self.set_error_message(err),
+        }
+    }
+
+    /// Sets the data as the response body for the data type,
+    /// which is usually obtained from
+    /// [`RequestContext::negotiate_data_type()`](crate::request::RequestContext::negotiate_data_type).
+    ///
+    /// # Note
+    ///
+    /// Currently, we support the following values: `cbor` | `csv` | `form` | `json` | `msgpack`
+    /// | `ndjson` | `text`. It falls back to the JSON response for unsupported data types.
+    pub fn set_data_response(&mut self, data_type: &str, data: impl Into<JsonValue>) {
+        fn inner<S: ResponseCode>(res: &mut Response<S>, data_type: &str, data: JsonValue) {
+            match data_type {
+                #[cfg(feature = "cbor")]
+                "cbor" => res.set_cbor_response(data),
+                "csv" => res.set_csv_response(data),
+                "form" => res.set_form_response(data),
+                #[cfg(feature = "msgpack")]
+                "msgpack" => res.set_msgpack_response(data),
+                "ndjson" => res.set_jsonlines_response(data),
+                "text" => res.set_text_response(data.to_string_unquoted()),
+                _ => res.set_json_response(data),
+            }
+        }
+        inner::<S>(self, data_type, data.into())
+    }
+
     /// Sets the plain text as the response body.
     #[inline]
     pub fn set_text_response(&mut self, data: impl Into<String>) {
@@ -463,6 +574,44 @@ impl<S: ResponseCode> Response<S> {
         self.headers.push((name.into(), value.to_string()));
     }
 
+    /// Sets the status code as `304 Not Modified`. The response body will be empty.
+    #[inline]
+    pub fn set_not_modified(&mut self) {
+        self.status_code = 304;
+        self.json_data = JsonValue::Null;
+        self.bytes_data = Bytes::new();
+    }
+
+    /// Sets the `ETag` header. It takes precedence over the entity tag derived from the body.
+    #[inline]
+    pub fn set_etag(&mut self, etag: impl ToString) {
+        self.insert_header("etag", etag);
+    }
+
+    /// Sets a weak `ETag` header derived from the validators of the resource and the scope,
+    /// and returns the entity tag. The validators consist of a version and the last modified time,
+    /// and the scope should identify everything else which affects the representation,
+    /// such as the query and the roles of the client.
+    pub fn set_validator_etag(&mut self, validators: (u64, DateTime), scope: &str) -> String {
+        let (version, last_modified) = validators;
+        let data = format!("{version}:{}\n{scope}", last_modified.timestamp_micros());
+        let etag = EntityTag::weak(EntityTag::from_data(data.as_bytes()).tag()).to_string();
+        self.set_etag(&etag);
+        etag
+    }
+
+    /// Sets the `Last-Modified` header.
+    #[inline]
+    pub fn set_last_modified(&mut self, last_modified: DateTime) {
+        self.insert_header("last-modified", last_modified.to_utc_string());
+    }
+
+    /// Sets the `Cache-Control` header.
+    #[inline]
+    pub fn set_cache_control(&mut self, directives: impl ToString) {
+        self.insert_header("cache-control", directives);
+    }
+
     /// Gets a custome header with the given name.
     #[inline]
     pub fn get_header(&self, name: &str) -> Option<&str> {
@@ -489,6 +638,18 @@ impl<S: ResponseCode> Response<S> {
         self.business_code.as_ref()
     }
 
+    /// Returns the invalid params in the problem details.
+    #[inline]
+    pub fn invalid_params(&self) -> &[Map] {
+        &self.invalid_params
+    }
+
+    /// Returns the extension members of the problem details.
+    #[inline]
+    pub fn extensions(&self) -> &Map {
+        &self.extensions
+    }
+
     /// Returns `true` if the response is successful or `false` otherwise.
     #[inline]
     pub fn is_success(&self) -> bool {
@@ -568,6 +729,11 @@ impl<S: ResponseCode> Response<S> {
 
     /// Reads the response into a byte buffer.
     pub fn read_bytes(&mut self) -> Result<Bytes, Error> {
+        if self.status_code == 304 {
+            return Ok(Bytes::new());
+        }
+
+        let has_etag = self.get_header("etag").is_some();
         let has_bytes_data = !self.bytes_data.is_empty();
         let has_json_data = !self.json_data.is_null();
         let bytes_opt = if has_bytes_data {
@@ -582,8 +748,10 @@ impl<S: ResponseCode> Response<S> {
             None
         };
         if let Some(bytes) = bytes_opt {
-            let etag = EntityTag::from_data(&bytes);
-            self.insert_header("x-etag", etag);
+            if !has_etag {
+                let etag = EntityTag::from_data(&bytes);
+                self.insert_header("x-etag", etag);
+            }
             return Ok(bytes);
         }
 
@@ -614,8 +782,10 @@ impl<S: ResponseCode> Response<S> {
         } else {
             (Vec::new(), None)
         };
-        let etag = etag_opt.unwrap_or_else(|| EntityTag::from_data(&bytes));
-        self.insert_header("x-etag", etag);
+        if !has_etag {
+            let etag = etag_opt.unwrap_or_else(|| EntityTag::from_data(&bytes));
+            self.insert_header("x-etag", etag);
+        }
         Ok(bytes.into())
     }
 
@@ -35,6 +35,8 @@ enum RejectionKind {
     MethodNotAllowed(Error),
     /// 409 Conflict
     Conflict(Error),
+    /// 429 Too Many Requests
+    TooManyRequests(Error),
     /// 500 Internal Server Error
     InternalServerError(Error),
     /// 503 Service Unavailable
@@ -102,6 +104,16 @@ impl Rejection {
         }
     }
 
+    /// Creates a `429 Too Many Requests` rejection.
+    #[inline]
+    pub fn too_many_requests(err: impl Into<Error>) -> Self {
+        Self {
+            kind: TooManyRequests(err.into()),
+            context: None,
+            trace_context: None,
+        }
+    }
+
     /// Creates a `500 Internal Server Error` rejection.
     #[inline]
     pub fn internal_server_error(err: impl Into<Error>) -> Self {
@@ -133,7 +145,9 @@ impl Rejection {
     pub fn from_error(err: impl Into<Error>) -> Self {
         fn inner(err: Error) -> Rejection {
             let message = err.message();
-            if message.starts_with("401 Unauthorized") {
+            if message.starts_with("400 Bad Request") {
+                Rejection::from_validation_entry("request", err)
+            } else if message.starts_with("401 Unauthorized") {
                 Rejection::unauthorized(err)
             } else if message.starts_with("403 Forbidden") {
                 Rejection::forbidden(err)
@@ -143,6 +157,8 @@ impl Rejection {
                 Rejection::method_not_allowed(err)
             } else if message.starts_with("409 Conflict") {
                 Rejection::conflict(err)
+            } else if message.starts_with("429 Too Many Requests") {
+                Rejection::too_many_requests(err)
             } else if message.starts_with("503 Service Unavailable") {
                 Rejection::service_unavailable(err)
             } else {
@@ -176,6 +192,7 @@ impl Rejection {
             NotFound(_) => 404,
             MethodNotAllowed(_) => 405,
             Conflict(_) => 409,
+            TooManyRequests(_) => 429,
             InternalServerError(_) => 500,
             ServiceUnavailable(_) => 503,
         }
@@ -188,6 +205,16 @@ macro_rules! impl_from_rejection {
             fn from(rejection: Rejection) -> Self {
                 let mut res = match rejection.kind {
                     BadRequest(validation) => {
+                        #[cfg(feature = "i18n")]
+                        let validation = {
+                            let mut validation = validation;
+                            let locale = rejection.context.as_ref().and_then(|ctx| ctx.locale());
+                            if let Some(locale) = locale {
+                                validation.localize(locale);
+                            }
+                            validation
+                        };
+
                         let mut res = Response::new(<$Ty>::BAD_REQUEST);
                         res.set_validation_data(validation);
                         res
@@ -217,6 +244,11 @@ macro_rules! impl_from_rejection {
                         res.set_error_message(err);
                         res
                     }
+                    TooManyRequests(err) => {
+                        let mut res = Response::new(<$Ty>::TOO_MANY_REQUESTS);
+                        res.set_error_message(err);
+                        res
+                    }
                     InternalServerError(err) => {
                         let mut res = Response::new(<$Ty>::INTERNAL_SERVER_ERROR);
                         res.set_error_message(err);
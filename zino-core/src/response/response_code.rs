@@ -1,9 +1,9 @@
-use crate::SharedString;
+use crate::{Map, SharedString};
 use serde::Serialize;
 use std::borrow::Cow;
 
 /// Trait for response code.
-/// See [Problem Details for HTTP APIs](https://tools.ietf.org/html/rfc7807).
+/// See [Problem Details for HTTP APIs](https://www.rfc-editor.org/rfc/rfc9457).
 pub trait ResponseCode {
     /// A type for the error code.
     type ErrorCode: Serialize;
@@ -53,6 +53,12 @@ pub trait ResponseCode {
     fn message(&self) -> Option<SharedString> {
         None
     }
+
+    /// Extension members of the problem details, which will be flattened
+    /// into the top-level JSON object of the response.
+    fn extensions(&self) -> Option<Map> {
+        None
+    }
 }
 
 macro_rules! impl_response_code {
@@ -1,10 +1,20 @@
 //! Scheduler for sync and async cron jobs.
 
-use super::AsyncScheduler;
-use crate::{datetime::DateTime, extension::TomlTableExt, BoxFuture, Map, Uuid};
+use super::{
+    monitor::{JobCommand, JobSnapshot},
+    trigger::MAX_MISFIRE_RUNS,
+    AsyncScheduler, BusinessCalendar, JobMonitor, JobRun, JobRunOutcome, LeaseManager,
+    MisfirePolicy, TickStore, Trigger,
+};
+use crate::{
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    warn, BoxFuture, Map, Uuid,
+};
 use chrono::Local;
-use cron::Schedule;
-use std::{str::FromStr, time::Duration};
+use futures::FutureExt;
+use std::{panic::AssertUnwindSafe, time::Duration};
 use toml::Table;
 
 /// A function pointer of the async cron job.
@@ -15,6 +25,8 @@ pub type AsyncCronJob =
 pub struct AsyncJob {
     /// Job ID.
     id: Uuid,
+    /// Job name.
+    name: String,
     /// Job data.
     data: Map,
     /// Flag to indicate whether the job is disabled.
@@ -23,8 +35,18 @@ pub struct AsyncJob {
     immediate: bool,
     /// Remaining ticks.
     remaining_ticks: Option<usize>,
-    /// Cron expression parser.
-    schedule: Schedule,
+    /// Lease key for the exclusive execution in a cluster.
+    lease_key: Option<String>,
+    /// Cron expression.
+    cron_expr: String,
+    /// Trigger of the job.
+    trigger: Trigger,
+    /// Policy for the missed ticks since the restored last tick.
+    misfire: MisfirePolicy,
+    /// Flag to indicate whether the last tick has been restored.
+    restored: bool,
+    /// Random delay of the next execution.
+    delay: Duration,
     /// Cron job to run.
     run: AsyncCronJob,
     /// Last time when running the job.
@@ -39,29 +61,61 @@ impl AsyncJob {
     /// Panics if the cron expression is invalid.
     #[inline]
     pub fn new(cron_expr: &str, exec: AsyncCronJob) -> Self {
-        let schedule = Schedule::from_str(cron_expr)
+        let trigger = cron_expr
+            .parse::<Trigger>()
             .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
         Self {
             id: Uuid::now_v7(),
+            name: cron_expr.to_owned(),
             data: Map::new(),
             disabled: false,
             immediate: false,
             remaining_ticks: None,
-            schedule,
+            lease_key: None,
+            cron_expr: cron_expr.to_owned(),
+            trigger,
+            misfire: MisfirePolicy::default(),
+            restored: false,
+            delay: Duration::ZERO,
             run: exec,
             last_tick: None,
         }
     }
 
     /// Creates a new instance with the configuration.
+    /// The job is identified by `name` in the execution history and metrics,
+    /// which defaults to the cron expression.
+    /// If `exclusive` is `true`, the job is executed by only one instance in a cluster
+    /// with a lease whose key is `lease-key`, `name` or the cron expression.
+    ///
+    /// # Examples
+    ///
+    /// ```toml
+    /// [[cron-job]]
+    /// name = "daily-report"
+    /// cron = "0 0 9 * * *"
+    /// timezone = "Asia/Shanghai"
+    /// jitter = "30s"
+    /// misfire = "run_once"
+    /// calendar = { holidays = ["2025-01-01"] }
+    /// ```
     ///
     /// # Panics
     ///
-    /// Panics if the `cron` expression is invalid.
+    /// Panics if the `cron` expression, the `timezone`, the `misfire` policy
+    /// or the `calendar` is invalid.
     pub fn with_config(config: &Table, exec: AsyncCronJob) -> Self {
         let cron_expr = config.get_str("cron").unwrap_or_default();
-        let schedule = Schedule::from_str(cron_expr)
-            .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
+        let trigger = Trigger::try_from_config(config)
+            .unwrap_or_else(|err| panic!("invalid trigger of `{cron_expr}`: {err}"));
+        let misfire = config
+            .get_str("misfire")
+            .map(|policy| {
+                policy
+                    .parse::<MisfirePolicy>()
+                    .unwrap_or_else(|err| panic!("{err}"))
+            })
+            .unwrap_or_default();
         let data = config
             .get_table("data")
             .map(|t| t.to_map())
@@ -72,18 +126,36 @@ impl AsyncJob {
             .get_bool("once")
             .and_then(|b| b.then_some(1))
             .or_else(|| config.get_usize("max-ticks"));
+        let name = config.get_str("name").unwrap_or(cron_expr);
+        let lease_key = config
+            .get_bool("exclusive")
+            .is_some_and(|b| b)
+            .then(|| config.get_str("lease-key").unwrap_or(name).to_owned());
         Self {
             id: Uuid::now_v7(),
+            name: name.to_owned(),
             data,
             disabled,
             immediate,
             remaining_ticks,
-            schedule,
+            lease_key,
+            cron_expr: cron_expr.to_owned(),
+            trigger,
+            misfire,
+            restored: false,
+            delay: Duration::ZERO,
             run: exec,
             last_tick: None,
         }
     }
 
+    /// Sets the job name used in the execution history and metrics.
+    #[inline]
+    pub fn name(mut self, name: impl ToString) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
     /// Enables the flag to indicate whether the job is disabled.
     #[inline]
     pub fn disable(mut self, disabled: bool) -> Self {
@@ -112,12 +184,81 @@ impl AsyncJob {
         self
     }
 
+    /// Sets the lease key to ensure that the job is executed by only one instance in a cluster.
+    /// The fencing token of the lease is available as `fencing_token` in the job data.
+    /// The job fails without running if the shared [`LeaseManager`] is not configured.
+    #[inline]
+    pub fn exclusive(mut self, lease_key: impl ToString) -> Self {
+        self.lease_key = Some(lease_key.to_string());
+        self
+    }
+
+    /// Sets the IANA time zone in which the cron expression is evaluated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time zone is invalid.
+    #[inline]
+    pub fn timezone(mut self, timezone: &str) -> Self {
+        self.trigger = self
+            .trigger
+            .timezone(timezone)
+            .unwrap_or_else(|err| panic!("{err}"));
+        self
+    }
+
+    /// Sets the maximum random delay of the execution.
+    #[inline]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.trigger = self.trigger.jitter(jitter);
+        self.delay = self.trigger.random_delay();
+        self
+    }
+
+    /// Sets the policy for the missed ticks since the restored last tick.
+    #[inline]
+    pub fn misfire(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire = policy;
+        self
+    }
+
+    /// Sets the business-day calendar.
+    #[inline]
+    pub fn calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.trigger = self.trigger.calendar(calendar);
+        self
+    }
+
     /// Returns the job ID.
     #[inline]
     pub fn id(&self) -> Uuid {
         self.id
     }
 
+    /// Returns the job name.
+    #[inline]
+    pub fn job_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the cron expression.
+    #[inline]
+    pub fn cron_expr(&self) -> &str {
+        &self.cron_expr
+    }
+
+    /// Returns a reference to the trigger.
+    #[inline]
+    pub fn trigger(&self) -> &Trigger {
+        &self.trigger
+    }
+
+    /// Previews the next `n` fire times, without the jitter.
+    #[inline]
+    pub fn preview(&self, n: usize) -> Vec<DateTime> {
+        self.trigger.preview(n)
+    }
+
     /// Returns a reference to the job data.
     #[inline]
     pub fn data(&self) -> &Map {
@@ -142,6 +283,12 @@ impl AsyncJob {
         self.immediate
     }
 
+    /// Returns `true` if the job is executed exclusively in a cluster.
+    #[inline]
+    pub fn is_exclusive(&self) -> bool {
+        self.lease_key.is_some()
+    }
+
     /// Returns `true` if the job is fused and can not be executed any more.
     #[inline]
     pub fn is_fused(&self) -> bool {
@@ -160,45 +307,155 @@ impl AsyncJob {
         self.disabled = false;
     }
 
+    /// Replaces the cron expression of the job.
+    pub fn reschedule(&mut self, cron_expr: &str) -> Result<(), Error> {
+        self.trigger.replace_schedule(cron_expr.parse()?);
+        self.cron_expr = cron_expr.to_owned();
+        Ok(())
+    }
+
     /// Sets the last tick when the job was executed.
+    /// The missed ticks since then are handled by the misfire policy at the next tick.
     #[inline]
     pub fn set_last_tick(&mut self, last_tick: Option<DateTime>) {
         self.last_tick = last_tick.map(|dt| dt.into());
+        self.restored = last_tick.is_some();
+    }
+
+    /// Returns the last tick when the job was executed.
+    #[inline]
+    pub fn last_tick(&self) -> Option<DateTime> {
+        self.last_tick.map(|dt| dt.into())
     }
 
     /// Executes the missed runs asynchronously.
+    #[inline]
     pub async fn tick(&mut self) {
+        self.run_missed().await;
+    }
+
+    /// Executes the missed runs asynchronously and returns the number of the runs.
+    async fn run_missed(&mut self) -> usize {
         let now = Local::now();
+        let due: chrono::DateTime<Local> = (DateTime::from(now) - self.delay).into();
         let disabled = self.disabled;
         let run = self.run;
+        let mut num_runs = 0;
         if let Some(last_tick) = self.last_tick {
-            for event in self.schedule.after(&last_tick) {
-                if event > now || self.is_fused() {
+            // The missed ticks are bounded, so that a long downtime will not result in
+            // walking through or running all of them.
+            let mut missed_ticks =
+                self.trigger
+                    .count_between(last_tick.into(), due.into(), MAX_MISFIRE_RUNS);
+            if self.restored {
+                missed_ticks = self.misfire.num_runs(missed_ticks);
+                self.restored = false;
+            }
+            for _ in 0..missed_ticks {
+                if self.is_fused() {
                     break;
                 }
                 if !disabled {
-                    run(self.id, &mut self.data, last_tick.into()).await;
+                    self.run_job(run, last_tick.into()).await;
                     if let Some(ticks) = self.remaining_ticks {
                         self.remaining_ticks = Some(ticks.saturating_sub(1));
                     }
+                    num_runs += 1;
                 }
             }
-        } else if !disabled && self.immediate && !self.is_fused() {
-            run(self.id, &mut self.data, now.into()).await;
-            if let Some(ticks) = self.remaining_ticks {
-                self.remaining_ticks = Some(ticks.saturating_sub(1));
+            self.last_tick = Some(due.max(last_tick));
+        } else {
+            if !disabled && self.immediate && !self.is_fused() {
+                self.run_job(run, now.into()).await;
+                if let Some(ticks) = self.remaining_ticks {
+                    self.remaining_ticks = Some(ticks.saturating_sub(1));
+                }
+                num_runs += 1;
             }
+            self.last_tick = Some(due);
         }
-        self.last_tick = Some(now);
+        if num_runs > 0 {
+            self.delay = self.trigger.random_delay();
+        }
+        num_runs
     }
 
     /// Executes the job manually.
     pub async fn execute(&mut self) {
         let now = Local::now();
-        let run = self.run;
-        run(self.id, &mut self.data, now.into()).await;
+        self.run_job(self.run, now.into()).await;
         self.last_tick = Some(now);
     }
+
+    /// Runs the job with a lease if the lease key is specified,
+    /// and records the run in the job monitor.
+    async fn run_job(&mut self, run: AsyncCronJob, last_tick: DateTime) {
+        let id = self.id;
+        let data = &mut self.data;
+        let started_at = DateTime::now();
+        let result = if let Some(lease_key) = self.lease_key.as_deref() {
+            if let Some(manager) = LeaseManager::shared() {
+                let result = manager
+                    .run_exclusive(lease_key, move |lease| {
+                        data.upsert("fencing_token", lease.token());
+                        catch_panic(run(id, data, last_tick))
+                    })
+                    .await;
+                match result {
+                    Ok(Some(result)) => result.map(Some),
+                    Ok(None) => {
+                        tracing::debug!(lease_key, "the lease is held by another instance");
+                        Ok(None)
+                    }
+                    Err(err) => Err(err),
+                }
+            } else {
+                Err(warn!(
+                    "lease manager is not configured for the exclusive job `{}`",
+                    lease_key
+                ))
+            }
+        } else {
+            catch_panic(run(id, data, last_tick)).await.map(Some)
+        };
+
+        let (outcome, error) = match result {
+            Ok(Some(())) => (JobRunOutcome::Succeeded, None),
+            Ok(None) => (JobRunOutcome::Skipped, None),
+            Err(err) => (JobRunOutcome::Failed, Some(err.message().to_owned())),
+        };
+        let job_run = JobRun::new(id, self.name.as_str(), started_at, outcome, error);
+        JobMonitor::shared().record_run(job_run);
+    }
+
+    /// Returns the next time when the job is supposed to run, including the random delay.
+    fn next_tick(&self) -> Option<DateTime> {
+        let due = DateTime::now() - self.delay;
+        let after = self.last_tick.map_or(due, |dt| due.max(dt.into()));
+        self.trigger
+            .next_after(after)
+            .map(|event| event + self.delay)
+    }
+
+    /// Returns a snapshot of the job.
+    fn snapshot(&self) -> JobSnapshot {
+        let next_tick = if self.disabled || self.is_fused() {
+            None
+        } else {
+            self.next_tick()
+        };
+        JobSnapshot {
+            id: self.id,
+            name: self.name.clone(),
+            cron_expr: self.cron_expr.clone(),
+            trigger: self.trigger.clone(),
+            disabled: self.disabled,
+            exclusive: self.is_exclusive(),
+            remaining_ticks: self.remaining_ticks,
+            last_tick: self.last_tick.map(|dt| dt.into()),
+            next_tick,
+        }
+    }
 }
 
 /// A type contains and executes the async scheduled jobs.
@@ -206,18 +463,35 @@ impl AsyncJob {
 pub struct AsyncJobScheduler {
     /// A list of async jobs.
     jobs: Vec<AsyncJob>,
+    /// Store for the last ticks of the jobs.
+    tick_store: Option<Box<dyn TickStore>>,
+    /// Flag to indicate whether the last ticks have been loaded from the store.
+    ticks_loaded: bool,
 }
 
 impl AsyncJobScheduler {
     /// Creates a new instance.
     #[inline]
     pub fn new() -> Self {
-        Self { jobs: Vec::new() }
+        Self {
+            jobs: Vec::new(),
+            tick_store: None,
+            ticks_loaded: false,
+        }
+    }
+
+    /// Sets the store for persisting the last ticks of the jobs by the job names,
+    /// so that the missed ticks after a restart are handled by the misfire policies.
+    #[inline]
+    pub fn with_tick_store(mut self, store: impl TickStore + 'static) -> Self {
+        self.tick_store = Some(Box::new(store));
+        self
     }
 
     /// Adds an async job to the scheduler and returns the job ID.
     pub fn add(&mut self, job: AsyncJob) -> Uuid {
         let job_id = job.id;
+        JobMonitor::shared().publish(job.snapshot());
         self.jobs.push(job);
         job_id
     }
@@ -227,6 +501,7 @@ impl AsyncJobScheduler {
         let position = self.jobs.iter().position(|job| job.id == job_id);
         if let Some(index) = position {
             self.jobs.remove(index);
+            JobMonitor::shared().withdraw(job_id);
             true
         } else {
             false
@@ -246,6 +521,8 @@ impl AsyncJobScheduler {
     }
 
     /// Returns the duration till the next job is supposed to run.
+    /// It is capped at one second so that the runtime commands from the [`JobMonitor`]
+    /// can be applied in time.
     pub fn time_till_next_job(&self) -> Duration {
         if self.jobs.is_empty() {
             Duration::from_millis(500)
@@ -253,8 +530,8 @@ impl AsyncJobScheduler {
             let mut duration = chrono::Duration::zero();
             let now = Local::now();
             for job in self.jobs.iter() {
-                for event in job.schedule.after(&now).take(1) {
-                    let interval = event - now;
+                if let Some(event) = job.next_tick() {
+                    let interval = chrono::DateTime::<Local>::from(event) - now;
                     if duration.is_zero() || interval < duration {
                         duration = interval;
                     }
@@ -263,18 +540,40 @@ impl AsyncJobScheduler {
             duration
                 .to_std()
                 .unwrap_or_else(|_| Duration::from_millis(500))
+                .min(Duration::from_secs(1))
         }
     }
 
     /// Increments time for the scheduler and executes any pending jobs asynchronously.
     /// It is recommended to sleep for at least 500 milliseconds between invocations of this method.
-    #[inline]
     pub async fn tick(&mut self) {
+        self.apply_commands().await;
+
+        let ticks_loaded = self.ticks_loaded;
+        if !ticks_loaded {
+            self.load_ticks().await;
+        }
+
+        let monitor = JobMonitor::shared();
         let mut fused_jobs = Vec::new();
         for job in &mut self.jobs {
-            job.tick().await;
+            let num_runs = job.run_missed().await;
+            if let Some(store) = self.tick_store.as_deref() {
+                if num_runs > 0 || !ticks_loaded {
+                    if let Some(last_tick) = job.last_tick() {
+                        if let Err(err) = store.save_tick(&job.name, last_tick).await {
+                            tracing::error!(
+                                job_name = job.name,
+                                "fail to save the last tick: {err}"
+                            );
+                        }
+                    }
+                }
+            }
             if job.is_fused() {
                 fused_jobs.push(job.id());
+            } else {
+                monitor.publish(job.snapshot());
             }
         }
         for job_id in fused_jobs {
@@ -282,6 +581,44 @@ impl AsyncJobScheduler {
         }
     }
 
+    /// Loads the last ticks of the jobs from the store.
+    async fn load_ticks(&mut self) {
+        if let Some(store) = self.tick_store.as_deref() {
+            for job in &mut self.jobs {
+                if job.last_tick.is_some() {
+                    continue;
+                }
+                match store.load_tick(&job.name).await {
+                    Ok(Some(last_tick)) => job.set_last_tick(Some(last_tick)),
+                    Ok(None) => (),
+                    Err(err) => {
+                        tracing::error!(job_name = job.name, "fail to load the last tick: {err}")
+                    }
+                }
+            }
+        }
+        self.ticks_loaded = true;
+    }
+
+    /// Applies the pending commands from the job monitor.
+    async fn apply_commands(&mut self) {
+        let job_ids = self.jobs.iter().map(|job| job.id).collect::<Vec<_>>();
+        for (job_id, command) in JobMonitor::shared().take_commands(&job_ids) {
+            let Some(job) = self.get_mut(job_id) else {
+                continue;
+            };
+            match command {
+                JobCommand::Pause => job.pause(),
+                JobCommand::Resume => job.resume(),
+                JobCommand::Trigger => job.execute().await,
+                JobCommand::Reschedule(cron_expr, trigger) => {
+                    job.cron_expr = cron_expr;
+                    job.trigger.replace_schedule(*trigger);
+                }
+            }
+        }
+    }
+
     /// Executes all the job manually.
     pub async fn execute(&mut self) {
         for job in &mut self.jobs {
@@ -306,3 +643,20 @@ impl AsyncScheduler for AsyncJobScheduler {
         self.tick().await;
     }
 }
+
+/// Runs the job and converts a panic into an error.
+async fn catch_panic(job: BoxFuture<'_>) -> Result<(), Error> {
+    AssertUnwindSafe(job)
+        .catch_unwind()
+        .await
+        .map_err(|payload| {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                (*message).to_owned()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "the job has panicked".to_owned()
+            };
+            Error::new(message)
+        })
+}
@@ -1,10 +1,9 @@
 //! Scheduler for sync and async cron jobs.
 
-use super::Scheduler;
+use super::{trigger::MAX_MISFIRE_RUNS, BusinessCalendar, MisfirePolicy, Scheduler, Trigger};
 use crate::{datetime::DateTime, extension::TomlTableExt, Map, Uuid};
 use chrono::Local;
-use cron::Schedule;
-use std::{str::FromStr, time::Duration};
+use std::time::Duration;
 use toml::Table;
 
 /// A function pointer of the cron job.
@@ -22,8 +21,14 @@ pub struct Job {
     immediate: bool,
     /// Remaining ticks.
     remaining_ticks: Option<usize>,
-    /// Cron expression parser.
-    schedule: Schedule,
+    /// Trigger of the job.
+    trigger: Trigger,
+    /// Policy for the missed ticks since the restored last tick.
+    misfire: MisfirePolicy,
+    /// Flag to indicate whether the last tick has been restored.
+    restored: bool,
+    /// Random delay of the next execution.
+    delay: Duration,
     /// Cron job to run.
     run: CronJob,
     /// Last time when running the job.
@@ -38,7 +43,8 @@ impl Job {
     /// Panics if the cron expression is invalid.
     #[inline]
     pub fn new(cron_expr: &str, exec: CronJob) -> Self {
-        let schedule = Schedule::from_str(cron_expr)
+        let trigger = cron_expr
+            .parse::<Trigger>()
             .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
         Self {
             id: Uuid::now_v7(),
@@ -46,7 +52,10 @@ impl Job {
             disabled: false,
             immediate: false,
             remaining_ticks: None,
-            schedule,
+            trigger,
+            misfire: MisfirePolicy::default(),
+            restored: false,
+            delay: Duration::ZERO,
             run: exec,
             last_tick: None,
         }
@@ -56,11 +65,20 @@ impl Job {
     ///
     /// # Panics
     ///
-    /// Panics if the `cron` expression is invalid.
+    /// Panics if the `cron` expression, the `timezone`, the `misfire` policy
+    /// or the `calendar` is invalid.
     pub fn with_config(config: &Table, exec: CronJob) -> Self {
         let cron_expr = config.get_str("cron").unwrap_or_default();
-        let schedule = Schedule::from_str(cron_expr)
-            .unwrap_or_else(|err| panic!("invalid cron expression `{cron_expr}`: {err}"));
+        let trigger = Trigger::try_from_config(config)
+            .unwrap_or_else(|err| panic!("invalid trigger of `{cron_expr}`: {err}"));
+        let misfire = config
+            .get_str("misfire")
+            .map(|policy| {
+                policy
+                    .parse::<MisfirePolicy>()
+                    .unwrap_or_else(|err| panic!("{err}"))
+            })
+            .unwrap_or_default();
         let data = config
             .get_table("data")
             .map(|t| t.to_map())
@@ -77,7 +95,10 @@ impl Job {
             disabled,
             immediate,
             remaining_ticks,
-            schedule,
+            trigger,
+            misfire,
+            restored: false,
+            delay: Duration::ZERO,
             run: exec,
             last_tick: None,
         }
@@ -111,12 +132,60 @@ impl Job {
         self
     }
 
+    /// Sets the IANA time zone in which the cron expression is evaluated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the time zone is invalid.
+    #[inline]
+    pub fn timezone(mut self, timezone: &str) -> Self {
+        self.trigger = self
+            .trigger
+            .timezone(timezone)
+            .unwrap_or_else(|err| panic!("{err}"));
+        self
+    }
+
+    /// Sets the maximum random delay of the execution.
+    #[inline]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.trigger = self.trigger.jitter(jitter);
+        self.delay = self.trigger.random_delay();
+        self
+    }
+
+    /// Sets the policy for the missed ticks since the restored last tick.
+    #[inline]
+    pub fn misfire(mut self, policy: MisfirePolicy) -> Self {
+        self.misfire = policy;
+        self
+    }
+
+    /// Sets the business-day calendar.
+    #[inline]
+    pub fn calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.trigger = self.trigger.calendar(calendar);
+        self
+    }
+
     /// Returns the job ID.
     #[inline]
     pub fn id(&self) -> Uuid {
         self.id
     }
 
+    /// Returns a reference to the trigger.
+    #[inline]
+    pub fn trigger(&self) -> &Trigger {
+        &self.trigger
+    }
+
+    /// Previews the next `n` fire times, without the jitter.
+    #[inline]
+    pub fn preview(&self, n: usize) -> Vec<DateTime> {
+        self.trigger.preview(n)
+    }
+
     /// Returns a reference to the job data.
     #[inline]
     pub fn data(&self) -> &Map {
@@ -160,19 +229,38 @@ impl Job {
     }
 
     /// Sets the last tick when the job was executed.
+    /// The missed ticks since then are handled by the misfire policy at the next tick.
     #[inline]
     pub fn set_last_tick(&mut self, last_tick: Option<DateTime>) {
         self.last_tick = last_tick.map(|dt| dt.into());
+        self.restored = last_tick.is_some();
+    }
+
+    /// Returns the last tick when the job was executed.
+    #[inline]
+    pub fn last_tick(&self) -> Option<DateTime> {
+        self.last_tick.map(|dt| dt.into())
     }
 
     /// Executes missed runs.
     pub fn tick(&mut self) {
         let now = Local::now();
+        let due: chrono::DateTime<Local> = (DateTime::from(now) - self.delay).into();
         let disabled = self.disabled;
         let run = self.run;
+        let mut fired = false;
         if let Some(last_tick) = self.last_tick {
-            for event in self.schedule.after(&last_tick) {
-                if event > now || self.is_fused() {
+            // The missed ticks are bounded, so that a long downtime will not result in
+            // walking through or running all of them.
+            let mut missed_ticks =
+                self.trigger
+                    .count_between(last_tick.into(), due.into(), MAX_MISFIRE_RUNS);
+            if self.restored {
+                missed_ticks = self.misfire.num_runs(missed_ticks);
+                self.restored = false;
+            }
+            for _ in 0..missed_ticks {
+                if self.is_fused() {
                     break;
                 }
                 if !disabled {
@@ -180,15 +268,32 @@ impl Job {
                     if let Some(ticks) = self.remaining_ticks {
                         self.remaining_ticks = Some(ticks.saturating_sub(1));
                     }
+                    fired = true;
                 }
             }
-        } else if !disabled && self.immediate && !self.is_fused() {
-            run(self.id, &mut self.data, now.into());
-            if let Some(ticks) = self.remaining_ticks {
-                self.remaining_ticks = Some(ticks.saturating_sub(1));
+            self.last_tick = Some(due.max(last_tick));
+        } else {
+            if !disabled && self.immediate && !self.is_fused() {
+                run(self.id, &mut self.data, now.into());
+                if let Some(ticks) = self.remaining_ticks {
+                    self.remaining_ticks = Some(ticks.saturating_sub(1));
+                }
+                fired = true;
             }
+            self.last_tick = Some(due);
         }
-        self.last_tick = Some(now);
+        if fired {
+            self.delay = self.trigger.random_delay();
+        }
+    }
+
+    /// Returns the next time when the job is supposed to run, including the random delay.
+    fn next_tick(&self) -> Option<DateTime> {
+        let due = DateTime::now() - self.delay;
+        let after = self.last_tick.map_or(due, |dt| due.max(dt.into()));
+        self.trigger
+            .next_after(after)
+            .map(|event| event + self.delay)
     }
 
     /// Executes the job manually.
@@ -252,8 +357,8 @@ impl JobScheduler {
             let mut duration = chrono::Duration::zero();
             let now = Local::now();
             for job in self.jobs.iter() {
-                for event in job.schedule.after(&now).take(1) {
-                    let interval = event - now;
+                if let Some(event) = job.next_tick() {
+                    let interval = chrono::DateTime::<Local>::from(event) - now;
                     if duration.is_zero() || interval < duration {
                         duration = interval;
                     }
@@ -0,0 +1,443 @@
+//! Leases for coordinating the scheduled jobs in a cluster.
+
+use crate::{
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    BoxFuture, Map, Uuid,
+};
+use futures::{
+    channel::mpsc as async_mpsc,
+    future::{self, Either},
+    Future, StreamExt,
+};
+use parking_lot::RwLock;
+use std::{
+    collections::HashMap,
+    sync::{mpsc, OnceLock},
+    thread,
+    time::Duration,
+};
+
+/// A lease held by an instance for exclusive execution.
+///
+/// The fencing token increases monotonically each time the lease is granted to a holder,
+/// so that the downstream services can reject the writes with a stale token.
+#[derive(Debug, Clone, Default)]
+pub struct Lease {
+    /// Lease key.
+    key: String,
+    /// Lease holder.
+    holder: String,
+    /// Fencing token.
+    token: u64,
+    /// Expiration time.
+    expires_at: DateTime,
+}
+
+impl Lease {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(key: impl ToString, holder: impl ToString, token: u64, ttl: Duration) -> Self {
+        Self {
+            key: key.to_string(),
+            holder: holder.to_string(),
+            token,
+            expires_at: DateTime::now() + ttl,
+        }
+    }
+
+    /// Attempts to create a new instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(key) = map.get_str("key") else {
+            bail!("the `key` field should be specified");
+        };
+        let Some(expires_at) = map.get_str("expires_at") else {
+            bail!("the `expires_at` field should be specified");
+        };
+        Ok(Self {
+            key: key.to_owned(),
+            holder: map.get_str("holder").unwrap_or_default().to_owned(),
+            token: map.get_u64("token").unwrap_or_default(),
+            expires_at: expires_at.parse()?,
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("key", self.key);
+        map.upsert("holder", self.holder);
+        map.upsert("token", self.token);
+        map.upsert("expires_at", self.expires_at.to_string());
+        map
+    }
+
+    /// Extends the expiration time of the lease.
+    #[inline]
+    pub fn extend(&mut self, ttl: Duration) {
+        self.expires_at = DateTime::now() + ttl;
+    }
+
+    /// Expires the lease immediately.
+    #[inline]
+    pub fn expire(&mut self) {
+        self.expires_at = DateTime::now();
+    }
+
+    /// Returns the lease key.
+    #[inline]
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the lease holder.
+    #[inline]
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Returns the fencing token.
+    #[inline]
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    /// Returns the expiration time.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+
+    /// Returns `true` if the lease has expired.
+    #[inline]
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= DateTime::now()
+    }
+
+    /// Returns `true` if the lease is held by the holder with the same token.
+    #[inline]
+    pub fn is_held_by(&self, lease: &Lease) -> bool {
+        self.holder == lease.holder && self.token == lease.token && !self.is_expired()
+    }
+}
+
+/// Storage backend for the leases.
+pub trait LeaseStore: Send + Sync {
+    /// Attempts to acquire the lease for the holder. It returns `None` if the lease
+    /// has been held by another holder and has not expired.
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        holder: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>>;
+
+    /// Renews the lease. It returns `None` if the lease has been lost.
+    fn renew<'a>(
+        &'a self,
+        lease: &'a Lease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>>;
+
+    /// Releases the lease.
+    fn release<'a>(&'a self, lease: &'a Lease) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// In-memory lease store, which is only suitable for a single instance.
+#[derive(Debug, Default)]
+pub struct MemoryLeaseStore {
+    /// Leases.
+    leases: RwLock<HashMap<String, Lease>>,
+}
+
+impl MemoryLeaseStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LeaseStore for MemoryLeaseStore {
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        holder: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            let mut leases = self.leases.write();
+            let lease = match leases.get(key) {
+                Some(lease) if !lease.is_expired() && lease.holder != holder => return Ok(None),
+                Some(lease) if !lease.is_expired() => Lease::new(key, holder, lease.token, ttl),
+                Some(lease) => Lease::new(key, holder, lease.token + 1, ttl),
+                None => Lease::new(key, holder, 1, ttl),
+            };
+            leases.insert(key.to_owned(), lease.clone());
+            Ok(Some(lease))
+        })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a Lease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            let mut leases = self.leases.write();
+            match leases.get_mut(&lease.key) {
+                Some(current_lease) if current_lease.is_held_by(lease) => {
+                    current_lease.extend(ttl);
+                    Ok(Some(current_lease.clone()))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, lease: &'a Lease) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            if let Some(current_lease) = self.leases.write().get_mut(&lease.key) {
+                if current_lease.is_held_by(lease) {
+                    current_lease.expire();
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Manager of the leases for the exclusive jobs.
+///
+/// The lease is renewed automatically at one third of the TTL while the job is running,
+/// and the job is cancelled if the lease has been lost.
+///
+/// The shared manager can be configured by the `lease` table with the `memory`,
+/// `accessor` or `advisory-lock` store, or registered by [`LeaseManager::register()`]
+/// before the first use. The `accessor` store requires a storage service
+/// which supports conditional writes.
+///
+/// # Examples
+///
+/// ```toml
+/// [lease]
+/// store = "advisory-lock"
+/// database = "main"
+/// ttl = "30s"
+/// ```
+pub struct LeaseManager {
+    /// Lease store.
+    store: Box<dyn LeaseStore>,
+    /// Holder of the leases.
+    holder: String,
+    /// Time-to-live of the leases.
+    ttl: Duration,
+}
+
+impl LeaseManager {
+    /// Creates a new instance with the lease store.
+    #[inline]
+    pub fn new(store: impl LeaseStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            holder: format!("{}-{}", std::process::id(), Uuid::now_v7()),
+            ttl: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the time-to-live of the leases.
+    #[inline]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Sets the holder of the leases.
+    #[inline]
+    pub fn with_holder(mut self, holder: impl ToString) -> Self {
+        self.holder = holder.to_string();
+        self
+    }
+
+    /// Registers the shared lease manager. It returns `false` if the shared manager
+    /// has already been initialized.
+    #[inline]
+    pub fn register(manager: Self) -> bool {
+        SHARED_LEASE_MANAGER.set(Some(manager)).is_ok()
+    }
+
+    /// Returns a reference to the shared lease manager if it has been configured.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_LEASE_MANAGER.get_or_init(Self::from_config).as_ref()
+    }
+
+    /// Returns the holder of the leases.
+    #[inline]
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Attempts to acquire the lease with the key.
+    #[inline]
+    pub async fn acquire(&self, key: &str) -> Result<Option<Lease>, Error> {
+        self.store.acquire(key, &self.holder, self.ttl).await
+    }
+
+    /// Releases the lease.
+    #[inline]
+    pub async fn release(&self, lease: &Lease) -> Result<(), Error> {
+        self.store.release(lease).await
+    }
+
+    /// Runs the future created by the closure while holding the lease with the key.
+    /// It returns `None` if the lease has been held by another instance.
+    ///
+    /// The lease is not released when the future completes, but kept until the TTL expires,
+    /// so that the instances whose ticks fire slightly later do not run the same tick again.
+    /// The holder can acquire the lease again for the next tick, and the TTL should be
+    /// longer than the clock skew and the jitter between the instances.
+    pub async fn run_exclusive<F, T>(
+        &self,
+        key: &str,
+        f: impl FnOnce(&Lease) -> F,
+    ) -> Result<Option<T>, Error>
+    where
+        F: Future<Output = T>,
+    {
+        let Some(mut lease) = self.acquire(key).await? else {
+            return Ok(None);
+        };
+
+        let mut fut = Box::pin(f(&lease));
+        let (mut ticks, _cancel_sender) = ticker(self.ttl / 3);
+        loop {
+            match future::select(fut, ticks.next()).await {
+                Either::Left((output, _)) => return Ok(Some(output)),
+                Either::Right((_, remaining_fut)) => {
+                    match self.store.renew(&lease, self.ttl).await {
+                        Ok(Some(renewed_lease)) => lease = renewed_lease,
+                        Ok(None) => bail!("the lease `{}` has been lost", key),
+                        Err(err) => {
+                            if lease.is_expired() {
+                                bail!("fail to renew the lease `{}`: {}", key, err.message());
+                            }
+                            tracing::warn!(key, "fail to renew the lease: {err}");
+                        }
+                    }
+                    fut = remaining_fut;
+                }
+            }
+        }
+    }
+
+    /// Creates a new instance from the `lease` config.
+    fn from_config() -> Option<Self> {
+        let config = State::shared().get_config("lease")?;
+        let manager = match config.get_str("store").unwrap_or("memory") {
+            "memory" => Self::new(MemoryLeaseStore::new()),
+            #[cfg(feature = "accessor")]
+            "accessor" => {
+                let accessor_name = config.get_str("accessor").unwrap_or("lease");
+                match crate::accessor::AccessorLeaseStore::try_new(accessor_name) {
+                    Ok(store) => Self::new(store),
+                    Err(err) => {
+                        tracing::error!("fail to configure the lease store: {err}");
+                        return None;
+                    }
+                }
+            }
+            #[cfg(all(
+                feature = "orm-postgres",
+                not(any(feature = "orm-mariadb", feature = "orm-mysql", feature = "orm-tidb"))
+            ))]
+            "advisory-lock" => {
+                let database = config.get_str("database").unwrap_or("main");
+                match crate::orm::AdvisoryLockStore::try_new(database) {
+                    Ok(store) => Self::new(store),
+                    Err(err) => {
+                        tracing::error!("fail to configure the lease store: {err}");
+                        return None;
+                    }
+                }
+            }
+            store => {
+                tracing::error!("unsupported lease store `{store}`");
+                return None;
+            }
+        };
+        if let Some(ttl) = config.get_duration("ttl") {
+            Some(manager.with_ttl(ttl))
+        } else {
+            Some(manager)
+        }
+    }
+}
+
+impl std::fmt::Debug for LeaseManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LeaseManager")
+            .field("holder", &self.holder)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Shared lease manager.
+static SHARED_LEASE_MANAGER: OnceLock<Option<LeaseManager>> = OnceLock::new();
+
+/// Creates a stream of ticks with the interval. It does not rely on the timer
+/// of any async runtime, and stops when the returned sender is dropped.
+fn ticker(interval: Duration) -> (async_mpsc::UnboundedReceiver<()>, mpsc::Sender<()>) {
+    let (cancel_sender, cancel_receiver) = mpsc::channel::<()>();
+    let (tick_sender, tick_receiver) = async_mpsc::unbounded();
+    thread::spawn(move || {
+        while cancel_receiver.recv_timeout(interval) == Err(mpsc::RecvTimeoutError::Timeout) {
+            if tick_sender.unbounded_send(()).is_err() {
+                break;
+            }
+        }
+    });
+    (tick_receiver, cancel_sender)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LeaseManager, LeaseStore, MemoryLeaseStore};
+    use std::time::Duration;
+
+    #[test]
+    fn it_grants_leases_with_fencing_tokens() {
+        futures::executor::block_on(async {
+            let store = MemoryLeaseStore::new();
+            let ttl = Duration::from_secs(30);
+            let lease = store.acquire("report", "a", ttl).await.unwrap().unwrap();
+            assert_eq!(lease.token(), 1);
+            assert!(store.acquire("report", "b", ttl).await.unwrap().is_none());
+            assert!(store.renew(&lease, ttl).await.unwrap().is_some());
+
+            store.release(&lease).await.unwrap();
+            let lease = store.acquire("report", "b", ttl).await.unwrap().unwrap();
+            assert_eq!(lease.token(), 2);
+
+            let manager = LeaseManager::new(MemoryLeaseStore::new());
+            let output = manager
+                .run_exclusive("report", |lease| {
+                    let token = lease.token();
+                    async move { token * 10 }
+                })
+                .await
+                .unwrap();
+            assert_eq!(output, Some(10));
+
+            // The lease is kept after the run, so another holder skips the same tick.
+            let store = &manager.store;
+            assert!(store.acquire("report", "b", ttl).await.unwrap().is_none());
+            let lease = manager.acquire("report").await.unwrap().unwrap();
+            assert_eq!(lease.token(), 1);
+        });
+    }
+}
@@ -1,12 +1,26 @@
-//! Scheduler for sync and async cron jobs.
+//! Scheduler for sync and async cron jobs, and the queue for background jobs.
 
 use std::{future::Future, time::Duration};
 
 mod async_job;
 mod job;
+mod lease;
+mod monitor;
+mod queue;
+mod timer;
+mod trigger;
+mod workflow;
 
 pub use async_job::{AsyncCronJob, AsyncJob, AsyncJobScheduler};
 pub use job::{CronJob, Job, JobScheduler};
+pub use lease::{Lease, LeaseManager, LeaseStore, MemoryLeaseStore};
+pub use monitor::{JobMonitor, JobRun, JobRunOutcome, JobSnapshot};
+pub use queue::{JobOptions, JobQueue, JobRecord, JobStatus, JobStore, MemoryJobStore, QueueJob};
+pub use trigger::{BusinessCalendar, MemoryTickStore, MisfirePolicy, TickStore, Trigger};
+pub use workflow::{
+    HistoryAction, MemoryWorkflowStore, StepAction, StepCompensation, StepOutcome, Workflow,
+    WorkflowEngine, WorkflowHistory, WorkflowRun, WorkflowStatus, WorkflowStore,
+};
 
 /// An interface for scheduling sync jobs.
 pub trait Scheduler {
@@ -31,3 +45,31 @@ pub trait AsyncScheduler {
     /// Increments time for the scheduler and executes any pending jobs asynchronously.
     fn tick(&mut self) -> impl Future<Output = ()> + Send;
 }
+
+impl<S, T> AsyncScheduler for (S, T)
+where
+    S: AsyncScheduler + Send,
+    T: AsyncScheduler + Send,
+{
+    #[inline]
+    fn is_ready(&self) -> bool {
+        self.0.is_ready() || self.1.is_ready()
+    }
+
+    fn time_till_next_job(&self) -> Duration {
+        match (self.0.is_ready(), self.1.is_ready()) {
+            (true, true) => self.0.time_till_next_job().min(self.1.time_till_next_job()),
+            (false, true) => self.1.time_till_next_job(),
+            _ => self.0.time_till_next_job(),
+        }
+    }
+
+    async fn tick(&mut self) {
+        if self.0.is_ready() {
+            self.0.tick().await;
+        }
+        if self.1.is_ready() {
+            self.1.tick().await;
+        }
+    }
+}
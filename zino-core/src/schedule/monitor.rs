@@ -0,0 +1,449 @@
+//! Execution history and runtime control of the async cron jobs.
+
+use super::Trigger;
+use crate::{
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    state::State,
+    warn, Map, Uuid,
+};
+use parking_lot::{Mutex, RwLock};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::OnceLock,
+    time::Duration,
+};
+
+/// Outcome of a job run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JobRunOutcome {
+    /// The job has succeeded.
+    Succeeded,
+    /// The job has failed with an error or a panic.
+    Failed,
+    /// The job has been skipped since the lease is held by another instance.
+    Skipped,
+}
+
+impl JobRunOutcome {
+    /// Returns the outcome as a `str`.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "Succeeded",
+            Self::Failed => "Failed",
+            Self::Skipped => "Skipped",
+        }
+    }
+}
+
+impl fmt::Display for JobRunOutcome {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A record of the job run.
+#[derive(Debug, Clone)]
+pub struct JobRun {
+    /// Job ID.
+    job_id: Uuid,
+    /// Job name.
+    job_name: String,
+    /// Start time.
+    started_at: DateTime,
+    /// End time.
+    ended_at: DateTime,
+    /// Outcome of the run.
+    outcome: JobRunOutcome,
+    /// Error message.
+    error: Option<String>,
+}
+
+impl JobRun {
+    /// Creates a new instance which ends now.
+    pub fn new(
+        job_id: Uuid,
+        job_name: impl Into<String>,
+        started_at: DateTime,
+        outcome: JobRunOutcome,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            job_id,
+            job_name: job_name.into(),
+            started_at,
+            ended_at: DateTime::now(),
+            outcome,
+            error,
+        }
+    }
+
+    /// Returns the job ID.
+    #[inline]
+    pub fn job_id(&self) -> Uuid {
+        self.job_id
+    }
+
+    /// Returns the job name.
+    #[inline]
+    pub fn job_name(&self) -> &str {
+        &self.job_name
+    }
+
+    /// Returns the start time.
+    #[inline]
+    pub fn started_at(&self) -> DateTime {
+        self.started_at
+    }
+
+    /// Returns the end time.
+    #[inline]
+    pub fn ended_at(&self) -> DateTime {
+        self.ended_at
+    }
+
+    /// Returns the duration of the run.
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        self.ended_at.duration_since(self.started_at)
+    }
+
+    /// Returns the outcome of the run.
+    #[inline]
+    pub fn outcome(&self) -> JobRunOutcome {
+        self.outcome
+    }
+
+    /// Returns the error message.
+    #[inline]
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Converts `self` to a JSON object.
+    pub fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.upsert("job_id", self.job_id.to_string());
+        map.upsert("job_name", self.job_name.as_str());
+        map.upsert("started_at", self.started_at.to_string());
+        map.upsert("ended_at", self.ended_at.to_string());
+        map.upsert("duration", self.duration().as_millis() as u64);
+        map.upsert("outcome", self.outcome.as_str());
+        map.upsert("error", self.error.as_deref());
+        map
+    }
+}
+
+/// A snapshot of the scheduled job.
+#[derive(Debug, Clone)]
+pub struct JobSnapshot {
+    /// Job ID.
+    pub(super) id: Uuid,
+    /// Job name.
+    pub(super) name: String,
+    /// Cron expression.
+    pub(super) cron_expr: String,
+    /// Trigger of the job.
+    pub(super) trigger: Trigger,
+    /// Flag to indicate whether the job is disabled.
+    pub(super) disabled: bool,
+    /// Flag to indicate whether the job is executed exclusively in a cluster.
+    pub(super) exclusive: bool,
+    /// Remaining ticks.
+    pub(super) remaining_ticks: Option<usize>,
+    /// Last time when running the job.
+    pub(super) last_tick: Option<DateTime>,
+    /// Next time when the job is supposed to run.
+    pub(super) next_tick: Option<DateTime>,
+}
+
+impl JobSnapshot {
+    /// Returns the job ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the job name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the cron expression.
+    #[inline]
+    pub fn cron_expr(&self) -> &str {
+        &self.cron_expr
+    }
+
+    /// Returns a reference to the trigger.
+    #[inline]
+    pub fn trigger(&self) -> &Trigger {
+        &self.trigger
+    }
+
+    /// Returns `true` if the job is disabled.
+    #[inline]
+    pub fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    /// Converts `self` to a JSON object.
+    pub fn to_map(&self) -> Map {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("name", self.name.as_str());
+        map.upsert("cron", self.cron_expr.as_str());
+        map.upsert("timezone", self.trigger.timezone_name());
+        map.upsert("jitter", self.trigger.max_jitter().as_millis() as u64);
+        map.upsert("disabled", self.disabled);
+        map.upsert("exclusive", self.exclusive);
+        map.upsert("remaining_ticks", self.remaining_ticks);
+        map.upsert("last_tick", self.last_tick.map(|dt| dt.to_string()));
+        map.upsert("next_tick", self.next_tick.map(|dt| dt.to_string()));
+        map
+    }
+}
+
+/// A command to control the scheduled job at runtime.
+#[derive(Debug, Clone)]
+pub(super) enum JobCommand {
+    /// Pauses the job.
+    Pause,
+    /// Resumes the job.
+    Resume,
+    /// Executes the job immediately.
+    Trigger,
+    /// Replaces the cron schedule or the interval.
+    Reschedule(String, Box<Trigger>),
+}
+
+/// Monitor for the execution history and the runtime control of async cron jobs.
+///
+/// The commands are applied by the scheduler which owns the job at its next tick.
+///
+/// # Examples
+///
+/// ```toml
+/// [scheduler]
+/// history-size = 20
+/// ```
+pub struct JobMonitor {
+    /// Maximum number of runs kept for each job.
+    history_size: usize,
+    /// Recent runs of the jobs.
+    history: RwLock<HashMap<Uuid, VecDeque<JobRun>>>,
+    /// Snapshots of the scheduled jobs.
+    snapshots: RwLock<HashMap<Uuid, JobSnapshot>>,
+    /// Pending commands.
+    commands: Mutex<Vec<(Uuid, JobCommand)>>,
+}
+
+impl JobMonitor {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(history_size: usize) -> Self {
+        Self {
+            history_size,
+            history: RwLock::new(HashMap::new()),
+            snapshots: RwLock::new(HashMap::new()),
+            commands: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the shared job monitor.
+    #[inline]
+    pub fn shared() -> &'static Self {
+        SHARED_JOB_MONITOR.get_or_init(|| {
+            let history_size = State::shared()
+                .get_config("scheduler")
+                .and_then(|config| config.get_usize("history-size"))
+                .unwrap_or(20);
+            Self::new(history_size)
+        })
+    }
+
+    /// Lists the snapshots of the scheduled jobs.
+    pub fn list_jobs(&self) -> Vec<JobSnapshot> {
+        let mut jobs = self.snapshots.read().values().cloned().collect::<Vec<_>>();
+        jobs.sort_by_key(|job| job.id);
+        jobs
+    }
+
+    /// Gets the snapshot of the scheduled job.
+    #[inline]
+    pub fn get_job(&self, job_id: Uuid) -> Option<JobSnapshot> {
+        self.snapshots.read().get(&job_id).cloned()
+    }
+
+    /// Lists the recent runs of the job, with the latest one at first.
+    pub fn list_runs(&self, job_id: Uuid) -> Vec<JobRun> {
+        self.history
+            .read()
+            .get(&job_id)
+            .map(|runs| runs.iter().rev().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Pauses the job.
+    #[inline]
+    pub fn pause(&self, job_id: Uuid) -> Result<(), Error> {
+        self.push_command(job_id, JobCommand::Pause)
+    }
+
+    /// Resumes the job.
+    #[inline]
+    pub fn resume(&self, job_id: Uuid) -> Result<(), Error> {
+        self.push_command(job_id, JobCommand::Resume)
+    }
+
+    /// Executes the job immediately, regardless of the schedule and the `disabled` flag.
+    #[inline]
+    pub fn trigger(&self, job_id: Uuid) -> Result<(), Error> {
+        self.push_command(job_id, JobCommand::Trigger)
+    }
+
+    /// Replaces the cron expression of the job, which can also be an interval like `@every 90s`.
+    pub fn reschedule(&self, job_id: Uuid, cron_expr: &str) -> Result<(), Error> {
+        let trigger = cron_expr.parse::<Trigger>().map_err(|err| {
+            warn!(
+                "400 Bad Request: invalid cron expression `{}`: {}",
+                cron_expr,
+                err.message()
+            )
+        })?;
+        let command = JobCommand::Reschedule(cron_expr.to_owned(), Box::new(trigger));
+        self.push_command(job_id, command)
+    }
+
+    /// Previews the next fire times of the job, without the jitter.
+    pub fn preview(&self, job_id: Uuid, count: usize) -> Result<Vec<DateTime>, Error> {
+        let snapshots = self.snapshots.read();
+        let Some(snapshot) = snapshots.get(&job_id) else {
+            return Err(warn!("404 Not Found: the job `{}` does not exist", job_id));
+        };
+        Ok(snapshot.trigger.preview(count))
+    }
+
+    /// Pushes a command for the job.
+    fn push_command(&self, job_id: Uuid, command: JobCommand) -> Result<(), Error> {
+        if !self.snapshots.read().contains_key(&job_id) {
+            return Err(warn!("404 Not Found: the job `{}` does not exist", job_id));
+        }
+        self.commands.lock().push((job_id, command));
+        Ok(())
+    }
+
+    /// Takes the pending commands for the jobs.
+    pub(super) fn take_commands(&self, job_ids: &[Uuid]) -> Vec<(Uuid, JobCommand)> {
+        let mut commands = self.commands.lock();
+        if commands.is_empty() {
+            return Vec::new();
+        }
+
+        let (taken, remaining) = commands
+            .drain(..)
+            .partition(|(job_id, _)| job_ids.contains(job_id));
+        *commands = remaining;
+        taken
+    }
+
+    /// Publishes the snapshot of a job.
+    pub(super) fn publish(&self, snapshot: JobSnapshot) {
+        self.snapshots.write().insert(snapshot.id, snapshot);
+    }
+
+    /// Withdraws the snapshot of a job which has been removed from the scheduler.
+    pub(super) fn withdraw(&self, job_id: Uuid) {
+        self.snapshots.write().remove(&job_id);
+    }
+
+    /// Records a job run and emits the metrics.
+    pub fn record_run(&self, run: JobRun) {
+        #[cfg(feature = "metrics")]
+        {
+            let labels = [
+                ("job", run.job_name.clone()),
+                ("outcome", run.outcome.as_str().to_owned()),
+            ];
+            metrics::counter!("zino_job_runs_total", &labels).increment(1);
+            metrics::histogram!("zino_job_run_duration_seconds", &labels[..1])
+                .record(run.duration().as_secs_f64());
+        }
+        if run.outcome == JobRunOutcome::Failed {
+            tracing::error!(
+                job_id = run.job_id.to_string(),
+                job_name = run.job_name,
+                "fail to run the job: {}",
+                run.error.as_deref().unwrap_or_default()
+            );
+        }
+        if self.history_size == 0 {
+            return;
+        }
+
+        let mut history = self.history.write();
+        let runs = history.entry(run.job_id).or_default();
+        if runs.len() >= self.history_size {
+            runs.pop_front();
+        }
+        runs.push_back(run);
+    }
+}
+
+/// Shared job monitor.
+static SHARED_JOB_MONITOR: OnceLock<JobMonitor> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{JobCommand, JobMonitor, JobRun, JobRunOutcome, JobSnapshot, Trigger};
+    use crate::{datetime::DateTime, Uuid};
+
+    #[test]
+    fn it_records_runs_and_queues_commands() {
+        let monitor = JobMonitor::new(2);
+        let job_id = Uuid::now_v7();
+        assert!(monitor.pause(job_id).is_err());
+
+        monitor.publish(JobSnapshot {
+            id: job_id,
+            name: "cleanup".to_owned(),
+            cron_expr: "0 0 * * * *".to_owned(),
+            trigger: "0 0 * * * *".parse::<Trigger>().unwrap(),
+            disabled: false,
+            exclusive: false,
+            remaining_ticks: None,
+            last_tick: None,
+            next_tick: None,
+        });
+        assert!(monitor.pause(job_id).is_ok());
+        assert!(monitor.reschedule(job_id, "invalid").is_err());
+        assert!(monitor.reschedule(job_id, "0 30 * * * *").is_ok());
+        assert_eq!(monitor.preview(job_id, 3).unwrap().len(), 3);
+        assert!(monitor.take_commands(&[Uuid::now_v7()]).is_empty());
+
+        let commands = monitor.take_commands(&[job_id]);
+        assert_eq!(commands.len(), 2);
+        assert!(matches!(commands[0].1, JobCommand::Pause));
+        assert!(monitor.take_commands(&[job_id]).is_empty());
+
+        for outcome in [
+            JobRunOutcome::Succeeded,
+            JobRunOutcome::Failed,
+            JobRunOutcome::Skipped,
+        ] {
+            let run = JobRun::new(job_id, "cleanup", DateTime::now(), outcome, None);
+            monitor.record_run(run);
+        }
+
+        let runs = monitor.list_runs(job_id);
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].outcome(), JobRunOutcome::Skipped);
+        assert_eq!(runs[1].outcome(), JobRunOutcome::Failed);
+    }
+}
@@ -0,0 +1,1087 @@
+//! Persistent queue for background jobs.
+
+use super::{timer, AsyncScheduler};
+use crate::{
+    application::ShutdownToken,
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    warn, BoxFuture, JsonValue, Map, Uuid,
+};
+use futures::{
+    future::{self, Either},
+    lock::Mutex,
+    stream::FuturesUnordered,
+    StreamExt,
+};
+use parking_lot::RwLock;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::HashMap, fmt, str::FromStr, sync::OnceLock, time::Duration};
+use toml::Table;
+
+/// A job with the typed payload which can be enqueued.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use serde::{Deserialize, Serialize};
+/// use zino_core::{error::Error, schedule::QueueJob, BoxFuture};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct SendEmail {
+///     to: String,
+///     subject: String,
+/// }
+///
+/// impl QueueJob for SendEmail {
+///     const JOB_NAME: &'static str = "send_email";
+///
+///     fn run(self) -> BoxFuture<'static, Result<(), Error>> {
+///         Box::pin(async move {
+///             tracing::info!(to = self.to, "send an email");
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+pub trait QueueJob: Serialize + DeserializeOwned + Send + 'static {
+    /// Name of the job.
+    const JOB_NAME: &'static str;
+
+    /// Runs the job.
+    fn run(self) -> BoxFuture<'static, Result<(), Error>>;
+}
+
+/// Status of a queued job.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JobStatus {
+    /// The job is waiting to be executed.
+    #[default]
+    Pending,
+    /// The job has been claimed by a worker.
+    Running,
+    /// The job has been executed successfully.
+    Succeeded,
+    /// The job has been moved to the dead-letter storage.
+    Dead,
+}
+
+impl JobStatus {
+    /// Returns the status as a str.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Running => "Running",
+            Self::Succeeded => "Succeeded",
+            Self::Dead => "Dead",
+        }
+    }
+
+    /// Returns `true` if the job has not been finished.
+    #[inline]
+    pub fn is_unfinished(&self) -> bool {
+        matches!(self, Self::Pending | Self::Running)
+    }
+}
+
+impl fmt::Display for JobStatus {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for JobStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Pending" => Ok(Self::Pending),
+            "Running" => Ok(Self::Running),
+            "Succeeded" => Ok(Self::Succeeded),
+            "Dead" => Ok(Self::Dead),
+            _ => bail!("invalid job status `{}`", s),
+        }
+    }
+}
+
+/// Options for enqueueing a job.
+#[derive(Debug, Clone, Default)]
+pub struct JobOptions {
+    /// Delay of the execution.
+    delay: Option<Duration>,
+    /// Unique key for deduplication.
+    unique_key: Option<String>,
+    /// Max number of attempts.
+    max_attempts: Option<u32>,
+    /// Timeout for each attempt.
+    timeout: Option<Duration>,
+}
+
+impl JobOptions {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delays the execution of the job.
+    #[inline]
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Sets a unique key so that the job will not be enqueued
+    /// if there is an unfinished job with the same key.
+    #[inline]
+    pub fn unique_key(mut self, key: impl ToString) -> Self {
+        self.unique_key = Some(key.to_string());
+        self
+    }
+
+    /// Sets the max number of attempts.
+    #[inline]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sets the timeout for each attempt.
+    #[inline]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+/// Record of a queued job.
+#[derive(Debug, Clone, Default)]
+pub struct JobRecord {
+    /// Job ID.
+    id: Uuid,
+    /// Queue name.
+    queue: String,
+    /// Job name.
+    job_name: String,
+    /// Job payload.
+    payload: Map,
+    /// Job status.
+    status: JobStatus,
+    /// Number of attempts.
+    attempts: u32,
+    /// Max number of attempts.
+    max_attempts: u32,
+    /// Timeout for each attempt.
+    timeout: Duration,
+    /// Unique key for deduplication.
+    unique_key: String,
+    /// Time when the job is scheduled to run, or when the lease of a running job expires.
+    run_at: DateTime,
+    /// Last error message.
+    last_error: String,
+    /// Creation time.
+    created_at: DateTime,
+    /// Update time.
+    updated_at: DateTime,
+}
+
+impl JobRecord {
+    /// Creates a new instance.
+    pub fn new(queue: impl ToString, job_name: impl ToString, payload: Map) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: Uuid::now_v7(),
+            queue: queue.to_string(),
+            job_name: job_name.to_string(),
+            payload,
+            max_attempts: 1,
+            run_at: now,
+            created_at: now,
+            updated_at: now,
+            ..Self::default()
+        }
+    }
+
+    /// Attempts to create a new instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(id) = map.get_uuid("id") else {
+            bail!("the `id` field should be a UUID");
+        };
+        let Some(job_name) = map.get_str("job_name") else {
+            bail!("the `job_name` field should be specified");
+        };
+        let parse_datetime = |key| -> Result<DateTime, Error> {
+            match map.get_str(key) {
+                Some(value) => Ok(value.parse()?),
+                None => bail!("the `{}` field should be specified", key),
+            }
+        };
+        Ok(Self {
+            id,
+            queue: map.get_str("queue").unwrap_or_default().to_owned(),
+            job_name: job_name.to_owned(),
+            payload: map.get_object("payload").cloned().unwrap_or_default(),
+            status: map.get_str("status").unwrap_or("Pending").parse()?,
+            attempts: map.get_u32("attempts").unwrap_or_default(),
+            max_attempts: map.get_u32("max_attempts").unwrap_or(1),
+            timeout: Duration::from_millis(map.get_u64("timeout").unwrap_or_default()),
+            unique_key: map.get_str("unique_key").unwrap_or_default().to_owned(),
+            run_at: parse_datetime("run_at")?,
+            last_error: map.get_str("last_error").unwrap_or_default().to_owned(),
+            created_at: parse_datetime("created_at")?,
+            updated_at: parse_datetime("updated_at")?,
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("queue", self.queue);
+        map.upsert("job_name", self.job_name);
+        map.upsert("payload", self.payload);
+        map.upsert("status", self.status.as_str());
+        map.upsert("attempts", self.attempts);
+        map.upsert("max_attempts", self.max_attempts);
+        map.upsert("timeout", self.timeout.as_millis() as u64);
+        map.upsert("unique_key", self.unique_key);
+        map.upsert("run_at", self.run_at.to_string());
+        map.upsert("last_error", self.last_error);
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        map
+    }
+
+    /// Claims the job with a lease, which is renewed periodically by the worker
+    /// until the job is finished. It is intended to be called by the job stores.
+    pub fn claim(&mut self) {
+        let now = DateTime::now();
+        self.status = JobStatus::Running;
+        self.attempts += 1;
+        self.run_at = now + JOB_LEASE_DURATION;
+        self.updated_at = now;
+    }
+
+    /// Extends the lease of the running job.
+    fn renew(&mut self) {
+        let now = DateTime::now();
+        self.run_at = now + JOB_LEASE_DURATION;
+        self.updated_at = now;
+    }
+
+    /// Marks the job as succeeded.
+    fn succeed(&mut self) {
+        self.status = JobStatus::Succeeded;
+        self.last_error.clear();
+        self.updated_at = DateTime::now();
+    }
+
+    /// Reschedules the job after the delay with the error message.
+    fn retry(&mut self, delay: Duration, error: String) {
+        let now = DateTime::now();
+        self.status = JobStatus::Pending;
+        self.run_at = now + delay;
+        self.last_error = error;
+        self.updated_at = now;
+    }
+
+    /// Marks the job as dead with the error message.
+    fn bury(&mut self, error: String) {
+        self.status = JobStatus::Dead;
+        self.last_error = error;
+        self.updated_at = DateTime::now();
+    }
+
+    /// Returns the job ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the queue name.
+    #[inline]
+    pub fn queue(&self) -> &str {
+        &self.queue
+    }
+
+    /// Returns the job name.
+    #[inline]
+    pub fn job_name(&self) -> &str {
+        &self.job_name
+    }
+
+    /// Returns a reference to the payload.
+    #[inline]
+    pub fn payload(&self) -> &Map {
+        &self.payload
+    }
+
+    /// Returns the job status.
+    #[inline]
+    pub fn status(&self) -> JobStatus {
+        self.status
+    }
+
+    /// Returns the number of attempts.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the max number of attempts.
+    #[inline]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the timeout for each attempt.
+    #[inline]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the unique key.
+    #[inline]
+    pub fn unique_key(&self) -> Option<&str> {
+        Some(self.unique_key.as_str()).filter(|s| !s.is_empty())
+    }
+
+    /// Returns the time when the job is scheduled to run.
+    #[inline]
+    pub fn run_at(&self) -> DateTime {
+        self.run_at
+    }
+
+    /// Returns the last error message.
+    #[inline]
+    pub fn last_error(&self) -> &str {
+        &self.last_error
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    /// Returns the update time.
+    #[inline]
+    pub fn updated_at(&self) -> DateTime {
+        self.updated_at
+    }
+
+    /// Returns `true` if the job is running with the same attempt as the other one,
+    /// which means that it has not been claimed by another worker since then.
+    #[inline]
+    pub fn is_claimed_as(&self, other: &JobRecord) -> bool {
+        self.status == JobStatus::Running && self.attempts == other.attempts
+    }
+
+    /// Returns `true` if the job can be claimed by a worker.
+    /// A running job whose lease has expired is considered to be abandoned.
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        self.status.is_unfinished() && self.run_at <= DateTime::now()
+    }
+}
+
+/// Storage backend for the queued jobs.
+pub trait JobStore: Send + Sync {
+    /// Pushes the job into the queue. It returns `false` if there is
+    /// an unfinished job with the same unique key in the queue.
+    fn push<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Claims at most `limit` due jobs in the queue by calling [`JobRecord::claim()`].
+    fn claim<'a>(
+        &'a self,
+        queue: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<JobRecord>, Error>>;
+
+    /// Renews the lease of the running job with the `run_at` time.
+    /// It returns `false` if the job has been claimed by another worker.
+    fn renew<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Saves the state of the job claimed by the worker.
+    /// It returns `false` if the job has been claimed by another worker.
+    fn save<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Moves the job claimed by the worker into the dead-letter storage.
+    /// It returns `false` if the job has been claimed by another worker.
+    fn bury<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Lists the dead jobs in the queue.
+    fn list_dead_jobs<'a>(&'a self, queue: &'a str)
+        -> BoxFuture<'a, Result<Vec<JobRecord>, Error>>;
+}
+
+/// In-memory job store, which is only suitable for tests or a single instance
+/// without the need of persistence.
+#[derive(Debug, Default)]
+pub struct MemoryJobStore {
+    /// Unfinished jobs.
+    jobs: RwLock<HashMap<Uuid, JobRecord>>,
+    /// Dead jobs.
+    dead_jobs: RwLock<Vec<JobRecord>>,
+}
+
+impl MemoryJobStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl JobStore for MemoryJobStore {
+    fn push<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut jobs = self.jobs.write();
+            if let Some(unique_key) = job.unique_key() {
+                let duplicated = jobs.values().any(|j| {
+                    j.queue == job.queue
+                        && j.unique_key() == Some(unique_key)
+                        && j.status.is_unfinished()
+                });
+                if duplicated {
+                    return Ok(false);
+                }
+            }
+            jobs.insert(job.id, job.clone());
+            Ok(true)
+        })
+    }
+
+    fn claim<'a>(
+        &'a self,
+        queue: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<JobRecord>, Error>> {
+        Box::pin(async move {
+            let mut jobs = self.jobs.write();
+            let mut due_jobs = jobs
+                .values_mut()
+                .filter(|job| job.queue == queue && job.is_due())
+                .collect::<Vec<_>>();
+            due_jobs.sort_by_key(|job| job.run_at);
+            let claimed_jobs = due_jobs
+                .into_iter()
+                .take(limit)
+                .map(|job| {
+                    job.claim();
+                    job.clone()
+                })
+                .collect();
+            Ok(claimed_jobs)
+        })
+    }
+
+    fn renew<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut jobs = self.jobs.write();
+            let Some(record) = jobs.get_mut(&job.id) else {
+                return Ok(false);
+            };
+            if !record.is_claimed_as(job) {
+                return Ok(false);
+            }
+            record.run_at = job.run_at;
+            record.updated_at = job.updated_at;
+            Ok(true)
+        })
+    }
+
+    fn save<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut jobs = self.jobs.write();
+            if !jobs
+                .get(&job.id)
+                .is_some_and(|record| record.is_claimed_as(job))
+            {
+                return Ok(false);
+            }
+            if job.status.is_unfinished() {
+                jobs.insert(job.id, job.clone());
+            } else {
+                jobs.remove(&job.id);
+            }
+            Ok(true)
+        })
+    }
+
+    fn bury<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut jobs = self.jobs.write();
+            if !jobs
+                .get(&job.id)
+                .is_some_and(|record| record.is_claimed_as(job))
+            {
+                return Ok(false);
+            }
+            jobs.remove(&job.id);
+            self.dead_jobs.write().push(job.clone());
+            Ok(true)
+        })
+    }
+
+    fn list_dead_jobs<'a>(
+        &'a self,
+        queue: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<JobRecord>, Error>> {
+        Box::pin(async move {
+            let dead_jobs = self
+                .dead_jobs
+                .read()
+                .iter()
+                .filter(|job| job.queue == queue)
+                .cloned()
+                .collect();
+            Ok(dead_jobs)
+        })
+    }
+}
+
+/// A function pointer of the job handler.
+type JobHandler = fn(Map) -> Result<BoxFuture<'static, Result<(), Error>>, Error>;
+
+/// Durable queue for background jobs.
+///
+/// Jobs are claimed from the [`JobStore`] by the workers with a lease, which is renewed
+/// while the job is running, so that a job abandoned by a crashed instance will be
+/// claimed again after the lease expires.
+/// Failed jobs are retried with an exponential backoff, and moved to the dead-letter storage
+/// after exhausting the attempts.
+///
+/// The shared queue should be registered by [`JobQueue::register()`], and then
+/// it can be run by `Application::run_with()` together with other schedulers.
+///
+/// # Examples
+///
+/// ```toml
+/// [job-queue]
+/// name = "default"
+/// concurrency = 8
+/// poll-interval = "1s"
+/// max-attempts = 5
+/// timeout = "5m"
+/// backoff-base = "10s"
+/// backoff-max = "1h"
+/// ```
+///
+/// ```rust,ignore
+/// use zino_core::schedule::{JobOptions, JobQueue, MemoryJobStore};
+///
+/// let config = State::shared().get_config("job-queue").unwrap_or_default();
+/// let queue = JobQueue::with_config(MemoryJobStore::new(), config)
+///     .with_handler::<SendEmail>();
+/// JobQueue::register(queue);
+///
+/// let options = JobOptions::new().unique_key("welcome:alice");
+/// JobQueue::shared().unwrap().enqueue(&email, options).await?;
+/// ```
+pub struct JobQueue {
+    /// Queue name.
+    name: String,
+    /// Job store.
+    store: Box<dyn JobStore>,
+    /// Job handlers.
+    handlers: HashMap<&'static str, JobHandler>,
+    /// Max number of jobs executed concurrently.
+    concurrency: usize,
+    /// Interval for polling the due jobs.
+    poll_interval: Duration,
+    /// Default max number of attempts.
+    max_attempts: u32,
+    /// Default timeout for each attempt.
+    timeout: Duration,
+    /// Base delay of the exponential backoff.
+    backoff_base: Duration,
+    /// Max delay of the exponential backoff.
+    backoff_max: Duration,
+    /// Jobs being executed by the scheduler.
+    running: Mutex<FuturesUnordered<BoxFuture<'static, ()>>>,
+}
+
+impl JobQueue {
+    /// Creates a new instance with the job store.
+    #[inline]
+    pub fn new(store: impl JobStore + 'static) -> Self {
+        Self {
+            name: "default".to_owned(),
+            store: Box::new(store),
+            handlers: HashMap::new(),
+            concurrency: 4,
+            poll_interval: Duration::from_secs(1),
+            max_attempts: 5,
+            timeout: Duration::from_secs(60 * 5),
+            backoff_base: Duration::from_secs(10),
+            backoff_max: Duration::from_secs(60 * 60),
+            running: Mutex::new(FuturesUnordered::new()),
+        }
+    }
+
+    /// Creates a new instance with the job store and the configuration.
+    pub fn with_config(store: impl JobStore + 'static, config: &Table) -> Self {
+        let mut queue = Self::new(store);
+        if let Some(name) = config.get_str("name") {
+            queue.name = name.to_owned();
+        }
+        if let Some(concurrency) = config.get_usize("concurrency") {
+            queue.concurrency = concurrency.max(1);
+        }
+        if let Some(poll_interval) = config.get_duration("poll-interval") {
+            queue.poll_interval = poll_interval;
+        }
+        if let Some(max_attempts) = config.get_u32("max-attempts") {
+            queue.max_attempts = max_attempts.max(1);
+        }
+        if let Some(timeout) = config.get_duration("timeout") {
+            queue.timeout = timeout;
+        }
+        if let Some(backoff_base) = config.get_duration("backoff-base") {
+            queue.backoff_base = backoff_base;
+        }
+        if let Some(backoff_max) = config.get_duration("backoff-max") {
+            queue.backoff_max = backoff_max;
+        }
+        queue
+    }
+
+    /// Registers a handler for the job.
+    #[inline]
+    pub fn with_handler<T: QueueJob>(mut self) -> Self {
+        self.handlers.insert(T::JOB_NAME, run_job::<T>);
+        self
+    }
+
+    /// Sets the max number of jobs executed concurrently.
+    #[inline]
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Registers the shared job queue. It returns `false` if the shared queue
+    /// has already been registered.
+    #[inline]
+    pub fn register(queue: Self) -> bool {
+        SHARED_JOB_QUEUE.set(queue).is_ok()
+    }
+
+    /// Returns a reference to the shared job queue if it has been registered.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_JOB_QUEUE.get()
+    }
+
+    /// Returns the queue name.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a reference to the job store.
+    #[inline]
+    pub fn store(&self) -> &dyn JobStore {
+        self.store.as_ref()
+    }
+
+    /// Prepares a record for the job without pushing it into the store.
+    /// It is useful for enqueueing the job inside of a database transaction.
+    pub fn prepare_job<T: QueueJob>(
+        &self,
+        job: &T,
+        options: JobOptions,
+    ) -> Result<JobRecord, Error> {
+        let JsonValue::Object(payload) = serde_json::to_value(job)? else {
+            bail!(
+                "the payload of the job `{}` should be an object",
+                T::JOB_NAME
+            );
+        };
+        let mut record = JobRecord::new(&self.name, T::JOB_NAME, payload);
+        record.max_attempts = options.max_attempts.unwrap_or(self.max_attempts).max(1);
+        record.timeout = options.timeout.unwrap_or(self.timeout);
+        if let Some(delay) = options.delay {
+            record.run_at += delay;
+        }
+        if let Some(unique_key) = options.unique_key {
+            record.unique_key = unique_key;
+        }
+        Ok(record)
+    }
+
+    /// Enqueues the job and returns the job ID.
+    /// It returns `None` if the job is deduplicated by the unique key.
+    pub async fn enqueue<T: QueueJob>(
+        &self,
+        job: &T,
+        options: JobOptions,
+    ) -> Result<Option<Uuid>, Error> {
+        let record = self.prepare_job(job, options)?;
+        if self.store.push(&record).await? {
+            Ok(Some(record.id))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Lists the dead jobs in the queue.
+    #[inline]
+    pub async fn list_dead_jobs(&self) -> Result<Vec<JobRecord>, Error> {
+        self.store.list_dead_jobs(&self.name).await
+    }
+
+    /// Claims the due jobs and executes them concurrently until all of them are finished.
+    pub async fn tick(&self) {
+        match self.store.claim(&self.name, self.concurrency).await {
+            Ok(jobs) => {
+                future::join_all(jobs.into_iter().map(|job| self.execute(job))).await;
+            }
+            Err(err) => tracing::error!(queue = self.name, "fail to claim the jobs: {err}"),
+        }
+    }
+
+    /// Claims the due jobs up to the free capacity, and drives the running jobs
+    /// for at most one poll interval. The unfinished jobs are kept for the next tick,
+    /// so that a slow job will not block other schedulers.
+    ///
+    /// Once the graceful shutdown has been started, no more jobs are claimed and
+    /// the running jobs are driven until they finish or the shutdown deadline elapses.
+    async fn run_pending(&'static self) {
+        let shutdown_token = ShutdownToken::shared();
+        let _guard = shutdown_token.track();
+        let mut running = self.running.lock().await;
+        let capacity = self.concurrency.saturating_sub(running.len());
+        if capacity > 0 && !shutdown_token.is_shutting_down() {
+            match self.store.claim(&self.name, capacity).await {
+                Ok(jobs) => {
+                    for job in jobs {
+                        running.push(Box::pin(self.execute(job)));
+                    }
+                }
+                Err(err) => tracing::error!(queue = self.name, "fail to claim the jobs: {err}"),
+            }
+        }
+
+        let mut delay = timer::sleep(self.poll_interval);
+        let mut draining = false;
+        while !running.is_empty() {
+            match future::select(running.next(), delay).await {
+                Either::Left((_, next_delay)) => delay = next_delay,
+                Either::Right(_) if !draining && shutdown_token.is_shutting_down() => {
+                    draining = true;
+                    delay = timer::sleep(shutdown_token.time_till_deadline());
+                }
+                Either::Right(_) => {
+                    if draining {
+                        tracing::warn!(
+                            queue = self.name,
+                            num_jobs = running.len(),
+                            "shutdown deadline elapsed with running jobs"
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Executes the claimed job and saves the result.
+    async fn execute(&self, mut job: JobRecord) {
+        let job_id = job.id.to_string();
+        if job.attempts > job.max_attempts {
+            job.bury("the job has been abandoned too many times".to_owned());
+        } else {
+            // The `attempts` identifies the claim, so it is not modified for a fatal error.
+            let mut retryable = true;
+            let result = match self.handlers.get(job.job_name.as_str()) {
+                Some(handler) => match handler(job.payload.clone()) {
+                    Ok(fut) => {
+                        let fut = Box::pin(run_with_timeout(fut, job.timeout));
+                        match self.run_with_lease(&job, fut).await {
+                            Some(result) => result,
+                            None => {
+                                tracing::warn!(
+                                    job_id,
+                                    job_name = job.job_name,
+                                    "the job is cancelled since it has been claimed by another worker"
+                                );
+                                return;
+                            }
+                        }
+                    }
+                    Err(err) => {
+                        retryable = false;
+                        Err(err)
+                    }
+                },
+                None => {
+                    retryable = false;
+                    Err(warn!(
+                        "no handler is registered for the job `{}`",
+                        job.job_name
+                    ))
+                }
+            };
+            match result {
+                Ok(()) => job.succeed(),
+                Err(err) if retryable && job.attempts < job.max_attempts => {
+                    let delay = self.backoff(job.attempts);
+                    tracing::warn!(
+                        job_id,
+                        job_name = job.job_name,
+                        attempts = job.attempts,
+                        "fail to execute the job, retry in {delay:?}: {err}"
+                    );
+                    job.retry(delay, err.message().to_owned());
+                }
+                Err(err) => {
+                    tracing::error!(
+                        job_id,
+                        job_name = job.job_name,
+                        attempts = job.attempts,
+                        "fail to execute the job: {err}"
+                    );
+                    job.bury(err.message().to_owned());
+                }
+            }
+        }
+
+        let result = if job.status == JobStatus::Dead {
+            self.store.bury(&job).await
+        } else {
+            self.store.save(&job).await
+        };
+        match result {
+            Ok(true) => (),
+            Ok(false) => tracing::warn!(
+                job_id,
+                "the result is discarded since the job has been claimed by another worker"
+            ),
+            Err(err) => tracing::error!(job_id, "fail to save the job: {err}"),
+        }
+    }
+
+    /// Runs the job and renews its lease periodically, so that a long-running job
+    /// will not be reclaimed by other workers. The job is cancelled and `None` is returned
+    /// if the lease has been lost.
+    async fn run_with_lease(
+        &self,
+        job: &JobRecord,
+        mut fut: BoxFuture<'_, Result<(), Error>>,
+    ) -> Option<Result<(), Error>> {
+        let mut lease = job.clone();
+        loop {
+            match future::select(fut, timer::sleep(JOB_LEASE_DURATION / 3)).await {
+                Either::Left((result, _)) => return Some(result),
+                Either::Right((_, next_fut)) => {
+                    fut = next_fut;
+                    lease.renew();
+                    match self.store.renew(&lease).await {
+                        Ok(true) => (),
+                        Ok(false) => return None,
+                        Err(err) => tracing::error!(
+                            job_id = lease.id.to_string(),
+                            "fail to renew the lease of the job: {err}"
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the delay of the exponential backoff for the attempts.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+impl fmt::Debug for JobQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobQueue")
+            .field("name", &self.name)
+            .field("concurrency", &self.concurrency)
+            .field("max_attempts", &self.max_attempts)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncScheduler for &'static JobQueue {
+    #[inline]
+    fn is_ready(&self) -> bool {
+        !self.handlers.is_empty()
+    }
+
+    fn time_till_next_job(&self) -> Duration {
+        let has_running_jobs = self
+            .running
+            .try_lock()
+            .map_or(true, |running| !running.is_empty());
+        if has_running_jobs {
+            Duration::ZERO
+        } else {
+            self.poll_interval
+        }
+    }
+
+    #[inline]
+    async fn tick(&mut self) {
+        self.run_pending().await;
+    }
+}
+
+/// Shared job queue.
+static SHARED_JOB_QUEUE: OnceLock<JobQueue> = OnceLock::new();
+
+/// Lease duration of a running job.
+const JOB_LEASE_DURATION: Duration = Duration::from_secs(60);
+
+/// Deserializes the payload and runs the job.
+fn run_job<T: QueueJob>(payload: Map) -> Result<BoxFuture<'static, Result<(), Error>>, Error> {
+    let job = serde_json::from_value::<T>(payload.into())?;
+    Ok(job.run())
+}
+
+/// Runs the future with a timeout. It does not rely on the timer of any async runtime.
+async fn run_with_timeout(
+    fut: BoxFuture<'static, Result<(), Error>>,
+    timeout: Duration,
+) -> Result<(), Error> {
+    if timeout.is_zero() {
+        return fut.await;
+    }
+    match future::select(fut, timer::sleep(timeout)).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(warn!("the job has timed out after {:?}", timeout)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JobOptions, JobQueue, JobStatus, MemoryJobStore, QueueJob};
+    use crate::{
+        bail,
+        error::Error,
+        schedule::{timer, AsyncScheduler},
+        BoxFuture,
+    };
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, Instant};
+
+    #[derive(Serialize, Deserialize)]
+    struct Resize {
+        width: u32,
+    }
+
+    impl QueueJob for Resize {
+        const JOB_NAME: &'static str = "resize";
+
+        fn run(self) -> BoxFuture<'static, Result<(), Error>> {
+            Box::pin(async move {
+                if self.width == 0 {
+                    bail!("the width should be positive");
+                }
+                Ok(())
+            })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Transcode {
+        seconds: u64,
+    }
+
+    impl QueueJob for Transcode {
+        const JOB_NAME: &'static str = "transcode";
+
+        fn run(self) -> BoxFuture<'static, Result<(), Error>> {
+            Box::pin(async move {
+                timer::sleep(Duration::from_secs(self.seconds)).await;
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn it_retries_and_buries_jobs() {
+        futures::executor::block_on(async {
+            let queue = JobQueue::new(MemoryJobStore::new())
+                .with_handler::<Resize>()
+                .with_concurrency(2);
+            let options = JobOptions::new().unique_key("resize:1");
+            assert!(queue
+                .enqueue(&Resize { width: 100 }, options.clone())
+                .await
+                .unwrap()
+                .is_some());
+            assert!(queue
+                .enqueue(&Resize { width: 100 }, options)
+                .await
+                .unwrap()
+                .is_none());
+
+            let options = JobOptions::new().max_attempts(2);
+            queue.enqueue(&Resize { width: 0 }, options).await.unwrap();
+            let options = JobOptions::new().delay(Duration::from_secs(60));
+            queue.enqueue(&Resize { width: 50 }, options).await.unwrap();
+
+            queue.tick().await;
+            assert!(queue.store().claim("default", 10).await.unwrap().is_empty());
+            assert!(queue.list_dead_jobs().await.unwrap().is_empty());
+
+            let options = JobOptions::new().max_attempts(1);
+            let job = queue.prepare_job(&Resize { width: 0 }, options).unwrap();
+            queue.store().push(&job).await.unwrap();
+            let mut claimed_jobs = queue.store().claim("default", 1).await.unwrap();
+            queue.execute(claimed_jobs.remove(0)).await;
+
+            let dead_jobs = queue.list_dead_jobs().await.unwrap();
+            assert_eq!(dead_jobs.len(), 1);
+            assert_eq!(dead_jobs[0].status(), JobStatus::Dead);
+            assert_eq!(dead_jobs[0].last_error(), "the width should be positive");
+        });
+        assert_eq!(
+            JobQueue::new(MemoryJobStore::new()).backoff(3),
+            Duration::from_secs(40)
+        );
+    }
+
+    #[test]
+    fn it_keeps_slow_jobs_running_across_ticks() {
+        futures::executor::block_on(async {
+            let queue = JobQueue::new(MemoryJobStore::new()).with_handler::<Transcode>();
+            let mut queue: &'static JobQueue = Box::leak(Box::new(queue));
+            let options = JobOptions::new().timeout(Duration::ZERO);
+            queue
+                .enqueue(&Transcode { seconds: 30 }, options)
+                .await
+                .unwrap();
+
+            let start = Instant::now();
+            AsyncScheduler::tick(&mut queue).await;
+            assert!(start.elapsed() < Duration::from_secs(10));
+            assert_eq!(queue.time_till_next_job(), Duration::ZERO);
+            assert!(queue.store().claim("default", 1).await.unwrap().is_empty());
+
+            let job = queue
+                .prepare_job(&Transcode { seconds: 1 }, JobOptions::new())
+                .unwrap();
+            queue.store().push(&job).await.unwrap();
+            let claimed_jobs = queue.store().claim("default", 1).await.unwrap();
+            assert!(queue.store().renew(&claimed_jobs[0]).await.unwrap());
+            assert!(!queue.store().renew(&job).await.unwrap());
+            assert!(!queue.store().save(&job).await.unwrap());
+            assert!(!queue.store().bury(&job).await.unwrap());
+            assert!(queue.store().save(&claimed_jobs[0]).await.unwrap());
+        });
+    }
+}
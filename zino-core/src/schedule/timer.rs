@@ -0,0 +1,179 @@
+//! A runtime-agnostic timer driven by a shared thread.
+
+use crate::LazyLock;
+use parking_lot::{Condvar, Mutex};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering::Relaxed},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Returns a future which completes after the duration.
+/// It does not rely on the timer of any async runtime, and all the delays
+/// share a single thread instead of spawning a thread for each one.
+pub(super) fn sleep(duration: Duration) -> Delay {
+    Delay {
+        deadline: Instant::now() + duration,
+        state: None,
+    }
+}
+
+/// A future which completes at the deadline.
+#[derive(Debug)]
+pub(super) struct Delay {
+    /// Deadline.
+    deadline: Instant,
+    /// Shared state with the timer thread, which is registered at the first poll.
+    state: Option<Arc<DelayState>>,
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        match &self.state {
+            Some(state) => {
+                if state.fired.load(Relaxed) {
+                    return Poll::Ready(());
+                }
+                *state.waker.lock() = Some(cx.waker().clone());
+                if state.fired.load(Relaxed) {
+                    return Poll::Ready(());
+                }
+            }
+            None => {
+                let state = Arc::new(DelayState {
+                    fired: AtomicBool::new(false),
+                    waker: Mutex::new(Some(cx.waker().clone())),
+                });
+                TIMER.register(self.deadline, state.clone());
+                self.state = Some(state);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// State of a delay shared with the timer thread.
+#[derive(Debug)]
+struct DelayState {
+    /// A flag to indicate whether the deadline has been reached.
+    fired: AtomicBool,
+    /// Waker of the task.
+    waker: Mutex<Option<Waker>>,
+}
+
+/// An entry in the timer heap, which is ordered by the deadline in reverse.
+struct TimerEntry {
+    /// Deadline.
+    deadline: Instant,
+    /// Shared state of the delay.
+    state: Arc<DelayState>,
+}
+
+impl PartialEq for TimerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for TimerEntry {}
+
+impl PartialOrd for TimerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TimerEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+/// Timer with a heap of pending delays.
+struct Timer {
+    /// Pending delays.
+    entries: Mutex<BinaryHeap<TimerEntry>>,
+    /// Condition variable to notify the timer thread.
+    condvar: Condvar,
+}
+
+impl Timer {
+    /// Creates a new instance.
+    fn new() -> Self {
+        Self {
+            entries: Mutex::new(BinaryHeap::new()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    /// Registers a delay with the deadline.
+    fn register(&self, deadline: Instant, state: Arc<DelayState>) {
+        self.entries.lock().push(TimerEntry { deadline, state });
+        self.condvar.notify_one();
+    }
+
+    /// Wakes the delays whose deadlines have been reached, and parks the thread
+    /// until the next deadline or a new registration.
+    fn run(&self) {
+        let mut entries = self.entries.lock();
+        loop {
+            let now = Instant::now();
+            while entries.peek().is_some_and(|entry| entry.deadline <= now) {
+                if let Some(entry) = entries.pop() {
+                    entry.state.fired.store(true, Relaxed);
+                    if let Some(waker) = entry.state.waker.lock().take() {
+                        waker.wake();
+                    }
+                }
+            }
+            match entries.peek() {
+                Some(entry) => {
+                    let deadline = entry.deadline;
+                    self.condvar.wait_until(&mut entries, deadline);
+                }
+                None => self.condvar.wait(&mut entries),
+            }
+        }
+    }
+}
+
+/// Shared timer.
+static TIMER: LazyLock<&'static Timer> = LazyLock::new(|| {
+    let timer: &'static Timer = Box::leak(Box::new(Timer::new()));
+    thread::Builder::new()
+        .name("zino-timer".to_owned())
+        .spawn(|| timer.run())
+        .expect("fail to spawn the timer thread");
+    timer
+});
+
+#[cfg(test)]
+mod tests {
+    use super::sleep;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn it_sleeps_on_the_shared_timer() {
+        futures::executor::block_on(async {
+            let start = Instant::now();
+            futures::join!(
+                sleep(Duration::from_millis(50)),
+                sleep(Duration::from_millis(20))
+            );
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        });
+    }
+}
@@ -0,0 +1,464 @@
+//! Triggers with time zones, jitters and business-day calendars for the scheduled jobs.
+
+use crate::{
+    bail,
+    datetime::{self, Date, DateTime},
+    error::Error,
+    extension::TomlTableExt,
+    warn, BoxFuture,
+};
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+use chrono_tz::Tz;
+use cron::Schedule;
+use parking_lot::RwLock;
+use std::{collections::BTreeSet, collections::HashMap, fmt, iter, str::FromStr, time::Duration};
+use toml::Table;
+
+/// Maximum number of runs for the missed ticks.
+pub(super) const MAX_MISFIRE_RUNS: usize = 100;
+
+/// Policy for the ticks missed since the persisted last tick, e.g. after a downtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MisfirePolicy {
+    /// Skips all the missed ticks.
+    Skip,
+    /// Runs the job once for the missed ticks.
+    #[default]
+    RunOnce,
+    /// Runs the job for each of the missed ticks, up to 100 runs.
+    RunAll,
+}
+
+impl MisfirePolicy {
+    /// Returns the policy as a str.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::RunOnce => "run_once",
+            Self::RunAll => "run_all",
+        }
+    }
+
+    /// Returns the number of runs for the missed ticks.
+    #[inline]
+    pub(super) fn num_runs(&self, missed_ticks: usize) -> usize {
+        match self {
+            Self::Skip => 0,
+            Self::RunOnce => missed_ticks.min(1),
+            Self::RunAll => missed_ticks.min(MAX_MISFIRE_RUNS),
+        }
+    }
+}
+
+impl fmt::Display for MisfirePolicy {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for MisfirePolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "run_once" => Ok(Self::RunOnce),
+            "run_all" => Ok(Self::RunAll),
+            _ => bail!("invalid misfire policy `{}`", s),
+        }
+    }
+}
+
+/// A calendar of business days, which consists of the working weekdays
+/// excluding the holidays.
+///
+/// # Examples
+///
+/// ```toml
+/// [calendar]
+/// weekdays = ["Mon", "Tue", "Wed", "Thu", "Fri"]
+/// holidays = ["2025-01-01", "2025-12-25"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessCalendar {
+    /// Working weekdays.
+    weekdays: Vec<Weekday>,
+    /// Holidays.
+    holidays: BTreeSet<Date>,
+}
+
+impl BusinessCalendar {
+    /// Creates a new instance with the working weekdays from Monday to Friday.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            weekdays: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// Attempts to construct an instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let mut calendar = Self::new();
+        if let Some(weekdays) = config.get_str_array("weekdays") {
+            let weekdays = weekdays
+                .into_iter()
+                .map(|weekday| {
+                    weekday
+                        .parse::<Weekday>()
+                        .map_err(|_| warn!("invalid weekday `{}`", weekday))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            calendar = calendar.with_weekdays(weekdays)?;
+        }
+        if let Some(holidays) = config.get_str_array("holidays") {
+            for holiday in holidays {
+                calendar.add_holiday(holiday.parse()?);
+            }
+        }
+        Ok(calendar)
+    }
+
+    /// Sets the working weekdays.
+    pub fn with_weekdays(mut self, weekdays: Vec<Weekday>) -> Result<Self, Error> {
+        if weekdays.is_empty() {
+            bail!("there should be at least one working weekday");
+        }
+        self.weekdays = weekdays;
+        Ok(self)
+    }
+
+    /// Adds a holiday.
+    #[inline]
+    pub fn add_holiday(&mut self, date: Date) {
+        self.holidays.insert(date);
+    }
+
+    /// Returns `true` if the date is a business day.
+    #[inline]
+    pub fn is_business_day(&self, date: Date) -> bool {
+        let weekday = NaiveDate::from(date).weekday();
+        self.weekdays.contains(&weekday) && !self.holidays.contains(&date)
+    }
+}
+
+impl Default for BusinessCalendar {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Kind of the trigger.
+#[derive(Debug, Clone)]
+enum TriggerKind {
+    /// Cron schedule.
+    Cron(Box<Schedule>),
+    /// Fixed interval.
+    Interval(Duration),
+}
+
+/// A trigger which determines the fire times of a scheduled job.
+///
+/// It can be parsed from a cron expression or an interval such as `@every 90s`.
+/// The cron expression is evaluated in the time zone of the trigger, which defaults to
+/// the local time zone. The fire times on non-business days of the calendar are skipped.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    /// Trigger kind.
+    kind: TriggerKind,
+    /// IANA time zone.
+    timezone: Option<Tz>,
+    /// Maximum random delay of the execution.
+    jitter: Duration,
+    /// Business-day calendar.
+    calendar: Option<BusinessCalendar>,
+}
+
+impl Trigger {
+    /// Creates a new instance with the cron schedule.
+    #[inline]
+    pub fn with_schedule(schedule: Schedule) -> Self {
+        Self {
+            kind: TriggerKind::Cron(Box::new(schedule)),
+            timezone: None,
+            jitter: Duration::ZERO,
+            calendar: None,
+        }
+    }
+
+    /// Creates a new instance with the interval.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the interval is zero.
+    #[inline]
+    pub fn with_interval(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "the interval should be positive");
+        Self {
+            kind: TriggerKind::Interval(interval),
+            timezone: None,
+            jitter: Duration::ZERO,
+            calendar: None,
+        }
+    }
+
+    /// Attempts to construct an instance with the configuration.
+    pub fn try_from_config(config: &Table) -> Result<Self, Error> {
+        let Some(cron_expr) = config.get_str("cron") else {
+            bail!("the `cron` field should be specified");
+        };
+        let mut trigger = cron_expr.parse::<Self>()?;
+        if let Some(timezone) = config.get_str("timezone") {
+            trigger = trigger.timezone(timezone)?;
+        }
+        if let Some(jitter) = config.get_duration("jitter") {
+            trigger = trigger.jitter(jitter);
+        }
+        if let Some(calendar) = config.get_table("calendar") {
+            trigger = trigger.calendar(BusinessCalendar::try_from_config(calendar)?);
+        }
+        Ok(trigger)
+    }
+
+    /// Sets the IANA time zone, such as `Asia/Shanghai`.
+    pub fn timezone(mut self, timezone: &str) -> Result<Self, Error> {
+        let tz = timezone
+            .parse::<Tz>()
+            .map_err(|err| warn!("invalid time zone `{}`: {}", timezone, err))?;
+        self.timezone = Some(tz);
+        Ok(self)
+    }
+
+    /// Sets the maximum random delay of the execution to avoid the thundering herds.
+    #[inline]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Sets the business-day calendar.
+    #[inline]
+    pub fn calendar(mut self, calendar: BusinessCalendar) -> Self {
+        self.calendar = Some(calendar);
+        self
+    }
+
+    /// Replaces the cron schedule or the interval with those of another trigger,
+    /// while the time zone, the jitter and the calendar are retained.
+    #[inline]
+    pub(super) fn replace_schedule(&mut self, trigger: Trigger) {
+        self.kind = trigger.kind;
+    }
+
+    /// Returns the time zone name.
+    #[inline]
+    pub fn timezone_name(&self) -> Option<&'static str> {
+        self.timezone.map(|tz| tz.name())
+    }
+
+    /// Returns the maximum random delay of the execution.
+    #[inline]
+    pub fn max_jitter(&self) -> Duration {
+        self.jitter
+    }
+
+    /// Returns a random delay no longer than the jitter.
+    pub fn random_delay(&self) -> Duration {
+        if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            self.jitter.mul_f64(rand::random::<f64>())
+        }
+    }
+
+    /// Returns an iterator over the fire times after the specific time.
+    pub fn fire_times_after(&self, after: DateTime) -> Box<dyn Iterator<Item = DateTime> + '_> {
+        let fire_times: Box<dyn Iterator<Item = DateTime> + '_> = match &self.kind {
+            TriggerKind::Cron(schedule) => {
+                let after = chrono::DateTime::<Local>::from(after);
+                if let Some(tz) = self.timezone {
+                    let fire_times = schedule
+                        .after(&after.with_timezone(&tz))
+                        .map(|dt| DateTime::from(dt.with_timezone(&Local)));
+                    Box::new(fire_times)
+                } else {
+                    Box::new(schedule.after(&after).map(DateTime::from))
+                }
+            }
+            TriggerKind::Interval(interval) => {
+                let interval = *interval;
+                Box::new(iter::successors(Some(after + interval), move |&dt| {
+                    Some(dt + interval)
+                }))
+            }
+        };
+        if self.calendar.is_some() {
+            Box::new(fire_times.filter(|&dt| self.is_business_time(dt)))
+        } else {
+            fire_times
+        }
+    }
+
+    /// Returns the next fire time after the specific time.
+    #[inline]
+    pub fn next_after(&self, after: DateTime) -> Option<DateTime> {
+        self.fire_times_after(after).next()
+    }
+
+    /// Returns the number of fire times in the range `(after, until]`, which is at most `limit`.
+    #[inline]
+    pub fn count_between(&self, after: DateTime, until: DateTime, limit: usize) -> usize {
+        self.fire_times_after(after)
+            .take_while(|&dt| dt <= until)
+            .take(limit)
+            .count()
+    }
+
+    /// Previews the next `n` fire times from now, without the jitter.
+    #[inline]
+    pub fn preview(&self, n: usize) -> Vec<DateTime> {
+        self.fire_times_after(DateTime::now()).take(n).collect()
+    }
+
+    /// Returns `true` if the time is on a business day in the time zone of the trigger.
+    fn is_business_time(&self, dt: DateTime) -> bool {
+        let Some(calendar) = self.calendar.as_ref() else {
+            return true;
+        };
+        let dt = chrono::DateTime::<Local>::from(dt);
+        let date = if let Some(tz) = self.timezone {
+            dt.with_timezone(&tz).date_naive()
+        } else {
+            dt.date_naive()
+        };
+        calendar.is_business_day(date.into())
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(interval) = s.strip_prefix("@every ") {
+            let interval = datetime::parse_duration(interval.trim())
+                .map_err(|err| warn!("invalid interval `{}`: {}", interval, err))?;
+            if interval.is_zero() {
+                bail!("the interval should be positive");
+            }
+            Ok(Self::with_interval(interval))
+        } else {
+            let schedule = Schedule::from_str(s)
+                .map_err(|err| warn!("invalid cron expression `{}`: {}", s, err))?;
+            Ok(Self::with_schedule(schedule))
+        }
+    }
+}
+
+/// An interface for persisting the last ticks of the scheduled jobs,
+/// so that the missed ticks can be handled by the [`MisfirePolicy`] after a restart.
+pub trait TickStore: Send + Sync {
+    /// Loads the last tick of the job.
+    fn load_tick<'a>(&'a self, job_name: &'a str)
+        -> BoxFuture<'a, Result<Option<DateTime>, Error>>;
+
+    /// Saves the last tick of the job.
+    fn save_tick<'a>(
+        &'a self,
+        job_name: &'a str,
+        last_tick: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// In-memory tick store, which is only suitable for tests.
+#[derive(Debug, Default)]
+pub struct MemoryTickStore {
+    /// Last ticks of the jobs.
+    ticks: RwLock<HashMap<String, DateTime>>,
+}
+
+impl MemoryTickStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TickStore for MemoryTickStore {
+    fn load_tick<'a>(
+        &'a self,
+        job_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<DateTime>, Error>> {
+        Box::pin(async move { Ok(self.ticks.read().get(job_name).copied()) })
+    }
+
+    fn save_tick<'a>(
+        &'a self,
+        job_name: &'a str,
+        last_tick: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.ticks.write().insert(job_name.to_owned(), last_tick);
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BusinessCalendar, MisfirePolicy, Trigger};
+    use crate::datetime::DateTime;
+    use std::time::Duration;
+
+    #[test]
+    fn it_computes_fire_times() {
+        let start = "2024-12-20T08:00:00Z".parse::<DateTime>().unwrap();
+        let trigger = "@every 90s".parse::<Trigger>().unwrap();
+        assert_eq!(
+            trigger.next_after(start),
+            Some(start + Duration::from_secs(90))
+        );
+        assert_eq!(
+            trigger.count_between(start, start + Duration::from_secs(600), 100),
+            6
+        );
+        assert_eq!(
+            trigger.count_between(start, start + Duration::from_secs(86400 * 365), 100),
+            100
+        );
+
+        // Every day at 9 o'clock in Shanghai, which is 01:00 in UTC.
+        let trigger = "0 0 9 * * *"
+            .parse::<Trigger>()
+            .unwrap()
+            .timezone("Asia/Shanghai")
+            .unwrap();
+        let expected = "2024-12-21T01:00:00Z".parse::<DateTime>().unwrap();
+        assert_eq!(trigger.next_after(start), Some(expected));
+
+        // Friday 2024-12-20 is a business day, and the weekend is skipped.
+        let mut calendar = BusinessCalendar::new();
+        calendar.add_holiday("2024-12-23".parse().unwrap());
+        let trigger = trigger.calendar(calendar);
+        let expected = "2024-12-24T01:00:00Z".parse::<DateTime>().unwrap();
+        assert_eq!(trigger.next_after(start), Some(expected));
+        assert!(trigger.timezone("Mars/Olympus").is_err());
+
+        assert_eq!(MisfirePolicy::Skip.num_runs(3), 0);
+        assert_eq!(MisfirePolicy::RunOnce.num_runs(3), 1);
+        assert_eq!("run_all".parse::<MisfirePolicy>().unwrap().num_runs(3), 3);
+        assert_eq!(MisfirePolicy::RunAll.num_runs(1000), 100);
+        assert_eq!(MisfirePolicy::default(), MisfirePolicy::RunOnce);
+        assert!("@every 0s".parse::<Trigger>().is_err());
+    }
+}
@@ -0,0 +1,1338 @@
+//! Workflow engine for multi-step processes with compensations.
+
+use super::AsyncScheduler;
+use crate::{
+    bail,
+    channel::CloudEvent,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    warn, BoxFuture, Map, Uuid,
+};
+use futures::future;
+use parking_lot::RwLock;
+use std::{collections::HashMap, fmt, mem, str::FromStr, sync::OnceLock, time::Duration};
+use toml::Table;
+
+/// Action of a workflow step.
+pub type StepAction = fn(Map) -> BoxFuture<'static, Result<StepOutcome, Error>>;
+
+/// Compensation of a workflow step.
+pub type StepCompensation = fn(Map) -> BoxFuture<'static, Result<(), Error>>;
+
+/// Outcome of a workflow step.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum StepOutcome {
+    /// Merges the data into the run, and proceeds to the next step immediately.
+    Next(Map),
+    /// Merges the data into the run, and proceeds to the next step after the duration.
+    Sleep(Map, Duration),
+    /// Merges the data into the run, and proceeds to the next step after receiving
+    /// a signal with the event type. If the signal is not received within the timeout,
+    /// the step will be regarded as failed.
+    Wait(Map, String, Option<Duration>),
+}
+
+impl StepOutcome {
+    /// Proceeds to the next step without any data.
+    #[inline]
+    pub fn next() -> Self {
+        Self::Next(Map::new())
+    }
+
+    /// Proceeds to the next step after the duration without any data.
+    #[inline]
+    pub fn sleep(duration: Duration) -> Self {
+        Self::Sleep(Map::new(), duration)
+    }
+
+    /// Waits for the signal without any data.
+    #[inline]
+    pub fn wait(signal: impl ToString, timeout: Option<Duration>) -> Self {
+        Self::Wait(Map::new(), signal.to_string(), timeout)
+    }
+}
+
+/// A step of the workflow.
+#[derive(Debug, Clone)]
+struct WorkflowStep {
+    /// Step name.
+    name: &'static str,
+    /// Step action.
+    action: StepAction,
+    /// Step compensation.
+    compensation: Option<StepCompensation>,
+}
+
+/// Definition of a workflow, which consists of the steps executed in order.
+/// If a step fails, the compensations of the completed steps are executed in reverse order.
+///
+/// Each step may be executed more than once when the process crashes after the step
+/// has been finished but before the state has been persisted, so the actions and
+/// compensations should be idempotent.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use std::time::Duration;
+/// use zino_core::{error::Error, schedule::{StepOutcome, Workflow}, BoxFuture, Map};
+///
+/// fn reserve_stock(data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+///     Box::pin(async move {
+///         let reservation_id = inventory::reserve(&data).await?;
+///         Ok(StepOutcome::Next(Map::from_entry("reservation_id", reservation_id)))
+///     })
+/// }
+///
+/// fn request_payment(data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+///     Box::pin(async move {
+///         payment::request(&data).await?;
+///         Ok(StepOutcome::wait("payment.succeeded", Some(Duration::from_secs(30 * 60))))
+///     })
+/// }
+///
+/// let workflow = Workflow::new("order")
+///     .step("reserve_stock", reserve_stock)
+///     .compensate(release_stock)
+///     .step("request_payment", request_payment)
+///     .compensate(refund_payment)
+///     .step("ship", ship_order);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Workflow {
+    /// Workflow name.
+    name: &'static str,
+    /// Workflow steps.
+    steps: Vec<WorkflowStep>,
+}
+
+impl Workflow {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Appends a step.
+    #[inline]
+    pub fn step(mut self, name: &'static str, action: StepAction) -> Self {
+        self.steps.push(WorkflowStep {
+            name,
+            action,
+            compensation: None,
+        });
+        self
+    }
+
+    /// Sets the compensation for the last step.
+    ///
+    /// # Panics
+    ///
+    /// It will panic if there is no step.
+    #[inline]
+    pub fn compensate(mut self, compensation: StepCompensation) -> Self {
+        let step = self
+            .steps
+            .last_mut()
+            .expect("the compensation should be set after a step");
+        step.compensation = Some(compensation);
+        self
+    }
+
+    /// Returns the workflow name.
+    #[inline]
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the step names.
+    #[inline]
+    pub fn step_names(&self) -> Vec<&'static str> {
+        self.steps.iter().map(|step| step.name).collect()
+    }
+}
+
+/// Status of a workflow run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WorkflowStatus {
+    /// The run is executing the steps or sleeping.
+    #[default]
+    Running,
+    /// The run is waiting for a signal.
+    Waiting,
+    /// The run is executing the compensations.
+    Compensating,
+    /// All the steps have been completed.
+    Completed,
+    /// All the compensations have been completed after a step failed.
+    Compensated,
+    /// A compensation has failed and the run requires a manual intervention.
+    Failed,
+}
+
+impl WorkflowStatus {
+    /// Returns the status as a str.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Running => "Running",
+            Self::Waiting => "Waiting",
+            Self::Compensating => "Compensating",
+            Self::Completed => "Completed",
+            Self::Compensated => "Compensated",
+            Self::Failed => "Failed",
+        }
+    }
+
+    /// Returns `true` if the run has been finished.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Compensated | Self::Failed)
+    }
+}
+
+impl fmt::Display for WorkflowStatus {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for WorkflowStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Running" => Ok(Self::Running),
+            "Waiting" => Ok(Self::Waiting),
+            "Compensating" => Ok(Self::Compensating),
+            "Completed" => Ok(Self::Completed),
+            "Compensated" => Ok(Self::Compensated),
+            "Failed" => Ok(Self::Failed),
+            _ => bail!("invalid workflow status `{}`", s),
+        }
+    }
+}
+
+/// Record of a workflow run.
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowRun {
+    /// Run ID.
+    id: Uuid,
+    /// Workflow name.
+    workflow: String,
+    /// Correlation key for matching the signals.
+    correlation_key: String,
+    /// Run status.
+    status: WorkflowStatus,
+    /// Index of the current step.
+    current_step: usize,
+    /// Run data.
+    data: Map,
+    /// Event type of the signal being waited for.
+    signal: String,
+    /// Number of attempts of the current step or compensation.
+    attempts: u32,
+    /// Time when the run is scheduled to proceed, when the signal times out,
+    /// or when the lease of a claimed run expires.
+    wake_at: Option<DateTime>,
+    /// Last error message.
+    last_error: String,
+    /// Creation time.
+    created_at: DateTime,
+    /// Update time.
+    updated_at: DateTime,
+    /// Version for the optimistic locking.
+    version: u64,
+}
+
+impl WorkflowRun {
+    /// Creates a new instance.
+    pub fn new(workflow: impl ToString, correlation_key: impl ToString, data: Map) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: Uuid::now_v7(),
+            workflow: workflow.to_string(),
+            correlation_key: correlation_key.to_string(),
+            data,
+            wake_at: Some(now),
+            created_at: now,
+            updated_at: now,
+            ..Self::default()
+        }
+    }
+
+    /// Attempts to construct an instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(id) = map.get_uuid("id") else {
+            bail!("the `id` field should be a UUID");
+        };
+        let Some(workflow) = map.get_str("workflow") else {
+            bail!("the `workflow` field should be specified");
+        };
+        let wake_at = match map.get_str("wake_at") {
+            Some(wake_at) => Some(wake_at.parse()?),
+            None => None,
+        };
+        let created_at = map
+            .get_str("created_at")
+            .ok_or_else(|| warn!("the `created_at` field should be specified"))?;
+        let updated_at = map
+            .get_str("updated_at")
+            .ok_or_else(|| warn!("the `updated_at` field should be specified"))?;
+        Ok(Self {
+            id,
+            workflow: workflow.to_owned(),
+            correlation_key: map
+                .get_str("correlation_key")
+                .unwrap_or_default()
+                .to_owned(),
+            status: map.get_str("status").unwrap_or_default().parse()?,
+            current_step: map.get_usize("current_step").unwrap_or_default(),
+            data: map.get_object("data").cloned().unwrap_or_default(),
+            signal: map.get_str("signal").unwrap_or_default().to_owned(),
+            attempts: map.get_u32("attempts").unwrap_or_default(),
+            wake_at,
+            last_error: map.get_str("last_error").unwrap_or_default().to_owned(),
+            created_at: created_at.parse()?,
+            updated_at: updated_at.parse()?,
+            version: map.get_u64("version").unwrap_or_default(),
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("workflow", self.workflow);
+        map.upsert("correlation_key", self.correlation_key);
+        map.upsert("status", self.status.as_str());
+        map.upsert("current_step", self.current_step);
+        map.upsert("data", self.data);
+        map.upsert("signal", self.signal);
+        map.upsert("attempts", self.attempts);
+        map.upsert("wake_at", self.wake_at.map(|dt| dt.to_string()));
+        map.upsert("last_error", self.last_error);
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        map.upsert("version", self.version);
+        map
+    }
+
+    /// Claims the run for the lease timeout, which is at least one minute,
+    /// so that it will not be executed by another worker. It is intended to be called
+    /// by the workflow stores, which should persist the run with the bumped version.
+    ///
+    /// If the run is waiting for a signal, the signal is cleared so that it can not be
+    /// resumed by a late signal, and the timeout is recorded as the last error.
+    pub fn claim(&mut self, lease_timeout: Duration) {
+        let now = DateTime::now();
+        if self.status == WorkflowStatus::Waiting && !self.signal.is_empty() {
+            self.last_error = format!("the signal `{}` has timed out", self.signal);
+            self.signal.clear();
+        }
+        self.wake_at = Some(now + lease_timeout.max(Duration::from_secs(60)));
+        self.updated_at = now;
+        self.version += 1;
+    }
+
+    /// Returns the run ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the workflow name.
+    #[inline]
+    pub fn workflow(&self) -> &str {
+        &self.workflow
+    }
+
+    /// Returns the correlation key.
+    #[inline]
+    pub fn correlation_key(&self) -> &str {
+        &self.correlation_key
+    }
+
+    /// Returns the run status.
+    #[inline]
+    pub fn status(&self) -> WorkflowStatus {
+        self.status
+    }
+
+    /// Returns the index of the current step.
+    #[inline]
+    pub fn current_step(&self) -> usize {
+        self.current_step
+    }
+
+    /// Returns a reference to the run data.
+    #[inline]
+    pub fn data(&self) -> &Map {
+        &self.data
+    }
+
+    /// Returns the event type of the signal being waited for.
+    #[inline]
+    pub fn signal(&self) -> Option<&str> {
+        Some(self.signal.as_str()).filter(|s| !s.is_empty())
+    }
+
+    /// Returns the number of attempts of the current step or compensation.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the time when the run is scheduled to proceed.
+    #[inline]
+    pub fn wake_at(&self) -> Option<DateTime> {
+        self.wake_at
+    }
+
+    /// Returns the last error message.
+    #[inline]
+    pub fn last_error(&self) -> &str {
+        &self.last_error
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+
+    /// Returns the update time.
+    #[inline]
+    pub fn updated_at(&self) -> DateTime {
+        self.updated_at
+    }
+
+    /// Returns the version.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns `true` if the run is due to proceed.
+    #[inline]
+    pub fn is_due(&self) -> bool {
+        !self.status.is_finished() && self.wake_at.is_some_and(|dt| dt <= DateTime::now())
+    }
+
+    /// Returns `true` if the run is waiting for the signal with the correlation key.
+    #[inline]
+    pub fn is_waiting_for(&self, signal: &str, correlation_key: &str) -> bool {
+        self.status == WorkflowStatus::Waiting
+            && self.signal == signal
+            && self.correlation_key == correlation_key
+    }
+}
+
+/// Action recorded in the history of a workflow run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HistoryAction {
+    /// The run has been started.
+    Started,
+    /// A step has been completed.
+    StepCompleted,
+    /// A step has failed and will be retried.
+    StepRetried,
+    /// A step has failed after the max attempts.
+    StepFailed,
+    /// The run has started sleeping.
+    Slept,
+    /// The run has started waiting for a signal.
+    Waited,
+    /// The run has received a signal.
+    Signaled,
+    /// A step has been compensated.
+    StepCompensated,
+    /// A compensation has failed and will be retried.
+    CompensationRetried,
+    /// The run has been finished.
+    Finished,
+}
+
+impl HistoryAction {
+    /// Returns the action as a str.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Started => "Started",
+            Self::StepCompleted => "StepCompleted",
+            Self::StepRetried => "StepRetried",
+            Self::StepFailed => "StepFailed",
+            Self::Slept => "Slept",
+            Self::Waited => "Waited",
+            Self::Signaled => "Signaled",
+            Self::StepCompensated => "StepCompensated",
+            Self::CompensationRetried => "CompensationRetried",
+            Self::Finished => "Finished",
+        }
+    }
+}
+
+impl fmt::Display for HistoryAction {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HistoryAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Started" => Ok(Self::Started),
+            "StepCompleted" => Ok(Self::StepCompleted),
+            "StepRetried" => Ok(Self::StepRetried),
+            "StepFailed" => Ok(Self::StepFailed),
+            "Slept" => Ok(Self::Slept),
+            "Waited" => Ok(Self::Waited),
+            "Signaled" => Ok(Self::Signaled),
+            "StepCompensated" => Ok(Self::StepCompensated),
+            "CompensationRetried" => Ok(Self::CompensationRetried),
+            "Finished" => Ok(Self::Finished),
+            _ => bail!("invalid workflow history action `{}`", s),
+        }
+    }
+}
+
+/// An entry in the history of a workflow run.
+#[derive(Debug, Clone)]
+pub struct WorkflowHistory {
+    /// Entry ID.
+    id: Uuid,
+    /// Run ID.
+    run_id: Uuid,
+    /// Step name.
+    step: String,
+    /// Recorded action.
+    action: HistoryAction,
+    /// Details such as the error message or the signal.
+    detail: String,
+    /// Creation time.
+    created_at: DateTime,
+}
+
+impl WorkflowHistory {
+    /// Creates a new instance.
+    pub fn new(run_id: Uuid, step: &str, action: HistoryAction, detail: impl ToString) -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            run_id,
+            step: step.to_owned(),
+            action,
+            detail: detail.to_string(),
+            created_at: DateTime::now(),
+        }
+    }
+
+    /// Attempts to construct an instance from a map.
+    pub fn try_from_map(map: &Map) -> Result<Self, Error> {
+        let Some(id) = map.get_uuid("id") else {
+            bail!("the `id` field should be a UUID");
+        };
+        let Some(run_id) = map.get_uuid("run_id") else {
+            bail!("the `run_id` field should be a UUID");
+        };
+        let Some(action) = map.get_str("action") else {
+            bail!("the `action` field should be specified");
+        };
+        let created_at = map
+            .get_str("created_at")
+            .ok_or_else(|| warn!("the `created_at` field should be specified"))?;
+        Ok(Self {
+            id,
+            run_id,
+            step: map.get_str("step").unwrap_or_default().to_owned(),
+            action: action.parse()?,
+            detail: map.get_str("detail").unwrap_or_default().to_owned(),
+            created_at: created_at.parse()?,
+        })
+    }
+
+    /// Consumes `self` and returns a map.
+    pub fn into_map(self) -> Map {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("run_id", self.run_id.to_string());
+        map.upsert("step", self.step);
+        map.upsert("action", self.action.as_str());
+        map.upsert("detail", self.detail);
+        map.upsert("created_at", self.created_at.to_string());
+        map
+    }
+
+    /// Returns the entry ID.
+    #[inline]
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Returns the run ID.
+    #[inline]
+    pub fn run_id(&self) -> Uuid {
+        self.run_id
+    }
+
+    /// Returns the step name.
+    #[inline]
+    pub fn step(&self) -> &str {
+        &self.step
+    }
+
+    /// Returns the recorded action.
+    #[inline]
+    pub fn action(&self) -> HistoryAction {
+        self.action
+    }
+
+    /// Returns the details.
+    #[inline]
+    pub fn detail(&self) -> &str {
+        &self.detail
+    }
+
+    /// Returns the creation time.
+    #[inline]
+    pub fn created_at(&self) -> DateTime {
+        self.created_at
+    }
+}
+
+/// An interface for storing the workflow runs and their history.
+pub trait WorkflowStore: Send + Sync {
+    /// Inserts a new run.
+    fn push_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Gets the run by ID.
+    fn get_run(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WorkflowRun>, Error>>;
+
+    /// Claims at most `limit` runs which are due to proceed.
+    fn claim_runs(
+        &self,
+        limit: usize,
+        lease_timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WorkflowRun>, Error>>;
+
+    /// Finds the runs waiting for the signal with the correlation key.
+    fn find_waiting_runs<'a>(
+        &'a self,
+        signal: &'a str,
+        correlation_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<WorkflowRun>, Error>>;
+
+    /// Updates the run if the stored version is the same as the version of the run,
+    /// and bumps the stored version. It returns `false` if the run has been updated
+    /// by another worker or a signal.
+    fn save_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Appends an entry to the history.
+    fn append_history<'a>(&'a self, entry: &'a WorkflowHistory)
+        -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Lists the history of a run in chronological order.
+    fn list_history(&self, run_id: Uuid) -> BoxFuture<'_, Result<Vec<WorkflowHistory>, Error>>;
+}
+
+/// In-memory workflow store, which is only suitable for tests or a single instance
+/// without the need of persistence.
+#[derive(Debug, Default)]
+pub struct MemoryWorkflowStore {
+    /// Workflow runs.
+    runs: RwLock<HashMap<Uuid, WorkflowRun>>,
+    /// History entries.
+    history: RwLock<Vec<WorkflowHistory>>,
+}
+
+impl MemoryWorkflowStore {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl WorkflowStore for MemoryWorkflowStore {
+    fn push_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.runs.write().insert(run.id, run.clone());
+            Ok(())
+        })
+    }
+
+    fn get_run(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WorkflowRun>, Error>> {
+        Box::pin(async move { Ok(self.runs.read().get(&id).cloned()) })
+    }
+
+    fn claim_runs(
+        &self,
+        limit: usize,
+        lease_timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WorkflowRun>, Error>> {
+        Box::pin(async move {
+            let mut runs = self.runs.write();
+            let mut due_runs = runs
+                .values_mut()
+                .filter(|run| run.is_due())
+                .collect::<Vec<_>>();
+            due_runs.sort_by_key(|run| run.wake_at);
+            let claimed_runs = due_runs
+                .into_iter()
+                .take(limit)
+                .map(|run| {
+                    run.claim(lease_timeout);
+                    run.clone()
+                })
+                .collect();
+            Ok(claimed_runs)
+        })
+    }
+
+    fn find_waiting_runs<'a>(
+        &'a self,
+        signal: &'a str,
+        correlation_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<WorkflowRun>, Error>> {
+        Box::pin(async move {
+            let runs = self
+                .runs
+                .read()
+                .values()
+                .filter(|run| run.is_waiting_for(signal, correlation_key))
+                .cloned()
+                .collect();
+            Ok(runs)
+        })
+    }
+
+    fn save_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut runs = self.runs.write();
+            match runs.get_mut(&run.id) {
+                Some(stored_run) if stored_run.version == run.version => {
+                    *stored_run = run.clone();
+                    stored_run.version += 1;
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        })
+    }
+
+    fn append_history<'a>(
+        &'a self,
+        entry: &'a WorkflowHistory,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.history.write().push(entry.clone());
+            Ok(())
+        })
+    }
+
+    fn list_history(&self, run_id: Uuid) -> BoxFuture<'_, Result<Vec<WorkflowHistory>, Error>> {
+        Box::pin(async move {
+            let history = self
+                .history
+                .read()
+                .iter()
+                .filter(|entry| entry.run_id == run_id)
+                .cloned()
+                .collect();
+            Ok(history)
+        })
+    }
+}
+
+/// An engine which executes the workflow runs, driven by the scheduler.
+///
+/// The state of a run is persisted after each step, so an interrupted run will be
+/// resumed by any instance after the lease of the claimed run has expired.
+///
+/// # Examples
+///
+/// ```toml
+/// [workflow]
+/// concurrency = 8
+/// poll-interval = "1s"
+/// max-attempts = 3
+/// lease-timeout = "5m"
+/// backoff-base = "10s"
+/// backoff-max = "1h"
+/// ```
+///
+/// ```rust,ignore
+/// use zino_core::schedule::{MemoryWorkflowStore, WorkflowEngine};
+///
+/// let config = State::shared().get_config("workflow").unwrap_or_default();
+/// let engine = WorkflowEngine::with_config(MemoryWorkflowStore::new(), config)
+///     .with_workflow(order_workflow());
+/// WorkflowEngine::register(engine);
+///
+/// let engine = WorkflowEngine::shared().unwrap();
+/// let run_id = engine.start("order", order.id(), order.to_map()).await?;
+/// app.run_with((scheduler, engine));
+/// ```
+pub struct WorkflowEngine {
+    /// Workflow store.
+    store: Box<dyn WorkflowStore>,
+    /// Workflow definitions.
+    workflows: HashMap<&'static str, Workflow>,
+    /// Max number of runs executed concurrently.
+    concurrency: usize,
+    /// Interval for polling the due runs.
+    poll_interval: Duration,
+    /// Max number of attempts for each step or compensation.
+    max_attempts: u32,
+    /// Lease timeout of a claimed run.
+    lease_timeout: Duration,
+    /// Base delay of the exponential backoff.
+    backoff_base: Duration,
+    /// Max delay of the exponential backoff.
+    backoff_max: Duration,
+}
+
+impl WorkflowEngine {
+    /// Creates a new instance with the workflow store.
+    #[inline]
+    pub fn new(store: impl WorkflowStore + 'static) -> Self {
+        Self {
+            store: Box::new(store),
+            workflows: HashMap::new(),
+            concurrency: 4,
+            poll_interval: Duration::from_secs(1),
+            max_attempts: 3,
+            lease_timeout: Duration::from_secs(60 * 5),
+            backoff_base: Duration::from_secs(10),
+            backoff_max: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Creates a new instance with the workflow store and the configuration.
+    pub fn with_config(store: impl WorkflowStore + 'static, config: &Table) -> Self {
+        let mut engine = Self::new(store);
+        if let Some(concurrency) = config.get_usize("concurrency") {
+            engine.concurrency = concurrency.max(1);
+        }
+        if let Some(poll_interval) = config.get_duration("poll-interval") {
+            engine.poll_interval = poll_interval;
+        }
+        if let Some(max_attempts) = config.get_u32("max-attempts") {
+            engine.max_attempts = max_attempts.max(1);
+        }
+        if let Some(lease_timeout) = config.get_duration("lease-timeout") {
+            engine.lease_timeout = lease_timeout;
+        }
+        if let Some(backoff_base) = config.get_duration("backoff-base") {
+            engine.backoff_base = backoff_base;
+        }
+        if let Some(backoff_max) = config.get_duration("backoff-max") {
+            engine.backoff_max = backoff_max;
+        }
+        engine
+    }
+
+    /// Registers a workflow definition.
+    #[inline]
+    pub fn with_workflow(mut self, workflow: Workflow) -> Self {
+        self.workflows.insert(workflow.name, workflow);
+        self
+    }
+
+    /// Registers the shared workflow engine. It returns `false` if the shared engine
+    /// has already been registered.
+    #[inline]
+    pub fn register(engine: Self) -> bool {
+        SHARED_WORKFLOW_ENGINE.set(engine).is_ok()
+    }
+
+    /// Returns a reference to the shared workflow engine if it has been registered.
+    #[inline]
+    pub fn shared() -> Option<&'static Self> {
+        SHARED_WORKFLOW_ENGINE.get()
+    }
+
+    /// Returns a reference to the workflow store.
+    #[inline]
+    pub fn store(&self) -> &dyn WorkflowStore {
+        self.store.as_ref()
+    }
+
+    /// Starts a run of the workflow with the correlation key and the initial data,
+    /// and returns the run ID. The first step will be executed at the next tick.
+    pub async fn start(
+        &self,
+        workflow: &str,
+        correlation_key: impl ToString,
+        data: Map,
+    ) -> Result<Uuid, Error> {
+        if !self.workflows.contains_key(workflow) {
+            bail!("404 Not Found: the workflow `{}` does not exist", workflow);
+        }
+
+        let run = WorkflowRun::new(workflow, correlation_key, data);
+        self.store.push_run(&run).await?;
+        self.record(&run, "", HistoryAction::Started, "").await;
+        Ok(run.id)
+    }
+
+    /// Delivers the signal to the runs waiting for the event type,
+    /// whose correlation keys are the same as the event subject.
+    /// The event data is merged into the run data with the event type as the key.
+    /// It returns the number of runs resumed.
+    ///
+    /// The signal is applied with the optimistic locking, so a run whose signal
+    /// has timed out and been claimed by a worker will not be resumed.
+    /// Since the signal drives the workflow, the caller is responsible for
+    /// authenticating the source of the event.
+    pub async fn signal(&self, event: &CloudEvent) -> Result<usize, Error> {
+        let Some(correlation_key) = event.subject() else {
+            bail!("the subject of the signal should be the correlation key");
+        };
+        let signal = event.event_type();
+        let runs = self
+            .store
+            .find_waiting_runs(signal, correlation_key)
+            .await?;
+        let mut num_runs = 0;
+        for mut run in runs {
+            let now = DateTime::now();
+            run.data.upsert(signal, event.data().clone());
+            run.status = WorkflowStatus::Running;
+            run.signal.clear();
+            run.wake_at = Some(now);
+            run.updated_at = now;
+            if self.store.save_run(&run).await? {
+                run.version += 1;
+                num_runs += 1;
+                self.record(&run, "", HistoryAction::Signaled, event.id())
+                    .await;
+            }
+        }
+        Ok(num_runs)
+    }
+
+    /// Gets the run by ID.
+    #[inline]
+    pub async fn get_run(&self, id: Uuid) -> Result<Option<WorkflowRun>, Error> {
+        self.store.get_run(id).await
+    }
+
+    /// Lists the history of a run in chronological order.
+    #[inline]
+    pub async fn list_history(&self, run_id: Uuid) -> Result<Vec<WorkflowHistory>, Error> {
+        self.store.list_history(run_id).await
+    }
+
+    /// Claims the due runs and executes them concurrently.
+    pub async fn tick(&self) {
+        match self
+            .store
+            .claim_runs(self.concurrency, self.lease_timeout)
+            .await
+        {
+            Ok(runs) => {
+                future::join_all(runs.into_iter().map(|run| self.execute(run))).await;
+            }
+            Err(err) => tracing::error!("fail to claim the workflow runs: {}", err.message()),
+        }
+    }
+
+    /// Executes the claimed run until it sleeps, waits or finishes.
+    async fn execute(&self, mut run: WorkflowRun) {
+        let Some(workflow) = self.workflows.get(run.workflow.as_str()) else {
+            run.last_error = format!("the workflow `{}` does not exist", run.workflow);
+            self.finish(&mut run, WorkflowStatus::Failed).await;
+            return;
+        };
+        if run.status == WorkflowStatus::Waiting {
+            // The waiting step will be compensated without retrying.
+            let step_name = Self::step_name(workflow, run.current_step.saturating_sub(1));
+            let error = mem::take(&mut run.last_error);
+            run.attempts = self.max_attempts.saturating_sub(1);
+            if !self.fail_step(&mut run, step_name, error).await {
+                return;
+            }
+        }
+        loop {
+            let proceeded = match run.status {
+                WorkflowStatus::Running => self.run_step(workflow, &mut run).await,
+                WorkflowStatus::Compensating => self.compensate_step(workflow, &mut run).await,
+                _ => false,
+            };
+            if !proceeded {
+                break;
+            }
+        }
+    }
+
+    /// Executes the current step. It returns `true` if the next step should be executed
+    /// immediately.
+    async fn run_step(&self, workflow: &Workflow, run: &mut WorkflowRun) -> bool {
+        let Some(step) = workflow.steps.get(run.current_step) else {
+            self.finish(run, WorkflowStatus::Completed).await;
+            return false;
+        };
+        match (step.action)(run.data.clone()).await {
+            Ok(outcome) => {
+                let now = DateTime::now();
+                run.current_step += 1;
+                run.attempts = 0;
+                run.last_error.clear();
+                run.updated_at = now;
+                let (data, action, detail) = match outcome {
+                    StepOutcome::Next(data) => {
+                        run.wake_at = Some(now);
+                        (data, None, String::new())
+                    }
+                    StepOutcome::Sleep(data, duration) => {
+                        run.wake_at = Some(now + duration);
+                        (data, Some(HistoryAction::Slept), format!("{duration:?}"))
+                    }
+                    StepOutcome::Wait(data, signal, timeout) => {
+                        run.status = WorkflowStatus::Waiting;
+                        run.wake_at = timeout.map(|timeout| now + timeout);
+                        run.signal.clone_from(&signal);
+                        (data, Some(HistoryAction::Waited), signal)
+                    }
+                };
+                run.data.extend(data);
+                if !self.save(run).await {
+                    return false;
+                }
+                self.record(run, step.name, HistoryAction::StepCompleted, "")
+                    .await;
+                if let Some(action) = action {
+                    self.record(run, step.name, action, detail).await;
+                    false
+                } else {
+                    true
+                }
+            }
+            Err(err) => {
+                self.fail_step(run, step.name, err.message().to_owned())
+                    .await
+            }
+        }
+    }
+
+    /// Records the failure of the current step, and starts the compensations
+    /// after the max attempts. It returns `true` if the compensations should be
+    /// executed immediately.
+    async fn fail_step(&self, run: &mut WorkflowRun, step_name: &str, error: String) -> bool {
+        let now = DateTime::now();
+        run.attempts += 1;
+        run.updated_at = now;
+        let action = if run.attempts < self.max_attempts {
+            run.wake_at = Some(now + self.backoff(run.attempts));
+            HistoryAction::StepRetried
+        } else {
+            tracing::warn!(
+                workflow = run.workflow,
+                run_id = run.id.to_string(),
+                step = step_name,
+                "the workflow step has failed: {}",
+                error
+            );
+            run.status = WorkflowStatus::Compensating;
+            run.attempts = 0;
+            run.wake_at = Some(now);
+            HistoryAction::StepFailed
+        };
+        run.last_error.clone_from(&error);
+        if !self.save(run).await {
+            return false;
+        }
+        self.record(run, step_name, action, error).await;
+        action == HistoryAction::StepFailed
+    }
+
+    /// Executes the compensation of the last completed step. It returns `true`
+    /// if the compensation of the previous step should be executed immediately.
+    async fn compensate_step(&self, workflow: &Workflow, run: &mut WorkflowRun) -> bool {
+        let Some(index) = run.current_step.checked_sub(1) else {
+            self.finish(run, WorkflowStatus::Compensated).await;
+            return false;
+        };
+        let Some(step) = workflow.steps.get(index) else {
+            run.last_error = format!("the step `{}` does not exist", index);
+            self.finish(run, WorkflowStatus::Failed).await;
+            return false;
+        };
+        let result = match step.compensation {
+            Some(compensation) => compensation(run.data.clone()).await,
+            None => Ok(()),
+        };
+        let now = DateTime::now();
+        run.updated_at = now;
+        match result {
+            Ok(()) => {
+                run.current_step = index;
+                run.attempts = 0;
+                run.wake_at = Some(now);
+                if !self.save(run).await {
+                    return false;
+                }
+                if step.compensation.is_some() {
+                    self.record(run, step.name, HistoryAction::StepCompensated, "")
+                        .await;
+                }
+                true
+            }
+            Err(err) => {
+                run.attempts += 1;
+                run.last_error = err.message().to_owned();
+                if run.attempts < self.max_attempts {
+                    run.wake_at = Some(now + self.backoff(run.attempts));
+                    if self.save(run).await {
+                        let error = err.message();
+                        self.record(run, step.name, HistoryAction::CompensationRetried, error)
+                            .await;
+                    }
+                } else {
+                    tracing::error!(
+                        workflow = run.workflow,
+                        run_id = run.id.to_string(),
+                        step = step.name,
+                        "fail to compensate the workflow step: {}",
+                        err.message()
+                    );
+                    self.finish(run, WorkflowStatus::Failed).await;
+                }
+                false
+            }
+        }
+    }
+
+    /// Finishes the run with the status.
+    async fn finish(&self, run: &mut WorkflowRun, status: WorkflowStatus) {
+        run.status = status;
+        run.wake_at = None;
+        run.updated_at = DateTime::now();
+        if self.save(run).await {
+            let detail = status.as_str();
+            self.record(run, "", HistoryAction::Finished, detail).await;
+        }
+    }
+
+    /// Saves the run. It returns `false` if the run fails to be saved
+    /// or has been updated by others after the lease expired.
+    async fn save(&self, run: &mut WorkflowRun) -> bool {
+        match self.store.save_run(run).await {
+            Ok(true) => {
+                run.version += 1;
+                true
+            }
+            Ok(false) => {
+                tracing::warn!(
+                    run_id = run.id.to_string(),
+                    "the workflow run has been updated by others"
+                );
+                false
+            }
+            Err(err) => {
+                tracing::error!(
+                    run_id = run.id.to_string(),
+                    "fail to save the workflow run: {}",
+                    err.message()
+                );
+                false
+            }
+        }
+    }
+
+    /// Appends an entry to the history of the run.
+    async fn record(
+        &self,
+        run: &WorkflowRun,
+        step: &str,
+        action: HistoryAction,
+        detail: impl ToString,
+    ) {
+        let entry = WorkflowHistory::new(run.id, step, action, detail);
+        if let Err(err) = self.store.append_history(&entry).await {
+            tracing::error!(
+                run_id = run.id.to_string(),
+                "fail to append the workflow history: {}",
+                err.message()
+            );
+        }
+    }
+
+    /// Returns the name of the step.
+    fn step_name(workflow: &Workflow, index: usize) -> &'static str {
+        workflow
+            .steps
+            .get(index)
+            .map(|step| step.name)
+            .unwrap_or_default()
+    }
+
+    /// Returns the delay of the exponential backoff for the attempts.
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+impl fmt::Debug for WorkflowEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WorkflowEngine")
+            .field("workflows", &self.workflows.keys().collect::<Vec<_>>())
+            .field("concurrency", &self.concurrency)
+            .field("poll_interval", &self.poll_interval)
+            .field("max_attempts", &self.max_attempts)
+            .field("lease_timeout", &self.lease_timeout)
+            .finish()
+    }
+}
+
+impl AsyncScheduler for &'static WorkflowEngine {
+    #[inline]
+    fn is_ready(&self) -> bool {
+        !self.workflows.is_empty()
+    }
+
+    #[inline]
+    fn time_till_next_job(&self) -> Duration {
+        self.poll_interval
+    }
+
+    #[inline]
+    async fn tick(&mut self) {
+        WorkflowEngine::tick(self).await;
+    }
+}
+
+/// Shared workflow engine.
+static SHARED_WORKFLOW_ENGINE: OnceLock<WorkflowEngine> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        HistoryAction, MemoryWorkflowStore, StepOutcome, Workflow, WorkflowEngine, WorkflowRun,
+        WorkflowStatus, WorkflowStore,
+    };
+    use crate::{
+        bail, channel::CloudEvent, error::Error, extension::JsonObjectExt, BoxFuture, Map,
+    };
+    use std::time::Duration;
+
+    fn reserve(_data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+        Box::pin(async { Ok(StepOutcome::Next(Map::from_entry("reserved", true))) })
+    }
+
+    fn release(_data: Map) -> BoxFuture<'static, Result<(), Error>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn pay(_data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+        Box::pin(async { Ok(StepOutcome::wait("payment.succeeded", None)) })
+    }
+
+    fn confirm(_data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+        Box::pin(async { Ok(StepOutcome::wait("order.confirmed", Some(Duration::ZERO))) })
+    }
+
+    fn ship(data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+        Box::pin(async move {
+            if data.get_str("address").is_none() {
+                bail!("the address should be specified");
+            }
+            Ok(StepOutcome::next())
+        })
+    }
+
+    #[test]
+    fn it_runs_and_compensates_workflows() {
+        futures::executor::block_on(async {
+            let workflow = Workflow::new("order")
+                .step("reserve", reserve)
+                .compensate(release)
+                .step("pay", pay)
+                .step("ship", ship);
+            let mut engine =
+                WorkflowEngine::new(MemoryWorkflowStore::new()).with_workflow(workflow);
+            engine.max_attempts = 1;
+
+            let run_id = engine.start("order", "order-1", Map::new()).await.unwrap();
+            engine.tick().await;
+            let run = engine.get_run(run_id).await.unwrap().unwrap();
+            assert_eq!(run.status(), WorkflowStatus::Waiting);
+            assert_eq!(run.signal(), Some("payment.succeeded"));
+            assert_eq!(run.data().get_bool("reserved"), Some(true));
+
+            let mut event = CloudEvent::new("1", "payment", "payment.succeeded");
+            event.set_subject("order-2");
+            assert_eq!(engine.signal(&event).await.unwrap(), 0);
+            event.set_subject("order-1");
+            assert_eq!(engine.signal(&event).await.unwrap(), 1);
+
+            engine.tick().await;
+            let run = engine.get_run(run_id).await.unwrap().unwrap();
+            assert_eq!(run.status(), WorkflowStatus::Compensated);
+            assert_eq!(run.last_error(), "the address should be specified");
+
+            let actions = engine
+                .list_history(run_id)
+                .await
+                .unwrap()
+                .iter()
+                .map(|entry| entry.action())
+                .collect::<Vec<_>>();
+            assert_eq!(
+                actions,
+                [
+                    HistoryAction::Started,
+                    HistoryAction::StepCompleted,
+                    HistoryAction::StepCompleted,
+                    HistoryAction::Waited,
+                    HistoryAction::Signaled,
+                    HistoryAction::StepFailed,
+                    HistoryAction::StepCompensated,
+                    HistoryAction::Finished,
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn it_rejects_stale_updates() {
+        futures::executor::block_on(async {
+            let store = MemoryWorkflowStore::new();
+            let run = WorkflowRun::new("order", "order-1", Map::new());
+            store.push_run(&run).await.unwrap();
+
+            let claimed_runs = store.claim_runs(1, Duration::ZERO).await.unwrap();
+            assert_eq!(claimed_runs.len(), 1);
+            assert!(!store.save_run(&run).await.unwrap());
+            assert!(store.save_run(&claimed_runs[0]).await.unwrap());
+            assert!(!store.save_run(&claimed_runs[0]).await.unwrap());
+
+            let workflow = Workflow::new("order").step("confirm", confirm);
+            let engine = WorkflowEngine::new(MemoryWorkflowStore::new()).with_workflow(workflow);
+            let run_id = engine.start("order", "order-2", Map::new()).await.unwrap();
+            engine.tick().await;
+            let run = engine.get_run(run_id).await.unwrap().unwrap();
+            assert_eq!(run.status(), WorkflowStatus::Waiting);
+
+            // The signal loses the race with the timeout claim.
+            let claimed_runs = engine.store().claim_runs(1, Duration::ZERO).await.unwrap();
+            assert_eq!(claimed_runs.len(), 1);
+            let mut event = CloudEvent::new("1", "order", "order.confirmed");
+            event.set_subject("order-2");
+            assert_eq!(engine.signal(&event).await.unwrap(), 0);
+        });
+    }
+}
@@ -1,8 +1,39 @@
 //! Generic validator and common validation rules.
+//!
+//! ## Error codes
+//!
+//! A failed entry can carry a machine-readable error code, which is used to
+//! construct the `invalid_params` in the problem details. The following codes
+//! are used by the built-in validation rules:
+//!
+//! | Error code        | Description                                     |
+//! |-------------------|-------------------------------------------------|
+//! | `duplicate_items` | Array items are not unique.                     |
+//! | `format`          | The value does not match the format.            |
+//! | `invalid`         | The value can not be parsed.                    |
+//! | `length`          | The length of the value is not expected.        |
+//! | `nonexistent`     | The referenced value does not exist.            |
+//! | `not_allowed`     | The value is not one of the allowed values.     |
+//! | `not_unique`      | The value is not unique.                        |
+//! | `out_of_range`    | The value is out of the range.                  |
+//! | `required`        | The value should be nonempty.                   |
+//! | `too_few_items`   | The array has too few items.                    |
+//! | `too_large`       | The value is greater than the maximum.          |
+//! | `too_long`        | The value is longer than the maximum length.    |
+//! | `too_many_items`  | The array has too many items.                   |
+//! | `too_short`       | The value is shorter than the minimum length.   |
+//! | `too_small`       | The value is less than the minimum.             |
+//!
+//! With the `i18n` feature, the error messages can be localized via the message ID
+//! `validation-{code}`, where the arguments `field` and `message` are provided.
+
 use crate::{error::Error, extension::JsonObjectExt, Map, SharedString};
 use smallvec::SmallVec;
 use std::fmt;
 
+#[cfg(feature = "i18n")]
+use unic_langid::LanguageIdentifier;
+
 mod validator;
 
 pub use validator::{
@@ -26,7 +57,7 @@ pub use validator::RegexValidator;
 /// A record of validation results.
 #[derive(Debug, Default)]
 pub struct Validation {
-    failed_entries: SmallVec<[(SharedString, Error); 4]>,
+    failed_entries: SmallVec<[(SharedString, Option<SharedString>, Error); 4]>,
 }
 
 impl Validation {
@@ -42,7 +73,7 @@ impl Validation {
     #[inline]
     pub fn from_entry(key: impl Into<SharedString>, err: impl Into<Error>) -> Self {
         let mut entries = SmallVec::new();
-        entries.push((key.into(), err.into()));
+        entries.push((key.into(), None, err.into()));
         Self {
             failed_entries: entries,
         }
@@ -51,13 +82,38 @@ impl Validation {
     /// Records an entry with the supplied message.
     #[inline]
     pub fn record(&mut self, key: impl Into<SharedString>, message: impl Into<SharedString>) {
-        self.failed_entries.push((key.into(), Error::new(message)));
+        self.failed_entries
+            .push((key.into(), None, Error::new(message)));
     }
 
     /// Records an entry for the error.
     #[inline]
     pub fn record_fail(&mut self, key: impl Into<SharedString>, err: impl Into<Error>) {
-        self.failed_entries.push((key.into(), err.into()));
+        self.failed_entries.push((key.into(), None, err.into()));
+    }
+
+    /// Records an entry with the error code and the supplied message.
+    #[inline]
+    pub fn record_with_code(
+        &mut self,
+        key: impl Into<SharedString>,
+        code: impl Into<SharedString>,
+        message: impl Into<SharedString>,
+    ) {
+        self.failed_entries
+            .push((key.into(), Some(code.into()), Error::new(message)));
+    }
+
+    /// Records an entry with the error code for the error.
+    #[inline]
+    pub fn record_fail_with_code(
+        &mut self,
+        key: impl Into<SharedString>,
+        code: impl Into<SharedString>,
+        err: impl Into<Error>,
+    ) {
+        self.failed_entries
+            .push((key.into(), Some(code.into()), err.into()));
     }
 
     /// Validates the string value with a specific format.
@@ -65,136 +121,136 @@ impl Validation {
         match format {
             "alphabetic" => {
                 if let Err(err) = AlphabeticValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "alphanumeric" => {
                 if let Err(err) = AlphanumericValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii" => {
                 if let Err(err) = AsciiValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-alphabetic" => {
                 if let Err(err) = AsciiAlphabeticValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-alphanumeric" => {
                 if let Err(err) = AsciiAlphanumericValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-digit" => {
                 if let Err(err) = AsciiDigitValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-hexdigit" => {
                 if let Err(err) = AsciiHexdigitValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-lowercase" => {
                 if let Err(err) = AsciiLowercaseValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ascii-uppercase" => {
                 if let Err(err) = AsciiUppercaseValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             #[cfg(feature = "validator-credit-card")]
             "credit-card" => {
                 if let Err(err) = CreditCardValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "date" => {
                 if let Err(err) = DateValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "date-time" => {
                 if let Err(err) = DateTimeValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             #[cfg(feature = "validator-email")]
             "email" => {
                 if let Err(err) = EmailValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "host" => {
                 if let Err(err) = HostValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "hostname" => {
                 if let Err(err) = HostnameValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ip" => {
                 if let Err(err) = IpAddrValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ipv4" => {
                 if let Err(err) = Ipv4AddrValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "ipv6" => {
                 if let Err(err) = Ipv6AddrValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "lowercase" => {
                 if let Err(err) = LowercaseValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "numeric" => {
                 if let Err(err) = NumericValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             #[cfg(feature = "validator-phone-number")]
             "phone-number" => {
                 if let Err(err) = PhoneNumberValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             #[cfg(feature = "validator-regex")]
             "regex" => {
                 if let Err(err) = RegexValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "time" => {
                 if let Err(err) = TimeValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "uppercase" => {
                 if let Err(err) = UppercaseValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "uri" => {
                 if let Err(err) = UriValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             "uuid" => {
                 if let Err(err) = UuidValidator.validate(value) {
-                    self.record_fail(key, err);
+                    self.record_fail_with_code(key, "format", err);
                 }
             }
             _ => {
@@ -207,7 +263,15 @@ impl Validation {
     /// Returns true if the validation contains a value for the specified key.
     #[inline]
     pub fn contains_key(&self, key: &str) -> bool {
-        self.failed_entries.iter().any(|(field, _)| field == key)
+        self.failed_entries.iter().any(|(field, ..)| field == key)
+    }
+
+    /// Returns the error code for the specified key.
+    #[inline]
+    pub fn error_code(&self, key: &str) -> Option<&str> {
+        self.failed_entries
+            .iter()
+            .find_map(|(field, code, _)| (field == key).then_some(code.as_deref()).flatten())
     }
 
     /// Returns `true` if the validation is success.
@@ -225,11 +289,44 @@ impl Validation {
             .collect()
     }
 
+    /// Localizes the error messages for the entries with an error code.
+    /// The message ID is `validation-{code}`, and the original message is kept
+    /// if there is no localization message for it.
+    #[cfg(feature = "i18n")]
+    pub fn localize(&mut self, locale: &LanguageIdentifier) {
+        for (key, code, err) in self.failed_entries.iter_mut() {
+            if let Some(code) = code {
+                let message_id = format!("validation-{code}");
+                let mut args = fluent::FluentArgs::new();
+                args.set("field", key.as_ref());
+                args.set("message", err.message());
+                if let Ok(message) = crate::i18n::translate(locale, &message_id, Some(args)) {
+                    *err = Error::new(message);
+                }
+            }
+        }
+    }
+
+    /// Returns a list of problem detail entries for the invalid params.
+    /// Each entry has the fields `name`, `code` and `reason`.
+    pub fn to_problem_entries(&self) -> Vec<Map> {
+        self.failed_entries
+            .iter()
+            .map(|(key, code, err)| {
+                let mut entry = Map::new();
+                entry.upsert("name", key.as_ref());
+                entry.upsert("code", code.as_deref().unwrap_or("invalid"));
+                entry.upsert("reason", err.message());
+                entry
+            })
+            .collect()
+    }
+
     /// Consumes the validation and returns as a json object.
     #[must_use]
     pub fn into_map(self) -> Map {
         let mut map = Map::new();
-        for (key, err) in self.failed_entries {
+        for (key, _, err) in self.failed_entries {
             let message = err.message();
             tracing::warn!("invalid value for `{key}`: {message}");
             map.upsert(key, message);
@@ -243,10 +340,50 @@ impl fmt::Display for Validation {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let failed_entries = &self.failed_entries;
         let mut errors = Vec::with_capacity(failed_entries.len());
-        for (key, err) in failed_entries {
+        for (key, _, err) in failed_entries {
             let message = format!("invalid value for `{key}`: {}", err.message());
             errors.push(message);
         }
         write!(f, "{}", errors.join(","))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Validation;
+    use crate::{error::Error, extension::JsonObjectExt};
+
+    #[test]
+    fn it_records_error_codes() {
+        let mut validation = Validation::new();
+        validation.record_with_code("name", "required", "it should be nonempty");
+        validation.record_fail_with_code("age", "too_small", Error::new("it is too small"));
+        validation.record("email", "invalid email address");
+        assert!(!validation.is_success());
+        assert!(validation.contains_key("email"));
+        assert_eq!(validation.error_code("name"), Some("required"));
+        assert_eq!(validation.error_code("age"), Some("too_small"));
+        assert_eq!(validation.error_code("email"), None);
+        assert_eq!(validation.invalid_params(), vec!["name", "age", "email"]);
+
+        let entries = validation.to_problem_entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].get_str("name"), Some("name"));
+        assert_eq!(entries[0].get_str("code"), Some("required"));
+        assert_eq!(entries[0].get_str("reason"), Some("it should be nonempty"));
+        assert_eq!(entries[2].get_str("code"), Some("invalid"));
+    }
+
+    #[cfg(feature = "i18n")]
+    #[test]
+    fn it_keeps_messages_without_localization() {
+        let mut validation = Validation::new();
+        validation.record_with_code("name", "required", "it should be nonempty");
+        validation.record("email", "invalid email address");
+        validation.localize(&"zxx".parse().unwrap());
+
+        let entries = validation.to_problem_entries();
+        assert_eq!(entries[0].get_str("reason"), Some("it should be nonempty"));
+        assert_eq!(entries[1].get_str("reason"), Some("invalid email address"));
+    }
+}
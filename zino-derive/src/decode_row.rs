@@ -18,19 +18,35 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
         if let Some(ident) = field.ident {
             let name = ident.to_string();
             let mut ignore = false;
+            let mut encrypted = false;
             'inner: for attr in field.attrs.iter() {
                 let arguments = parser::parse_schema_attr(attr);
                 for (key, _value) in arguments.iter() {
                     if key == "ignore" || key == "write_only" {
                         ignore = true;
                         break 'inner;
+                    } else if key == "encrypted" {
+                        encrypted = true;
                     }
                 }
             }
             if ignore {
                 continue;
             }
-            if type_name == "Uuid" {
+            if encrypted && type_name == "String" {
+                decode_model_fields.push(quote! {
+                    if let Some(value) = orm::decode_optional::<String>(row, #name)? {
+                        model.#ident = orm::ColumnCipher::shared().decrypt(&value)?.into_owned();
+                    }
+                });
+            } else if encrypted && type_name == "Option<String>" {
+                decode_model_fields.push(quote! {
+                    if let Some(value) = orm::decode_optional::<String>(row, #name)? {
+                        let plaintext = orm::ColumnCipher::shared().decrypt(&value)?;
+                        model.#ident = Some(plaintext.into_owned());
+                    }
+                });
+            } else if type_name == "Uuid" {
                 decode_model_fields.push(quote! {
                     model.#ident = orm::decode_uuid(row, #name)?;
                 });
@@ -92,7 +92,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             }
                                         }
                                         if !errors.is_empty() {
-                                            validation.record(#name, errors.join(";"));
+                                            validation.record_with_code(#name, "invalid", errors.join(";"));
                                         }
                                         self.#ident = models;
                                     }
@@ -102,7 +102,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     if let Some(object) = data.parse_object(#name) {
                                         match object.read_as_model() {
                                             Ok(model) => self.#ident = Some(model),
-                                            Err(err) => validation.record(#name, err.to_string()),
+                                            Err(err) => validation.record_with_code(#name, "invalid", err.to_string()),
                                         }
                                     }
                                 }
@@ -112,7 +112,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         match object.read_as_model() {
                                             Ok(model) => self.#ident = model,
                                             Err(err) => {
-                                                validation.record(#name, err.to_string());
+                                                validation.record_with_code(#name, "invalid", err.to_string());
                                             },
                                         }
                                     }
@@ -215,7 +215,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                             if let Some(value) = data.parse_string(#name) {
                                 match Self::#parser_ident(&value) {
                                     Ok(value) => self.#ident = value,
-                                    Err(err) => validation.record_fail(#name, err),
+                                    Err(err) => validation.record_fail_with_code(#name, "invalid", err),
                                 }
                             }
                         }
@@ -225,7 +225,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                 use zino_core::orm::ModelHelper;
                                 match Self::encrypt_password(&password) {
                                     Ok(password) => self.password = password,
-                                    Err(err) => validation.record_fail(#name, err),
+                                    Err(err) => validation.record_fail_with_code(#name, "invalid", err),
                                 }
                             }
                         }
@@ -259,7 +259,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                         if let Some(result) = data.parse_array(#name) {
                             match result {
                                 Ok(values) => self.#ident = values,
-                                Err(err) => validation.record_fail(#name, err),
+                                Err(err) => validation.record_fail_with_code(#name, "invalid", err),
                             }
                         }
                     }
@@ -278,7 +278,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         .as_deref()
                                         .unwrap_or_default();
                                     let message = format!("{err}: `{raw_value_str}`");
-                                    validation.record(#name, message);
+                                    validation.record_with_code(#name, "invalid", message);
                                 },
                             }
                         }
@@ -298,7 +298,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         .as_deref()
                                         .unwrap_or_default();
                                     let message = format!("{err}: `{raw_value_str}`");
-                                    validation.record(#name, message);
+                                    validation.record_with_code(#name, "invalid", message);
                                 },
                             }
                         }
@@ -335,7 +335,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
 
                 let mut validation = zino_core::validation::Validation::new();
                 if data.is_empty() {
-                    validation.record("data", "should be nonempty");
+                    validation.record_with_code("data", "required", "should be nonempty");
                 } else {
                     #(#field_setters)*
                 }
@@ -36,7 +36,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                     composite_constraints.push(quote! {
                         let columns = vec![#(#column_values),*];
                         if !self.is_unique_on(columns).await? {
-                            validation.record(#composite_field, "the composite values should be unique");
+                            validation.record_with_code(#composite_field, "not_unique", "the composite values should be unique");
                         }
                     });
                 }
@@ -113,7 +113,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let values = vec![self.#ident.to_string()];
                                         let data = <#model_ident>::filter(values).await?;
                                         if data.len() != 1 {
-                                            validation.record(#name, "it is a nonexistent value");
+                                            validation.record_with_code(#name, "nonexistent", "it is a nonexistent value");
                                         }
                                     });
                                 } else if matches!(type_name, "Option<Uuid>" | "Option<String>") {
@@ -122,7 +122,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                                 let values = vec![value.to_string()];
                                                 let data = <#model_ident>::filter(values).await?;
                                                 if data.len() != 1 {
-                                                    validation.record(#name, "it is a nonexistent value");
+                                                    validation.record_with_code(#name, "nonexistent", "it is a nonexistent value");
                                                 }
                                             }
                                         });
@@ -136,7 +136,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             if length > 0 {
                                                 let data = <#model_ident>::filter(values).await?;
                                                 if data.len() != length {
-                                                    validation.record(#name, "there are nonexistent values");
+                                                    validation.record_with_code(#name, "nonexistent", "there are nonexistent values");
                                                 }
                                             }
                                         });
@@ -147,7 +147,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             if length > 0 {
                                                 let data = <#model_ident>::filter(values).await?;
                                                 if data.len() != length {
-                                                    validation.record(#name, "there are nonexistent values");
+                                                    validation.record_with_code(#name, "nonexistent", "there are nonexistent values");
                                                 }
                                             }
                                         });
@@ -157,7 +157,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                                 let values = vec![value.clone()];
                                                 let data = <#model_ident>::filter(values).await?;
                                                 if data.len() != 1 {
-                                                    validation.record(#name, "it is a nonexistent value");
+                                                    validation.record_with_code(#name, "nonexistent", "it is a nonexistent value");
                                                 }
                                             }
                                         });
@@ -166,7 +166,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let values = vec![self.#ident.clone()];
                                         let data = <#model_ident>::filter(values).await?;
                                         if data.len() != 1 {
-                                            validation.record(#name, "it is a nonexistent value");
+                                            validation.record_with_code(#name, "nonexistent", "it is a nonexistent value");
                                         }
                                     });
                                 }
@@ -213,7 +213,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             let columns = vec![(#name, value)];
                                             if !self.is_unique_on(columns).await? {
                                                 let message = format!("the value `{value}` is not unique");
-                                                validation.record(#name, message);
+                                                validation.record_with_code(#name, "not_unique", message);
                                             }
                                         }
                                     });
@@ -224,7 +224,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             let columns = vec![(#name, value)];
                                             if !self.is_unique_on(columns).await? {
                                                 let message = format!("the value `{value}` is not unique");
-                                                validation.record(#name, message);
+                                                validation.record_with_code(#name, "not_unique", message);
                                             }
                                         }
                                     });
@@ -234,7 +234,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             let columns = vec![(#name, value)];
                                             if !self.is_unique_on(columns).await? {
                                                 let message = format!("the value `{value}` is not unique");
-                                                validation.record(#name, message);
+                                                validation.record_with_code(#name, "not_unique", message);
                                             }
                                         }
                                     });
@@ -244,7 +244,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             let columns = vec![(#name, value)];
                                             if !self.is_unique_on(columns).await? {
                                                 let message = format!("the value `{value}` is not unique");
-                                                validation.record(#name, message);
+                                                validation.record_with_code(#name, "not_unique", message);
                                             }
                                         }
                                     });
@@ -254,7 +254,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             let columns = vec![(#name, value)];
                                             if !self.is_unique_on(columns).await? {
                                                 let message = format!("the value `{value}` is not unique");
-                                                validation.record(#name, message);
+                                                validation.record_with_code(#name, "not_unique", message);
                                             }
                                         }
                                     });
@@ -264,7 +264,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     let columns = vec![(#name, value)];
                                     if !self.is_unique_on(columns).await? {
                                         let message = format!("the value `{value}` is not unique");
-                                        validation.record(#name, message);
+                                        validation.record_with_code(#name, "not_unique", message);
                                     }
                                 });
                             }
@@ -273,13 +273,13 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                             if type_name == "String" {
                                 field_constraints.push(quote! {
                                     if self.#ident.is_empty() {
-                                        validation.record(#name, "it should be nonempty");
+                                        validation.record_with_code(#name, "required", "it should be nonempty");
                                     }
                                 });
                             } else if type_name == "Uuid" {
                                 field_constraints.push(quote! {
                                     if self.#ident.is_nil() {
-                                        validation.record(#name, "it should not be nil");
+                                        validation.record_with_code(#name, "required", "it should not be nil");
                                     }
                                 });
                             }
@@ -290,7 +290,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                             {
                                 field_constraints.push(quote! {
                                     if self.#ident.is_empty() {
-                                        validation.record(#name, "it should be nonempty");
+                                        validation.record_with_code(#name, "required", "it should be nonempty");
                                     }
                                 });
                             }
@@ -304,7 +304,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             if !self.#ident.is_empty() {
                                                 let validator = <#validator_ident>::#validator_fn_ident();
                                                 if let Err(err) = validator.validate(self.#ident.as_str()) {
-                                                    validation.record_fail(#name, err);
+                                                    validation.record_fail_with_code(#name, "format", err);
                                                 }
                                             }
                                         });
@@ -313,7 +313,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     field_constraints.push(quote! {
                                             if !self.#ident.is_empty() {
                                                 if let Err(err) = #validator_ident.validate(self.#ident.as_str()) {
-                                                    validation.record_fail(#name, err);
+                                                    validation.record_fail_with_code(#name, "format", err);
                                                 }
                                             }
                                         });
@@ -339,7 +339,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                                 let value = self.#ident.as_str();
                                                 if !values.contains(&value) {
                                                     let message = format!("the value `{value}` is not allowed");
-                                                    validation.record(#name, message);
+                                                    validation.record_with_code(#name, "not_allowed", message);
                                                 }
                                             }
                                         });
@@ -349,7 +349,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                             for value in self.#ident.iter() {
                                                 if !values.contains(&value.as_str()) {
                                                     let message = format!("the value `{value}` is not allowed");
-                                                    validation.record(#name, message);
+                                                    validation.record_with_code(#name, "not_allowed", message);
                                                     break;
                                                 }
                                             }
@@ -366,7 +366,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     let length = #length;
                                     if self.#ident.len() != length {
                                         let message = format!("the length should be {length}");
-                                        validation.record(#name, message);
+                                        validation.record_with_code(#name, "length", message);
                                     }
                                 });
                             } else if type_name == "Option<String>" {
@@ -374,7 +374,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     let length = #length;
                                     if let Some(ref s) = self.#ident && s.len() != length {
                                         let message = format!("the length should be {length}");
-                                        validation.record(#name, message);
+                                        validation.record_with_code(#name, "length", message);
                                     }
                                 });
                             }
@@ -388,7 +388,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if self.#ident.len() > length {
                                             let message = format!("the length should be at most {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_long", message);
                                         }
                                     });
                             } else if type_name == "Option<String>" {
@@ -396,7 +396,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if let Some(ref s) = self.#ident && s.len() > length {
                                             let message = format!("the length should be at most {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_long", message);
                                         }
                                     });
                             }
@@ -410,7 +410,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if self.#ident.len() < length {
                                             let message = format!("the length should be at least {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_short", message);
                                         }
                                     });
                             } else if type_name == "Option<String>" {
@@ -418,7 +418,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if let Some(ref s) = self.#ident && s.len() < length {
                                             let message = format!("the length should be at least {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_short", message);
                                         }
                                     });
                             }
@@ -430,7 +430,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if self.#ident.len() > length {
                                             let message = format!("the length should be at most {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_many_items", message);
                                         }
                                     });
                                 }
@@ -443,7 +443,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let length = #length;
                                         if self.#ident.len() < length {
                                             let message = format!("the length should be at least {length}");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "too_few_items", message);
                                         }
                                     });
                                 }
@@ -456,7 +456,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     for index in 1..slice.len() {
                                         if slice[index..].contains(&slice[index - 1]) {
                                             let message = format!("array items should be unique");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "duplicate_items", message);
                                             break;
                                         }
                                     }
@@ -469,7 +469,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     let minimum = #value;
                                     if self.#ident < minimum {
                                         let message = format!("should be not less than {minimum}");
-                                        validation.record(#name, message);
+                                        validation.record_with_code(#name, "too_small", message);
                                     }
                                 });
                             }
@@ -480,7 +480,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                     let maximum = #value;
                                     if self.#ident > maximum {
                                         let message = format!("should be not greater than {maximum}");
-                                        validation.record(#name, message);
+                                        validation.record_with_code(#name, "too_large", message);
                                     }
                                 });
                             }
@@ -494,7 +494,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let field_value = <#field_type_ident>::#field_type_fn_ident();
                                         if self.#ident >= field_value {
                                             let message = format!("should be less than `{field_value}`");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "out_of_range", message);
                                         }
                                     });
                                 } else {
@@ -503,7 +503,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let field_value = self.#field_ident;
                                         if self.#ident >= field_value {
                                             let message = format!("should be less than `{field_value}`");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "out_of_range", message);
                                         }
                                     });
                                 }
@@ -518,7 +518,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let field_value = <#field_type_ident>::#field_type_fn_ident();
                                         if self.#ident <= field_value {
                                             let message = format!("should be greater than `{field_value}`");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "out_of_range", message);
                                         }
                                     });
                                 } else {
@@ -527,7 +527,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                         let field_value = self.#field_ident;
                                         if self.#ident <= field_value {
                                             let message = format!("should be greater than `{field_value}`");
-                                            validation.record(#name, message);
+                                            validation.record_with_code(#name, "out_of_range", message);
                                         }
                                     });
                                 }
@@ -721,6 +721,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
     fetched_queries.push(quote! {
         let mut models = Self::find::<Map>(query).await?;
         for model in models.iter_mut() {
+            Self::decrypt_columns(model)?;
             Self::after_decode(model).await?;
             translate_enabled.then(|| Self::translate_model(model));
         }
@@ -729,6 +730,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
         let mut model = Self::find_by_id::<Map>(id)
             .await?
             .ok_or_else(|| zino_core::warn!("404 Not Found: cannot find the model `{}`", id))?;
+        Self::decrypt_columns(&mut model)?;
         Self::after_decode(&mut model).await?;
         Self::translate_model(&mut model);
     });
@@ -843,7 +845,7 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                 if self.id() == &<#model_primary_key_type>::default()
                     && !Self::primary_key_column().auto_increment()
                 {
-                    validation.record(Self::PRIMARY_KEY_NAME, "should not be a default value");
+                    validation.record_with_code(Self::PRIMARY_KEY_NAME, "required", "should not be a default value");
                 }
                 #(#composite_constraints)*
                 #(#field_constraints)*
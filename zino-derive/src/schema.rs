@@ -168,6 +168,15 @@ pub(super) fn parse_token_stream(input: DeriveInput) -> TokenStream {
                                 "write_only" => {
                                     write_only_fields.push(quote! { #name });
                                 }
+                                "mask" => {
+                                    if let Some(value) = value {
+                                        extra_attributes.push(quote! {
+                                            if let Err(err) = #value.parse::<zino_core::model::MaskingRule>() {
+                                                panic!("{err} for the field `{}`", #name);
+                                            }
+                                        });
+                                    }
+                                }
                                 "constructor" | "validator" => {
                                     extra_attributes.push(quote! {
                                         column.set_extra_attribute(#key, true);
@@ -0,0 +1,247 @@
+//! The `api_key` model and related services.
+
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    auth::{ApiKeyRecord, ApiKeyStore},
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `api_key` model.
+///
+/// Only the digest of the random secret access key is stored.
+/// A revoked key is kept with the `Revoked` status for auditing.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct ApiKey {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, unique, read_only)]
+    access_key_id: String,
+    #[schema(read_only, index_type = "hash")]
+    owner_id: String,
+    #[schema(unique_items)]
+    scopes: Vec<String>,
+    #[schema(unique_items)]
+    ip_allowlist: Vec<String>,
+    #[schema(write_only)]
+    secret_digest: String,
+    rotated_at: DateTime,
+    expires_at: Option<DateTime>,
+    last_used_at: Option<DateTime>,
+    #[schema(format = "ip")]
+    last_used_ip: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for ApiKey {
+    const MODEL_NAME: &'static str = "api_key";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for ApiKey {
+    type Data = ();
+    type Extension = ();
+}
+
+impl ApiKey {
+    /// Creates a new instance from the API key record.
+    pub fn from_record(api_key: &ApiKeyRecord) -> Self {
+        Self {
+            name: api_key.name().to_owned(),
+            status: api_key.status().to_owned(),
+            access_key_id: api_key.access_key_id().to_owned(),
+            owner_id: api_key.owner_id().to_owned(),
+            scopes: api_key.scopes().to_vec(),
+            ip_allowlist: api_key.ip_allowlist().to_vec(),
+            secret_digest: api_key.secret_digest().to_owned(),
+            rotated_at: api_key.rotated_at(),
+            expires_at: api_key.expires_at(),
+            last_used_at: api_key.last_used_at(),
+            last_used_ip: api_key.last_used_ip().to_owned(),
+            created_at: api_key.created_at(),
+            updated_at: DateTime::now(),
+            ..Self::new()
+        }
+    }
+
+    /// Converts `self` to an API key record.
+    pub fn to_record(&self) -> Result<ApiKeyRecord, Error> {
+        let mut map = Map::new();
+        map.upsert("access_key_id", self.access_key_id.as_str());
+        map.upsert("name", self.name.as_str());
+        map.upsert("owner_id", self.owner_id.as_str());
+        map.upsert("status", self.status.as_str());
+        map.upsert("scopes", self.scopes.clone());
+        map.upsert("ip_allowlist", self.ip_allowlist.clone());
+        map.upsert("secret_digest", self.secret_digest.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("rotated_at", self.rotated_at.to_string());
+        map.upsert("expires_at", self.expires_at.map(|dt| dt.to_string()));
+        map.upsert("last_used_at", self.last_used_at.map(|dt| dt.to_string()));
+        map.upsert("last_used_ip", self.last_used_ip.as_str());
+        ApiKeyRecord::try_from_map(&map)
+    }
+
+    /// Returns the `access_key_id` field.
+    #[inline]
+    pub fn access_key_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    /// Returns the `owner_id` field.
+    #[inline]
+    pub fn owner_id(&self) -> &str {
+        &self.owner_id
+    }
+}
+
+/// API key store backed by the `api_key` table.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::auth::ApiKeyManager;
+/// use zino_model::api_key::SqlApiKeyStore;
+///
+/// ApiKeyManager::register(ApiKeyManager::new(SqlApiKeyStore));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlApiKeyStore;
+
+impl ApiKeyStore for SqlApiKeyStore {
+    fn save_api_key<'a>(&'a self, api_key: &'a ApiKeyRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let filters = Map::from_entry("access_key_id", api_key.access_key_id());
+            let mut model = ApiKey::from_record(api_key);
+            if let Some(existing) = ApiKey::find_one::<ApiKey>(&Query::new(filters)).await? {
+                model.id = existing.id;
+                model.version = existing.version;
+            }
+            model.upsert().await?;
+            Ok(())
+        })
+    }
+
+    fn find_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<ApiKeyRecord>, Error>> {
+        Box::pin(async move {
+            // The write-only `secret_digest` is not decoded into the model.
+            let filters = Map::from_entry("access_key_id", access_key_id);
+            ApiKey::find_one::<Map>(&Query::new(filters))
+                .await?
+                .map(|api_key| ApiKeyRecord::try_from_map(&api_key))
+                .transpose()
+        })
+    }
+
+    fn touch_api_key<'a>(
+        &'a self,
+        access_key_id: &'a str,
+        last_used_at: DateTime,
+        last_used_ip: &'a str,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("access_key_id", access_key_id));
+            let mut updates = Map::from_entry("last_used_at", last_used_at);
+            updates.upsert("last_used_ip", last_used_ip);
+
+            let mut mutation = Mutation::new(updates);
+            ApiKey::update_one(&query, &mut mutation).await?;
+            Ok(())
+        })
+    }
+
+    fn list_api_keys<'a>(
+        &'a self,
+        owner_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<ApiKeyRecord>, Error>> {
+        Box::pin(async move {
+            let filters = Map::from_entry("owner_id", owner_id);
+            ApiKey::find::<ApiKey>(&Query::new(filters))
+                .await?
+                .iter()
+                .map(|api_key| api_key.to_record())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqlApiKeyStore;
+    use zino_core::auth::{ApiKeyManager, ApiKeyRecord};
+
+    #[tokio::test]
+    async fn it_touches_api_keys_without_overwriting() {
+        crate::prepare_test_database();
+
+        let manager = ApiKeyManager::new(SqlApiKeyStore);
+        let api_key = ApiKeyRecord::new("alice", "deploy");
+        let (mut api_key, secret_access_key) = manager.create_api_key(api_key).await.unwrap();
+        let access_key_id = api_key.access_key_id().to_owned();
+
+        let stored_key = manager.find_api_key(&access_key_id).await.unwrap().unwrap();
+        assert!(stored_key.verify_secret(&secret_access_key));
+        assert!(!stored_key.verify_secret(api_key.secret_digest()));
+
+        // Recording the usage of a stale copy does not resurrect the revoked key.
+        assert!(manager.revoke_api_key(&access_key_id).await.unwrap());
+        manager.touch_api_key(&mut api_key, None).await.unwrap();
+
+        let stored_key = manager.find_api_key(&access_key_id).await.unwrap().unwrap();
+        assert!(!stored_key.is_active());
+        assert!(stored_key.last_used_at().is_some());
+    }
+}
@@ -19,6 +19,8 @@ use crate::tag::Tag;
 #[cfg(feature = "maintainer-id")]
 use zino_core::auth::UserSession;
 
+mod oauth;
+
 /// The `application` model.
 #[derive(
     Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
@@ -46,6 +48,16 @@ pub struct Application {
     manager_id: Uuid, // user.id
     #[schema(not_null, unique, write_only)]
     access_key_id: String,
+    #[schema(write_only)]
+    client_secret: String,
+    #[schema(default_value = "Confidential")]
+    client_type: String,
+    #[schema(unique_items)]
+    redirect_uris: Vec<String>,
+    #[schema(unique_items)]
+    grant_types: Vec<String>,
+    #[schema(unique_items)]
+    scopes: Vec<String>,
     #[cfg(feature = "tags")]
     #[schema(reference = "Tag", index_type = "gin")]
     tags: Vec<Uuid>, // tag.id, tag.namespace = "*:application"
@@ -101,6 +113,29 @@ impl Model for Application {
                 Err(err) => validation.record_fail("manager_id", err),
             }
         }
+        if let Some(client_secret) = data.parse_string("client_secret") {
+            if let Err(err) = self.set_client_secret(&client_secret) {
+                validation.record_fail("client_secret", err);
+            }
+        }
+        if let Some(client_type) = data.parse_string("client_type") {
+            if ["Confidential", "Public"].contains(&client_type.as_ref()) {
+                self.client_type = client_type.into_owned();
+            } else {
+                validation.record("client_type", "should be `Confidential` or `Public`");
+            }
+        }
+        if let Some(redirect_uris) = data.parse_str_array("redirect_uris") {
+            self.redirect_uris = redirect_uris.into_iter().map(|s| s.to_owned()).collect();
+        }
+        if let Some(grant_types) = data.parse_str_array("grant_types") {
+            if let Err(err) = self.set_grant_types(grant_types) {
+                validation.record_fail("grant_types", err);
+            }
+        }
+        if let Some(scopes) = data.parse_str_array("scopes") {
+            self.scopes = scopes.into_iter().map(|s| s.to_owned()).collect();
+        }
         #[cfg(feature = "tags")]
         if let Some(result) = data.parse_array("tags") {
             match result {
@@ -151,6 +186,13 @@ impl ModelHooks for Application {
         }
         Ok(())
     }
+
+    async fn after_validation(&mut self, data: &mut Map) -> Result<(), Error> {
+        if data.contains_key("client_secret") {
+            data.upsert("client_secret", self.client_secret.clone());
+        }
+        Ok(())
+    }
 }
 
 impl Application {
@@ -160,3 +202,35 @@ impl Application {
         self.access_key_id = access_key_id.to_string();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Application;
+    use zino_core::{extension::JsonObjectExt, model::Model, Map};
+
+    #[test]
+    fn it_checks_oauth_clients() {
+        let mut client = Application::new();
+        let mut data = Map::new();
+        data.upsert("name", "console");
+        data.upsert("client_type", "Public");
+        data.upsert("grant_types", vec!["authorization_code", "refresh_token"]);
+        data.upsert(
+            "redirect_uris",
+            vec!["https://console.example.com/callback"],
+        );
+
+        let validation = client.read_map(&data);
+        assert!(validation.is_success());
+        assert!(client.is_public_client());
+        assert!(client.allows_grant_type("refresh_token"));
+        assert!(!client.allows_grant_type("client_credentials"));
+
+        data.upsert("client_type", "Trusted");
+        data.upsert("grant_types", vec!["password"]);
+
+        let validation = client.read_map(&data);
+        assert!(validation.contains_key("client_type"));
+        assert!(validation.contains_key("grant_types"));
+    }
+}
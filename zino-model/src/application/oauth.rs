@@ -0,0 +1,383 @@
+use super::Application;
+use crate::{consent::Consent, user::User};
+use zino_core::{
+    auth::AuthorizationServer,
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::Query,
+    orm::{ModelHelper, Schema},
+    warn, Map, Uuid,
+};
+
+/// Supported grant types.
+const GRANT_TYPES: [&str; 3] = ["authorization_code", "client_credentials", "refresh_token"];
+
+impl Application {
+    /// Sets the `client_secret`, which is encrypted before being stored.
+    pub fn set_client_secret(&mut self, client_secret: &str) -> Result<(), Error> {
+        if client_secret.len() < 16 {
+            bail!("the client secret should have at least 16 characters");
+        }
+        self.client_secret = Application::encrypt_password(client_secret)?;
+        Ok(())
+    }
+
+    /// Generates a new `client_secret` and returns the plaintext,
+    /// which can only be retrieved once.
+    pub fn generate_client_secret(&mut self) -> Result<String, Error> {
+        let client_secret = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        self.set_client_secret(&client_secret)?;
+        Ok(client_secret)
+    }
+
+    /// Sets the `grant_types` field.
+    pub fn set_grant_types(&mut self, grant_types: Vec<&str>) -> Result<(), Error> {
+        for grant_type in &grant_types {
+            if !GRANT_TYPES.contains(grant_type) {
+                bail!("the grant type `{}` is unsupported", grant_type);
+            }
+        }
+        self.grant_types = grant_types.into_iter().map(|s| s.to_owned()).collect();
+        Ok(())
+    }
+
+    /// Returns the OAuth client identifier, which is the `access_key_id`.
+    #[inline]
+    pub fn client_id(&self) -> &str {
+        &self.access_key_id
+    }
+
+    /// Returns `true` if the application is a public client
+    /// which can not keep the client secret confidential.
+    #[inline]
+    pub fn is_public_client(&self) -> bool {
+        self.client_type == "Public"
+    }
+
+    /// Returns `true` if the application is allowed to use the grant type.
+    #[inline]
+    pub fn allows_grant_type(&self, grant_type: &str) -> bool {
+        self.grant_types.iter().any(|s| s == grant_type)
+    }
+
+    /// Finds the active client application by the client ID.
+    pub async fn find_client(client_id: &str) -> Result<Self, Error> {
+        let mut query = Query::default();
+        query.add_filter("access_key_id", client_id);
+        query.add_filter("status", "Active");
+        Self::find_one::<Self>(&query)
+            .await?
+            .ok_or_else(|| warn!("401 Unauthorized: invalid_client: the client is unknown"))
+    }
+
+    /// Authenticates the client with the `Authorization` header or the request body.
+    /// A public client is identified by the `client_id` without a secret.
+    pub async fn authenticate_client(
+        params: &Map,
+        authorization: Option<&str>,
+    ) -> Result<Self, Error> {
+        let (client_id, client_secret) =
+            AuthorizationServer::parse_client_credentials(authorization, params)?;
+        let client = Self::find_client(&client_id).await?;
+        if !client.is_public_client() {
+            let verified = client_secret
+                .filter(|_| !client.client_secret.is_empty())
+                .map(|secret| Self::verify_password(&secret, &client.client_secret))
+                .transpose()?
+                .unwrap_or(false);
+            if !verified {
+                bail!("401 Unauthorized: invalid_client: the client authentication failed");
+            }
+        }
+        Ok(client)
+    }
+
+    /// Handles the authorization request of the authorization code grant
+    /// for the authenticated user, and returns the `redirect_uri` with the code.
+    ///
+    /// The request is rejected with `consent_required` if the user has not granted
+    /// the scopes to the client, unless the `consent` parameter is `true`,
+    /// which records the consent of the user.
+    pub async fn authorize(user_id: &Uuid, params: &Map) -> Result<Map, Error> {
+        if params.get_str("response_type") != Some("code") {
+            bail!("400 Bad Request: unsupported_response_type: only `code` is supported");
+        }
+
+        let client_id = params
+            .get_str("client_id")
+            .ok_or_else(|| warn!("400 Bad Request: invalid_request: `client_id` is absent"))?;
+        let client = Self::find_client(client_id).await?;
+        if !client.allows_grant_type("authorization_code") {
+            bail!("400 Bad Request: unauthorized_client: the grant type is not allowed");
+        }
+
+        let redirect_uri = match params.get_str("redirect_uri") {
+            Some(redirect_uri) => client
+                .redirect_uris
+                .iter()
+                .find(|&uri| uri == redirect_uri)
+                .ok_or_else(|| {
+                    warn!("400 Bad Request: invalid_request: `redirect_uri` is not registered")
+                })?,
+            None if client.redirect_uris.len() == 1 => &client.redirect_uris[0],
+            None => bail!("400 Bad Request: invalid_request: `redirect_uri` is absent"),
+        };
+        let scopes = client.requested_scopes(params.get_str("scope"))?;
+
+        let code_challenge = params.get_str("code_challenge");
+        if let Some(code_challenge) = code_challenge {
+            if params.get_str("code_challenge_method") != Some("S256") {
+                bail!("400 Bad Request: invalid_request: the `S256` method is required");
+            }
+            if code_challenge.len() != 43 {
+                bail!("400 Bad Request: invalid_request: `code_challenge` is invalid");
+            }
+        } else if client.is_public_client() {
+            bail!("400 Bad Request: invalid_request: PKCE is required for a public client");
+        }
+
+        let consent = Consent::find_active(user_id, &client.id).await?;
+        if !consent.is_some_and(|consent| consent.covers(&scopes)) {
+            if matches!(params.parse_bool("consent"), Some(Ok(true))) {
+                Consent::grant(user_id, &client.id, &scopes).await?;
+            } else {
+                bail!("403 Forbidden: consent_required: the user has not granted the scopes");
+            }
+        }
+
+        let scope = scopes.join(" ");
+        let mut grant = Map::new();
+        grant.upsert("client_id", client_id);
+        if params.contains_key("redirect_uri") {
+            grant.upsert("redirect_uri", redirect_uri.as_str());
+        }
+        grant.upsert("scope", scope.as_str());
+        if let Some(code_challenge) = code_challenge {
+            grant.upsert("code_challenge", code_challenge);
+            grant.upsert("code_challenge_method", "S256");
+        }
+        if let Some(nonce) = params.get_str("nonce") {
+            grant.upsert("nonce", nonce);
+        }
+
+        let server = AuthorizationServer::shared();
+        let state = params.get_str("state");
+        let code = server.issue_authorization_code(&user_id.to_string(), grant)?;
+        let mut data = Map::new();
+        data.upsert(
+            "redirect_uri",
+            server.authorization_response(redirect_uri, &code, state)?,
+        );
+        data.upsert("code", code);
+        data.upsert("state", state);
+        data.upsert("scope", scope);
+        Ok(data)
+    }
+
+    /// Handles the token request of the `authorization_code`, `refresh_token`
+    /// and `client_credentials` grants, and returns the token response.
+    pub async fn grant_token(params: &Map, authorization: Option<&str>) -> Result<Map, Error> {
+        let client = Self::authenticate_client(params, authorization).await?;
+        let client_id = client.client_id();
+        let grant_type = params.get_str("grant_type").unwrap_or_default();
+        if !GRANT_TYPES.contains(&grant_type) {
+            bail!("400 Bad Request: unsupported_grant_type: `{}`", grant_type);
+        }
+        if !client.allows_grant_type(grant_type) {
+            bail!("400 Bad Request: unauthorized_client: the grant type is not allowed");
+        }
+
+        let server = AuthorizationServer::shared();
+        let refreshable = client.allows_grant_type("refresh_token");
+        match grant_type {
+            "authorization_code" => {
+                let code = params
+                    .get_str("code")
+                    .ok_or_else(|| warn!("400 Bad Request: invalid_request: `code` is absent"))?;
+                let redirect_uri = params.get_str("redirect_uri");
+                let code_verifier = params.get_str("code_verifier");
+                let claims = server
+                    .redeem_authorization_code(code, client_id, redirect_uri, code_verifier)
+                    .await?;
+                let user_id = claims.subject().unwrap_or_default();
+                let scope = claims.data().get_str("scope").unwrap_or_default();
+                let nonce = claims.data().get_str("nonce");
+                client
+                    .issue_user_tokens(user_id, scope, nonce, refreshable)
+                    .await
+            }
+            "refresh_token" => {
+                let refresh_token = params.get_str("refresh_token").ok_or_else(|| {
+                    warn!("400 Bad Request: invalid_request: `refresh_token` is absent")
+                })?;
+                let claims = server
+                    .redeem_refresh_token(refresh_token, client_id)
+                    .await?;
+                let subject = claims.subject().unwrap_or_default();
+                let granted_scope = claims.data().get_str("scope").unwrap_or_default();
+                let scope = match params.get_str("scope") {
+                    Some(scope) => {
+                        let granted_scopes = granted_scope.split_whitespace().collect::<Vec<_>>();
+                        if !scope
+                            .split_whitespace()
+                            .all(|s| granted_scopes.contains(&s))
+                        {
+                            bail!("400 Bad Request: invalid_scope: the scope exceeds the grant");
+                        }
+                        scope
+                    }
+                    None => granted_scope,
+                };
+                let user_id = subject.parse::<Uuid>()?;
+                let scopes = scope.split_whitespace().collect::<Vec<_>>();
+                let consent = Consent::find_active(&user_id, &client.id).await?;
+                if !consent.is_some_and(|consent| consent.covers(&scopes)) {
+                    bail!("400 Bad Request: invalid_grant: the consent has been revoked");
+                }
+                client
+                    .issue_user_tokens(subject, scope, None, refreshable)
+                    .await
+            }
+            _ => {
+                if client.is_public_client() {
+                    bail!("400 Bad Request: unauthorized_client: a public client is not allowed");
+                }
+                let scope = client.requested_scopes(params.get_str("scope"))?.join(" ");
+                server.issue_tokens(client_id, client_id, &scope, false)
+            }
+        }
+    }
+
+    /// Handles the token introspection request as RFC 7662.
+    /// The caller should be an authenticated client.
+    pub async fn introspect_token(params: &Map, authorization: Option<&str>) -> Result<Map, Error> {
+        Self::authenticate_client(params, authorization).await?;
+        let token = params
+            .get_str("token")
+            .ok_or_else(|| warn!("400 Bad Request: invalid_request: `token` is absent"))?;
+        Ok(AuthorizationServer::shared().introspect(token))
+    }
+
+    /// Handles the token revocation request as RFC 7009.
+    pub async fn revoke_token(params: &Map, authorization: Option<&str>) -> Result<(), Error> {
+        let client = Self::authenticate_client(params, authorization).await?;
+        let token = params
+            .get_str("token")
+            .ok_or_else(|| warn!("400 Bad Request: invalid_request: `token` is absent"))?;
+        AuthorizationServer::shared()
+            .revoke(token, client.client_id())
+            .await
+    }
+
+    /// Returns the claims about the user authorized by the access token
+    /// for the OIDC userinfo endpoint.
+    pub async fn userinfo(access_token: &str) -> Result<Map, Error> {
+        let claims = AuthorizationServer::shared()
+            .verify_token(access_token)
+            .map_err(|err| warn!("401 Unauthorized: invalid_token: {}", err.message()))?;
+        let data = claims.data();
+        if data.get_str("token_use") != Some("access") {
+            bail!("401 Unauthorized: invalid_token: an access token is required");
+        }
+        let scope = data.get_str("scope").unwrap_or_default();
+        if !scope.split_whitespace().any(|s| s == "openid") {
+            bail!("403 Forbidden: insufficient_scope: the `openid` scope is required");
+        }
+
+        let user_id = claims
+            .subject()
+            .ok_or_else(|| warn!("401 Unauthorized: invalid_token: the subject is absent"))?;
+        standard_claims(user_id, scope).await
+    }
+
+    /// Returns the requested scopes, which should be a subset of the client scopes.
+    /// All of the client scopes are granted if the scope is not specified.
+    fn requested_scopes<'a>(&'a self, scope: Option<&'a str>) -> Result<Vec<&'a str>, Error> {
+        let Some(scope) = scope else {
+            return Ok(self.scopes.iter().map(|s| s.as_str()).collect());
+        };
+        let server_scopes = AuthorizationServer::shared().scopes();
+        let mut scopes = Vec::new();
+        for scope in scope.split_whitespace() {
+            if !(self.scopes.iter().any(|s| s == scope) && server_scopes.iter().any(|s| s == scope))
+            {
+                bail!(
+                    "400 Bad Request: invalid_scope: the scope `{}` is not allowed",
+                    scope
+                );
+            }
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+        Ok(scopes)
+    }
+
+    /// Issues the tokens on behalf of the user, and an ID token for the `openid` scope.
+    async fn issue_user_tokens(
+        &self,
+        user_id: &str,
+        scope: &str,
+        nonce: Option<&str>,
+        refreshable: bool,
+    ) -> Result<Map, Error> {
+        let server = AuthorizationServer::shared();
+        let claims = standard_claims(user_id, scope).await?;
+        let client_id = self.client_id();
+        let mut data = server.issue_tokens(user_id, client_id, scope, refreshable)?;
+        if scope.split_whitespace().any(|s| s == "openid") {
+            let id_token = server.issue_id_token(user_id, client_id, nonce, claims)?;
+            data.upsert("id_token", id_token);
+        }
+        Ok(data)
+    }
+}
+
+/// Fetches the standard claims of an active user for the scope.
+async fn standard_claims(user_id: &str, scope: &str) -> Result<Map, Error> {
+    let mut query = Query::default();
+    query.allow_fields(&[
+        "id",
+        "name",
+        "nickname",
+        "avatar",
+        "website",
+        "locale",
+        "email",
+        "updated_at",
+    ]);
+    query.add_filter("id", user_id);
+    query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+
+    let user: Map = User::find_one(&query).await?.ok_or_else(|| {
+        warn!(
+            "400 Bad Request: invalid_grant: the user `{}` is inactive",
+            user_id
+        )
+    })?;
+    let mut claims = Map::from_entry("sub", user_id);
+    for scope in scope.split_whitespace() {
+        match scope {
+            "profile" => {
+                claims.upsert("name", user.get("name").cloned());
+                claims.upsert("nickname", user.get("nickname").cloned());
+                claims.upsert("picture", user.get("avatar").cloned());
+                claims.upsert("website", user.get("website").cloned());
+                claims.upsert("locale", user.get("locale").cloned());
+                if let Some(updated_at) = user.get_str("updated_at") {
+                    if let Ok(updated_at) = updated_at.parse::<DateTime>() {
+                        claims.upsert("updated_at", updated_at.timestamp());
+                    }
+                }
+            }
+            "email" => {
+                claims.upsert("email", user.get("email").cloned());
+            }
+            _ => (),
+        }
+    }
+    claims.retain(|_, value| !(value.is_null() || value.as_str() == Some("")));
+    Ok(claims)
+}
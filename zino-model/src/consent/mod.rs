@@ -0,0 +1,148 @@
+//! The `consent` model and related services.
+
+use crate::{application::Application, user::User};
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `consent` model.
+///
+/// It records the scopes which a user has granted to an OAuth client application.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct Consent {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(read_only, reference = "User", index_type = "hash")]
+    user_id: Uuid, // user.id
+    #[schema(read_only, reference = "Application", index_type = "hash")]
+    application_id: Uuid, // application.id
+    #[schema(unique_items)]
+    scopes: Vec<String>,
+    granted_at: DateTime,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for Consent {
+    const MODEL_NAME: &'static str = "consent";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for Consent {
+    type Data = ();
+    type Extension = ();
+}
+
+impl Consent {
+    /// Finds the active consent of the user for the application.
+    pub async fn find_active(user_id: &Uuid, application_id: &Uuid) -> Result<Option<Self>, Error> {
+        let mut filters = Map::from_entry("user_id", user_id.to_string());
+        filters.upsert("application_id", application_id.to_string());
+        filters.upsert("status", "Active");
+        Self::find_one::<Self>(&Query::new(filters)).await
+    }
+
+    /// Grants the scopes to the application on behalf of the user.
+    /// The scopes are merged into the existing consent.
+    pub async fn grant(
+        user_id: &Uuid,
+        application_id: &Uuid,
+        scopes: &[&str],
+    ) -> Result<Self, Error> {
+        let mut consent = Self::find_active(user_id, application_id)
+            .await?
+            .unwrap_or_else(|| Self {
+                name: application_id.to_string(),
+                status: "Active".to_owned(),
+                user_id: *user_id,
+                application_id: *application_id,
+                ..Self::new()
+            });
+        for &scope in scopes {
+            if !consent.scopes.iter().any(|s| s == scope) {
+                consent.scopes.push(scope.to_owned());
+            }
+        }
+        consent.granted_at = DateTime::now();
+        consent.updated_at = DateTime::now();
+        consent.clone().upsert().await?;
+        Ok(consent)
+    }
+
+    /// Revokes the consent of the user for the application.
+    pub async fn revoke(user_id: &Uuid, application_id: &Uuid) -> Result<bool, Error> {
+        let mut filters = Map::from_entry("user_id", user_id.to_string());
+        filters.upsert("application_id", application_id.to_string());
+        filters.upsert("status", "Active");
+
+        let query = Query::new(filters);
+        let mut mutation = Mutation::new(Map::from_entry("status", "Revoked"));
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        Ok(ctx.rows_affected() == Some(1))
+    }
+
+    /// Returns `true` if the consent covers all the scopes.
+    #[inline]
+    pub fn covers(&self, scopes: &[&str]) -> bool {
+        scopes
+            .iter()
+            .all(|&scope| self.scopes.iter().any(|s| s == scope))
+    }
+
+    /// Returns the `scopes` field.
+    #[inline]
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+}
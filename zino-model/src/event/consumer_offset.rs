@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `consumer_offset` model.
+///
+/// The `name` field is the key `{consumer_group}:{partition}`,
+/// and the `committed_offset` field is the offset of the next event to consume.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct ConsumerOffset {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "unique")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    consumer_group: String,
+    #[schema(read_only)]
+    event_partition: u32,
+    committed_offset: u64,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for ConsumerOffset {
+    const MODEL_NAME: &'static str = "consumer_offset";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for ConsumerOffset {
+    type Data = ();
+    type Extension = ();
+}
+
+impl ConsumerOffset {
+    /// Returns the `consumer_group` field.
+    #[inline]
+    pub fn consumer_group(&self) -> &str {
+        &self.consumer_group
+    }
+
+    /// Returns the `event_partition` field.
+    #[inline]
+    pub fn event_partition(&self) -> u32 {
+        self.event_partition
+    }
+
+    /// Returns the `committed_offset` field.
+    #[inline]
+    pub fn committed_offset(&self) -> u64 {
+        self.committed_offset
+    }
+
+    /// Returns the committed offset of the consumer group in the partition.
+    pub(super) async fn get_offset(group: &str, partition: u32) -> Result<u64, Error> {
+        let query = Query::new(Map::from_entry("name", format!("{group}:{partition}")));
+        let model = Self::find_one::<Self>(&query).await?;
+        Ok(model
+            .map(|model| model.committed_offset)
+            .unwrap_or_default())
+    }
+
+    /// Returns the minimum committed offset of all the consumer groups in the partition.
+    pub(super) async fn min_committed_offset(partition: u32) -> Result<Option<u64>, Error> {
+        let mut query = Query::new(Map::from_entry("event_partition", partition));
+        query.order_asc("committed_offset");
+        query.set_limit(1);
+        let model = Self::find_one::<Self>(&query).await?;
+        Ok(model.map(|model| model.committed_offset))
+    }
+
+    /// Sets the committed offset of the consumer group in the partition.
+    /// If `rewind` is `false`, the offset will only be moved forward.
+    pub(super) async fn set_offset(
+        group: &str,
+        partition: u32,
+        offset: u64,
+        rewind: bool,
+    ) -> Result<(), Error> {
+        let name = format!("{group}:{partition}");
+        let mut filters = Map::from_entry("name", name.as_str());
+        if !rewind {
+            filters.upsert("committed_offset", Map::from_entry("$lt", offset));
+        }
+
+        let mut updates = Map::from_entry("committed_offset", offset);
+        updates.upsert("updated_at", DateTime::now());
+        updates.upsert("$inc", Map::from_entry("version", 1));
+
+        let query = Query::new(filters);
+        let mut mutation = Mutation::new(updates);
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() == Some(1) {
+            return Ok(());
+        }
+
+        let name_query = Query::new(Map::from_entry("name", name.as_str()));
+        if !Self::exists(&name_query).await? {
+            let model = Self {
+                name,
+                consumer_group: group.to_owned(),
+                event_partition: partition,
+                committed_offset: offset,
+                ..Self::new()
+            };
+            if let Err(err) = model.insert().await {
+                // The insertion fails on the unique index of `name`
+                // when the offset has been committed concurrently.
+                if !Self::exists(&name_query).await? {
+                    return Err(err);
+                }
+                Self::update_one(&query, &mut mutation).await?;
+            }
+        }
+        Ok(())
+    }
+}
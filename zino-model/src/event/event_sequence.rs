@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `event_sequence` model.
+///
+/// The `name` field is the partition, and the `next_offset` field is
+/// the offset of the next event to append in the partition.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct EventSequence {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "unique")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(read_only)]
+    event_partition: u32,
+    next_offset: u64,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for EventSequence {
+    const MODEL_NAME: &'static str = "event_sequence";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for EventSequence {
+    type Data = ();
+    type Extension = ();
+}
+
+impl EventSequence {
+    /// Returns the `event_partition` field.
+    #[inline]
+    pub fn event_partition(&self) -> u32 {
+        self.event_partition
+    }
+
+    /// Returns the `next_offset` field.
+    #[inline]
+    pub fn next_offset(&self) -> u64 {
+        self.next_offset
+    }
+
+    /// Creates the sequence of the partition if it does not exist.
+    pub(super) async fn init(partition: u32, next_offset: u64) -> Result<(), Error> {
+        let name = partition.to_string();
+        let query = Query::new(Map::from_entry("name", name.as_str()));
+        if Self::exists(&query).await? {
+            return Ok(());
+        }
+
+        let model = Self {
+            name,
+            event_partition: partition,
+            next_offset,
+            ..Self::new()
+        };
+        if let Err(err) = model.insert().await {
+            // The insertion fails on the unique index of `name`
+            // when the sequence has been created concurrently.
+            if !Self::exists(&query).await? {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
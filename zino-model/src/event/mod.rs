@@ -0,0 +1,421 @@
+//! The `event_log` model and related services.
+
+use crate::lease::SqlLeaseStore;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zino_core::{
+    bail,
+    channel::{partition_of, CloudEvent, EventBus, EventRecord, ReplayPosition},
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{DecodeRow, Model, ModelHooks},
+    orm::{DatabaseDriver, Executor, QueryBuilder, Transaction},
+    schedule::LeaseStore,
+    validation::Validation,
+    BoxFuture, JsonValue, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+mod consumer_offset;
+mod event_sequence;
+
+pub use consumer_offset::{ConsumerOffset, ConsumerOffsetColumn};
+pub use event_sequence::{EventSequence, EventSequenceColumn};
+
+/// The `event_log` model.
+///
+/// The `name` field is the log key `{partition}:{offset}`,
+/// whose unique index guards against appending two events with the same offset.
+/// The offsets are allocated from the `event_sequence` table.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct EventLog {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "unique")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(read_only, index_type = "hash")]
+    topic: String,
+    #[schema(read_only, index_type = "hash")]
+    source: String,
+    #[schema(read_only, index_type = "hash")]
+    session_id: String,
+    #[schema(read_only, index_type = "hash")]
+    event_partition: u32,
+    #[schema(read_only, index_type = "btree")]
+    event_offset: u64,
+    #[schema(read_only)]
+    event: Map,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for EventLog {
+    const MODEL_NAME: &'static str = "event_log";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for EventLog {
+    type Data = ();
+    type Extension = ();
+}
+
+impl EventLog {
+    /// Converts `self` to an event record.
+    pub fn to_record(&self) -> Result<EventRecord, Error> {
+        let mut event = self.event.clone();
+        if let Some(Ok(timestamp)) = event.parse_date_time("time") {
+            // The timestamp is serialized without the offset for some database drivers.
+            event.upsert("time", timestamp.to_string());
+        }
+
+        let event = CloudEvent::deserialize(JsonValue::Object(event))?;
+        Ok(EventRecord::new(
+            self.event_partition,
+            self.event_offset,
+            self.created_at,
+            event,
+        ))
+    }
+}
+
+/// Event bus backed by the `event_log`, `event_sequence` and `consumer_offset` tables.
+///
+/// The offsets are allocated by locking the sequence of the partition inside of
+/// the transaction which appends the event, so the concurrent publishers are serialized.
+/// A consumer polls a partition only while holding the lease `event:{group}:{partition}`,
+/// so the consumers within a group never receive the events in the same partition
+/// at the same time. If the lease expires before the events have been committed,
+/// they will be redelivered to another consumer in the group.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::channel::register_event_bus;
+/// use zino_model::event::SqlEventBus;
+///
+/// register_event_bus(SqlEventBus::new(4).with_max_lag(100000));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SqlEventBus {
+    /// Number of partitions.
+    partitions: u32,
+    /// Maximum number of events not committed by the slowest consumer group.
+    max_lag: Option<u64>,
+    /// Holder of the partition leases.
+    holder: String,
+    /// Time-to-live of the partition leases.
+    lease_ttl: Duration,
+}
+
+impl SqlEventBus {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(partitions: u32) -> Self {
+        Self {
+            partitions: partitions.max(1),
+            max_lag: None,
+            holder: format!("{}-{}", std::process::id(), Uuid::now_v7()),
+            lease_ttl: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the maximum number of events not committed by the slowest consumer group
+    /// in a partition. The events will be rejected if the lag exceeds the limit.
+    #[inline]
+    pub fn with_max_lag(mut self, max_lag: u64) -> Self {
+        self.max_lag = Some(max_lag);
+        self
+    }
+
+    /// Sets the time-to-live of the partition leases held by the consumer.
+    /// The events polled should be committed before the leases expire.
+    #[inline]
+    pub fn with_lease_ttl(mut self, ttl: Duration) -> Self {
+        self.lease_ttl = ttl.max(Duration::from_secs(1));
+        self
+    }
+
+    /// Sets the holder of the partition leases.
+    #[inline]
+    pub fn with_holder(mut self, holder: impl ToString) -> Self {
+        self.holder = holder.to_string();
+        self
+    }
+
+    /// Returns the offset of the first or last event in the partition.
+    async fn boundary_offset(partition: u32, earliest: bool) -> Result<Option<u64>, Error> {
+        let mut query = Query::new(Map::from_entry("event_partition", partition));
+        if earliest {
+            query.order_asc("event_offset");
+        } else {
+            query.order_desc("event_offset");
+        }
+        query.set_limit(1);
+        let model = EventLog::find_one::<EventLog>(&query).await?;
+        Ok(model.map(|model| model.event_offset))
+    }
+
+    /// Returns the offset of the next event to append in the partition.
+    async fn end_offset(partition: u32) -> Result<u64, Error> {
+        let offset = Self::boundary_offset(partition, false).await?;
+        Ok(offset.map(|offset| offset + 1).unwrap_or_default())
+    }
+
+    /// Appends the event to the partition inside of a transaction, and returns the offset.
+    /// It returns `None` if the sequence of the partition does not exist.
+    async fn append(
+        partition: u32,
+        event: &CloudEvent,
+        appended_at: DateTime,
+    ) -> Result<Option<u64>, Error> {
+        let name = partition.to_string();
+        let query = Query::new(Map::from_entry("name", name.as_str()));
+        let mut updates = Map::from_entry("updated_at", appended_at);
+        let mut inc_ops = Map::from_entry("next_offset", 1);
+        inc_ops.upsert("version", 1);
+        updates.upsert("$inc", inc_ops);
+
+        let mut mutation = Mutation::new(updates);
+        let update_ctx = EventSequence::prepare_update_one(&query, &mut mutation).await?;
+        let subquery = QueryBuilder::<EventSequence>::new()
+            .field(EventSequenceColumn::NextOffset)
+            .and_eq(EventSequenceColumn::Name, name)
+            .limit(1)
+            .build_subquery();
+        let select_sql = format!("SELECT * FROM {subquery} AS t;");
+        let model = EventLog {
+            topic: event.event_type().to_owned(),
+            source: event.source().to_owned(),
+            session_id: event.session_id().unwrap_or_default().to_owned(),
+            event_partition: partition,
+            event: event.clone().into_map(),
+            created_at: appended_at,
+            updated_at: appended_at,
+            ..EventLog::new()
+        };
+        EventSequence::transaction(move |tx: &mut sqlx::Transaction<'_, DatabaseDriver>| {
+            Box::pin(async move {
+                // The row of the sequence is locked until the transaction is committed.
+                let result = (&mut **tx).execute(update_ctx.query()).await?;
+                if result.rows_affected() != 1 {
+                    return Ok(None);
+                }
+
+                let row = (&mut **tx).fetch_one(&select_sql).await?;
+                let offset = EventSequence::decode_row(&row)?
+                    .next_offset()
+                    .saturating_sub(1);
+                let model = EventLog {
+                    name: format!("{partition}:{offset}"),
+                    event_offset: offset,
+                    ..model
+                };
+                let insert_ctx = model.prepare_insert().await?;
+                (&mut **tx).execute(insert_ctx.query()).await?;
+                Ok(Some(offset))
+            })
+        })
+        .await
+    }
+}
+
+impl Default for SqlEventBus {
+    #[inline]
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl EventBus for SqlEventBus {
+    #[inline]
+    fn partitions(&self) -> u32 {
+        self.partitions
+    }
+
+    fn publish(&self, event: CloudEvent) -> BoxFuture<'_, Result<EventRecord, Error>> {
+        Box::pin(async move {
+            let partition = partition_of(&event, self.partitions);
+            if let Some(max_lag) = self.max_lag {
+                let committed_offset = ConsumerOffset::min_committed_offset(partition).await?;
+                if let Some(committed_offset) = committed_offset {
+                    let offset = Self::end_offset(partition).await?;
+                    if offset.saturating_sub(committed_offset) >= max_lag {
+                        bail!(
+                            "429 Too Many Requests: the partition `{}` of the event bus is full",
+                            partition
+                        );
+                    }
+                }
+            }
+
+            let appended_at = DateTime::now();
+            let offset = match Self::append(partition, &event, appended_at).await? {
+                Some(offset) => offset,
+                None => {
+                    // The sequence starts from the end of the existing events in the partition.
+                    let next_offset = Self::end_offset(partition).await?;
+                    EventSequence::init(partition, next_offset).await?;
+                    let Some(offset) = Self::append(partition, &event, appended_at).await? else {
+                        bail!(
+                            "404 Not Found: the sequence of the partition `{}` does not exist",
+                            partition
+                        );
+                    };
+                    offset
+                }
+            };
+            Ok(EventRecord::new(partition, offset, appended_at, event))
+        })
+    }
+
+    fn poll<'a>(
+        &'a self,
+        group: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<EventRecord>, Error>> {
+        Box::pin(async move {
+            let mut records = Vec::new();
+            for partition in 0..self.partitions {
+                let remaining = limit.saturating_sub(records.len());
+                if remaining == 0 {
+                    break;
+                }
+
+                // Only the holder of the lease can poll the partition within the group.
+                let key = format!("event:{group}:{partition}");
+                let lease = SqlLeaseStore
+                    .acquire(&key, &self.holder, self.lease_ttl)
+                    .await?;
+                if lease.is_none() {
+                    continue;
+                }
+
+                let committed_offset = ConsumerOffset::get_offset(group, partition).await?;
+                let mut query = Query::new(Map::from_entry("event_partition", partition));
+                query.add_filter("event_offset", Map::from_entry("$ge", committed_offset));
+                query.order_asc("event_offset");
+                query.set_limit(remaining);
+                for model in EventLog::find::<EventLog>(&query).await? {
+                    records.push(model.to_record()?);
+                }
+            }
+            Ok(records)
+        })
+    }
+
+    fn commit<'a>(
+        &'a self,
+        group: &'a str,
+        record: &'a EventRecord,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let next_offset = record.offset() + 1;
+            ConsumerOffset::set_offset(group, record.partition(), next_offset, false).await
+        })
+    }
+
+    fn seek<'a>(
+        &'a self,
+        group: &'a str,
+        position: ReplayPosition,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            for partition in 0..self.partitions {
+                let offset = match position {
+                    ReplayPosition::Earliest => Self::boundary_offset(partition, true)
+                        .await?
+                        .unwrap_or_default(),
+                    ReplayPosition::Latest => Self::end_offset(partition).await?,
+                    ReplayPosition::Offset(offset) => offset,
+                    ReplayPosition::Timestamp(timestamp) => {
+                        let mut query = Query::new(Map::from_entry("event_partition", partition));
+                        query.add_filter("created_at", Map::from_entry("$ge", timestamp));
+                        query.order_asc("event_offset");
+                        query.set_limit(1);
+                        match EventLog::find_one::<EventLog>(&query).await? {
+                            Some(model) => model.event_offset,
+                            None => Self::end_offset(partition).await?,
+                        }
+                    }
+                };
+                ConsumerOffset::set_offset(group, partition, offset, true).await?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqlEventBus;
+    use zino_core::channel::{CloudEvent, EventBus};
+
+    #[tokio::test]
+    async fn it_appends_and_polls_events_exclusively() {
+        crate::prepare_test_database();
+
+        let bus = SqlEventBus::new(1);
+        let event = |id: &str| CloudEvent::new(id, "orders", "order.created");
+        assert_eq!(bus.publish(event("a")).await.unwrap().offset(), 0);
+
+        let (b, c, d) = tokio::join!(
+            bus.publish(event("b")),
+            bus.publish(event("c")),
+            bus.publish(event("d")),
+        );
+        let mut offsets = [b, c, d].map(|record| record.unwrap().offset());
+        offsets.sort_unstable();
+        assert_eq!(offsets, [1, 2, 3]);
+
+        let records = bus.poll("billing", 10).await.unwrap();
+        assert_eq!(records.len(), 4);
+
+        // Another consumer in the same group can not poll the partition.
+        let another_bus = SqlEventBus::new(1);
+        assert!(another_bus.poll("billing", 10).await.unwrap().is_empty());
+        assert_eq!(another_bus.poll("shipping", 10).await.unwrap().len(), 4);
+
+        bus.commit("billing", &records[3]).await.unwrap();
+        assert!(bus.poll("billing", 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    schedule::TickStore,
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `job_checkpoint` model.
+///
+/// The `name` field is the job name, and the `last_tick` field is the last time
+/// when the job was executed, which is used for the missed ticks after a restart.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct JobCheckpoint {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "unique")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(index_type = "btree")]
+    last_tick: DateTime,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for JobCheckpoint {
+    const MODEL_NAME: &'static str = "job_checkpoint";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for JobCheckpoint {
+    type Data = ();
+    type Extension = ();
+}
+
+impl JobCheckpoint {
+    /// Returns the `last_tick` field.
+    #[inline]
+    pub fn last_tick(&self) -> DateTime {
+        self.last_tick
+    }
+}
+
+/// Tick store backed by the `job_checkpoint` table.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::schedule::AsyncJobScheduler;
+/// use zino_model::job::SqlTickStore;
+///
+/// let scheduler = AsyncJobScheduler::new().with_tick_store(SqlTickStore);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlTickStore;
+
+impl TickStore for SqlTickStore {
+    fn load_tick<'a>(
+        &'a self,
+        job_name: &'a str,
+    ) -> BoxFuture<'a, Result<Option<DateTime>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("name", job_name));
+            let checkpoint = JobCheckpoint::find_one::<JobCheckpoint>(&query).await?;
+            Ok(checkpoint.map(|checkpoint| checkpoint.last_tick))
+        })
+    }
+
+    fn save_tick<'a>(
+        &'a self,
+        job_name: &'a str,
+        last_tick: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("name", job_name));
+            if !JobCheckpoint::exists(&query).await? {
+                let checkpoint = JobCheckpoint {
+                    name: job_name.to_owned(),
+                    last_tick,
+                    ..JobCheckpoint::new()
+                };
+                match checkpoint.insert().await {
+                    Ok(_) => return Ok(()),
+                    Err(err) => {
+                        // The insertion fails on the unique index of `name`
+                        // when the checkpoint has been saved concurrently.
+                        if !JobCheckpoint::exists(&query).await? {
+                            return Err(err);
+                        }
+                    }
+                }
+            }
+
+            let mut updates = Map::new();
+            updates.upsert("last_tick", last_tick);
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let mut mutation = Mutation::new(updates);
+            JobCheckpoint::update_one(&query, &mut mutation).await?;
+            Ok(())
+        })
+    }
+}
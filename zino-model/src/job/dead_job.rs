@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    schedule::JobRecord,
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `dead_job` model.
+///
+/// A job is moved into the dead-letter table after exhausting the attempts,
+/// and the `updated_at` field is the time when it was moved.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct DeadJob {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Dead", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    queue: String,
+    #[schema(read_only)]
+    payload: Map,
+    attempts: u32,
+    max_attempts: u32,
+    #[schema(comment = "Timeout in milliseconds")]
+    timeout: u64,
+    unique_key: String,
+    last_error: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for DeadJob {
+    const MODEL_NAME: &'static str = "dead_job";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for DeadJob {
+    type Data = ();
+    type Extension = ();
+}
+
+impl DeadJob {
+    /// Creates a new instance from the job record.
+    pub fn from_record(job: &JobRecord) -> Self {
+        Self {
+            id: job.id(),
+            name: job.job_name().to_owned(),
+            queue: job.queue().to_owned(),
+            payload: job.payload().clone(),
+            attempts: job.attempts(),
+            max_attempts: job.max_attempts(),
+            timeout: job.timeout().as_millis() as u64,
+            unique_key: job.unique_key().unwrap_or_default().to_owned(),
+            last_error: job.last_error().to_owned(),
+            created_at: job.created_at(),
+            updated_at: DateTime::now(),
+            ..Self::default()
+        }
+    }
+
+    /// Converts `self` to a job record.
+    pub fn to_record(&self) -> Result<JobRecord, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("queue", self.queue.as_str());
+        map.upsert("job_name", self.name.as_str());
+        map.upsert("payload", self.payload.clone());
+        map.upsert("status", "Dead");
+        map.upsert("attempts", self.attempts);
+        map.upsert("max_attempts", self.max_attempts);
+        map.upsert("timeout", self.timeout);
+        map.upsert("unique_key", self.unique_key.as_str());
+        map.upsert("run_at", self.updated_at.to_string());
+        map.upsert("last_error", self.last_error.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        JobRecord::try_from_map(&map)
+    }
+
+    /// Returns the `queue` field.
+    #[inline]
+    pub fn queue(&self) -> &str {
+        &self.queue
+    }
+
+    /// Returns the `last_error` field.
+    #[inline]
+    pub fn last_error(&self) -> &str {
+        &self.last_error
+    }
+}
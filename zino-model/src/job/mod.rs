@@ -0,0 +1,386 @@
+//! The `queued_job` model and related services.
+
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    orm::{DatabaseDriver, Executor, Transaction},
+    schedule::{JobRecord, JobStore},
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+mod checkpoint;
+mod dead_job;
+
+pub use checkpoint::{JobCheckpoint, JobCheckpointColumn, SqlTickStore};
+pub use dead_job::{DeadJob, DeadJobColumn};
+
+/// The `queued_job` model.
+///
+/// The `run_at` field is the scheduled time of a pending job,
+/// or the lease expiration of a running job.
+///
+/// The `dedup_key` field is `{queue}:{unique_key}` for an unfinished job with a unique key,
+/// or the job ID otherwise, whose unique index guards against enqueueing duplicated jobs.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct QueuedJob {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Pending", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    queue: String,
+    #[schema(read_only)]
+    payload: Map,
+    attempts: u32,
+    max_attempts: u32,
+    #[schema(comment = "Timeout in milliseconds")]
+    timeout: u64,
+    #[schema(read_only, index_type = "hash")]
+    unique_key: String,
+    #[schema(not_null, index_type = "unique")]
+    dedup_key: String,
+    #[schema(index_type = "btree")]
+    run_at: DateTime,
+    last_error: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for QueuedJob {
+    const MODEL_NAME: &'static str = "queued_job";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for QueuedJob {
+    type Data = ();
+    type Extension = ();
+}
+
+impl QueuedJob {
+    /// Creates a new instance from the job record.
+    pub fn from_record(job: &JobRecord) -> Self {
+        Self {
+            id: job.id(),
+            name: job.job_name().to_owned(),
+            status: job.status().to_string(),
+            queue: job.queue().to_owned(),
+            payload: job.payload().clone(),
+            attempts: job.attempts(),
+            max_attempts: job.max_attempts(),
+            timeout: job.timeout().as_millis() as u64,
+            unique_key: job.unique_key().unwrap_or_default().to_owned(),
+            dedup_key: Self::dedup_key(job),
+            run_at: job.run_at(),
+            last_error: job.last_error().to_owned(),
+            created_at: job.created_at(),
+            updated_at: job.updated_at(),
+            ..Self::default()
+        }
+    }
+
+    /// Converts `self` to a job record.
+    pub fn to_record(&self) -> Result<JobRecord, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("queue", self.queue.as_str());
+        map.upsert("job_name", self.name.as_str());
+        map.upsert("payload", self.payload.clone());
+        map.upsert("status", self.status.as_str());
+        map.upsert("attempts", self.attempts);
+        map.upsert("max_attempts", self.max_attempts);
+        map.upsert("timeout", self.timeout);
+        map.upsert("unique_key", self.unique_key.as_str());
+        map.upsert("run_at", self.run_at.to_string());
+        map.upsert("last_error", self.last_error.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        JobRecord::try_from_map(&map)
+    }
+
+    /// Returns the deduplication key of the job.
+    fn dedup_key(job: &JobRecord) -> String {
+        match job.unique_key() {
+            Some(unique_key) if job.status().is_unfinished() => {
+                format!("{}:{}", job.queue(), unique_key)
+            }
+            _ => job.id().to_string(),
+        }
+    }
+
+    /// Returns the filters to match the job which is running with the same claim.
+    fn claim_filters(job: &JobRecord) -> Map {
+        let mut filters = Map::from_entry("id", job.id().to_string());
+        filters.upsert("status", "Running");
+        filters.upsert("attempts", job.attempts());
+        filters
+    }
+
+    /// Enqueues the job inside of a transaction, so that the job will only be visible
+    /// to the workers after the transaction has been committed.
+    /// It returns `false` if there is an unfinished job with the same unique key.
+    /// If a job with the same unique key is enqueued concurrently, the insertion
+    /// will fail on the unique index of `dedup_key` and the transaction should be rolled back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use zino_core::orm::{Executor, Schema, Transaction};
+    /// use zino_model::job::QueuedJob;
+    ///
+    /// let record = JobQueue::shared().unwrap().prepare_job(&email, JobOptions::new())?;
+    /// User::transaction(move |tx| {
+    ///     Box::pin(async move {
+    ///         let ctx = user.prepare_insert().await?;
+    ///         (&mut **tx).execute(ctx.query()).await?;
+    ///         QueuedJob::enqueue_in(tx, &record).await
+    ///     })
+    /// })
+    /// .await?;
+    /// ```
+    pub async fn enqueue_in(
+        tx: &mut sqlx::Transaction<'_, DatabaseDriver>,
+        job: &JobRecord,
+    ) -> Result<bool, Error> {
+        if let Some(unique_key) = job.unique_key() {
+            if SqlJobStore::has_unfinished(job.queue(), unique_key).await? {
+                return Ok(false);
+            }
+        }
+
+        let ctx = Self::from_record(job).prepare_insert().await?;
+        (&mut **tx).execute(ctx.query()).await?;
+        Ok(true)
+    }
+}
+
+/// Job store backed by the `queued_job` and `dead_job` tables.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::schedule::JobQueue;
+/// use zino_model::job::SqlJobStore;
+///
+/// JobQueue::register(JobQueue::new(SqlJobStore).with_handler::<SendEmail>());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlJobStore;
+
+impl SqlJobStore {
+    /// Returns `true` if there is an unfinished job with the unique key in the queue.
+    /// The unique index of `dedup_key` is the source of truth, and this check is
+    /// used to skip an insertion or to find out the cause of an insertion failure.
+    async fn has_unfinished(queue: &str, unique_key: &str) -> Result<bool, Error> {
+        let mut query = Query::default();
+        query.add_filter("queue", queue);
+        query.add_filter("unique_key", unique_key);
+        query.add_filter("status", Map::from_entry("$in", vec!["Pending", "Running"]));
+        QueuedJob::exists(&query).await
+    }
+}
+
+impl JobStore for SqlJobStore {
+    fn push<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let Some(unique_key) = job.unique_key() else {
+                QueuedJob::from_record(job).insert().await?;
+                return Ok(true);
+            };
+            match QueuedJob::from_record(job).insert().await {
+                Ok(_) => Ok(true),
+                Err(err) => {
+                    // The insertion fails on the unique index of `dedup_key`.
+                    if Self::has_unfinished(job.queue(), unique_key).await? {
+                        Ok(false)
+                    } else {
+                        Err(err)
+                    }
+                }
+            }
+        })
+    }
+
+    fn claim<'a>(
+        &'a self,
+        queue: &'a str,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<JobRecord>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::default();
+            query.add_filter("queue", queue);
+            query.add_filter("status", Map::from_entry("$in", vec!["Pending", "Running"]));
+            query.add_filter("run_at", Map::from_entry("$le", DateTime::now()));
+            query.order_asc("run_at");
+            query.set_limit(limit);
+
+            let mut claimed_jobs = Vec::new();
+            for model in QueuedJob::find::<QueuedJob>(&query).await? {
+                let mut job = model.to_record()?;
+                job.claim();
+
+                // Optimistic locking by the version to avoid claiming a job twice.
+                let mut filters = Map::from_entry("id", model.id.to_string());
+                filters.upsert("version", model.version);
+
+                let mut updates = Map::new();
+                updates.upsert("status", job.status().as_str());
+                updates.upsert("attempts", job.attempts());
+                updates.upsert("run_at", job.run_at());
+                updates.upsert("updated_at", job.updated_at());
+                updates.upsert("version", model.version + 1);
+
+                let query = Query::new(filters);
+                let mut mutation = Mutation::new(updates);
+                let ctx = QueuedJob::update_one(&query, &mut mutation).await?;
+                if ctx.rows_affected() == Some(1) {
+                    claimed_jobs.push(job);
+                }
+            }
+            Ok(claimed_jobs)
+        })
+    }
+
+    fn renew<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut updates = Map::new();
+            updates.upsert("run_at", job.run_at());
+            updates.upsert("updated_at", job.updated_at());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(QueuedJob::claim_filters(job));
+            let mut mutation = Mutation::new(updates);
+            let ctx = QueuedJob::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn save<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut updates = Map::new();
+            updates.upsert("status", job.status().as_str());
+            updates.upsert("dedup_key", QueuedJob::dedup_key(job));
+            updates.upsert("run_at", job.run_at());
+            updates.upsert("last_error", job.last_error());
+            updates.upsert("updated_at", job.updated_at());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(QueuedJob::claim_filters(job));
+            let mut mutation = Mutation::new(updates);
+            let ctx = QueuedJob::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn bury<'a>(&'a self, job: &'a JobRecord) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let insert_ctx = DeadJob::from_record(job).prepare_insert().await?;
+            let query = Query::new(QueuedJob::claim_filters(job));
+            let delete_ctx = QueuedJob::prepare_delete_many(&query).await?;
+            QueuedJob::transaction(move |tx: &mut sqlx::Transaction<'_, DatabaseDriver>| {
+                Box::pin(async move {
+                    // The job is moved only if it has not been claimed by another worker.
+                    let result = (&mut **tx).execute(delete_ctx.query()).await?;
+                    if result.rows_affected() != 1 {
+                        return Ok(false);
+                    }
+                    (&mut **tx).execute(insert_ctx.query()).await?;
+                    Ok(true)
+                })
+            })
+            .await
+        })
+    }
+
+    fn list_dead_jobs<'a>(
+        &'a self,
+        queue: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<JobRecord>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::new(Map::from_entry("queue", queue));
+            query.order_desc("updated_at");
+            DeadJob::find::<DeadJob>(&query)
+                .await?
+                .iter()
+                .map(|job| job.to_record())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqlJobStore;
+    use zino_core::{
+        schedule::{JobRecord, JobStore},
+        Map,
+    };
+
+    #[tokio::test]
+    async fn it_saves_only_the_claimed_jobs() {
+        crate::prepare_test_database();
+
+        let store = SqlJobStore;
+        let job = JobRecord::new("thumbnails", "resize", Map::new());
+        assert!(store.push(&job).await.unwrap());
+
+        let claimed_jobs = store.claim("thumbnails", 1).await.unwrap();
+        assert_eq!(claimed_jobs.len(), 1);
+        assert!(store.renew(&claimed_jobs[0]).await.unwrap());
+
+        // A stale copy of the job can not overwrite or bury the claimed one.
+        assert!(!store.save(&job).await.unwrap());
+        assert!(!store.bury(&job).await.unwrap());
+        assert!(store.list_dead_jobs("thumbnails").await.unwrap().is_empty());
+
+        assert!(store.bury(&claimed_jobs[0]).await.unwrap());
+        assert!(!store.bury(&claimed_jobs[0]).await.unwrap());
+        assert_eq!(store.list_dead_jobs("thumbnails").await.unwrap().len(), 1);
+    }
+}
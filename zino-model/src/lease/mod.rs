@@ -0,0 +1,229 @@
+//! The `lease` model and related services.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    schedule::{Lease, LeaseStore},
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `lease` model.
+///
+/// The `name` field is the lease key, and the `token` field is the fencing token
+/// which is incremented each time the lease is granted to a new holder.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct SchedulerLease {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "unique")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    holder: String,
+    token: u64,
+    #[schema(index_type = "btree")]
+    expires_at: DateTime,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for SchedulerLease {
+    const MODEL_NAME: &'static str = "lease";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for SchedulerLease {
+    type Data = ();
+    type Extension = ();
+}
+
+impl SchedulerLease {
+    /// Returns the `holder` field.
+    #[inline]
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// Returns the `token` field.
+    #[inline]
+    pub fn token(&self) -> u64 {
+        self.token
+    }
+
+    /// Returns the `expires_at` field.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+}
+
+/// Lease store backed by the `lease` table.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::schedule::LeaseManager;
+/// use zino_model::lease::SqlLeaseStore;
+///
+/// LeaseManager::register(LeaseManager::new(SqlLeaseStore));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlLeaseStore;
+
+impl SqlLeaseStore {
+    /// Updates the lease which matches the filters, and returns `true` if it succeeds.
+    async fn update_lease(filters: Map, updates: Map) -> Result<bool, Error> {
+        let mut updates = updates;
+        updates.upsert("updated_at", DateTime::now());
+        updates.upsert("$inc", Map::from_entry("version", 1));
+
+        let query = Query::new(filters);
+        let mut mutation = Mutation::new(updates);
+        let ctx = SchedulerLease::update_one(&query, &mut mutation).await?;
+        Ok(ctx.rows_affected() == Some(1))
+    }
+
+    /// Returns the filters for the lease held by the holder with the same token.
+    fn held_lease_filters(lease: &Lease) -> Map {
+        let mut filters = Map::from_entry("name", lease.key());
+        filters.upsert("holder", lease.holder());
+        filters.upsert("token", lease.token());
+        filters.upsert("expires_at", Map::from_entry("$gt", DateTime::now()));
+        filters
+    }
+}
+
+impl LeaseStore for SqlLeaseStore {
+    fn acquire<'a>(
+        &'a self,
+        key: &'a str,
+        holder: &'a str,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("name", key));
+            let Some(model) = SchedulerLease::find_one::<SchedulerLease>(&query).await? else {
+                let lease = Lease::new(key, holder, 1, ttl);
+                let model = SchedulerLease {
+                    name: key.to_owned(),
+                    holder: holder.to_owned(),
+                    token: lease.token(),
+                    expires_at: lease.expires_at(),
+                    ..SchedulerLease::new()
+                };
+                return match model.insert().await {
+                    Ok(_) => Ok(Some(lease)),
+                    Err(err) => {
+                        // The insertion fails on the unique index of `name`
+                        // when the lease has been acquired concurrently.
+                        if SchedulerLease::exists(&query).await? {
+                            tracing::debug!(key, "fail to insert the lease: {err}");
+                            Ok(None)
+                        } else {
+                            Err(err)
+                        }
+                    }
+                };
+            };
+
+            let is_expired = model.expires_at <= DateTime::now();
+            if !is_expired && model.holder != holder {
+                return Ok(None);
+            }
+
+            let token = if is_expired {
+                model.token + 1
+            } else {
+                model.token
+            };
+            let lease = Lease::new(key, holder, token, ttl);
+
+            // Optimistic locking by the version.
+            let mut filters = Map::from_entry("name", key);
+            filters.upsert("version", model.version);
+
+            let mut updates = Map::from_entry("holder", holder);
+            updates.upsert("token", token);
+            updates.upsert("expires_at", lease.expires_at());
+            if Self::update_lease(filters, updates).await? {
+                Ok(Some(lease))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn renew<'a>(
+        &'a self,
+        lease: &'a Lease,
+        ttl: Duration,
+    ) -> BoxFuture<'a, Result<Option<Lease>, Error>> {
+        Box::pin(async move {
+            let mut renewed_lease = lease.clone();
+            renewed_lease.extend(ttl);
+
+            let filters = Self::held_lease_filters(lease);
+            let updates = Map::from_entry("expires_at", renewed_lease.expires_at());
+            if Self::update_lease(filters, updates).await? {
+                Ok(Some(renewed_lease))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    fn release<'a>(&'a self, lease: &'a Lease) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let filters = Self::held_lease_filters(lease);
+            let updates = Map::from_entry("expires_at", DateTime::now());
+            Self::update_lease(filters, updates).await?;
+            Ok(())
+        })
+    }
+}
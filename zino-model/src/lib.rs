@@ -5,40 +5,75 @@
 #![allow(async_fn_in_trait)]
 #![forbid(unsafe_code)]
 
+pub mod api_key;
+pub mod consent;
 pub mod group;
 pub mod policy;
 pub mod resource;
+pub mod session;
 pub mod tag;
 pub mod user;
 
 pub mod application;
 pub mod message;
 pub mod order;
+pub mod outbox;
+pub mod webhook;
 
 pub mod collection;
 pub mod dataset;
+pub mod event;
+pub mod job;
+pub mod lease;
 pub mod project;
 pub mod source;
 pub mod task;
+pub mod workflow;
 
 pub mod log;
 pub mod record;
 
+pub use api_key::{ApiKey, ApiKeyColumn};
+pub use consent::{Consent, ConsentColumn};
 pub use group::{Group, GroupColumn};
 pub use policy::{Policy, PolicyColumn};
 pub use resource::{Resource, ResourceColumn};
+pub use session::{Session, SessionColumn};
 pub use tag::{Tag, TagColumn};
 pub use user::{User, UserColumn};
 
 pub use application::{Application, ApplicationColumn};
 pub use message::{Message, MessageColumn};
 pub use order::{Order, OrderColumn};
+pub use outbox::{OutboxEvent, OutboxEventColumn};
+pub use webhook::{DeliveryLog, DeliveryLogColumn, WebhookEndpoint, WebhookEndpointColumn};
 
 pub use collection::{Collection, CollectionColumn};
 pub use dataset::{Dataset, DatasetColumn};
+pub use event::{
+    ConsumerOffset, ConsumerOffsetColumn, EventLog, EventLogColumn, EventSequence,
+    EventSequenceColumn,
+};
+pub use job::{
+    DeadJob, DeadJobColumn, JobCheckpoint, JobCheckpointColumn, QueuedJob, QueuedJobColumn,
+};
+pub use lease::{SchedulerLease, SchedulerLeaseColumn};
 pub use project::{Project, ProjectColumn};
 pub use source::{Source, SourceColumn};
 pub use task::{Task, TaskColumn};
+pub use workflow::{WorkflowEvent, WorkflowEventColumn, WorkflowInstance, WorkflowInstanceColumn};
 
 pub use log::{Log, LogColumn};
 pub use record::{Record, RecordColumn};
+
+/// Prepares an empty SQLite database configured in `config/config.dev.toml`.
+/// It is only done once since the tests share the connection pool.
+#[cfg(test)]
+pub(crate) fn prepare_test_database() {
+    static PREPARE: std::sync::Once = std::sync::Once::new();
+    PREPARE.call_once(|| {
+        let data_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/local/data");
+        std::fs::create_dir_all(data_dir).unwrap();
+        std::fs::remove_file(format!("{data_dir}/test.db")).ok();
+    });
+}
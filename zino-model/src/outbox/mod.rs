@@ -0,0 +1,480 @@
+//! The `outbox_event` model and related services.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, time::Duration};
+use zino_core::{
+    channel::{CloudEvent, EventBus},
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    orm::{DatabaseDriver, Executor},
+    schedule::AsyncScheduler,
+    validation::Validation,
+    JsonValue, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `outbox_event` model.
+///
+/// An outbox event is written in the same transaction as the model change,
+/// and published later by the [`OutboxRelay`]. The events with the same aggregate
+/// are published in the order of their IDs. The `claimed_until` field is the time
+/// until which the event is claimed by a relay.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct OutboxEvent {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Pending", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    aggregate_type: String,
+    #[schema(not_null, read_only, index_type = "hash")]
+    aggregate_id: String,
+    #[schema(read_only)]
+    event: Map,
+    attempts: u32,
+    last_error: String,
+    #[schema(index_type = "btree")]
+    claimed_until: DateTime,
+    delivered_at: Option<DateTime>,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for OutboxEvent {
+    const MODEL_NAME: &'static str = "outbox_event";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for OutboxEvent {
+    type Data = ();
+    type Extension = ();
+}
+
+impl OutboxEvent {
+    /// Creates a new instance for the cloud event of an aggregate.
+    pub fn with_event(
+        aggregate_type: impl ToString,
+        aggregate_id: impl ToString,
+        event: CloudEvent,
+    ) -> Self {
+        Self {
+            name: event.event_type().to_owned(),
+            status: "Pending".to_owned(),
+            aggregate_type: aggregate_type.to_string(),
+            aggregate_id: aggregate_id.to_string(),
+            event: event.into_map(),
+            ..Self::new()
+        }
+    }
+
+    /// Returns the `aggregate_type` field.
+    #[inline]
+    pub fn aggregate_type(&self) -> &str {
+        &self.aggregate_type
+    }
+
+    /// Returns the `aggregate_id` field.
+    #[inline]
+    pub fn aggregate_id(&self) -> &str {
+        &self.aggregate_id
+    }
+
+    /// Returns the `attempts` field.
+    #[inline]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns the `claimed_until` field.
+    #[inline]
+    pub fn claimed_until(&self) -> DateTime {
+        self.claimed_until
+    }
+
+    /// Returns the `delivered_at` field.
+    #[inline]
+    pub fn delivered_at(&self) -> Option<DateTime> {
+        self.delivered_at
+    }
+
+    /// Returns the key of the aggregate.
+    #[inline]
+    fn aggregate_key(&self) -> String {
+        format!("{}:{}", self.aggregate_type, self.aggregate_id)
+    }
+
+    /// Decodes the `event` field as a cloud event.
+    pub fn to_event(&self) -> Result<CloudEvent, Error> {
+        let event = CloudEvent::deserialize(JsonValue::Object(self.event.clone()))?;
+        Ok(event)
+    }
+
+    /// Writes the outbox event inside of a transaction, so that the event will only be
+    /// published after the model change has been committed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use zino_core::{channel::CloudEvent, orm::{Executor, Schema, Transaction}};
+    /// use zino_model::outbox::OutboxEvent;
+    ///
+    /// let event = CloudEvent::new(Uuid::now_v7(), "user-service", "user.created");
+    /// let outbox_event = OutboxEvent::with_event("user", user.id(), event);
+    /// User::transaction(move |tx| {
+    ///     Box::pin(async move {
+    ///         let ctx = user.prepare_insert().await?;
+    ///         (&mut **tx).execute(ctx.query()).await?;
+    ///         outbox_event.write_in(tx).await
+    ///     })
+    /// })
+    /// .await?;
+    /// ```
+    pub async fn write_in(
+        self,
+        tx: &mut sqlx::Transaction<'_, DatabaseDriver>,
+    ) -> Result<(), Error> {
+        let ctx = self.prepare_insert().await?;
+        (&mut **tx).execute(ctx.query()).await?;
+        Ok(())
+    }
+}
+
+/// A relay which publishes the pending outbox events to the event bus
+/// and marks them delivered.
+///
+/// Each event is claimed by an optimistic update of the version before publishing,
+/// so that multiple relays in a cluster do not publish the same event. If an event
+/// is claimed by another relay or fails to be published, the later events of the same
+/// aggregate are held until it succeeds. An event exceeding the maximum attempts
+/// is marked as `Dead`, and the aggregate is blocked until the dead event is handled.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::channel::MessageChannel;
+/// use zino_model::outbox::OutboxRelay;
+///
+/// let relay = OutboxRelay::new(MessageChannel::new());
+/// app.run_with((scheduler, relay));
+/// ```
+pub struct OutboxRelay {
+    /// Event bus to publish the events.
+    bus: Box<dyn EventBus>,
+    /// Maximum number of events in a batch.
+    batch_size: usize,
+    /// Maximum number of attempts.
+    max_attempts: u32,
+    /// Interval between polling.
+    poll_interval: Duration,
+    /// Timeout of the claims.
+    claim_timeout: Duration,
+}
+
+impl OutboxRelay {
+    /// Creates a new instance.
+    #[inline]
+    pub fn new(bus: impl EventBus + 'static) -> Self {
+        Self {
+            bus: Box::new(bus),
+            batch_size: 100,
+            max_attempts: 10,
+            poll_interval: Duration::from_secs(1),
+            claim_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets the maximum number of events in a batch.
+    #[inline]
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the maximum number of attempts.
+    #[inline]
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Sets the interval between polling.
+    #[inline]
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the timeout of the claims, after which an unfinished event
+    /// can be claimed by another relay.
+    #[inline]
+    pub fn with_claim_timeout(mut self, claim_timeout: Duration) -> Self {
+        self.claim_timeout = claim_timeout;
+        self
+    }
+
+    /// Publishes a batch of pending events, and returns the number of delivered events.
+    pub async fn relay(&self) -> Result<usize, Error> {
+        let mut query = Query::new(Map::from_entry("status", "Pending"));
+        query.order_asc("id");
+        query.set_limit(self.batch_size);
+
+        let models = OutboxEvent::find::<OutboxEvent>(&query).await?;
+        if models.is_empty() {
+            return Ok(0);
+        }
+
+        let mut guard = AggregateGuard::new(DateTime::now());
+        for aggregate_key in Self::dead_aggregates(&models).await? {
+            guard.block(aggregate_key);
+        }
+
+        let mut num_delivered = 0;
+        for model in &models {
+            if !guard.admit(model) {
+                continue;
+            }
+            let Some(version) = self.claim(model).await? else {
+                // The event has been claimed by another relay.
+                guard.hold(model);
+                continue;
+            };
+
+            let result = match model.to_event() {
+                Ok(event) => self.bus.publish(event).await.map(|_| ()),
+                Err(err) => Err(err),
+            };
+            let mut updates = Map::new();
+            match result {
+                Ok(()) => {
+                    updates.upsert("status", "Delivered");
+                    updates.upsert("delivered_at", DateTime::now());
+                    num_delivered += 1;
+                }
+                Err(err) => {
+                    let attempts = model.attempts + 1;
+                    if attempts >= self.max_attempts {
+                        tracing::error!(
+                            id = model.id.to_string(),
+                            attempts,
+                            "fail to publish the outbox event: {}",
+                            err.message()
+                        );
+                        updates.upsert("status", "Dead");
+                    }
+                    guard.hold(model);
+                    updates.upsert("attempts", attempts);
+                    updates.upsert("last_error", err.message());
+                    updates.upsert("claimed_until", DateTime::now());
+                }
+            }
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("version", version + 1);
+
+            let mut filters = Map::from_entry("id", model.id.to_string());
+            filters.upsert("version", version);
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(updates);
+            let ctx = OutboxEvent::update_one(&query, &mut mutation).await?;
+            if ctx.rows_affected() != Some(1) {
+                tracing::warn!(
+                    id = model.id.to_string(),
+                    "the claim of the outbox event has expired"
+                );
+            }
+        }
+        Ok(num_delivered)
+    }
+
+    /// Deletes the events delivered before the time, and returns the number of rows affected.
+    pub async fn purge_delivered(before: DateTime) -> Result<u64, Error> {
+        let mut query = Query::new(Map::from_entry("status", "Delivered"));
+        query.add_filter("delivered_at", Map::from_entry("$lt", before));
+        let ctx = OutboxEvent::delete_many(&query).await?;
+        Ok(ctx.rows_affected().unwrap_or_default())
+    }
+
+    /// Claims the event by an optimistic update of the version,
+    /// and returns the new version if succeeded.
+    async fn claim(&self, model: &OutboxEvent) -> Result<Option<u64>, Error> {
+        let mut filters = Map::from_entry("id", model.id.to_string());
+        filters.upsert("status", "Pending");
+        filters.upsert("version", model.version);
+
+        let version = model.version + 1;
+        let mut updates = Map::new();
+        updates.upsert("claimed_until", DateTime::now() + self.claim_timeout);
+        updates.upsert("updated_at", DateTime::now());
+        updates.upsert("version", version);
+
+        let query = Query::new(filters);
+        let mut mutation = Mutation::new(updates);
+        let ctx = OutboxEvent::update_one(&query, &mut mutation).await?;
+        Ok((ctx.rows_affected() == Some(1)).then_some(version))
+    }
+
+    /// Returns the keys of the aggregates which have dead events.
+    async fn dead_aggregates(models: &[OutboxEvent]) -> Result<Vec<String>, Error> {
+        let mut aggregate_ids = models
+            .iter()
+            .map(|model| model.aggregate_id.as_str())
+            .collect::<Vec<_>>();
+        aggregate_ids.sort_unstable();
+        aggregate_ids.dedup();
+
+        let mut query = Query::new(Map::from_entry("status", "Dead"));
+        query.allow_fields(&["aggregate_type", "aggregate_id"]);
+        query.add_filter("aggregate_id", Map::from_entry("$in", aggregate_ids));
+
+        let dead_aggregates = OutboxEvent::find::<Map>(&query)
+            .await?
+            .into_iter()
+            .map(|model| {
+                let aggregate_type = model.get_str("aggregate_type").unwrap_or_default();
+                let aggregate_id = model.get_str("aggregate_id").unwrap_or_default();
+                format!("{aggregate_type}:{aggregate_id}")
+            })
+            .collect();
+        Ok(dead_aggregates)
+    }
+}
+
+/// A guard which keeps the events of the same aggregate in order within a batch.
+#[derive(Debug)]
+struct AggregateGuard {
+    /// Current time.
+    now: DateTime,
+    /// Keys of the aggregates whose later events should be held.
+    held_aggregates: HashSet<String>,
+}
+
+impl AggregateGuard {
+    /// Creates a new instance.
+    #[inline]
+    fn new(now: DateTime) -> Self {
+        Self {
+            now,
+            held_aggregates: HashSet::new(),
+        }
+    }
+
+    /// Blocks the aggregate with the key.
+    #[inline]
+    fn block(&mut self, aggregate_key: String) {
+        self.held_aggregates.insert(aggregate_key);
+    }
+
+    /// Holds the later events of the aggregate.
+    #[inline]
+    fn hold(&mut self, model: &OutboxEvent) {
+        self.held_aggregates.insert(model.aggregate_key());
+    }
+
+    /// Returns `true` if the event can be claimed. The aggregate is held
+    /// if the event is being claimed by another relay.
+    fn admit(&mut self, model: &OutboxEvent) -> bool {
+        let aggregate_key = model.aggregate_key();
+        if self.held_aggregates.contains(&aggregate_key) {
+            false
+        } else if model.claimed_until > self.now {
+            self.held_aggregates.insert(aggregate_key);
+            false
+        } else {
+            true
+        }
+    }
+}
+
+impl AsyncScheduler for OutboxRelay {
+    #[inline]
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    #[inline]
+    fn time_till_next_job(&self) -> Duration {
+        self.poll_interval
+    }
+
+    async fn tick(&mut self) {
+        if let Err(err) = self.relay().await {
+            tracing::error!("fail to relay the outbox events: {}", err.message());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AggregateGuard, OutboxEvent};
+    use std::time::Duration;
+    use zino_core::{channel::CloudEvent, datetime::DateTime};
+
+    #[test]
+    fn it_keeps_aggregates_in_order() {
+        let event = |aggregate_id: &str| {
+            let event: CloudEvent = CloudEvent::new("1", "shop", "order.updated");
+            OutboxEvent::with_event("order", aggregate_id, event)
+        };
+        let mut claimed_event = event("1");
+        claimed_event.claimed_until = DateTime::now() + Duration::from_secs(60);
+        let events = [
+            claimed_event,
+            event("1"),
+            event("2"),
+            event("3"),
+            event("2"),
+        ];
+
+        let mut guard = AggregateGuard::new(DateTime::now());
+        guard.block("order:3".to_owned());
+        assert!(!guard.admit(&events[0]));
+        assert!(!guard.admit(&events[1]));
+        assert!(guard.admit(&events[2]));
+        assert!(!guard.admit(&events[3]));
+
+        // A failed event holds the later events of the same aggregate.
+        guard.hold(&events[2]);
+        assert!(!guard.admit(&events[4]));
+    }
+}
@@ -0,0 +1,352 @@
+use super::Policy;
+use std::{collections::HashMap, fmt::Display};
+use zino_core::{
+    auth::UserSession,
+    datetime::DateTime,
+    error::Error,
+    extension::{JsonObjectExt, TomlTableExt},
+    model::Query,
+    orm::Schema,
+    request::RequestContext,
+    response::Rejection,
+    state::State,
+    LazyLock, Map, Uuid,
+};
+
+#[cfg(feature = "opa")]
+use zino_core::{auth::RegoEngine, JsonValue};
+
+/// Decision of the policy evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyDecision {
+    /// The access is allowed by an applicable policy.
+    Allow,
+    /// The access is denied explicitly by an applicable policy.
+    Deny,
+    /// There is no applicable policy.
+    NotApplicable,
+}
+
+impl PolicyDecision {
+    /// Returns `true` if the access is allowed.
+    #[inline]
+    pub fn is_allowed(self) -> bool {
+        self == Self::Allow
+    }
+}
+
+/// Policy-driven authorization for incoming requests.
+///
+/// Each request is mapped to a pair of resource and action. The mapping can be configured
+/// by the `authorization.routes`, otherwise the first segment of the matched route
+/// is the resource and the last static segment is the action, such as `user` and `view`
+/// for the route `/user/:id/view`.
+///
+/// The policies are loaded for the subjects `*`, `user:{user_id}` and `role:{role}`
+/// of the user session, and the tenant of the session. A policy with the `Deny` effect
+/// takes precedence over those with the `Allow` effect, and the access is denied
+/// if there is no applicable policy. A policy can have a Rego query as the condition,
+/// which is evaluated by the shared [`RegoEngine`](zino_core::auth::RegoEngine)
+/// with the `opa` feature. The policy is in effect from `valid_from` to `expires_at`,
+/// and it never expires if `expires_at` is not later than `valid_from`.
+///
+/// An enforcer should be created for each request. It loads the policies at most once
+/// and caches the decision for each pair of resource and action.
+///
+/// # Examples
+///
+/// ```toml
+/// [[authorization.routes]]
+/// route = "/file/presign"
+/// method = "GET"
+/// resource = "file"
+/// action = "download"
+/// ```
+///
+/// ```rust,ignore
+/// use zino_model::policy::PolicyEnforcer;
+///
+/// pub async fn check_policies(req: Request, next: Next) -> Result<Response> {
+///     if let Some(session) = req.get_data::<UserSession<i64>>() {
+///         PolicyEnforcer::new(session).authorize(&req).await?;
+///     }
+///     Ok(next.run(req.into()).await)
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PolicyEnforcer {
+    /// Subjects of the user session.
+    subjects: Vec<String>,
+    /// Tenant ID of the user session.
+    tenant_id: Option<Uuid>,
+    /// Input data for evaluating the conditions.
+    #[cfg(feature = "opa")]
+    input: Map,
+    /// Applicable policies.
+    policies: Option<Vec<Policy>>,
+    /// Cached decisions.
+    decisions: HashMap<(String, String), PolicyDecision>,
+}
+
+impl PolicyEnforcer {
+    /// Creates a new instance for the user session.
+    pub fn new<U, R, T>(session: &UserSession<U, R, T>) -> Self
+    where
+        U: Display,
+        R: Display,
+        T: Display,
+    {
+        let user_id = session.user_id().to_string();
+        let roles = session
+            .roles()
+            .iter()
+            .map(|role| role.to_string())
+            .collect::<Vec<_>>();
+        let tenant_id = session.tenant_id().map(|tenant_id| tenant_id.to_string());
+
+        let mut subjects = vec!["*".to_owned(), format!("user:{user_id}")];
+        subjects.extend(roles.iter().map(|role| format!("role:{role}")));
+
+        #[cfg(feature = "opa")]
+        let input = {
+            let mut input = Map::new();
+            input.upsert("user_id", user_id);
+            input.upsert("roles", roles);
+            input.upsert("tenant_id", tenant_id.clone());
+            input
+        };
+        Self {
+            subjects,
+            tenant_id: tenant_id.and_then(|tenant_id| tenant_id.parse().ok()),
+            #[cfg(feature = "opa")]
+            input,
+            policies: None,
+            decisions: HashMap::new(),
+        }
+    }
+
+    /// Authorizes the request and returns a `403 Forbidden` rejection
+    /// if the access is not allowed.
+    pub async fn authorize<Ctx>(&mut self, req: &Ctx) -> Result<(), Rejection>
+    where
+        Ctx: RequestContext + ?Sized,
+    {
+        let (resource, action) = Self::resolve_route(req);
+        match self.evaluate(&resource, &action).await {
+            Ok(decision) if decision.is_allowed() => Ok(()),
+            Ok(_) => {
+                let message =
+                    format!("the action `{action}` on the resource `{resource}` is not allowed");
+                Err(Rejection::forbidden(Error::new(message)).context(req))
+            }
+            Err(err) => Err(Rejection::from_error(err).context(req)),
+        }
+    }
+
+    /// Evaluates the policies for the action on the resource.
+    pub async fn evaluate(
+        &mut self,
+        resource: &str,
+        action: &str,
+    ) -> Result<PolicyDecision, Error> {
+        let key = (resource.to_owned(), action.to_owned());
+        if let Some(decision) = self.decisions.get(&key) {
+            return Ok(*decision);
+        }
+        if self.policies.is_none() {
+            let policies = self.load_policies().await?;
+            self.policies = Some(policies);
+        }
+
+        let now = DateTime::now();
+        let mut decision = PolicyDecision::NotApplicable;
+        for policy in self.policies.iter().flatten() {
+            if !(policy.is_effective_at(now) && policy.matches(resource, action)) {
+                continue;
+            }
+            if !self.check_condition(policy, resource, action)? {
+                continue;
+            }
+            if policy.effect == "Deny" {
+                decision = PolicyDecision::Deny;
+                break;
+            } else {
+                decision = PolicyDecision::Allow;
+            }
+        }
+        self.decisions.insert(key, decision);
+        Ok(decision)
+    }
+
+    /// Resolves the resource and action of the request.
+    pub fn resolve_route<Ctx>(req: &Ctx) -> (String, String)
+    where
+        Ctx: RequestContext + ?Sized,
+    {
+        let route = req.matched_route();
+        let method = req.request_method().as_ref();
+        for rule in ROUTE_RULES.iter() {
+            if rule.route == route && rule.method.as_ref().map_or(true, |m| m == method) {
+                return (rule.resource.clone(), rule.action.clone());
+            }
+        }
+
+        let segments = route
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        let resource = segments.first().copied().unwrap_or_default();
+        let action = segments
+            .iter()
+            .skip(1)
+            .rev()
+            .find(|s| !s.starts_with([':', '*', '{']))
+            .copied()
+            .unwrap_or_else(|| match method {
+                "GET" | "HEAD" => "read",
+                "POST" => "create",
+                "PUT" | "PATCH" => "update",
+                "DELETE" => "delete",
+                _ => "execute",
+            });
+        (resource.to_owned(), action.to_owned())
+    }
+
+    /// Loads the active policies for the subjects and the tenant.
+    async fn load_policies(&self) -> Result<Vec<Policy>, Error> {
+        let mut tenant_ids = vec![Uuid::nil().to_string()];
+        if let Some(tenant_id) = self.tenant_id {
+            tenant_ids.push(tenant_id.to_string());
+        }
+
+        let subjects = Map::from_entry("$overlaps", self.subjects.clone());
+        let mut filters = Map::new();
+        filters.upsert("status", "Active");
+        filters.upsert("subjects", subjects);
+        filters.upsert("tenant_id", Map::from_entry("$in", tenant_ids));
+        Policy::find::<Policy>(&Query::new(filters)).await
+    }
+
+    /// Checks the condition of the policy.
+    #[cfg(feature = "opa")]
+    fn check_condition(
+        &self,
+        policy: &Policy,
+        resource: &str,
+        action: &str,
+    ) -> Result<bool, Error> {
+        let condition = policy.condition.trim();
+        if condition.is_empty() {
+            return Ok(true);
+        }
+
+        let mut input = self.input.clone();
+        input.upsert("resource", resource);
+        input.upsert("action", action);
+        input.upsert("policy", policy.name.as_str());
+
+        let input_json = JsonValue::from(input).to_string();
+        RegoEngine::shared().eval_bool_query_with_input(&input_json, condition)
+    }
+
+    /// Checks the condition of the policy.
+    #[cfg(not(feature = "opa"))]
+    fn check_condition(
+        &self,
+        policy: &Policy,
+        _resource: &str,
+        _action: &str,
+    ) -> Result<bool, Error> {
+        if policy.condition.trim().is_empty() {
+            Ok(true)
+        } else {
+            tracing::warn!(
+                policy_name = policy.name.as_str(),
+                "the `opa` feature should be enabled to evaluate the policy condition"
+            );
+            Ok(false)
+        }
+    }
+}
+
+impl Policy {
+    /// Returns `true` if the policy is in effect at the time.
+    fn is_effective_at(&self, time: DateTime) -> bool {
+        self.valid_from <= time && (self.expires_at <= self.valid_from || time < self.expires_at)
+    }
+
+    /// Returns `true` if the policy applies to the action on the resource.
+    fn matches(&self, resource: &str, action: &str) -> bool {
+        match_pattern(&self.resource, resource)
+            && self
+                .actions
+                .iter()
+                .any(|pattern| match_pattern(pattern, action))
+    }
+}
+
+/// Rule for mapping a route to the resource and action.
+#[derive(Debug)]
+struct RouteRule {
+    /// Matched route.
+    route: String,
+    /// Request method.
+    method: Option<String>,
+    /// Resource.
+    resource: String,
+    /// Action.
+    action: String,
+}
+
+/// Returns `true` if the value matches the pattern,
+/// which can be `*` or have a trailing wildcard.
+fn match_pattern(pattern: &str, value: &str) -> bool {
+    pattern == "*"
+        || pattern == value
+        || pattern
+            .strip_suffix('*')
+            .is_some_and(|prefix| value.starts_with(prefix))
+}
+
+/// Route rules for the authorization.
+static ROUTE_RULES: LazyLock<Vec<RouteRule>> = LazyLock::new(|| {
+    let mut rules = Vec::new();
+    if let Some(routes) = State::shared()
+        .get_config("authorization")
+        .and_then(|config| config.get_array("routes"))
+    {
+        for config in routes.iter().filter_map(|v| v.as_table()) {
+            let rule = config.get_str("route").and_then(|route| {
+                let resource = config.get_str("resource")?;
+                let action = config.get_str("action")?;
+                Some(RouteRule {
+                    route: route.to_owned(),
+                    method: config.get_str("method").map(|s| s.to_ascii_uppercase()),
+                    resource: resource.to_owned(),
+                    action: action.to_owned(),
+                })
+            });
+            match rule {
+                Some(rule) => rules.push(rule),
+                None => tracing::error!(
+                    "the `route`, `resource` and `action` fields should be specified"
+                ),
+            }
+        }
+    }
+    rules
+});
+
+#[cfg(test)]
+mod tests {
+    use super::match_pattern;
+
+    #[test]
+    fn it_matches_pattern() {
+        assert!(match_pattern("*", "user"));
+        assert!(match_pattern("user", "user"));
+        assert!(match_pattern("file:*", "file:upload"));
+        assert!(!match_pattern("file:*", "user"));
+        assert!(!match_pattern("user", "users"));
+    }
+}
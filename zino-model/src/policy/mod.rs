@@ -21,6 +21,10 @@ use crate::user::User;
 #[cfg(feature = "maintainer-id")]
 use zino_core::auth::UserSession;
 
+mod enforcer;
+
+pub use enforcer::{PolicyDecision, PolicyEnforcer};
+
 /// The `policy` model.
 #[derive(
     Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
@@ -49,7 +53,11 @@ pub struct Policy {
     #[schema(not_null)]
     resource: String,
     actions: Vec<String>,
+    #[schema(index_type = "gin")]
+    subjects: Vec<String>,
+    #[schema(default_value = "Allow")]
     effect: String,
+    condition: String,
     valid_from: DateTime,
     expires_at: DateTime,
     #[cfg(feature = "tags")]
@@ -80,8 +88,11 @@ impl Model for Policy {
 
     #[inline]
     fn new() -> Self {
+        let now = DateTime::now();
         Self {
             id: Uuid::now_v7(),
+            valid_from: now,
+            expires_at: now,
             ..Self::default()
         }
     }
@@ -100,6 +111,42 @@ impl Model for Policy {
         if let Some(description) = data.parse_string("description") {
             self.description = description.into_owned();
         }
+        if let Some(result) = data.parse_uuid("tenant_id") {
+            match result {
+                Ok(tenant_id) => self.tenant_id = tenant_id,
+                Err(err) => validation.record_fail("tenant_id", err),
+            }
+        }
+        if let Some(resource) = data.parse_string("resource") {
+            self.resource = resource.into_owned();
+        }
+        if let Some(actions) = data.parse_str_array("actions") {
+            self.actions = actions.into_iter().map(|s| s.to_owned()).collect();
+        }
+        if let Some(subjects) = data.parse_str_array("subjects") {
+            self.subjects = subjects.into_iter().map(|s| s.to_owned()).collect();
+        }
+        if let Some(effect) = data.parse_string("effect") {
+            match effect.as_ref() {
+                "Allow" | "Deny" => self.effect = effect.into_owned(),
+                _ => validation.record("effect", "should be `Allow` or `Deny`"),
+            }
+        }
+        if let Some(condition) = data.parse_string("condition") {
+            self.condition = condition.into_owned();
+        }
+        if let Some(result) = data.parse_date_time("valid_from") {
+            match result {
+                Ok(valid_from) => self.valid_from = valid_from,
+                Err(err) => validation.record_fail("valid_from", err),
+            }
+        }
+        if let Some(result) = data.parse_date_time("expires_at") {
+            match result {
+                Ok(expires_at) => self.expires_at = expires_at,
+                Err(err) => validation.record_fail("expires_at", err),
+            }
+        }
         #[cfg(feature = "tags")]
         if let Some(result) = data.parse_array("tags") {
             match result {
@@ -0,0 +1,313 @@
+//! The `session` model and related services.
+
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    auth::{SessionRecord, SessionStore},
+    bail,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `session` model.
+///
+/// A terminated session is kept with the `Revoked` status until it expires,
+/// so that the tokens of the session remain in the revocation list.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct Session {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(read_only, index_type = "hash")]
+    user_id: String,
+    #[schema(read_only)]
+    device: String,
+    #[schema(format = "ip")]
+    ip_address: String,
+    last_seen_at: DateTime,
+    #[schema(index_type = "btree")]
+    expires_at: DateTime,
+    #[schema(write_only)]
+    refresh_token_id: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for Session {
+    const MODEL_NAME: &'static str = "session";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for Session {
+    type Data = ();
+    type Extension = ();
+}
+
+impl Session {
+    /// Creates a new instance from the session record.
+    pub fn try_from_record(session: &SessionRecord) -> Result<Self, Error> {
+        Ok(Self {
+            id: session.session_id().parse()?,
+            name: session.device().to_owned(),
+            user_id: session.user_id().to_owned(),
+            device: session.device().to_owned(),
+            ip_address: session.ip().to_owned(),
+            last_seen_at: session.last_seen_at(),
+            expires_at: session.expires_at(),
+            refresh_token_id: session.refresh_token_id().to_owned(),
+            created_at: session.created_at(),
+            updated_at: DateTime::now(),
+            ..Self::default()
+        })
+    }
+
+    /// Converts `self` to a session record.
+    pub fn to_record(&self) -> Result<SessionRecord, Error> {
+        let mut map = Map::new();
+        map.upsert("session_id", self.id.to_string());
+        map.upsert("user_id", self.user_id.as_str());
+        map.upsert("device", self.device.as_str());
+        map.upsert("ip", self.ip_address.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("last_seen_at", self.last_seen_at.to_string());
+        map.upsert("expires_at", self.expires_at.to_string());
+        map.upsert("refresh_token_id", self.refresh_token_id.as_str());
+        SessionRecord::try_from_map(&map)
+    }
+
+    /// Returns the `user_id` field.
+    #[inline]
+    pub fn user_id(&self) -> &str {
+        &self.user_id
+    }
+
+    /// Returns the `expires_at` field.
+    #[inline]
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+}
+
+/// Session store backed by the `session` table.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::auth::SessionManager;
+/// use zino_model::session::SqlSessionStore;
+///
+/// SessionManager::register(SessionManager::new(SqlSessionStore));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlSessionStore;
+
+impl SqlSessionStore {
+    /// Finds the active session model by ID.
+    async fn find_active(session_id: &str) -> Result<Option<Session>, Error> {
+        let mut filters = Map::from_entry("id", session_id);
+        filters.upsert("status", "Active");
+        Session::find_one::<Session>(&Query::new(filters)).await
+    }
+
+    /// Deletes the expired sessions.
+    async fn delete_expired() -> Result<(), Error> {
+        let filters = Map::from_entry("expires_at", Map::from_entry("$lt", DateTime::now()));
+        Session::delete_many(&Query::new(filters)).await?;
+        Ok(())
+    }
+}
+
+impl SessionStore for SqlSessionStore {
+    fn save_session<'a>(&'a self, session: &'a SessionRecord) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            Session::try_from_record(session)?.upsert().await?;
+            Ok(())
+        })
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session: &'a SessionRecord,
+        refresh_token_id: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut filters = Map::from_entry("id", session.session_id());
+            filters.upsert("status", "Active");
+            filters.upsert("refresh_token_id", refresh_token_id);
+
+            let mut updates = Map::new();
+            updates.upsert("ip_address", session.ip());
+            updates.upsert("last_seen_at", session.last_seen_at());
+            updates.upsert("refresh_token_id", session.refresh_token_id());
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(updates);
+            let ctx = Session::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn find_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SessionRecord>, Error>> {
+        Box::pin(async move {
+            Self::find_active(session_id)
+                .await?
+                .map(|session| session.to_record())
+                .transpose()
+        })
+    }
+
+    fn list_sessions<'a>(
+        &'a self,
+        user_id: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<SessionRecord>, Error>> {
+        Box::pin(async move {
+            let mut filters = Map::from_entry("user_id", user_id);
+            filters.upsert("status", "Active");
+            filters.upsert("expires_at", Map::from_entry("$gt", DateTime::now()));
+            Session::find::<Session>(&Query::new(filters))
+                .await?
+                .iter()
+                .map(|session| session.to_record())
+                .collect()
+        })
+    }
+
+    fn remove_session<'a>(&'a self, session_id: &'a str) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut filters = Map::from_entry("id", session_id);
+            filters.upsert("status", "Active");
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(Map::from_entry("status", "Revoked"));
+            let ctx = Session::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let Ok(id) = jwt_id.parse::<Uuid>() else {
+                bail!("the JWT ID `{}` should be a UUID", jwt_id);
+            };
+            Self::delete_expired().await?;
+
+            let mut session = Session::find_by_id::<Session>(&id)
+                .await?
+                .unwrap_or_else(|| Session {
+                    id,
+                    name: jwt_id.to_owned(),
+                    ..Session::default()
+                });
+            session.status = "Revoked".to_owned();
+            session.expires_at = session.expires_at.max(expires_at);
+            session.updated_at = DateTime::now();
+            session.upsert().await?;
+            Ok(())
+        })
+    }
+
+    fn try_revoke_token<'a>(
+        &'a self,
+        jwt_id: &'a str,
+        expires_at: DateTime,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let Ok(id) = jwt_id.parse::<Uuid>() else {
+                bail!("the JWT ID `{}` should be a UUID", jwt_id);
+            };
+            Self::delete_expired().await?;
+
+            // The insertion fails on the primary key if the JWT ID has been revoked.
+            let session = Session {
+                id,
+                name: jwt_id.to_owned(),
+                status: "Revoked".to_owned(),
+                expires_at,
+                ..Session::default()
+            };
+            if let Err(err) = session.insert().await {
+                if Session::find_by_id::<Map>(&id).await?.is_some() {
+                    return Ok(false);
+                }
+                return Err(err);
+            }
+            Ok(true)
+        })
+    }
+
+    fn list_revoked_tokens(&self) -> BoxFuture<'_, Result<Vec<(String, DateTime)>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::default();
+            query.allow_fields(&["id", "expires_at"]);
+            query.add_filter("status", "Revoked");
+            query.add_filter("expires_at", Map::from_entry("$gt", DateTime::now()));
+
+            let revoked_tokens = Session::find::<Map>(&query)
+                .await?
+                .into_iter()
+                .filter_map(|session| {
+                    let id = session.parse_string("id")?.into_owned();
+                    let expires_at = session.get_str("expires_at")?.parse().ok()?;
+                    Some((id, expires_at))
+                })
+                .collect();
+            Ok(revoked_tokens)
+        })
+    }
+}
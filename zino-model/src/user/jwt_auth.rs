@@ -1,15 +1,18 @@
-use std::{fmt::Display, str::FromStr};
+use std::{fmt::Display, str::FromStr, time::Duration};
 use zino_core::{
-    auth::JwtClaims,
+    auth::{AccessKeyId, JwtClaims, LoginThrottle, SessionManager, SessionRecord, TotpKey},
     bail,
     datetime::DateTime,
     error::Error,
     extension::{JsonObjectExt, JsonValueExt},
-    model::Query,
+    model::{Mutation, Query},
     orm::{ModelAccessor, ModelHelper},
-    warn, Map, Uuid,
+    warn, JsonValue, Map, Uuid,
 };
 
+#[cfg(feature = "webauthn")]
+use zino_core::auth::{PasskeyCredential, RelyingParty};
+
 /// JWT authentication service.
 pub trait JwtAuthService<K = Uuid>
 where
@@ -29,6 +32,22 @@ where
     const LOGIN_AT_FIELD: Option<&'static str> = None;
     /// Login-IP field name.
     const LOGIN_IP_FIELD: Option<&'static str> = None;
+    /// TOTP secret field name.
+    const TOTP_SECRET_FIELD: Option<&'static str> = None;
+    /// TOTP counter field name, which records the time step of the last used code.
+    const TOTP_COUNTER_FIELD: Option<&'static str> = None;
+    /// Recovery codes field name.
+    const RECOVERY_CODES_FIELD: Option<&'static str> = None;
+    /// Passkeys field name.
+    const PASSKEYS_FIELD: Option<&'static str> = None;
+    /// Passkey challenge field name, which records the expiration time of the last used challenge.
+    const PASSKEY_CHALLENGE_FIELD: Option<&'static str> = None;
+    /// Failed-login-count field name.
+    const FAILED_LOGIN_COUNT_FIELD: Option<&'static str> = None;
+    /// Locked-until field name.
+    const LOCKED_UNTIL_FIELD: Option<&'static str> = None;
+    /// Max age of the MFA token.
+    const MFA_TOKEN_MAX_AGE: Duration = Duration::from_secs(5 * 60);
 
     /// Consumes the user into standard claims without a `sub` field,
     /// which can be used to create a [`JwtClaims`] and generate an ID token.
@@ -114,7 +133,36 @@ where
     }
 
     /// Generates the access token and refresh token.
+    ///
+    /// If the user has enrolled a second factor, the tokens are not issued.
+    /// Instead, the data contains `mfa_required`, `mfa_methods` and a short-lived
+    /// `mfa_token`, which should be exchanged by [`verify_second_factor()`].
+    ///
+    /// Failed attempts are throttled by the shared [`LoginThrottle`] if the
+    /// `FAILED_LOGIN_COUNT_FIELD` and `LOCKED_UNTIL_FIELD` are specified.
+    /// The account is locked temporarily after too many failed attempts.
+    ///
+    /// If the shared [`SessionManager`] is configured, a server-side session is created
+    /// without the client info, and the `session_id` is returned.
+    /// Use [`generate_token_with_client()`] to record the device and IP address.
+    ///
+    /// [`verify_second_factor()`]: JwtAuthService::verify_second_factor
+    /// [`generate_token_with_client()`]: JwtAuthService::generate_token_with_client
     async fn generate_token(body: Map) -> Result<(K, Map), Error> {
+        Self::generate_token_with_client(body, "", "").await
+    }
+
+    /// Generates the access token and refresh token like [`generate_token()`],
+    /// and creates the server-side session with the `device` and `ip` of the client.
+    /// They should be taken from the `user-agent` header and the remote address
+    /// of the request, since the request body is controlled by the client.
+    ///
+    /// [`generate_token()`]: JwtAuthService::generate_token
+    async fn generate_token_with_client(
+        body: Map,
+        device: &str,
+        ip: &str,
+    ) -> Result<(K, Map), Error> {
         let account = body
             .get_str("account")
             .ok_or_else(|| warn!("401 Unauthorized: user `account` should be specified"))?;
@@ -123,65 +171,327 @@ where
             .ok_or_else(|| warn!("401 Unauthorized: user `password` should be specified"))?;
         let mut query = Query::default();
         let mut fields = vec![Self::PRIMARY_KEY_NAME, Self::PASSWORD_FIELD];
-        if let Some(role_field) = Self::ROLE_FIELD {
-            fields.push(role_field);
-        }
-        if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
-            fields.push(tenant_id_field);
-        }
-        if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
-            fields.push(login_at_field);
-        }
-        if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
-            fields.push(login_ip_field);
-        }
+        fields.extend(session_fields::<Self, K>());
+        fields.extend(second_factor_fields::<Self, K>());
+        fields.extend(lockout_fields::<Self, K>());
         query.allow_fields(&fields);
-        query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+        query.add_filter("status", Map::from_entry("$ne", "Deleted"));
         query.add_filter(Self::ACCOUNT_FIELD, account);
 
         let mut user: Map = Self::find_one(&query)
             .await?
             .ok_or_else(|| warn!("404 Not Found: invalid user account or password"))?;
+        check_lockout::<Self, K>(&user)?;
+
+        // Cann't use `get_str` because the primary key may be an integer
+        let user_id = user
+            .parse_string(Self::PRIMARY_KEY_NAME)
+            .ok_or_else(|| warn!("404 Not Found: user id is absent"))?
+            .into_owned();
         let encrypted_password = user
             .get_str(Self::PASSWORD_FIELD)
             .ok_or_else(|| warn!("404 Not Found: user password is absent"))?;
         if Self::verify_password(passowrd, encrypted_password)
             .map_err(|_| warn!("401 Unauthorized: invalid user account or password"))?
         {
-            // Cann't use `get_str` because the primary key may be an integer
-            let user_id = user
-                .parse_string(Self::PRIMARY_KEY_NAME)
-                .ok_or_else(|| warn!("404 Not Found: user id is absent"))?;
-            let mut claims = JwtClaims::new(user_id.as_ref());
-
-            let user_id = user_id.parse()?;
-            if let Some(role_field) = Self::ROLE_FIELD.filter(|&field| user.contains_key(field)) {
-                claims.add_data_entry("roles", user.parse_str_array(role_field));
-            }
-            if let Some(tenant_id_field) = Self::TENANT_ID_FIELD {
-                if let Some(tenant_id) = user.remove(tenant_id_field) {
-                    claims.add_data_entry("tenant_id", tenant_id);
+            let mfa_methods = enrolled_mfa_methods::<Self, K>(&user);
+            if !mfa_methods.is_empty() {
+                let mut claims = JwtClaims::with_max_age(&user_id, Self::MFA_TOKEN_MAX_AGE);
+                claims.add_data_entry("mfa", mfa_methods.clone());
+
+                let mut data = Map::new();
+                data.upsert("mfa_required", true);
+                data.upsert("expires_in", claims.expires_in().as_secs());
+                data.upsert("mfa_token", claims.access_token()?);
+                #[cfg(feature = "webauthn")]
+                if mfa_methods.contains(&"passkey") {
+                    let passkeys = parse_passkeys::<Self, K>(&user);
+                    let options = RelyingParty::shared().request_options(&user_id, &passkeys);
+                    data.upsert("passkey_options", options);
                 }
+                data.upsert("mfa_methods", mfa_methods);
+                return Ok((user_id.parse()?, data));
             }
 
-            let mut data = Map::new();
-            data.upsert("token_type", "Bearer");
-            data.upsert("expires_in", claims.expires_in().as_secs());
-            data.upsert("refresh_token", claims.refresh_token()?);
-            data.upsert("access_token", claims.access_token()?);
-            if let Some(login_at_field) = Self::LOGIN_AT_FIELD {
-                data.upsert(login_at_field, user.remove(login_at_field));
+            reset_failed_logins::<Self, K>(&user_id, &user).await?;
+
+            let session = create_session(&user_id, device, ip).await?;
+            let data = issue_tokens::<Self, K>(&user_id, &mut user, session.as_ref())?;
+            Ok((user_id.parse()?, data))
+        } else {
+            record_failed_login::<Self, K>(&user_id).await?;
+            Err(warn!("401 Unauthorized: invalid user account or password"))
+        }
+    }
+
+    /// Verifies the second factor for the MFA token issued by [`generate_token()`],
+    /// and generates the access token and refresh token.
+    ///
+    /// The body should contain one of `totp_code`, `recovery_code` or `passkey`,
+    /// where the `passkey` is the JSON serialization of a WebAuthn assertion.
+    /// A recovery code can only be used once, which is guarded by the `version` of the user.
+    /// A TOTP code or a WebAuthn challenge can not be replayed
+    /// if `TOTP_COUNTER_FIELD` or `PASSKEY_CHALLENGE_FIELD` is specified.
+    ///
+    /// The failed attempts are throttled in the same way as the password,
+    /// so `FAILED_LOGIN_COUNT_FIELD` and `LOCKED_UNTIL_FIELD` should be specified.
+    ///
+    /// The server-side session is created without the client info.
+    /// Use [`verify_second_factor_with_client()`] to record the device and IP address.
+    ///
+    /// [`generate_token()`]: JwtAuthService::generate_token
+    /// [`verify_second_factor_with_client()`]: JwtAuthService::verify_second_factor_with_client
+    async fn verify_second_factor(claims: &JwtClaims, body: Map) -> Result<(K, Map), Error> {
+        Self::verify_second_factor_with_client(claims, body, "", "").await
+    }
+
+    /// Verifies the second factor like [`verify_second_factor()`], and creates
+    /// the server-side session with the `device` and `ip` of the client, which should be
+    /// taken from the `user-agent` header and the remote address of the request.
+    ///
+    /// [`verify_second_factor()`]: JwtAuthService::verify_second_factor
+    async fn verify_second_factor_with_client(
+        claims: &JwtClaims,
+        body: Map,
+        device: &str,
+        ip: &str,
+    ) -> Result<(K, Map), Error> {
+        let Some(mfa_methods) = claims.data().get_str_array("mfa") else {
+            bail!("401 Unauthorized: JWT token is not an MFA token");
+        };
+        if Self::FAILED_LOGIN_COUNT_FIELD.is_none() || Self::LOCKED_UNTIL_FIELD.is_none() {
+            bail!("the second factor can not be verified without throttling the failed attempts");
+        }
+        let Some(user_id) = claims.subject() else {
+            bail!("401 Unauthorized: JWT token does not have a subject");
+        };
+
+        let mut query = Query::default();
+        let mut fields = vec![Self::PRIMARY_KEY_NAME];
+        fields.extend(session_fields::<Self, K>());
+        fields.extend(second_factor_fields::<Self, K>());
+        fields.extend(lockout_fields::<Self, K>());
+        if Self::has_column("version") {
+            fields.push("version");
+        }
+        query.allow_fields(&fields);
+        query.add_filter(Self::PRIMARY_KEY_NAME, user_id);
+        query.add_filter("status", Map::from_entry("$ne", "Deleted"));
+
+        let mut user: Map = Self::find_one(&query)
+            .await?
+            .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+        check_lockout::<Self, K>(&user)?;
+        let verified = if let Some(code) = body.get_str("totp_code") {
+            let secret = Self::TOTP_SECRET_FIELD
+                .filter(|_| mfa_methods.contains(&"totp"))
+                .and_then(|field| user.get_str(field))
+                .ok_or_else(|| warn!("401 Unauthorized: TOTP is not enrolled"))?;
+            match TotpKey::decrypt_with(secret, Self::secret_key())?.match_code(code) {
+                Some(counter) => match Self::TOTP_COUNTER_FIELD {
+                    Some(field) => advance_field::<Self, K>(user_id, field, counter).await?,
+                    None => true,
+                },
+                None => false,
             }
-            if let Some(login_ip_field) = Self::LOGIN_IP_FIELD {
-                data.upsert(login_ip_field, user.remove(login_ip_field));
+        } else if let Some(code) = body.get_str("recovery_code") {
+            let Some(field) = Self::RECOVERY_CODES_FIELD else {
+                bail!("401 Unauthorized: recovery codes are not supported");
+            };
+            let mut hashed_codes = user
+                .parse_str_array(field)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| s.to_owned())
+                .collect::<Vec<_>>();
+            let code = normalize_recovery_code(code);
+            let position = hashed_codes
+                .iter()
+                .position(|hash| Self::verify_password(&code, hash).unwrap_or(false));
+            if let Some(index) = position {
+                // The code is redeemed only if the user has not been modified
+                // by a concurrent login, so that it can not be used twice.
+                let version = user
+                    .get_u64("version")
+                    .ok_or_else(|| warn!("recovery codes can not be redeemed without a version"))?;
+                hashed_codes.remove(index);
+                let updates = Map::from_entry(field, hashed_codes);
+                update_user_version::<Self, K>(user_id, version, updates).await?
+            } else {
+                false
             }
-            Ok((user_id, data))
+        } else if let Some(credential) = body.get_object("passkey") {
+            let passkeys_field = Self::PASSKEYS_FIELD
+                .filter(|_| mfa_methods.contains(&"passkey"))
+                .ok_or_else(|| warn!("401 Unauthorized: passkeys are not enrolled"))?;
+            verify_passkey::<Self, K>(user_id, &user, passkeys_field, credential).await?
         } else {
-            Err(warn!("fail to generate access token"))
+            bail!("401 Unauthorized: the second factor should be specified");
+        };
+        if !verified {
+            record_failed_login::<Self, K>(user_id).await?;
+            bail!("401 Unauthorized: invalid second factor");
+        }
+        reset_failed_logins::<Self, K>(user_id, &user).await?;
+
+        let session = create_session(user_id, device, ip).await?;
+        let data = issue_tokens::<Self, K>(user_id, &mut user, session.as_ref())?;
+        Ok((user_id.parse()?, data))
+    }
+
+    /// Generates a new TOTP key for the enrolment. It returns the base32-encoded `secret`
+    /// and the `provisioning_uri` for a QR code. The key should be confirmed
+    /// by [`enable_totp()`] before it takes effect.
+    ///
+    /// [`enable_totp()`]: JwtAuthService::enable_totp
+    fn prepare_totp(account: &str, issuer: &str) -> Map {
+        let key = TotpKey::generate();
+        let mut data = Map::new();
+        data.upsert("secret", key.to_base32());
+        data.upsert("provisioning_uri", key.provisioning_uri(issuer, account));
+        data
+    }
+
+    /// Enables the TOTP for the user after verifying the code,
+    /// and returns a new set of recovery codes.
+    async fn enable_totp(user_id: &K, secret: &str, code: &str) -> Result<Vec<String>, Error> {
+        let Some(totp_secret_field) = Self::TOTP_SECRET_FIELD else {
+            bail!(
+                "TOTP is not supported for the model `{}`",
+                Self::model_name()
+            );
+        };
+        let key = TotpKey::from_base32(secret)?;
+        if !key.verify_code(code) {
+            bail!("401 Unauthorized: invalid TOTP code");
+        }
+
+        let mut updates = Map::new();
+        updates.upsert(totp_secret_field, key.encrypt_with(Self::secret_key())?);
+
+        let mut recovery_codes = Vec::new();
+        if let Some(recovery_codes_field) = Self::RECOVERY_CODES_FIELD {
+            let (codes, hashed_codes) = generate_recovery_codes::<Self, K>()?;
+            updates.upsert(recovery_codes_field, hashed_codes);
+            recovery_codes = codes;
+        }
+        update_user::<Self, K>(&user_id.to_string(), updates).await?;
+        Ok(recovery_codes)
+    }
+
+    /// Disables the TOTP for the user and removes the recovery codes.
+    async fn disable_totp(user_id: &K) -> Result<(), Error> {
+        let mut updates = Map::new();
+        if let Some(totp_secret_field) = Self::TOTP_SECRET_FIELD {
+            updates.upsert(totp_secret_field, "");
+        }
+        if let Some(recovery_codes_field) = Self::RECOVERY_CODES_FIELD {
+            updates.upsert(recovery_codes_field, Vec::<String>::new());
+        }
+        if !updates.is_empty() {
+            update_user::<Self, K>(&user_id.to_string(), updates).await?;
+        }
+        Ok(())
+    }
+
+    /// Regenerates the recovery codes for the user. The previous codes are invalidated.
+    async fn regenerate_recovery_codes(user_id: &K) -> Result<Vec<String>, Error> {
+        let Some(recovery_codes_field) = Self::RECOVERY_CODES_FIELD else {
+            bail!(
+                "recovery codes are not supported for the model `{}`",
+                Self::model_name()
+            );
+        };
+        let (codes, hashed_codes) = generate_recovery_codes::<Self, K>()?;
+        let updates = Map::from_entry(recovery_codes_field, hashed_codes);
+        update_user::<Self, K>(&user_id.to_string(), updates).await?;
+        Ok(codes)
+    }
+
+    /// Returns the options for registering a new passkey for the user.
+    #[cfg(feature = "webauthn")]
+    async fn prepare_passkey_registration(user_id: &K, user_name: &str) -> Result<Map, Error> {
+        let Some(passkeys_field) = Self::PASSKEYS_FIELD else {
+            bail!(
+                "passkeys are not supported for the model `{}`",
+                Self::model_name()
+            );
+        };
+        let user_id = user_id.to_string();
+        let user =
+            fetch_user::<Self, K>(&user_id, &[Self::PRIMARY_KEY_NAME, passkeys_field]).await?;
+        let passkeys = parse_passkeys::<Self, K>(&user);
+        Ok(RelyingParty::shared().creation_options(&user_id, user_name, &passkeys))
+    }
+
+    /// Verifies the registration response and saves the passkey for the user.
+    #[cfg(feature = "webauthn")]
+    async fn register_passkey(user_id: &K, credential: &Map) -> Result<Map, Error> {
+        let Some(passkeys_field) = Self::PASSKEYS_FIELD else {
+            bail!(
+                "passkeys are not supported for the model `{}`",
+                Self::model_name()
+            );
+        };
+        let user_id = user_id.to_string();
+        let passkey = RelyingParty::shared().verify_registration(&user_id, credential)?;
+        let user =
+            fetch_user::<Self, K>(&user_id, &[Self::PRIMARY_KEY_NAME, passkeys_field]).await?;
+        let mut passkeys = user.get_object(passkeys_field).cloned().unwrap_or_default();
+        passkeys.upsert(passkey.credential_id(), passkey.to_map());
+        update_user::<Self, K>(&user_id, Map::from_entry(passkeys_field, passkeys)).await?;
+        Ok(passkey.to_map())
+    }
+
+    /// Removes the passkey with the credential ID for the user.
+    #[cfg(feature = "webauthn")]
+    async fn remove_passkey(user_id: &K, credential_id: &str) -> Result<(), Error> {
+        let Some(passkeys_field) = Self::PASSKEYS_FIELD else {
+            bail!(
+                "passkeys are not supported for the model `{}`",
+                Self::model_name()
+            );
+        };
+        let user_id = user_id.to_string();
+        let user =
+            fetch_user::<Self, K>(&user_id, &[Self::PRIMARY_KEY_NAME, passkeys_field]).await?;
+        let mut passkeys = user.get_object(passkeys_field).cloned().unwrap_or_default();
+        if passkeys.remove(credential_id).is_none() {
+            bail!(
+                "404 Not Found: the passkey `{}` does not exist",
+                credential_id
+            );
+        }
+        update_user::<Self, K>(&user_id, Map::from_entry(passkeys_field, passkeys)).await
+    }
+
+    /// Unlocks the user account and clears the failed login attempts.
+    /// It is intended to be used by the administrators.
+    async fn unlock_account(user_id: &K) -> Result<(), Error> {
+        let mut filters = Map::from_entry(Self::PRIMARY_KEY_NAME, user_id.to_string());
+        filters.upsert("status", "Locked");
+
+        let mut updates = Map::from_entry("status", "Active");
+        if let Some(failed_login_count_field) = Self::FAILED_LOGIN_COUNT_FIELD {
+            updates.upsert(failed_login_count_field, 0);
+        }
+        if let Some(locked_until_field) = Self::LOCKED_UNTIL_FIELD {
+            updates.upsert(locked_until_field, DateTime::now());
+        }
+
+        let query = Query::new(filters);
+        let mut mutation = internal_mutation::<Self, K>(updates);
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() != Some(1) {
+            bail!("404 Not Found: the user `{}` is not locked", user_id);
         }
+        Ok(())
     }
 
     /// Refreshes the access token.
+    ///
+    /// If the shared [`SessionManager`] is configured, the refresh token is rotated
+    /// and a new one is returned. Reusing a rotated refresh token terminates the session.
     async fn refresh_token(claims: &JwtClaims) -> Result<Map, Error> {
         if !claims.data().is_empty() {
             bail!("401 Unauthorized: JWT token is not a refresh token");
@@ -191,6 +501,19 @@ where
             bail!("401 Unauthorized: JWT token does not have a subject");
         };
 
+        let session = if let Some(manager) = SessionManager::shared() {
+            let Some(jwt_id) = claims.jwt_id() else {
+                bail!("401 Unauthorized: the refresh token is not bound to a session");
+            };
+            let session = manager.rotate_refresh_token(jwt_id).await?;
+            if session.user_id() != user_id {
+                bail!("401 Unauthorized: the session does not belong to the user");
+            }
+            Some(session)
+        } else {
+            None
+        };
+
         let mut query = Query::default();
         let mut fields = vec![Self::PRIMARY_KEY_NAME];
         if let Some(role_field) = Self::ROLE_FIELD {
@@ -222,10 +545,55 @@ where
         let mut data = Map::new();
         data.upsert("token_type", "Bearer");
         data.upsert("expires_in", claims.expires_in().as_secs());
+        if let Some(session) = session {
+            claims.set_jwt_id(session.refresh_token_id());
+            data.upsert("refresh_token", claims.refresh_token()?);
+            claims.set_jwt_id(session.session_id());
+            data.upsert("session_id", session.session_id());
+        }
         data.upsert("access_token", claims.access_token()?);
         Ok(data)
     }
 
+    /// Terminates the session of the JWT claims and revokes its tokens.
+    /// It returns `false` if the session does not exist.
+    async fn logout(claims: &JwtClaims) -> Result<bool, Error> {
+        let Some(manager) = SessionManager::shared() else {
+            bail!("the session manager is not configured");
+        };
+        let Some(jwt_id) = claims.jwt_id() else {
+            bail!("401 Unauthorized: JWT token is not bound to a session");
+        };
+        let session_id = jwt_id.split_once('.').map_or(jwt_id, |(s, _)| s);
+        manager.logout(session_id).await
+    }
+
+    /// Terminates all the sessions of the user and returns the number of sessions.
+    async fn logout_all(user_id: &K) -> Result<usize, Error> {
+        let Some(manager) = SessionManager::shared() else {
+            bail!("the session manager is not configured");
+        };
+        manager.logout_all(&user_id.to_string()).await
+    }
+
+    /// Lists the active sessions of the user with the device, IP and last-seen time.
+    async fn list_sessions(user_id: &K) -> Result<Vec<Map>, Error> {
+        let Some(manager) = SessionManager::shared() else {
+            bail!("the session manager is not configured");
+        };
+        let sessions = manager
+            .list_sessions(&user_id.to_string())
+            .await?
+            .into_iter()
+            .map(|session| {
+                let mut session = session.into_map();
+                session.remove("refresh_token_id");
+                session
+            })
+            .collect();
+        Ok(sessions)
+    }
+
     /// Verfifies the JWT claims.
     async fn verify_jwt_claims(claims: &JwtClaims) -> Result<bool, Error> {
         let Some(user_id) = claims.subject() else {
@@ -254,6 +622,25 @@ where
             .await?
             .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
         let data = claims.data();
+        if data.contains_key("mfa") {
+            bail!("401 Unauthorized: JWT token is an MFA token");
+        }
+        if data.contains_key("token_use") {
+            bail!("401 Unauthorized: JWT token is not an access token");
+        }
+        if data.contains_key("client_id") || data.contains_key("scope") {
+            // Access tokens issued to third-party clients are limited by the scopes,
+            // and should only be accepted by the resource server routes.
+            bail!("401 Unauthorized: JWT token is issued to a third-party client");
+        }
+        if let Some(manager) = SessionManager::shared() {
+            if let Some(session_id) = claims.jwt_id() {
+                let session = manager.touch_session(session_id, None).await?;
+                if !session.is_some_and(|session| session.user_id() == user_id) {
+                    bail!("401 Unauthorized: the session has been terminated");
+                }
+            }
+        }
         if let Some(role_field) = Self::ROLE_FIELD {
             if let Some(roles) = data.get("roles") {
                 if user.get(role_field) != Some(roles) {
@@ -298,6 +685,7 @@ where
         };
         let password = if let Some(passowrd) = body.get_str("password") {
             fields.push(Self::PASSWORD_FIELD);
+            fields.extend(lockout_fields::<Self, K>());
             passowrd
         } else {
             ""
@@ -309,6 +697,9 @@ where
         let user: Map = Self::find_one(&query)
             .await?
             .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))?;
+        if !password.is_empty() {
+            check_lockout::<Self, K>(&user)?;
+        }
 
         let mut data = Map::new();
         if let Some(user_account) = user.get_str(Self::ACCOUNT_FIELD) {
@@ -317,6 +708,9 @@ where
         }
         if let Some(encrypted_password) = user.get_str(Self::PASSWORD_FIELD) {
             let password_verified = Self::verify_password(password, encrypted_password)?;
+            if !password_verified {
+                record_failed_login::<Self, K>(&user_id.to_string()).await?;
+            }
             data.upsert("password_verified", password_verified);
         }
         Ok(data)
@@ -326,4 +720,442 @@ where
 impl JwtAuthService<Uuid> for super::User {
     const LOGIN_AT_FIELD: Option<&'static str> = Some("current_login_at");
     const LOGIN_IP_FIELD: Option<&'static str> = Some("current_login_ip");
+    const TOTP_SECRET_FIELD: Option<&'static str> = Some("totp_secret");
+    const TOTP_COUNTER_FIELD: Option<&'static str> = Some("totp_counter");
+    const RECOVERY_CODES_FIELD: Option<&'static str> = Some("recovery_codes");
+    const FAILED_LOGIN_COUNT_FIELD: Option<&'static str> = Some("failed_login_count");
+    const LOCKED_UNTIL_FIELD: Option<&'static str> = Some("locked_until");
+    #[cfg(feature = "webauthn")]
+    const PASSKEYS_FIELD: Option<&'static str> = Some("passkeys");
+    #[cfg(feature = "webauthn")]
+    const PASSKEY_CHALLENGE_FIELD: Option<&'static str> = Some("passkey_challenge_expires_at");
+}
+
+/// Returns the fields for the user session and the login info.
+fn session_fields<M, K>() -> Vec<&'static str>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    [
+        M::ROLE_FIELD,
+        M::TENANT_ID_FIELD,
+        M::LOGIN_AT_FIELD,
+        M::LOGIN_IP_FIELD,
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Returns the fields for the second factors.
+fn second_factor_fields<M, K>() -> Vec<&'static str>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    [
+        M::TOTP_SECRET_FIELD,
+        M::RECOVERY_CODES_FIELD,
+        M::PASSKEYS_FIELD,
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Returns the enrolled MFA methods of the user.
+fn enrolled_mfa_methods<M, K>(user: &Map) -> Vec<&'static str>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut mfa_methods = Vec::new();
+    if M::TOTP_SECRET_FIELD
+        .and_then(|field| user.get_str(field))
+        .is_some_and(|secret| !secret.is_empty())
+    {
+        mfa_methods.push("totp");
+        if M::RECOVERY_CODES_FIELD
+            .and_then(|field| user.get_array(field))
+            .is_some_and(|codes| !codes.is_empty())
+        {
+            mfa_methods.push("recovery_code");
+        }
+    }
+    #[cfg(feature = "webauthn")]
+    if M::PASSKEYS_FIELD
+        .and_then(|field| user.get_object(field))
+        .is_some_and(|passkeys| !passkeys.is_empty())
+    {
+        mfa_methods.push("passkey");
+    }
+    mfa_methods
+}
+
+/// Returns the fields for the login throttling and the lockout.
+fn lockout_fields<M, K>() -> Vec<&'static str>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut fields = vec!["status"];
+    fields.extend(M::FAILED_LOGIN_COUNT_FIELD);
+    fields.extend(M::LOCKED_UNTIL_FIELD);
+    fields
+}
+
+/// Returns the number of failed login attempts of the user.
+fn failed_login_count<M, K>(user: &Map) -> u32
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    M::FAILED_LOGIN_COUNT_FIELD
+        .and_then(|field| user.get_u32(field))
+        .unwrap_or_default()
+}
+
+/// Checks whether the user is allowed to log in at present.
+/// A temporary lockout expires at the `locked_until` time,
+/// while a lockout by the administrators should be removed by `unlock_account()`.
+fn check_lockout<M, K>(user: &Map) -> Result<(), Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let locked_until = M::LOCKED_UNTIL_FIELD
+        .and_then(|field| user.get_str(field))
+        .and_then(|s| s.parse::<DateTime>().ok());
+    if user.get_str("status") == Some("Locked") {
+        let temporarily_locked =
+            LoginThrottle::shared().should_lock(failed_login_count::<M, K>(user));
+        match locked_until {
+            Some(locked_until) if temporarily_locked => {
+                if locked_until.span_after_now().is_some() {
+                    bail!(
+                        "403 Forbidden: the account is locked until `{}`",
+                        locked_until
+                    );
+                }
+            }
+            _ => bail!("403 Forbidden: the account is locked"),
+        }
+    } else if let Some(delay) = locked_until.and_then(|t| t.span_after_now()) {
+        bail!(
+            "429 Too Many Requests: retry after {} seconds",
+            delay.as_secs().max(1)
+        );
+    }
+    Ok(())
+}
+
+/// Records a failed login attempt, which delays the next attempt progressively
+/// and locks the account temporarily after too many failed attempts.
+///
+/// The counter is incremented atomically so that concurrent attempts are all counted,
+/// and the delay is only updated by the latest attempt.
+async fn record_failed_login<M, K>(user_id: &str) -> Result<(), Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let Some(failed_login_count_field) = M::FAILED_LOGIN_COUNT_FIELD else {
+        return Ok(());
+    };
+    let updates = Map::from_entry("$inc", Map::from_entry(failed_login_count_field, 1));
+    update_user::<M, K>(user_id, updates).await?;
+
+    let Some(locked_until_field) = M::LOCKED_UNTIL_FIELD else {
+        return Ok(());
+    };
+    let mut query = Query::default();
+    query.allow_fields(&[failed_login_count_field]);
+    query.add_filter(M::PRIMARY_KEY_NAME, user_id);
+    let Some(user) = M::find_one::<Map>(&query).await? else {
+        return Ok(());
+    };
+    let failed_attempts = failed_login_count::<M, K>(&user);
+
+    let throttle = LoginThrottle::shared();
+    let now = DateTime::now();
+    let mut updates = Map::new();
+    if throttle.should_lock(failed_attempts) {
+        updates.upsert("status", "Locked");
+        updates.upsert(locked_until_field, now + throttle.lockout_duration());
+        tracing::warn!(
+            user_id,
+            failed_attempts,
+            "the account is locked temporarily"
+        );
+    } else {
+        updates.upsert(locked_until_field, now + throttle.delay(failed_attempts));
+    }
+
+    let mut filters = Map::from_entry(M::PRIMARY_KEY_NAME, user_id);
+    filters.upsert(failed_login_count_field, failed_attempts);
+    let query = Query::new(filters);
+    let mut mutation = internal_mutation::<M, K>(updates);
+    M::update_one(&query, &mut mutation).await?;
+    Ok(())
+}
+
+/// Resets the failed login attempts after a successful login,
+/// which also removes an expired temporary lockout.
+async fn reset_failed_logins<M, K>(user_id: &str, user: &Map) -> Result<(), Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let Some(failed_login_count_field) = M::FAILED_LOGIN_COUNT_FIELD else {
+        return Ok(());
+    };
+    let locked = user.get_str("status") == Some("Locked");
+    if failed_login_count::<M, K>(user) > 0 || locked {
+        let mut updates = Map::from_entry(failed_login_count_field, 0);
+        if locked {
+            updates.upsert("status", "Active");
+        }
+        update_user::<M, K>(user_id, updates).await?;
+    }
+    Ok(())
+}
+
+/// Issues the access token and refresh token for the user.
+fn issue_tokens<M, K>(
+    user_id: &str,
+    user: &mut Map,
+    session: Option<&SessionRecord>,
+) -> Result<Map, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut claims = JwtClaims::new(user_id);
+    if let Some(role_field) = M::ROLE_FIELD.filter(|&field| user.contains_key(field)) {
+        claims.add_data_entry("roles", user.parse_str_array(role_field));
+    }
+    if let Some(tenant_id_field) = M::TENANT_ID_FIELD {
+        if let Some(tenant_id) = user.remove(tenant_id_field) {
+            claims.add_data_entry("tenant_id", tenant_id);
+        }
+    }
+
+    let mut data = Map::new();
+    data.upsert("token_type", "Bearer");
+    data.upsert("expires_in", claims.expires_in().as_secs());
+    if let Some(session) = session {
+        claims.set_jwt_id(session.refresh_token_id());
+        data.upsert("refresh_token", claims.refresh_token()?);
+        claims.set_jwt_id(session.session_id());
+        data.upsert("session_id", session.session_id());
+    } else {
+        data.upsert("refresh_token", claims.refresh_token()?);
+    }
+    data.upsert("access_token", claims.access_token()?);
+    if let Some(login_at_field) = M::LOGIN_AT_FIELD {
+        data.upsert(login_at_field, user.remove(login_at_field));
+    }
+    if let Some(login_ip_field) = M::LOGIN_IP_FIELD {
+        data.upsert(login_ip_field, user.remove(login_ip_field));
+    }
+    Ok(data)
+}
+
+/// Creates a new session for the user if the shared session manager is configured.
+async fn create_session(
+    user_id: &str,
+    device: &str,
+    ip: &str,
+) -> Result<Option<SessionRecord>, Error> {
+    let Some(manager) = SessionManager::shared() else {
+        return Ok(None);
+    };
+    manager.create_session(user_id, device, ip).await.map(Some)
+}
+
+/// Constructs a mutation for the internal updates of the user,
+/// which can modify the read-only fields.
+fn internal_mutation<M, K>(updates: Map) -> Mutation
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut mutation = Mutation::new(updates);
+    mutation.allow_fields(M::fields());
+    mutation
+}
+
+/// Updates the fields of the user.
+async fn update_user<M, K>(user_id: &str, updates: Map) -> Result<(), Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let query = Query::new(Map::from_entry(M::PRIMARY_KEY_NAME, user_id));
+    let mut mutation = internal_mutation::<M, K>(updates);
+    M::update_one(&query, &mut mutation).await?;
+    Ok(())
+}
+
+/// Updates the fields of the user of the specific version, and increments the version.
+/// It returns `false` if the user has been modified concurrently.
+async fn update_user_version<M, K>(
+    user_id: &str,
+    version: u64,
+    mut updates: Map,
+) -> Result<bool, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut filters = Map::from_entry(M::PRIMARY_KEY_NAME, user_id);
+    filters.upsert("version", version);
+    updates.upsert("version", version + 1);
+
+    let query = Query::new(filters);
+    let mut mutation = internal_mutation::<M, K>(updates);
+    let ctx = M::update_one(&query, &mut mutation).await?;
+    Ok(ctx.rows_affected() == Some(1))
+}
+
+/// Updates the field of the user only if the value is greater than the current one.
+/// It returns `false` if the value has been used, which means a replay attack.
+async fn advance_field<M, K>(
+    user_id: &str,
+    field: &str,
+    value: impl Into<JsonValue> + Clone,
+) -> Result<bool, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut filters = Map::from_entry(M::PRIMARY_KEY_NAME, user_id);
+    filters.upsert(field, Map::from_entry("$lt", value.clone()));
+
+    let query = Query::new(filters);
+    let mut mutation = internal_mutation::<M, K>(Map::from_entry(field, value));
+    let ctx = M::update_one(&query, &mut mutation).await?;
+    Ok(ctx.rows_affected() == Some(1))
+}
+
+/// Generates the recovery codes and their hashes.
+fn generate_recovery_codes<M, K>() -> Result<(Vec<String>, Vec<String>), Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut codes = Vec::with_capacity(10);
+    let mut hashed_codes = Vec::with_capacity(10);
+    for _ in 0..10 {
+        let code = AccessKeyId::with_length(10).as_str().to_ascii_lowercase();
+        hashed_codes.push(M::encrypt_password(&code)?);
+        codes.push(format!("{}-{}", &code[..5], &code[5..]));
+    }
+    Ok((codes, hashed_codes))
+}
+
+/// Normalizes the recovery code entered by the user.
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Fetches the fields of the user.
+#[cfg(feature = "webauthn")]
+async fn fetch_user<M, K>(user_id: &str, fields: &[&str]) -> Result<Map, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut query = Query::default();
+    query.allow_fields(fields);
+    query.add_filter(M::PRIMARY_KEY_NAME, user_id);
+    query.add_filter("status", Map::from_entry("$nin", vec!["Locked", "Deleted"]));
+    M::find_one(&query)
+        .await?
+        .ok_or_else(|| warn!("404 Not Found: cannot get the user `{}`", user_id))
+}
+
+/// Parses the registered passkeys of the user.
+#[cfg(feature = "webauthn")]
+fn parse_passkeys<M, K>(user: &Map) -> Vec<PasskeyCredential>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    M::PASSKEYS_FIELD
+        .and_then(|field| user.get_object(field))
+        .map(|passkeys| {
+            passkeys
+                .values()
+                .filter_map(|v| v.as_object())
+                .filter_map(|passkey| PasskeyCredential::try_from_map(passkey).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Verifies the passkey assertion and saves the signature counter.
+#[cfg(feature = "webauthn")]
+async fn verify_passkey<M, K>(
+    user_id: &str,
+    user: &Map,
+    passkeys_field: &str,
+    credential: &Map,
+) -> Result<bool, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    let mut passkeys = user.get_object(passkeys_field).cloned().unwrap_or_default();
+    let credential_id = credential.get_str("id").unwrap_or_default();
+    let mut passkey = passkeys
+        .get_object(credential_id)
+        .ok_or_else(|| warn!("401 Unauthorized: the passkey is not registered"))
+        .and_then(PasskeyCredential::try_from_map)?;
+    let expires_at = RelyingParty::shared().verify_assertion(user_id, &mut passkey, credential)?;
+    if let Some(field) = M::PASSKEY_CHALLENGE_FIELD {
+        if !advance_field::<M, K>(user_id, field, expires_at).await? {
+            return Ok(false);
+        }
+    }
+    passkeys.upsert(credential_id, passkey.to_map());
+    update_user::<M, K>(user_id, Map::from_entry(passkeys_field, passkeys)).await?;
+    Ok(true)
+}
+
+/// Verifies the passkey assertion, which requires the `webauthn` feature.
+#[cfg(not(feature = "webauthn"))]
+async fn verify_passkey<M, K>(
+    _user_id: &str,
+    _user: &Map,
+    _passkeys_field: &str,
+    _credential: &Map,
+) -> Result<bool, Error>
+where
+    M: JwtAuthService<K> + ?Sized,
+    K: Default + Display + FromStr + PartialEq + serde::de::DeserializeOwned,
+    <K as FromStr>::Err: std::error::Error + Send + 'static,
+{
+    bail!("the `webauthn` feature should be enabled to verify passkeys");
 }
@@ -1,8 +1,9 @@
 //! The `user` model and related services.
 
 use serde::{Deserialize, Serialize};
+use std::mem;
 use zino_core::{
-    auth::{AccessKeyId, UserSession},
+    auth::{AccessKeyId, PasswordPolicy, UserSession},
     bail,
     datetime::DateTime,
     error::Error,
@@ -10,7 +11,7 @@ use zino_core::{
     model::{Model, ModelHooks},
     orm::ModelHelper,
     validation::Validation,
-    Map, Uuid,
+    warn, Map, Uuid,
 };
 use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
 
@@ -88,7 +89,22 @@ pub struct User {
     #[schema(format = "ip")]
     current_login_ip: String,
     login_count: u32,
+    #[schema(read_only)]
     failed_login_count: u8,
+    #[schema(read_only)]
+    locked_until: DateTime,
+    #[schema(read_only, write_only)]
+    password_history: Vec<String>,
+    #[schema(read_only, write_only)]
+    totp_secret: String,
+    #[schema(read_only, write_only)]
+    totp_counter: u64,
+    #[schema(read_only, write_only)]
+    recovery_codes: Vec<String>,
+    #[schema(read_only, write_only)]
+    passkeys: Map,
+    #[schema(read_only, write_only)]
+    passkey_challenge_expires_at: DateTime,
 
     // Extensions.
     extra: Map,
@@ -139,9 +155,8 @@ impl Model for User {
             self.account = account.into_owned();
         }
         if let Some(password) = data.parse_string("password") {
-            match User::encrypt_password(&password) {
-                Ok(password) => self.password = password,
-                Err(err) => validation.record_fail("password", err),
+            if let Err(err) = self.change_password(&password) {
+                validation.record_fail("password", err);
             }
         }
         if let Some(roles) = data.parse_str_array("roles") {
@@ -150,7 +165,7 @@ impl Model for User {
             }
         }
         if self.roles.is_empty() && !validation.contains_key("roles") {
-            validation.record("roles", "should be nonempty");
+            validation.record_with_code("roles", "required", "should be nonempty");
         }
         #[cfg(feature = "tags")]
         if let Some(result) = data.parse_array("tags") {
@@ -202,6 +217,56 @@ impl ModelHooks for User {
         }
         Ok(())
     }
+
+    async fn after_validation(&mut self, data: &mut Map) -> Result<(), Error> {
+        let Some(password) = data.get_str("password").map(|s| s.to_owned()) else {
+            return Ok(());
+        };
+        let Some(stored_hashes) = Self::fetch_password_hashes(&self.id).await? else {
+            // The user has not been inserted yet.
+            data.upsert("password", self.password.clone());
+            return Ok(());
+        };
+
+        // The password hashes are not loaded by the default query,
+        // so the reuse of the password is checked against the stored ones.
+        self.password = stored_hashes
+            .get_str("password")
+            .unwrap_or_default()
+            .to_owned();
+        self.password_history = stored_hashes
+            .parse_str_array("password_history")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|s| s.to_owned())
+            .collect();
+        self.change_password(&password)
+            .map_err(|err| warn!("400 Bad Request: {}", err.message()))?;
+
+        // The `password_history` is read-only, so the password is changed
+        // with a targeted update which allows the field explicitly.
+        let mut filters = Map::from_entry("id", self.id.to_string());
+        filters.upsert("version", self.version);
+
+        let mut updates = Map::new();
+        updates.upsert("password", self.password.clone());
+        updates.upsert("password_history", self.password_history.clone());
+        updates.upsert("version", self.version + 1);
+
+        let query = Query::new(filters);
+        let mut mutation = Mutation::new(updates);
+        mutation.allow_fields(&["password", "password_history", "version"]);
+        let ctx = Self::update_one(&query, &mut mutation).await?;
+        if ctx.rows_affected() != Some(1) {
+            bail!(
+                "409 Conflict: there is a version conflict for the user `{}`",
+                self.id
+            );
+        }
+        self.version += 1;
+        data.remove("password");
+        Ok(())
+    }
 }
 
 impl User {
@@ -226,6 +291,41 @@ impl User {
         Ok(())
     }
 
+    /// Fetches the stored hashes of the password and the password history,
+    /// which are write-only fields. It returns `None` if the user does not exist.
+    async fn fetch_password_hashes(id: &Uuid) -> Result<Option<Map>, Error> {
+        let mut query = Query::new(Map::from_entry("id", id.to_string()));
+        query.allow_fields(&["password", "password_history"]);
+        Self::find_one(&query).await
+    }
+
+    /// Changes the password after validating it with the shared [`PasswordPolicy`].
+    /// The password can not be the same as the recent ones in the history.
+    pub fn change_password(&mut self, password: &str) -> Result<(), Error> {
+        let policy = PasswordPolicy::shared();
+        policy.validate(password)?;
+
+        let history_size = policy.history_size();
+        if history_size > 0 {
+            let reused = [&self.password]
+                .into_iter()
+                .chain(&self.password_history)
+                .filter(|hash| !hash.is_empty())
+                .take(history_size)
+                .any(|hash| User::verify_password(password, hash).unwrap_or(false));
+            if reused {
+                bail!("the password has been used recently");
+            }
+            if !self.password.is_empty() {
+                let previous_password = mem::take(&mut self.password);
+                self.password_history.insert(0, previous_password);
+            }
+            self.password_history.truncate(history_size);
+        }
+        self.password = User::encrypt_password(password)?;
+        Ok(())
+    }
+
     /// Returns the `union_id` field.
     #[inline]
     pub fn union_id(&self) -> &str {
@@ -255,8 +355,15 @@ impl User {
 
 #[cfg(test)]
 mod tests {
-    use super::User;
-    use zino_core::{extension::JsonObjectExt, model::Model, Map};
+    use super::{JwtAuthService, User};
+    use std::time::Duration;
+    use zino_core::{
+        auth::JwtClaims,
+        extension::JsonObjectExt,
+        model::Model,
+        orm::{ModelAccessor, Schema},
+        Map,
+    };
 
     #[test]
     fn it_checks_user_roles() {
@@ -277,4 +384,62 @@ mod tests {
         assert!(user_session.has_role("auditor:log"));
         assert!(!user_session.has_role("auditor_record"));
     }
+
+    #[tokio::test]
+    async fn it_rejects_reused_passwords() {
+        crate::prepare_test_database();
+
+        let mut alice = User::new();
+        let mut data = Map::new();
+        data.upsert("name", "alice");
+        data.upsert("account", "alice");
+        data.upsert("password", "correct horse");
+        data.upsert("roles", vec!["user"]);
+        assert!(alice.read_map(&data).is_success());
+
+        let id = alice.id;
+        alice.insert().await.unwrap();
+
+        // The password history is checked on the update path.
+        let mut data = Map::from_entry("password", "battery staple");
+        let (validation, _) = User::mutate_by_id(&id, &mut data, None).await.unwrap();
+        assert!(validation.is_success());
+
+        let mut data = Map::from_entry("password", "correct horse");
+        assert!(User::mutate_by_id(&id, &mut data, None).await.is_err());
+
+        let mut data = Map::from_entry("password", "battery staple");
+        assert!(User::mutate_by_id(&id, &mut data, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_redeems_recovery_codes_once() {
+        crate::prepare_test_database();
+
+        let mut bob = User::new();
+        let mut data = Map::new();
+        data.upsert("name", "bob");
+        data.upsert("account", "bob");
+        data.upsert("password", "correct horse");
+        data.upsert("roles", vec!["user"]);
+        assert!(bob.read_map(&data).is_success());
+
+        let id = bob.id;
+        bob.insert().await.unwrap();
+
+        let codes = User::regenerate_recovery_codes(&id).await.unwrap();
+        let mut claims = JwtClaims::with_max_age(&id.to_string(), Duration::from_secs(300));
+        claims.add_data_entry("mfa", vec!["totp", "recovery_code"]);
+
+        // Concurrent logins can not redeem the same recovery code twice.
+        let body = Map::from_entry("recovery_code", codes[0].as_str());
+        let (first, second) = tokio::join!(
+            User::verify_second_factor(&claims, body.clone()),
+            User::verify_second_factor(&claims, body.clone()),
+        );
+        assert_ne!(first.is_ok(), second.is_ok());
+
+        let body = Map::from_entry("recovery_code", codes[1].as_str());
+        assert!(User::verify_second_factor(&claims, body).await.is_ok());
+    }
 }
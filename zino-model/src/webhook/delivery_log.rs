@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    response::WebhookDelivery,
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `delivery_log` model.
+///
+/// The `next_attempt_at` field is the time of the next attempt of a pending delivery,
+/// or the lease expiration of a delivery being sent.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct DeliveryLog {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Pending", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    subscriber_id: Uuid,
+    #[schema(read_only)]
+    payload: Map,
+    attempts: u32,
+    #[schema(index_type = "btree")]
+    next_attempt_at: DateTime,
+    #[schema(comment = "Status code of the last response, or 0 if there is no response")]
+    response_status: u16,
+    response_excerpt: String,
+    last_error: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for DeliveryLog {
+    const MODEL_NAME: &'static str = "delivery_log";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for DeliveryLog {
+    type Data = ();
+    type Extension = ();
+}
+
+impl DeliveryLog {
+    /// Creates a new instance from the webhook delivery.
+    pub fn from_delivery(delivery: &WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id(),
+            name: delivery.event_type().to_owned(),
+            status: delivery.status().to_string(),
+            subscriber_id: delivery.subscriber_id(),
+            payload: delivery.payload().clone(),
+            attempts: delivery.attempts(),
+            next_attempt_at: delivery.next_attempt_at(),
+            response_status: delivery.response_status().unwrap_or_default(),
+            response_excerpt: delivery.response_excerpt().to_owned(),
+            last_error: delivery.last_error().to_owned(),
+            created_at: delivery.created_at(),
+            updated_at: delivery.updated_at(),
+            ..Self::default()
+        }
+    }
+
+    /// Converts `self` to a webhook delivery.
+    pub fn to_delivery(&self) -> Result<WebhookDelivery, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("subscriber_id", self.subscriber_id.to_string());
+        map.upsert("event_type", self.name.as_str());
+        map.upsert("payload", self.payload.clone());
+        map.upsert("status", self.status.as_str());
+        map.upsert("attempts", self.attempts);
+        map.upsert("next_attempt_at", self.next_attempt_at.to_string());
+        map.upsert("response_status", self.response_status().map(u32::from));
+        map.upsert("response_excerpt", self.response_excerpt.as_str());
+        map.upsert("last_error", self.last_error.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        WebhookDelivery::try_from_map(&map)
+    }
+
+    /// Returns the `subscriber_id` field.
+    #[inline]
+    pub fn subscriber_id(&self) -> Uuid {
+        self.subscriber_id
+    }
+
+    /// Returns the `response_status` field.
+    #[inline]
+    pub fn response_status(&self) -> Option<u16> {
+        Some(self.response_status).filter(|&status| status > 0)
+    }
+}
@@ -0,0 +1,349 @@
+//! The `webhook_endpoint` model and related services.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zino_core::{
+    channel::Subscription,
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    json,
+    model::{Model, ModelHooks},
+    orm::ModelAccessor,
+    response::{self, WebhookDelivery, WebhookStore, WebhookSubscriber},
+    validation::Validation,
+    BoxFuture, JsonValue, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+mod delivery_log;
+
+pub use delivery_log::{DeliveryLog, DeliveryLogColumn};
+
+/// The `webhook_endpoint` model.
+///
+/// An endpoint with the `Disabled` status will not receive any deliveries
+/// until it is enabled by a manual redelivery.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct WebhookEndpoint {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null)]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, format = "uri")]
+    url: String,
+    #[schema(not_null, encrypted)]
+    secret: String,
+    subscription: Map,
+    consecutive_failures: u32,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for WebhookEndpoint {
+    const MODEL_NAME: &'static str = "webhook_endpoint";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(name) = data.parse_string("name") {
+            self.name = name.into_owned();
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        if let Some(url) = data.parse_string("url") {
+            match response::check_webhook_url(&url) {
+                Ok(()) => self.url = url.into_owned(),
+                Err(err) => validation.record_fail("url", err),
+            }
+        }
+        if let Some(secret) = data.parse_string("secret") {
+            self.secret = secret.into_owned();
+        }
+        if let Some(subscription) = data.get_object("subscription") {
+            match Subscription::deserialize(JsonValue::Object(subscription.clone())) {
+                Ok(_) => self.subscription = subscription.clone(),
+                Err(err) => validation.record_fail("subscription", err),
+            }
+        }
+        validation
+    }
+}
+
+impl ModelHooks for WebhookEndpoint {
+    type Data = ();
+    type Extension = ();
+}
+
+impl WebhookEndpoint {
+    /// Creates a new instance from the webhook subscriber.
+    pub fn from_subscriber(subscriber: &WebhookSubscriber) -> Self {
+        let subscription = match json!(subscriber.subscription()) {
+            JsonValue::Object(map) => map,
+            _ => Map::new(),
+        };
+        Self {
+            id: subscriber.id(),
+            name: subscriber.url().to_owned(),
+            status: Self::status_of(subscriber).to_owned(),
+            url: subscriber.url().to_owned(),
+            secret: subscriber.secret().to_owned(),
+            subscription,
+            consecutive_failures: subscriber.consecutive_failures(),
+            ..Self::new()
+        }
+    }
+
+    /// Converts `self` to a webhook subscriber.
+    pub fn to_subscriber(&self) -> Result<WebhookSubscriber, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("url", self.url.as_str());
+        map.upsert("secret", self.secret.as_str());
+        map.upsert("subscription", self.subscription.clone());
+        map.upsert("enabled", self.status == "Active");
+        map.upsert("consecutive_failures", self.consecutive_failures);
+        WebhookSubscriber::try_from_map(&map)
+    }
+
+    /// Returns the `url` field.
+    #[inline]
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the `consecutive_failures` field.
+    #[inline]
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Returns the status of the webhook subscriber.
+    #[inline]
+    fn status_of(subscriber: &WebhookSubscriber) -> &'static str {
+        if subscriber.is_enabled() {
+            "Active"
+        } else {
+            "Disabled"
+        }
+    }
+}
+
+/// Webhook store backed by the `webhook_endpoint` and `delivery_log` tables.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::response::WebhookDispatcher;
+/// use zino_model::webhook::SqlWebhookStore;
+///
+/// WebhookDispatcher::register(WebhookDispatcher::new(SqlWebhookStore));
+/// app.run_with((scheduler, WebhookDispatcher::shared().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlWebhookStore;
+
+impl WebhookStore for SqlWebhookStore {
+    fn list_subscribers(&self) -> BoxFuture<'_, Result<Vec<WebhookSubscriber>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("status", "Active"));
+            WebhookEndpoint::find::<WebhookEndpoint>(&query)
+                .await?
+                .iter()
+                .map(|endpoint| endpoint.to_subscriber())
+                .collect()
+        })
+    }
+
+    fn get_subscriber(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookSubscriber>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("id", id.to_string()));
+            WebhookEndpoint::find_one::<WebhookEndpoint>(&query)
+                .await?
+                .map(|endpoint| endpoint.to_subscriber())
+                .transpose()
+        })
+    }
+
+    fn save_subscriber<'a>(
+        &'a self,
+        subscriber: &'a WebhookSubscriber,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("id", subscriber.id().to_string()));
+            if !WebhookEndpoint::exists(&query).await? {
+                WebhookEndpoint::from_subscriber(subscriber)
+                    .insert()
+                    .await?;
+                return Ok(());
+            }
+
+            let mut updates = Map::new();
+            updates.upsert("status", WebhookEndpoint::status_of(subscriber));
+            updates.upsert("consecutive_failures", subscriber.consecutive_failures());
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let mut mutation = Mutation::new(updates);
+            WebhookEndpoint::update_one(&query, &mut mutation).await?;
+            Ok(())
+        })
+    }
+
+    fn record_failure(&self, id: Uuid, threshold: u32) -> BoxFuture<'_, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut increments = Map::from_entry("consecutive_failures", 1);
+            increments.upsert("version", 1);
+
+            let mut updates = Map::from_entry("$inc", increments);
+            updates.upsert("updated_at", DateTime::now());
+
+            let query = Query::new(Map::from_entry("id", id.to_string()));
+            let mut mutation = Mutation::new(updates);
+            WebhookEndpoint::update_one(&query, &mut mutation).await?;
+
+            // Only one of the concurrent failures can disable the endpoint.
+            let mut filters = Map::from_entry("id", id.to_string());
+            filters.upsert("status", "Active");
+            filters.upsert("consecutive_failures", Map::from_entry("$ge", threshold));
+
+            let mut updates = Map::from_entry("status", "Disabled");
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(updates);
+            let ctx = WebhookEndpoint::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn reset_failures(&self, id: Uuid) -> BoxFuture<'_, Result<(), Error>> {
+        Box::pin(async move {
+            let mut filters = Map::from_entry("id", id.to_string());
+            filters.upsert("consecutive_failures", Map::from_entry("$gt", 0));
+
+            let mut updates = Map::from_entry("consecutive_failures", 0);
+            updates.upsert("updated_at", DateTime::now());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(updates);
+            WebhookEndpoint::update_one(&query, &mut mutation).await?;
+            Ok(())
+        })
+    }
+
+    fn push_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            DeliveryLog::from_delivery(delivery).insert().await?;
+            Ok(())
+        })
+    }
+
+    fn claim_deliveries(
+        &self,
+        limit: usize,
+        timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WebhookDelivery>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::new(Map::from_entry("status", "Pending"));
+            query.add_filter("next_attempt_at", Map::from_entry("$le", DateTime::now()));
+            query.order_asc("next_attempt_at");
+            query.set_limit(limit);
+
+            let mut claimed_deliveries = Vec::new();
+            for model in DeliveryLog::find::<DeliveryLog>(&query).await? {
+                let mut delivery = model.to_delivery()?;
+                delivery.claim(timeout);
+
+                // Optimistic locking by the version to avoid sending a delivery twice.
+                let mut filters = Map::from_entry("id", model.id().to_string());
+                filters.upsert("version", model.version());
+
+                let mut updates = Map::new();
+                updates.upsert("next_attempt_at", delivery.next_attempt_at());
+                updates.upsert("updated_at", delivery.updated_at());
+                updates.upsert("version", model.version() + 1);
+
+                let query = Query::new(filters);
+                let mut mutation = Mutation::new(updates);
+                let ctx = DeliveryLog::update_one(&query, &mut mutation).await?;
+                if ctx.rows_affected() == Some(1) {
+                    claimed_deliveries.push(delivery);
+                }
+            }
+            Ok(claimed_deliveries)
+        })
+    }
+
+    fn get_delivery(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WebhookDelivery>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("id", id.to_string()));
+            DeliveryLog::find_one::<DeliveryLog>(&query)
+                .await?
+                .map(|model| model.to_delivery())
+                .transpose()
+        })
+    }
+
+    fn save_delivery<'a>(
+        &'a self,
+        delivery: &'a WebhookDelivery,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut updates = Map::new();
+            updates.upsert("status", delivery.status().as_str());
+            updates.upsert("attempts", delivery.attempts());
+            updates.upsert("next_attempt_at", delivery.next_attempt_at());
+            updates.upsert(
+                "response_status",
+                delivery.response_status().unwrap_or_default(),
+            );
+            updates.upsert("response_excerpt", delivery.response_excerpt());
+            updates.upsert("last_error", delivery.last_error());
+            updates.upsert("updated_at", delivery.updated_at());
+            updates.upsert("$inc", Map::from_entry("version", 1));
+
+            let query = Query::new(Map::from_entry("id", delivery.id().to_string()));
+            let mut mutation = Mutation::new(updates);
+            DeliveryLog::update_one(&query, &mut mutation).await?;
+            Ok(())
+        })
+    }
+}
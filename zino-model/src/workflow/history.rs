@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    schedule::WorkflowHistory,
+    validation::Validation,
+    Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+/// The `workflow_event` model, which is an entry in the history of a workflow run.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct WorkflowEvent {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Active", index_type = "hash")]
+    status: String,
+    #[schema(read_only)]
+    description: String,
+
+    // Info fields.
+    #[schema(not_null, read_only, index_type = "hash")]
+    run_id: Uuid,
+    #[schema(read_only)]
+    step: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for WorkflowEvent {
+    const MODEL_NAME: &'static str = "workflow_event";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        validation
+    }
+}
+
+impl ModelHooks for WorkflowEvent {
+    type Data = ();
+    type Extension = ();
+}
+
+impl WorkflowEvent {
+    /// Creates a new instance from the workflow history entry,
+    /// where the `name` field is the action and the `description` field is the detail.
+    pub fn from_history(entry: &WorkflowHistory) -> Self {
+        Self {
+            id: entry.id(),
+            name: entry.action().to_string(),
+            status: "Active".to_owned(),
+            description: entry.detail().to_owned(),
+            run_id: entry.run_id(),
+            step: entry.step().to_owned(),
+            created_at: entry.created_at(),
+            updated_at: entry.created_at(),
+            ..Self::default()
+        }
+    }
+
+    /// Converts `self` to a workflow history entry.
+    pub fn to_history(&self) -> Result<WorkflowHistory, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("run_id", self.run_id.to_string());
+        map.upsert("step", self.step.as_str());
+        map.upsert("action", self.name.as_str());
+        map.upsert("detail", self.description.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        WorkflowHistory::try_from_map(&map)
+    }
+
+    /// Returns the `run_id` field.
+    #[inline]
+    pub fn run_id(&self) -> Uuid {
+        self.run_id
+    }
+}
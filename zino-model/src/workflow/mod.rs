@@ -0,0 +1,334 @@
+//! The `workflow_run` model and related services.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use zino_core::{
+    datetime::DateTime,
+    error::Error,
+    extension::JsonObjectExt,
+    model::{Model, ModelHooks},
+    schedule::{WorkflowHistory, WorkflowRun, WorkflowStore},
+    validation::Validation,
+    BoxFuture, Map, Uuid,
+};
+use zino_derive::{DecodeRow, Entity, ModelAccessor, Schema};
+
+mod history;
+
+pub use history::{WorkflowEvent, WorkflowEventColumn};
+
+/// The `workflow_run` model.
+///
+/// The `wake_at` field is the time when a run is scheduled to proceed,
+/// when the signal of a waiting run times out, or when the lease of a claimed run expires.
+#[derive(
+    Debug, Clone, Default, Serialize, Deserialize, DecodeRow, Entity, Schema, ModelAccessor,
+)]
+#[serde(default)]
+#[schema(auto_rename)]
+pub struct WorkflowInstance {
+    // Basic fields.
+    #[schema(read_only)]
+    id: Uuid,
+    #[schema(not_null, read_only, index_type = "hash")]
+    name: String,
+    #[schema(default_value = "Running", index_type = "hash")]
+    status: String,
+    description: String,
+
+    // Info fields.
+    #[schema(read_only, index_type = "hash")]
+    correlation_key: String,
+    current_step: u32,
+    data: Map,
+    #[schema(index_type = "hash")]
+    signal: String,
+    attempts: u32,
+    #[schema(index_type = "btree")]
+    wake_at: Option<DateTime>,
+    last_error: String,
+
+    // Extensions.
+    extra: Map,
+
+    // Revisions.
+    #[schema(read_only, default_value = "now", index_type = "btree")]
+    created_at: DateTime,
+    #[schema(default_value = "now", index_type = "btree")]
+    updated_at: DateTime,
+    version: u64,
+}
+
+impl Model for WorkflowInstance {
+    const MODEL_NAME: &'static str = "workflow_run";
+
+    #[inline]
+    fn new() -> Self {
+        Self {
+            id: Uuid::now_v7(),
+            ..Self::default()
+        }
+    }
+
+    fn read_map(&mut self, data: &Map) -> Validation {
+        let mut validation = Validation::new();
+        if let Some(result) = data.parse_uuid("id") {
+            match result {
+                Ok(id) => self.id = id,
+                Err(err) => validation.record_fail("id", err),
+            }
+        }
+        if let Some(description) = data.parse_string("description") {
+            self.description = description.into_owned();
+        }
+        validation
+    }
+}
+
+impl ModelHooks for WorkflowInstance {
+    type Data = ();
+    type Extension = ();
+}
+
+impl WorkflowInstance {
+    /// Creates a new instance from the workflow run.
+    pub fn from_run(run: &WorkflowRun) -> Self {
+        Self {
+            id: run.id(),
+            name: run.workflow().to_owned(),
+            status: run.status().to_string(),
+            correlation_key: run.correlation_key().to_owned(),
+            current_step: run.current_step().try_into().unwrap_or_default(),
+            data: run.data().clone(),
+            signal: run.signal().unwrap_or_default().to_owned(),
+            attempts: run.attempts(),
+            wake_at: run.wake_at(),
+            last_error: run.last_error().to_owned(),
+            created_at: run.created_at(),
+            updated_at: run.updated_at(),
+            version: run.version(),
+            ..Self::default()
+        }
+    }
+
+    /// Converts `self` to a workflow run.
+    pub fn to_run(&self) -> Result<WorkflowRun, Error> {
+        let mut map = Map::new();
+        map.upsert("id", self.id.to_string());
+        map.upsert("workflow", self.name.as_str());
+        map.upsert("correlation_key", self.correlation_key.as_str());
+        map.upsert("status", self.status.as_str());
+        map.upsert("current_step", self.current_step);
+        map.upsert("data", self.data.clone());
+        map.upsert("signal", self.signal.as_str());
+        map.upsert("attempts", self.attempts);
+        map.upsert("wake_at", self.wake_at.map(|dt| dt.to_string()));
+        map.upsert("last_error", self.last_error.as_str());
+        map.upsert("created_at", self.created_at.to_string());
+        map.upsert("updated_at", self.updated_at.to_string());
+        map.upsert("version", self.version);
+        WorkflowRun::try_from_map(&map)
+    }
+
+    /// Returns the `correlation_key` field.
+    #[inline]
+    pub fn correlation_key(&self) -> &str {
+        &self.correlation_key
+    }
+}
+
+/// Workflow store backed by the `workflow_run` and `workflow_event` tables.
+/// It works with any database supported by the ORM, including SQLite.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use zino_core::schedule::WorkflowEngine;
+/// use zino_model::workflow::SqlWorkflowStore;
+///
+/// WorkflowEngine::register(WorkflowEngine::new(SqlWorkflowStore).with_workflow(workflow));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SqlWorkflowStore;
+
+impl WorkflowStore for SqlWorkflowStore {
+    fn push_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            WorkflowInstance::from_run(run).insert().await?;
+            Ok(())
+        })
+    }
+
+    fn get_run(&self, id: Uuid) -> BoxFuture<'_, Result<Option<WorkflowRun>, Error>> {
+        Box::pin(async move {
+            let query = Query::new(Map::from_entry("id", id.to_string()));
+            WorkflowInstance::find_one::<WorkflowInstance>(&query)
+                .await?
+                .map(|model| model.to_run())
+                .transpose()
+        })
+    }
+
+    fn claim_runs(
+        &self,
+        limit: usize,
+        lease_timeout: Duration,
+    ) -> BoxFuture<'_, Result<Vec<WorkflowRun>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::default();
+            let statuses = vec!["Running", "Waiting", "Compensating"];
+            query.add_filter("status", Map::from_entry("$in", statuses));
+            query.add_filter("wake_at", Map::from_entry("$le", DateTime::now()));
+            query.order_asc("wake_at");
+            query.set_limit(limit);
+
+            let mut claimed_runs = Vec::new();
+            for model in WorkflowInstance::find::<WorkflowInstance>(&query).await? {
+                let mut run = model.to_run()?;
+                run.claim(lease_timeout);
+
+                // Optimistic locking by the version to avoid claiming a run twice.
+                let mut filters = Map::from_entry("id", model.id.to_string());
+                filters.upsert("version", model.version);
+
+                let mut updates = Map::new();
+                updates.upsert("signal", run.signal().unwrap_or_default());
+                updates.upsert("wake_at", run.wake_at());
+                updates.upsert("last_error", run.last_error());
+                updates.upsert("updated_at", run.updated_at());
+                updates.upsert("version", run.version());
+
+                let query = Query::new(filters);
+                let mut mutation = Mutation::new(updates);
+                let ctx = WorkflowInstance::update_one(&query, &mut mutation).await?;
+                if ctx.rows_affected() == Some(1) {
+                    claimed_runs.push(run);
+                }
+            }
+            Ok(claimed_runs)
+        })
+    }
+
+    fn find_waiting_runs<'a>(
+        &'a self,
+        signal: &'a str,
+        correlation_key: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<WorkflowRun>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::new(Map::from_entry("status", "Waiting"));
+            query.add_filter("signal", signal);
+            query.add_filter("correlation_key", correlation_key);
+            WorkflowInstance::find::<WorkflowInstance>(&query)
+                .await?
+                .iter()
+                .map(|model| model.to_run())
+                .collect()
+        })
+    }
+
+    fn save_run<'a>(&'a self, run: &'a WorkflowRun) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut updates = Map::new();
+            updates.upsert("status", run.status().as_str());
+            updates.upsert("current_step", run.current_step());
+            updates.upsert("data", run.data().clone());
+            updates.upsert("signal", run.signal().unwrap_or_default());
+            updates.upsert("attempts", run.attempts());
+            updates.upsert("wake_at", run.wake_at());
+            updates.upsert("last_error", run.last_error());
+            updates.upsert("updated_at", run.updated_at());
+            updates.upsert("version", run.version() + 1);
+
+            // Optimistic locking by the version to avoid overwriting the updates
+            // by a signal or by another worker after the lease has expired.
+            let mut filters = Map::from_entry("id", run.id().to_string());
+            filters.upsert("version", run.version());
+
+            let query = Query::new(filters);
+            let mut mutation = Mutation::new(updates);
+            let ctx = WorkflowInstance::update_one(&query, &mut mutation).await?;
+            Ok(ctx.rows_affected() == Some(1))
+        })
+    }
+
+    fn append_history<'a>(
+        &'a self,
+        entry: &'a WorkflowHistory,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            WorkflowEvent::from_history(entry).insert().await?;
+            Ok(())
+        })
+    }
+
+    fn list_history(&self, run_id: Uuid) -> BoxFuture<'_, Result<Vec<WorkflowHistory>, Error>> {
+        Box::pin(async move {
+            let mut query = Query::new(Map::from_entry("run_id", run_id.to_string()));
+            query.order_asc("id");
+            WorkflowEvent::find::<WorkflowEvent>(&query)
+                .await?
+                .iter()
+                .map(|model| model.to_history())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqlWorkflowStore;
+    use std::time::Duration;
+    use zino_core::{
+        channel::CloudEvent,
+        error::Error,
+        schedule::{
+            StepOutcome, Workflow, WorkflowEngine, WorkflowRun, WorkflowStatus, WorkflowStore,
+        },
+        BoxFuture, Map,
+    };
+
+    fn confirm(_data: Map) -> BoxFuture<'static, Result<StepOutcome, Error>> {
+        Box::pin(async { Ok(StepOutcome::wait("order.confirmed", Some(Duration::ZERO))) })
+    }
+
+    #[tokio::test]
+    async fn it_saves_runs_with_optimistic_locking() {
+        crate::prepare_test_database();
+
+        let store = SqlWorkflowStore;
+        let run = WorkflowRun::new("invoice", "invoice-1", Map::new());
+        store.push_run(&run).await.unwrap();
+
+        let claimed_runs = store.claim_runs(1, Duration::ZERO).await.unwrap();
+        assert_eq!(claimed_runs.len(), 1);
+        assert!(store
+            .claim_runs(1, Duration::ZERO)
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A stale copy of the run can not overwrite the claimed run.
+        let claimed_run = &claimed_runs[0];
+        assert_eq!(claimed_run.version(), 1);
+        assert!(!store.save_run(&run).await.unwrap());
+        assert!(store.save_run(claimed_run).await.unwrap());
+        assert!(!store.save_run(claimed_run).await.unwrap());
+
+        let saved_run = store.get_run(run.id()).await.unwrap().unwrap();
+        assert_eq!(saved_run.version(), 2);
+
+        let workflow = Workflow::new("order").step("confirm", confirm);
+        let engine = WorkflowEngine::new(SqlWorkflowStore).with_workflow(workflow);
+        let run_id = engine.start("order", "order-1", Map::new()).await.unwrap();
+        engine.tick().await;
+        let run = engine.get_run(run_id).await.unwrap().unwrap();
+        assert_eq!(run.status(), WorkflowStatus::Waiting);
+
+        // The signal loses the race with the timeout claim.
+        let claimed_runs = engine.store().claim_runs(1, Duration::ZERO).await.unwrap();
+        assert_eq!(claimed_runs.len(), 1);
+        let mut event = CloudEvent::new("1", "order", "order.confirmed");
+        event.set_subject("order-1");
+        assert_eq!(engine.signal(&event).await.unwrap(), 0);
+    }
+}
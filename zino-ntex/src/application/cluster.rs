@@ -1,6 +1,6 @@
 use crate::RouterConfigure;
 use ntex::{
-    rt::System,
+    rt::{self, System},
     time::{self, Seconds},
     web::{
         self,
@@ -11,7 +11,7 @@ use ntex::{
 };
 use ntex_files::{Files, NamedFile};
 use zino_core::{
-    application::{Application, Plugin, ServerTag},
+    application::{Application, Plugin, ServerTag, ShutdownToken},
     extension::TomlTableExt,
     schedule::AsyncScheduler,
 };
@@ -54,6 +54,7 @@ impl Application for Cluster {
             Self::load().await;
             app_env.load_plugins(self.custom_plugins).await;
         });
+        let shutdown_token = ShutdownToken::shared();
         if scheduler.is_ready() {
             // It should be fixed by pasing `System::current()` from `block_on`.
             // https://github.com/ntex-rs/ntex/issues/335#issuecomment-2071498572
@@ -62,15 +63,23 @@ impl Application for Cluster {
                 .arbiter()
                 .spawn(Box::pin(async move {
                     loop {
+                        let guard = shutdown_token.track();
+                        if shutdown_token.is_shutting_down() {
+                            break;
+                        }
                         scheduler.tick().await;
+                        drop(guard);
 
                         // Cannot use `std::thread::sleep` because it blocks the Tokio runtime.
-                        time::sleep(scheduler.time_till_next_job()).await;
+                        let duration = scheduler.time_till_next_job();
+                        shutdown_token
+                            .run_until_cancelled(time::sleep(duration))
+                            .await;
                     }
                 }));
         }
 
-        System::new("main").block_on(async {
+        System::new("main").block_on(async move {
             let default_routes = self.default_routes.leak() as &'static [_];
             let tagged_routes = self.tagged_routes.leak() as &'static [_];
             let app_state = Self::shared_state();
@@ -78,6 +87,11 @@ impl Application for Cluster {
             let app_version = Self::version();
             let app_domain = Self::domain();
             let listeners = app_state.listeners();
+            let shutdown_timeout = shutdown_token
+                .drain_timeout()
+                .as_secs()
+                .try_into()
+                .unwrap_or(u16::MAX);
             let servers = listeners.into_iter().map(|listener| {
                 let server_tag = listener.0;
                 let addr = listener.1;
@@ -171,15 +185,59 @@ impl Application for Cluster {
                 .backlog(backlog)
                 .maxconn(max_connections)
                 .client_timeout(Seconds(request_timeout))
+                .shutdown_timeout(Seconds(shutdown_timeout))
                 .bind(addr)
                 .unwrap_or_else(|err| panic!("fail to create an HTTP server: {err}"))
                 .run()
             });
+            let servers = servers.collect::<Vec<_>>();
+            let handles = servers.clone();
+            rt::spawn(async move {
+                Self::wait_for_signal().await;
+
+                // Stops accepting connections after the readiness delay.
+                time::sleep(shutdown_token.readiness_delay()).await;
+                futures::future::join_all(handles.iter().map(|handle| handle.stop(true))).await;
+            });
             for result in futures::future::join_all(servers).await {
                 if let Err(err) = result {
                     tracing::error!("ntex server error: {err}");
                 }
             }
+            if shutdown_token.is_shutting_down() {
+                // The servers and the in-flight tasks share the deadline.
+                let time_till_deadline = shutdown_token.time_till_deadline();
+                if time::timeout(time_till_deadline, shutdown_token.drained())
+                    .await
+                    .is_err()
+                {
+                    tracing::warn!("drain timeout elapsed with in-flight tasks");
+                }
+            }
+            Self::shutdown().await;
         });
     }
 }
+
+impl Cluster {
+    /// Waits for a shutdown signal and starts the graceful shutdown.
+    async fn wait_for_signal() {
+        let ctrl_c = async {
+            if let Err(err) = tokio::signal::ctrl_c().await {
+                tracing::error!("fail to install the `Ctrl+C` handler: {err}");
+            }
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                .expect("fail to install the terminate signal handler")
+                .recv()
+                .await;
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        futures::future::select(Box::pin(ctrl_c), Box::pin(terminate)).await;
+        tracing::warn!("signal received, starting graceful shutdown");
+        ShutdownToken::shared().shutdown();
+    }
+}
@@ -0,0 +1,52 @@
+use zino_core::{
+    application::ShutdownToken,
+    error::Error,
+    extension::JsonObjectExt,
+    response::{Rejection, Response},
+    Map,
+};
+
+/// Controller for the liveness and readiness probes.
+///
+/// The readiness flips to "not ready" as soon as the graceful shutdown has been started,
+/// so that the load balancers can stop routing new requests before draining.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use axum::{routing::get, Router};
+/// use zino::HealthController;
+///
+/// let router = Router::new()
+///     .route("/health/live", get(HealthController::live))
+///     .route("/health/ready", get(HealthController::ready));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HealthController;
+
+impl HealthController {
+    /// Returns a success response as long as the server is running.
+    pub async fn live(req: crate::Request) -> crate::Result {
+        let shutdown_token = ShutdownToken::shared();
+        let mut data = Map::from_entry("phase", shutdown_token.phase().as_str());
+        data.upsert("in_flight", shutdown_token.in_flight());
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(data);
+        Ok(res.into())
+    }
+
+    /// Returns a `503 Service Unavailable` response if the application is not ready.
+    pub async fn ready(req: crate::Request) -> crate::Result {
+        let shutdown_token = ShutdownToken::shared();
+        if !shutdown_token.is_ready() {
+            let err = Error::new("the application is shutting down");
+            return Err(Rejection::service_unavailable(err).context(&req).into());
+        }
+
+        let data = Map::from_entry("phase", shutdown_token.phase().as_str());
+        let mut res = Response::default().context(&req);
+        res.set_json_data(data);
+        Ok(res.into())
+    }
+}
@@ -0,0 +1,129 @@
+use zino_core::{
+    error::Error,
+    extension::JsonObjectExt,
+    request::RequestContext,
+    response::{ExtractRejection, Rejection, Response},
+    schedule::JobMonitor,
+    Map, Uuid,
+};
+
+/// Controller for the runtime control of async cron jobs,
+/// which is supposed to be mounted on the debug server.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use axum::{routing::{get, post}, Router};
+/// use zino::JobController;
+///
+/// let router = Router::new()
+///     .route("/job/list", get(JobController::list))
+///     .route("/job/:id/history", get(JobController::history))
+///     .route("/job/:id/preview", get(JobController::preview))
+///     .route("/job/:id/pause", post(JobController::pause))
+///     .route("/job/:id/resume", post(JobController::resume))
+///     .route("/job/:id/trigger", post(JobController::trigger))
+///     .route("/job/:id/reschedule", post(JobController::reschedule));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JobController;
+
+impl JobController {
+    /// Lists the scheduled jobs.
+    pub async fn list(req: crate::Request) -> crate::Result {
+        let jobs = JobMonitor::shared()
+            .list_jobs()
+            .iter()
+            .map(|job| job.to_map())
+            .collect::<Vec<_>>();
+        let mut res = Response::default().context(&req);
+        res.set_json_data(Map::data_entries(jobs));
+        Ok(res.into())
+    }
+
+    /// Lists the recent runs of a job.
+    pub async fn history(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        let monitor = JobMonitor::shared();
+        let job = monitor.get_job(id).extract(&req)?;
+        let runs = monitor
+            .list_runs(id)
+            .iter()
+            .map(|run| run.to_map())
+            .collect::<Vec<_>>();
+        let mut data = Map::data_entries(runs);
+        data.upsert("job", job.to_map());
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(data);
+        Ok(res.into())
+    }
+
+    /// Previews the next fire times of a job, whose number is specified by the `count` query
+    /// with a default value of 10 and a maximum value of 100.
+    pub async fn preview(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        let count = req
+            .get_query("count")
+            .and_then(|count| count.parse::<usize>().ok())
+            .unwrap_or(10)
+            .min(100);
+        let fire_times = JobMonitor::shared()
+            .preview(id, count)
+            .extract(&req)?
+            .iter()
+            .map(|dt| dt.to_string())
+            .collect::<Vec<_>>();
+        let mut data = Map::from_entry("job_id", id.to_string());
+        data.upsert("fire_times", fire_times);
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(data);
+        Ok(res.into())
+    }
+
+    /// Pauses a job.
+    pub async fn pause(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        JobMonitor::shared().pause(id).extract(&req)?;
+
+        let res = Response::default().context(&req);
+        Ok(res.into())
+    }
+
+    /// Resumes a job.
+    pub async fn resume(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        JobMonitor::shared().resume(id).extract(&req)?;
+
+        let res = Response::default().context(&req);
+        Ok(res.into())
+    }
+
+    /// Executes a job immediately.
+    pub async fn trigger(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        JobMonitor::shared().trigger(id).extract(&req)?;
+
+        let res = Response::default().context(&req);
+        Ok(res.into())
+    }
+
+    /// Replaces the cron expression of a job with the `cron` field in the body.
+    pub async fn reschedule(mut req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        let body = req.parse_body::<Map>().await?;
+        let Some(cron_expr) = body.get_str("cron") else {
+            let err = Error::new("the `cron` field should be specified");
+            return Err(Rejection::from_validation_entry("cron", err)
+                .context(&req)
+                .into());
+        };
+        JobMonitor::shared()
+            .reschedule(id, cron_expr)
+            .extract(&req)?;
+
+        let res = Response::default().context(&req);
+        Ok(res.into())
+    }
+}
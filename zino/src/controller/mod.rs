@@ -1,3 +1,21 @@
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+mod health;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+mod job;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+mod webhook;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+mod workflow;
+
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+pub use health::HealthController;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+pub use job::JobController;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+pub use webhook::WebhookController;
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+pub use workflow::WorkflowController;
+
 /// Default controller for the `Model`.
 pub trait DefaultController<K> {
     /// A type for the request extractor.
@@ -64,6 +82,7 @@ pub trait DefaultController<K> {
 #[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
 #[cfg(feature = "orm")]
 use zino_core::{
+    datetime::DateTime,
     error::Error,
     extension::JsonObjectExt,
     model::{ModelHooks, Mutation, Query},
@@ -85,9 +104,12 @@ where
     type Result = crate::Result;
 
     async fn new(mut req: Self::Request) -> Self::Result {
-        let mut model = Self::new();
-        let mut res = req.model_validation(&mut model).await?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        let mut model = Self::new();
+        let mut res = req
+            .model_validation_with(&mut model, |data| Self::check_writable_fields(data, &roles))
+            .await?;
         model
             .before_insert_check(extension.as_ref())
             .await
@@ -114,6 +136,8 @@ where
         Self::before_respond(&mut model_snapshot, extension.as_ref())
             .await
             .extract(&req)?;
+
+        Self::restrict_model(&mut model_snapshot, &roles);
         res.set_json_data(Self::data_item(model_snapshot));
         Ok(res.into())
     }
@@ -132,6 +156,10 @@ where
         let mut body = req.parse_body().await?;
 
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        Self::check_writable_fields(&body, &roles).extract(&req)?;
+        Self::strip_masked_values(&mut body, &roles);
+
         let (validation, model) = Self::mutate_by_id(&id, &mut body, extension)
             .await
             .extract(&req)?;
@@ -145,7 +173,21 @@ where
 
     async fn view(req: Self::Request) -> Self::Result {
         let id = req.parse_param::<K>("id")?;
+        let mut res = Response::default().context(&req);
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+
+        // The validators are checked before fetching the model.
+        let validators: Option<(u64, DateTime)> =
+            Self::fetch_validators_by_id(&id).await.extract(&req)?;
+        if let Some(validators) = validators {
+            let session_dependent = extension.is_some() || Self::is_role_dependent();
+            let cache_control = Self::cache_control(session_dependent);
+            if check_validators(&req, &mut res, validators, &roles, cache_control) {
+                return Ok(res.into());
+            }
+        }
+
         let mut model = if req.get_query("fetch") == Some("false") {
             Self::find_by_id(&id).await.extract(&req)?
         } else {
@@ -155,7 +197,7 @@ where
             .await
             .extract(&req)?;
 
-        let mut res = Response::default().context(&req);
+        Self::restrict_model(&mut model, &roles);
         res.set_json_data(Self::data_item(model));
         Ok(res.into())
     }
@@ -168,11 +210,24 @@ where
         };
         let mut res = req.query_validation(&mut query)?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        query.restrict_fields(&Self::unreadable_fields(&roles));
         Self::before_list(&mut query, extension.as_ref())
             .await
             .extract(&req)?;
 
-        let models = if query.populate_enabled() {
+        // The validators are checked before fetching the models.
+        let validators: Option<(u64, DateTime)> =
+            Self::fetch_list_validators(&query).await.extract(&req)?;
+        if let Some(validators) = validators {
+            let session_dependent = extension.is_some() || Self::is_role_dependent();
+            let cache_control = Self::cache_control(session_dependent);
+            if check_validators(&req, &mut res, validators, &roles, cache_control) {
+                return Ok(res.into());
+            }
+        }
+
+        let mut models = if query.populate_enabled() {
             let mut models = Self::fetch(&query).await.extract(&req)?;
             for model in models.iter_mut() {
                 Self::before_respond(model, extension.as_ref())
@@ -184,6 +239,7 @@ where
             let mut models = Self::find(&query).await.extract(&req)?;
             let translate_enabled = query.translate_enabled();
             for model in models.iter_mut() {
+                Self::decrypt_columns(model).extract(&req)?;
                 Self::after_decode(model).await.extract(&req)?;
                 translate_enabled.then(|| Self::translate_model(model));
                 Self::before_respond(model, extension.as_ref())
@@ -193,6 +249,10 @@ where
             models
         };
 
+        for model in models.iter_mut() {
+            Self::restrict_model(model, &roles);
+        }
+
         let mut data = Self::data_items(models);
         if let Some(page_size) = req.get_query("page_size").and_then(|s| s.parse().ok()) {
             if req.get_query("total_rows").is_none() {
@@ -213,6 +273,8 @@ where
         query.append_filters(&mut body);
 
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        query.restrict_fields(&Self::unreadable_fields(&roles));
         Self::before_list(&mut query, extension.as_ref())
             .await
             .extract(&req)?;
@@ -222,6 +284,7 @@ where
             Self::before_respond(model, extension.as_ref())
                 .await
                 .extract(&req)?;
+            Self::restrict_model(model, &roles);
         }
 
         let mut data = Self::data_items(models);
@@ -264,9 +327,11 @@ where
     async fn batch_insert(mut req: Self::Request) -> Self::Result {
         let data = req.parse_body::<Vec<Map>>().await?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
         let mut models = Vec::with_capacity(data.len());
         let mut validations = Vec::new();
         for (index, mut map) in data.into_iter().enumerate() {
+            Self::check_writable_fields(&map, &roles).extract(&req)?;
             Self::before_extract()
                 .await
                 .map_err(|err| Rejection::from_error(err).context(&req))?;
@@ -341,11 +406,15 @@ where
 
     async fn batch_update(mut req: Self::Request) -> Self::Result {
         let data = req.parse_body::<Vec<Map>>().await?;
+        let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
 
         // Should use `Self::transaction` when the `Send` bound is resolved
         let primary_key_name = Self::PRIMARY_KEY_NAME;
         let mut rows_affected = 0;
         for mut map in data.into_iter() {
+            Self::check_writable_fields(&map, &roles).extract(&req)?;
+            Self::strip_masked_values(&mut map, &roles);
             if let Some(id) = map.remove(primary_key_name) {
                 let query = Query::from_entry(primary_key_name, id);
                 let mut mutation = Mutation::new(map);
@@ -367,6 +436,7 @@ where
 
         let data = req.parse_body::<Vec<Map>>().await?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
         let validate_only = query.validate_only();
         let no_check = query.no_check();
         let limit = query.limit();
@@ -396,6 +466,7 @@ where
                 models.append(&mut batch_models);
                 Self::insert_many(models).await.extract(&req)?;
             }
+            Self::check_writable_fields(&map, &roles).extract(&req)?;
             Self::before_extract()
                 .await
                 .map_err(|err| Rejection::from_error(err).context(&req))?;
@@ -460,6 +531,8 @@ where
         let mut query = Self::default_query();
         let mut res = req.query_validation(&mut query)?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        query.restrict_fields(&Self::unreadable_fields(&roles));
         Self::before_list(&mut query, extension.as_ref())
             .await
             .extract(&req)?;
@@ -467,18 +540,30 @@ where
         let mut models = Self::find(&query).await.extract(&req)?;
         let translate_enabled = query.translate_enabled();
         for model in models.iter_mut() {
+            Self::decrypt_columns(model).extract(&req)?;
             Self::after_decode(model).await.extract(&req)?;
             translate_enabled.then(|| Self::translate_model(model));
             Self::before_respond(model, extension.as_ref())
                 .await
                 .extract(&req)?;
+            Self::restrict_model(model, &roles);
         }
 
-        let format = req.get_query("format").unwrap_or("json");
-        match format {
-            "csv" => res.set_csv_response(models),
-            "jsonlines" => res.set_jsonlines_response(models),
-            _ => res.set_json_response(models),
+        let data_type = req.negotiate_data_type();
+        if matches!(data_type, "arrow" | "avro") {
+            let mut records = Vec::with_capacity(models.len());
+            for model in models {
+                let model = Self::try_from_map(model).extract(&req)?;
+                records.push(model.into_avro_record());
+            }
+            #[cfg(feature = "connector-arrow")]
+            if data_type == "arrow" {
+                res.set_arrow_response(records);
+                return Ok(res.into());
+            }
+            res.set_avro_response(Self::schema(), records);
+        } else {
+            res.set_data_response(data_type, models);
         }
         Ok(res.into())
     }
@@ -487,6 +572,9 @@ where
         let mut query = Self::default_list_query();
         let mut res = req.query_validation(&mut query)?;
         let extension = req.get_data::<<Self as ModelHooks>::Extension>();
+        let roles = Self::access_roles(extension.as_ref());
+        let unreadable_fields = Self::unreadable_fields(&roles);
+        query.restrict_fields(&unreadable_fields);
         Self::before_list(&mut query, extension.as_ref())
             .await
             .extract(&req)?;
@@ -500,6 +588,7 @@ where
             let mut models = Self::find(&query).await.extract(&req)?;
             let translate_enabled = query.translate_enabled();
             for model in models.iter_mut() {
+                Self::decrypt_columns(model).extract(&req)?;
                 Self::after_decode(model).await.extract(&req)?;
                 translate_enabled.then(|| Self::translate_model(model));
             }
@@ -512,6 +601,7 @@ where
             .filter_map(|model| model.get(primary_key_name).cloned())
             .collect::<Vec<_>>();
         let mut query = Self::default_snapshot_query();
+        query.restrict_fields(&unreadable_fields);
         query.add_filter("parent_id", Map::from_entry("$in", values));
         query.add_filter("status", Map::from_entry("$ne", "Deleted"));
         query.order_desc("parent_id");
@@ -519,6 +609,9 @@ where
         query.disable_limit();
 
         let mut children = Self::find::<Map>(&query).await.extract(&req)?;
+        for child in children.iter_mut() {
+            Self::restrict_model(child, &roles);
+        }
         let total_rows = children.len();
         for model in models.iter_mut() {
             let model_id = model.get(primary_key_name);
@@ -534,6 +627,7 @@ where
                     index += 1;
                 }
             }
+            Self::restrict_model(model, &roles);
             model.upsert("children", model_children);
         }
 
@@ -623,3 +717,29 @@ where
         Ok(res.into())
     }
 }
+
+/// Sets the validators for a conditional request, and returns `true` if the representation
+/// has not been modified. The entity tag depends on the query and the roles of the client.
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+#[cfg(feature = "orm")]
+fn check_validators(
+    req: &crate::Request,
+    res: &mut crate::Response,
+    validators: (u64, DateTime),
+    roles: &[&str],
+    cache_control: Option<std::borrow::Cow<'static, str>>,
+) -> bool {
+    let scope = [req.get_query_string().unwrap_or_default(), &roles.join(",")].join("\n");
+    let etag = res.set_validator_etag(validators, &scope);
+    let last_modified = validators.1;
+    res.set_last_modified(last_modified);
+    if let Some(cache_control) = cache_control {
+        res.set_cache_control(cache_control);
+    }
+    if req.is_not_modified(&etag, Some(last_modified)) {
+        res.set_not_modified();
+        true
+    } else {
+        false
+    }
+}
@@ -0,0 +1,51 @@
+use zino_core::{
+    error::Error,
+    request::RequestContext,
+    response::{ExtractRejection, Rejection, Response, WebhookDelivery, WebhookDispatcher},
+    Uuid,
+};
+
+/// Controller for the webhook deliveries of the shared [`WebhookDispatcher`].
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use axum::{routing::{get, post}, Router};
+/// use zino::WebhookController;
+///
+/// let router = Router::new()
+///     .route("/webhook/delivery/:id", get(WebhookController::delivery))
+///     .route("/webhook/delivery/:id/redeliver", post(WebhookController::redeliver));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WebhookController;
+
+impl WebhookController {
+    /// Gets a webhook delivery with the response status and the body excerpt.
+    pub async fn delivery(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        let dispatcher = Self::dispatcher(&req)?;
+        let delivery: WebhookDelivery = dispatcher.store().get_delivery(id).await.extract(&req)?;
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(delivery.into_map());
+        Ok(res.into())
+    }
+
+    /// Sends a webhook delivery again, which enables the endpoint if it has been disabled.
+    pub async fn redeliver(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        Self::dispatcher(&req)?.redeliver(id).await.extract(&req)?;
+
+        let res = Response::default().context(&req);
+        Ok(res.into())
+    }
+
+    /// Returns the shared webhook dispatcher.
+    fn dispatcher(req: &crate::Request) -> Result<&'static WebhookDispatcher, Rejection> {
+        WebhookDispatcher::shared().ok_or_else(|| {
+            let err = Error::new("the webhook dispatcher has not been registered");
+            Rejection::internal_server_error(err).context(req)
+        })
+    }
+}
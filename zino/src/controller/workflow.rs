@@ -0,0 +1,72 @@
+use zino_core::{
+    channel::CloudEvent,
+    error::Error,
+    extension::JsonObjectExt,
+    request::RequestContext,
+    response::{ExtractRejection, Rejection, Response},
+    schedule::{WorkflowEngine, WorkflowRun},
+    Map, Uuid,
+};
+
+/// Controller for the runs of the shared [`WorkflowEngine`].
+///
+/// Since a signal resumes the runs waiting for it, the `signal` handler requires
+/// an API key with the `workflow:signal` scope, which is verified by
+/// [`RequestContext::verify_api_key()`]. The `view` handler should be protected
+/// by the authentication middleware of the application.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use axum::{routing::{get, post}, Router};
+/// use zino::WorkflowController;
+///
+/// let router = Router::new()
+///     .route("/workflow/run/:id", get(WorkflowController::view))
+///     .route("/workflow/signal", post(WorkflowController::signal));
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WorkflowController;
+
+impl WorkflowController {
+    /// Gets the state of a workflow run with the history.
+    pub async fn view(req: crate::Request) -> crate::Result {
+        let id = req.parse_param::<Uuid>("id")?;
+        let engine = Self::engine(&req)?;
+        let run: WorkflowRun = engine.get_run(id).await.extract(&req)?;
+        let history = engine
+            .list_history(id)
+            .await
+            .extract(&req)?
+            .into_iter()
+            .map(|entry| entry.into_map())
+            .collect::<Vec<_>>();
+        let mut data = Map::data_entries(history);
+        data.upsert("run", run.into_map());
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(data);
+        Ok(res.into())
+    }
+
+    /// Delivers a signal with the cloud event in the body.
+    /// The request should be authenticated by an API key with the `workflow:signal` scope.
+    pub async fn signal(mut req: crate::Request) -> crate::Result {
+        req.verify_api_key("workflow", "signal").await?;
+
+        let event = req.parse_body::<CloudEvent>().await?;
+        let num_runs = Self::engine(&req)?.signal(&event).await.extract(&req)?;
+
+        let mut res = Response::default().context(&req);
+        res.set_json_data(Map::from_entry("num_runs", num_runs));
+        Ok(res.into())
+    }
+
+    /// Returns the shared workflow engine.
+    fn engine(req: &crate::Request) -> Result<&'static WorkflowEngine, Rejection> {
+        WorkflowEngine::shared().ok_or_else(|| {
+            let err = Error::new("the workflow engine has not been registered");
+            Rejection::internal_server_error(err).context(req)
+        })
+    }
+}
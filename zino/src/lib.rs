@@ -11,6 +11,9 @@ pub mod prelude;
 
 pub use controller::DefaultController;
 
+#[cfg(any(feature = "actix", feature = "axum", feature = "ntex"))]
+pub use controller::{HealthController, JobController, WebhookController, WorkflowController};
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "actix")] {
         #[doc(no_inline)]
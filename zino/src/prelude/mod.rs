@@ -28,7 +28,7 @@ pub use zino_core::fluent_args;
 
 #[cfg(feature = "jwt")]
 #[doc(no_inline)]
-pub use zino_core::auth::JwtClaims;
+pub use zino_core::auth::{AuthorizationServer, JwkSet, JwtClaims};
 
 #[cfg(feature = "opa")]
 #[doc(no_inline)]